@@ -1,7 +1,11 @@
 use qonduit::command::{Command, CommandHandler};
+use qonduit::error::DispatchError;
+use qonduit::validation::{Validate, Violation, Violations};
 use qonduit::{async_trait, command_bus};
+use std::collections::HashSet;
 use std::error::Error;
 use std::fmt;
+use std::sync::Mutex;
 
 // Product entity
 #[derive(Debug)]
@@ -11,19 +15,17 @@ pub struct Product {
     pub price: f64,
 }
 
-// Command error types
+// Command error types (business rules only; input checks live in `Validate`)
 #[derive(Debug)]
 pub enum CreateProductError {
-    InvalidPrice,
-    NameTooShort,
+    NameAlreadyTaken,
 }
 
 // Implement Display for the error
 impl fmt::Display for CreateProductError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            CreateProductError::InvalidPrice => write!(f, "Price must be greater than zero"),
-            CreateProductError::NameTooShort => write!(f, "Product name is too short"),
+            CreateProductError::NameAlreadyTaken => write!(f, "Product name is already taken"),
         }
     }
 }
@@ -44,20 +46,40 @@ impl Command for CreateProductCommand {
     type Error = CreateProductError;
 }
 
+// Input validation, run by the bus before the handler is invoked
+impl Validate for CreateProductCommand {
+    fn validate(&self) -> Result<(), Violations> {
+        let mut violations = Violations::new();
+        if self.price <= 0.0 {
+            violations.push(Violation::new(
+                "price",
+                "not_positive",
+                "Price must be greater than zero",
+            ));
+        }
+        if self.name.len() < 3 {
+            violations.push(Violation::new(
+                "name",
+                "too_short",
+                "Product name is too short",
+            ));
+        }
+        violations.into_result()
+    }
+}
+
 // Handler for product creation
 pub struct CreateProductCommandHandler {
     pub next_id: u64, // In a real app, this would be in a database
+    pub names: Mutex<HashSet<String>>,
 }
 
 #[async_trait]
 impl CommandHandler<CreateProductCommand> for CreateProductCommandHandler {
     async fn handle(&self, cmd: CreateProductCommand) -> Result<u64, CreateProductError> {
-        // Validate command data
-        if cmd.price <= 0.0 {
-            return Err(CreateProductError::InvalidPrice);
-        }
-        if cmd.name.len() < 3 {
-            return Err(CreateProductError::NameTooShort);
+        // The command is known to be well-formed; only business rules are checked here
+        if !self.names.lock().unwrap().insert(cmd.name.clone()) {
+            return Err(CreateProductError::NameAlreadyTaken);
         }
 
         // In a real application, we would save to a database
@@ -74,10 +96,14 @@ impl CommandHandler<CreateProductCommand> for CreateProductCommandHandler {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    // Create command bus with registered handler
+    // Create command bus with registered handler and validation enabled
     let command_bus = command_bus! {
-        CreateProductCommand => CreateProductCommandHandler { next_id: 1 },
-    };
+        CreateProductCommand => CreateProductCommandHandler {
+            next_id: 1,
+            names: Mutex::new(HashSet::new()),
+        },
+    }
+    .with_validation::<CreateProductCommand>();
 
     // Execute commands
     let valid_command = CreateProductCommand {
@@ -87,5 +113,20 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     let product_id = command_bus.dispatch(valid_command).await?;
     println!("Product created with ID: {}", product_id);
+
+    // Invalid commands are rejected before reaching the handler
+    let invalid_command = CreateProductCommand {
+        name: "TV".to_string(),
+        price: -1.0,
+    };
+
+    match command_bus.dispatch(invalid_command).await {
+        Err(DispatchError::Invalid(violations)) => {
+            for violation in &violations {
+                println!("Rejected: {}", violation);
+            }
+        }
+        other => println!("Unexpected result: {:?}", other),
+    }
     Ok(())
 }
@@ -0,0 +1,77 @@
+//! The `qonduit-derive` crate provides the derive macros of the `qonduit` crate.
+//!
+//! The macros are re-exported by `qonduit` with its `derive` feature, which should be used
+//! instead of depending on this crate directly.
+//!
+//! - [MessageName]: Derives `qonduit::message::MessageName`.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::DeriveInput;
+use syn::LitInt;
+use syn::LitStr;
+use syn::parse_macro_input;
+
+/// Derives `qonduit::message::MessageName` from a `#[message(...)]` attribute.
+///
+/// The attribute takes the `name` of the message and an optional `version`, which is appended
+/// to the name as a `.v<version>` suffix:
+///
+/// ```ignore
+/// #[derive(Debug, MessageName)]
+/// #[message(name = "orders.place", version = 2)]
+/// struct PlaceOrderCommand;
+///
+/// assert_eq!(PlaceOrderCommand::NAME, "orders.place.v2");
+/// ```
+#[proc_macro_derive(MessageName, attributes(message))]
+pub fn derive_message_name(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_message_name(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_message_name(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let mut name: Option<LitStr> = None;
+    let mut version: Option<LitInt> = None;
+    for attribute in input.attrs.iter().filter(|a| a.path().is_ident("message")) {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                name = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("version") {
+                version = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `name` or `version`"))
+            }
+        })?;
+    }
+
+    let Some(name) = name else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "missing `#[message(name = \"...\")]` attribute",
+        ));
+    };
+    if name.value().is_empty() {
+        return Err(syn::Error::new_spanned(
+            name,
+            "message name must not be empty",
+        ));
+    }
+    let full_name = match version {
+        Some(version) => format!("{}.v{}", name.value(), version.base10_parse::<u32>()?),
+        None => name.value(),
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::qonduit::message::MessageName for #ident #type_generics #where_clause {
+            const NAME: &'static str = #full_name;
+        }
+    })
+}
@@ -0,0 +1,442 @@
+//! The `audit` module keeps a record of every command dispatched on a `CommandBus`.
+//!
+//! This module is available with the `audit` feature. Once an [AuditSink] is configured on the
+//! bus, every dispatched command produces an [AuditRecord] describing its type, payload, principal,
+//! timestamp, outcome and duration, including commands rejected before reaching their handler.
+//!
+//! Payloads are only recorded for command types that opted in with `with_audit_payload`, which
+//! requires them to implement `serde::Serialize`. Fields holding secrets can be masked per command
+//! type with `with_audit_redaction`, so they never reach the sink.
+//!
+//! A sink failing to store a record does not fail the dispatch, whose command already ran. The
+//! failure is passed to the callback registered with `on_audit_failure`, and logged with the
+//! `tracing` feature.
+//!
+//! - [AuditRecord]: The record of a single dispatched command.
+//! - [AuditSink]: Receives the records produced by the bus.
+//! - [JsonLinesFileSink]: Appends records as JSON lines to a file, rotating it by size.
+//! - [InMemoryAuditSink]: Keeps records in memory, mainly for tests.
+
+use std::any::Any;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::future::Future;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use serde::Serialize;
+use serde_json::Value;
+use serde_json::json;
+use tokio::time::Instant;
+
+use crate::async_trait;
+use crate::authorization::current_principal;
+use crate::error::DispatchError;
+
+/// The value replacing redacted fields in recorded payloads.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// The record of a single command dispatched on a `CommandBus`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditRecord {
+    /// The [stable name](crate::message::MessageName) of the command, or its type name.
+    pub message_type: &'static str,
+    /// The serialized command with redacted fields masked, or `None` if the payload of the
+    /// command type is not recorded.
+    pub payload: Option<Value>,
+    /// The identifier of the principal on whose behalf the command was dispatched, or `None` if
+    /// it was anonymous.
+    pub principal: Option<String>,
+    /// When the dispatch started.
+    pub timestamp: SystemTime,
+    /// `ok`, or the [kind](DispatchError::kind) of failure.
+    pub outcome: &'static str,
+    /// How long the dispatch took.
+    pub duration: Duration,
+}
+
+/// Implementation of the `AuditRecord`.
+impl AuditRecord {
+    /// Returns the record as a JSON object.
+    ///
+    /// The timestamp is rendered as milliseconds since the Unix epoch and the duration as
+    /// fractional milliseconds.
+    pub fn to_json(&self) -> Value {
+        let timestamp_ms = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        json!({
+            "message_type": self.message_type,
+            "payload": self.payload,
+            "principal": self.principal,
+            "timestamp_ms": timestamp_ms,
+            "outcome": self.outcome,
+            "duration_ms": self.duration.as_secs_f64() * 1000.0,
+        })
+    }
+}
+
+/// The `AuditSink` trait receives the audit records produced by a `CommandBus`.
+///
+/// A failure to record is not reported to the caller of the dispatch, whose command already ran,
+/// but to the callback registered with `on_audit_failure`; sinks that must never lose records
+/// should retry or buffer internally.
+///
+/// # Example
+///
+/// ```
+/// use std::error::Error;
+/// use qonduit::async_trait;
+/// use qonduit::audit::{AuditRecord, AuditSink};
+///
+/// struct StdoutSink;
+///
+/// #[async_trait]
+/// impl AuditSink for StdoutSink {
+///     async fn record(&self, record: &AuditRecord) -> Result<(), Box<dyn Error + Send + Sync>> {
+///         println!("{}", record.to_json());
+///         Ok(())
+///     }
+/// }
+/// ```
+#[async_trait]
+pub trait AuditSink: Send + Sync {
+    /// Stores the record of a dispatched command.
+    async fn record(&self, record: &AuditRecord) -> Result<(), Box<dyn Error + Send + Sync>>;
+}
+
+/// An audit sink keeping records in memory.
+///
+/// Clones share the same records.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryAuditSink {
+    #[doc(hidden)]
+    records: Arc<Mutex<Vec<AuditRecord>>>,
+}
+
+/// Implementation of the `InMemoryAuditSink`.
+impl InMemoryAuditSink {
+    /// Creates an empty sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the records received so far, oldest first.
+    pub fn records(&self) -> Vec<AuditRecord> {
+        self.records.lock().unwrap().clone()
+    }
+}
+
+/// AuditSink implementation for `InMemoryAuditSink`
+#[async_trait]
+impl AuditSink for InMemoryAuditSink {
+    async fn record(&self, record: &AuditRecord) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.records.lock().unwrap().push(record.clone());
+        Ok(())
+    }
+}
+
+/// An audit sink appending records as JSON lines to a file.
+///
+/// Every record is synced to disk before [record](AuditSink::record) returns, so that a recorded
+/// command survives a crash of the process or the machine. Once the file would exceed its maximum
+/// size, it is rotated: `audit.log` is renamed to `audit.log.1`, `audit.log.1` to `audit.log.2`
+/// and so on, and the oldest file beyond the maximum number of rotated files is deleted. File
+/// operations run on the blocking threads of the runtime. Clones share the same file.
+///
+/// # Example
+///
+/// ```no_run
+/// use qonduit::audit::JsonLinesFileSink;
+///
+/// let sink = JsonLinesFileSink::open("/var/log/orders/audit.log")
+///     .unwrap()
+///     .with_max_bytes(50 * 1024 * 1024)
+///     .with_max_files(10);
+/// # drop(sink);
+/// ```
+#[derive(Clone)]
+pub struct JsonLinesFileSink {
+    #[doc(hidden)]
+    path: PathBuf,
+    #[doc(hidden)]
+    max_bytes: u64,
+    #[doc(hidden)]
+    max_files: usize,
+    #[doc(hidden)]
+    file: Arc<Mutex<(File, u64)>>,
+}
+
+/// Implementation of the `JsonLinesFileSink`.
+impl JsonLinesFileSink {
+    /// Opens `path` for appending, creating it if needed.
+    ///
+    /// By default, the file is rotated once it reaches 10 MiB, and 5 rotated files are kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+            file: Arc::new(Mutex::new((file, size))),
+        })
+    }
+
+    /// Sets the size in bytes above which the file is rotated.
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Sets how many rotated files are kept; zero discards the file on rotation.
+    pub fn with_max_files(mut self, max_files: usize) -> Self {
+        self.max_files = max_files;
+        self
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&self) -> io::Result<File> {
+        if self.max_files == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    std::fs::rename(from, self.rotated_path(index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+    }
+
+    fn append(&self, line: &[u8]) -> io::Result<()> {
+        let mut guard = self.file.lock().unwrap();
+        let (file, size) = &mut *guard;
+        if *size > 0 && *size + line.len() as u64 > self.max_bytes {
+            *file = self.rotate()?;
+            *size = 0;
+        }
+        file.write_all(line)?;
+        file.sync_all()?;
+        *size += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// AuditSink implementation for `JsonLinesFileSink`
+#[async_trait]
+impl AuditSink for JsonLinesFileSink {
+    async fn record(&self, record: &AuditRecord) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut line = serde_json::to_vec(&record.to_json())?;
+        line.push(b'\n');
+        let sink = self.clone();
+        tokio::task::spawn_blocking(move || sink.append(&line)).await??;
+        Ok(())
+    }
+}
+
+/// Debug implementation for `JsonLinesFileSink`
+impl Debug for JsonLinesFileSink {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("JsonLinesFileSink")
+            .field("path", &self.path)
+            .field("max_bytes", &self.max_bytes)
+            .field("max_files", &self.max_files)
+            .finish()
+    }
+}
+
+type Serializer = fn(&dyn Any) -> Result<Value, serde_json::Error>;
+
+type FailureCallback = Arc<dyn Fn(&AuditRecord, &(dyn Error + Send + Sync)) + Send + Sync>;
+
+/// Replaces the field at the dotted `path` of `value` with [REDACTED], if it exists.
+fn redact(value: &mut Value, path: &str) {
+    let mut current = value;
+    let mut segments = path.split('.').peekable();
+    while let Some(segment) = segments.next() {
+        let next = match current {
+            Value::Object(fields) => fields.get_mut(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get_mut(i)),
+            _ => None,
+        };
+        let Some(next) = next else {
+            return;
+        };
+        if segments.peek().is_none() {
+            *next = Value::String(REDACTED.to_string());
+            return;
+        }
+        current = next;
+    }
+}
+
+/// The audit settings of a bus.
+#[derive(Clone, Default)]
+pub(crate) struct Auditor {
+    sink: Option<Arc<dyn AuditSink>>,
+    serializers: HashMap<TypeId, Serializer>,
+    redactions: HashMap<TypeId, Vec<String>>,
+    on_failure: Option<FailureCallback>,
+}
+
+/// A dispatch whose record is pending until its outcome is known.
+pub(crate) struct PendingRecord {
+    sink: Arc<dyn AuditSink>,
+    on_failure: Option<FailureCallback>,
+    message_type: &'static str,
+    payload: Option<Value>,
+    principal: Option<String>,
+    timestamp: SystemTime,
+}
+
+impl Auditor {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends the records of dispatched messages to `sink`, replacing any previous sink.
+    pub(crate) fn set_sink(&mut self, sink: impl AuditSink + 'static) {
+        self.sink = Some(Arc::new(sink));
+    }
+
+    /// Passes the records that the sink fails to store, with the error, to `callback`.
+    pub(crate) fn on_failure(
+        &mut self,
+        callback: impl Fn(&AuditRecord, &(dyn Error + Send + Sync)) + Send + Sync + 'static,
+    ) {
+        self.on_failure = Some(Arc::new(callback));
+    }
+
+    /// Records the serialized payload of messages of type `T`.
+    pub(crate) fn record_payload<T: Serialize + 'static>(&mut self) {
+        self.serializers.insert(TypeId::of::<T>(), |message| {
+            serde_json::to_value(
+                message
+                    .downcast_ref::<T>()
+                    .expect("Cannot downcast message to correct type"),
+            )
+        });
+    }
+
+    /// Masks the fields at the dotted `paths` in the payloads of messages of type `T`.
+    pub(crate) fn redact<T: 'static>(&mut self, paths: Vec<String>) {
+        self.redactions
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .extend(paths);
+    }
+
+    /// Captures what is known about a dispatch of `message`, named `message_type`, before it runs.
+    pub(crate) fn start<T: 'static>(
+        &self,
+        message_type: &'static str,
+        message: &T,
+    ) -> Option<PendingRecord> {
+        let sink = self.sink.clone()?;
+        let payload = self
+            .serializers
+            .get(&TypeId::of::<T>())
+            .map(|serialize| {
+                serialize(message)
+                    .unwrap_or_else(|error| json!({ "serialization_error": error.to_string() }))
+            })
+            .map(|mut payload| {
+                for path in self
+                    .redactions
+                    .get(&TypeId::of::<T>())
+                    .into_iter()
+                    .flatten()
+                {
+                    redact(&mut payload, path);
+                }
+                payload
+            });
+        Some(PendingRecord {
+            sink,
+            on_failure: self.on_failure.clone(),
+            message_type,
+            payload,
+            principal: current_principal().and_then(|principal| principal.id().map(str::to_string)),
+            timestamp: SystemTime::now(),
+        })
+    }
+}
+
+/// Debug implementation for `Auditor`
+impl Debug for Auditor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("Auditor")
+            .field("enabled", &self.sink.is_some())
+            .finish()
+    }
+}
+
+/// Runs `future`, a dispatch, and sends its record to the sink once it completes.
+pub(crate) async fn audit<R, E, F>(
+    pending: Option<PendingRecord>,
+    future: F,
+) -> Result<R, DispatchError<E>>
+where
+    F: Future<Output = Result<R, DispatchError<E>>>,
+{
+    let Some(pending) = pending else {
+        return future.await;
+    };
+
+    let started_at = Instant::now();
+    let result = future.await;
+    let record = AuditRecord {
+        message_type: pending.message_type,
+        payload: pending.payload,
+        principal: pending.principal,
+        timestamp: pending.timestamp,
+        outcome: match &result {
+            Ok(_) => "ok",
+            Err(error) => error.kind(),
+        },
+        duration: started_at.elapsed(),
+    };
+    if let Err(error) = pending.sink.record(&record).await {
+        #[cfg(feature = "tracing")]
+        tracing::error!(
+            message_type = record.message_type,
+            error = %error,
+            "failed to record audit record"
+        );
+        if let Some(on_failure) = &pending.on_failure {
+            on_failure(&record, error.as_ref());
+        }
+    }
+    result
+}
@@ -0,0 +1,401 @@
+//! The `authorization` module decides whether a principal may dispatch a command or query.
+//!
+//! Policies are registered per message type on the `CommandBus` and `QueryBus` and evaluated
+//! against the [Principal] supplied with `dispatch_as` before anything else happens. A dispatch
+//! denied by its policy fails with [DispatchError::Forbidden] without invoking the handler, so the
+//! access rules of every message type live in one place instead of being spread across handlers.
+//!
+//! The principal of a dispatch is visible to its handler through [current_principal], and
+//! dispatches made while a handler is running act on behalf of the same principal. A plain
+//! `dispatch` outside of any handler acts on behalf of the [anonymous](Principal::anonymous)
+//! principal.
+//!
+//! Policies compose: [has_role], [has_claim] and [authenticated] are building blocks that can be
+//! combined with [PolicyExt::and], [PolicyExt::or], [PolicyExt::not], [all_of] and [any_of], and
+//! any `Fn(&Principal, &M) -> bool` closure is a policy as well. A bus can also be configured to
+//! deny every message type without a policy, so that forgetting to declare one fails closed.
+//!
+//! - [Principal]: The identity on whose behalf a message is dispatched.
+//! - [Policy]: Decides whether a principal may dispatch a message.
+//! - [Forbidden]: Why a dispatch was denied.
+
+use std::any::Any;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::error::DispatchError;
+
+tokio::task_local! {
+    static PRINCIPAL: Arc<Principal>;
+}
+
+/// The identity on whose behalf a message is dispatched.
+///
+/// # Example
+///
+/// ```
+/// use qonduit::authorization::Principal;
+///
+/// let principal = Principal::new("user-42")
+///     .with_role("catalog-editor")
+///     .with_claim("tenant", "acme");
+///
+/// assert!(principal.is_authenticated());
+/// assert!(principal.has_role("catalog-editor"));
+/// assert_eq!(principal.claim("tenant"), Some("acme"));
+/// assert!(!Principal::anonymous().is_authenticated());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Principal {
+    #[doc(hidden)]
+    id: Option<String>,
+    #[doc(hidden)]
+    roles: HashSet<String>,
+    #[doc(hidden)]
+    claims: HashMap<String, String>,
+}
+
+/// Implementation of the `Principal`.
+impl Principal {
+    /// Creates an authenticated principal identified by `id`, without roles or claims.
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: Some(id.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Creates the unauthenticated principal.
+    pub fn anonymous() -> Self {
+        Self::default()
+    }
+
+    /// Adds a role to the principal.
+    pub fn with_role(mut self, role: impl Into<String>) -> Self {
+        self.roles.insert(role.into());
+        self
+    }
+
+    /// Adds a claim to the principal, replacing any previous value of the claim.
+    pub fn with_claim(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.claims.insert(name.into(), value.into());
+        self
+    }
+
+    /// Returns the identifier of the principal, or `None` if it is anonymous.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Returns `true` unless the principal is anonymous.
+    pub fn is_authenticated(&self) -> bool {
+        self.id.is_some()
+    }
+
+    /// Returns `true` if the principal has the role `role`.
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.contains(role)
+    }
+
+    /// Returns the value of the claim `name`, if the principal has it.
+    pub fn claim(&self, name: &str) -> Option<&str> {
+        self.claims.get(name).map(String::as_str)
+    }
+}
+
+/// Returns the principal on whose behalf the current message is dispatched.
+///
+/// Returns `None` when called outside of a dispatch started with `dispatch_as`.
+pub fn current_principal() -> Option<Arc<Principal>> {
+    PRINCIPAL.try_with(Arc::clone).ok()
+}
+
+/// Runs `future`, a dispatch, on behalf of `principal`.
+pub(crate) async fn scope<F: Future>(principal: Principal, future: F) -> F::Output {
+    PRINCIPAL.scope(Arc::new(principal), future).await
+}
+
+/// Why a dispatch was denied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Forbidden {
+    /// The policy of the message type denied the principal.
+    Denied,
+    /// The bus denies by default and the message type has no policy.
+    NoPolicy,
+}
+
+/// The `Policy` trait decides whether a principal may dispatch a message of type `M`.
+///
+/// Closures taking the principal and the message implement this trait, which makes it possible
+/// to express rules depending on the content of the message.
+///
+/// # Example
+///
+/// ```
+/// use qonduit::authorization::{has_role, Policy, PolicyExt, Principal};
+///
+/// #[derive(Debug)]
+/// struct DeleteProductCommand {
+///     tenant: String,
+/// }
+///
+/// // Admins of the tenant owning the product may delete it.
+/// let policy = has_role("admin").and(|principal: &Principal, command: &DeleteProductCommand| {
+///     principal.claim("tenant") == Some(command.tenant.as_str())
+/// });
+///
+/// let command = DeleteProductCommand { tenant: "acme".to_string() };
+/// let admin = Principal::new("u1").with_role("admin").with_claim("tenant", "acme");
+/// let foreign_admin = Principal::new("u2").with_role("admin").with_claim("tenant", "globex");
+///
+/// assert!(policy.allows(&admin, &command));
+/// assert!(!policy.allows(&foreign_admin, &command));
+/// ```
+pub trait Policy<M>: Send + Sync {
+    /// Returns `true` if `principal` may dispatch `message`.
+    fn allows(&self, principal: &Principal, message: &M) -> bool;
+}
+
+/// Policy implementation for closures
+impl<M, F> Policy<M> for F
+where
+    F: Fn(&Principal, &M) -> bool + Send + Sync,
+{
+    fn allows(&self, principal: &Principal, message: &M) -> bool {
+        self(principal, message)
+    }
+}
+
+/// Policy implementation for boxed policies
+impl<M> Policy<M> for Box<dyn Policy<M>> {
+    fn allows(&self, principal: &Principal, message: &M) -> bool {
+        (**self).allows(principal, message)
+    }
+}
+
+/// Combinators available on every policy.
+pub trait PolicyExt<M>: Policy<M> + Sized {
+    /// Allows a message only if both `self` and `other` allow it.
+    fn and<P: Policy<M>>(self, other: P) -> And<Self, P> {
+        And(self, other)
+    }
+
+    /// Allows a message if `self` or `other` allows it.
+    fn or<P: Policy<M>>(self, other: P) -> Or<Self, P> {
+        Or(self, other)
+    }
+
+    /// Allows a message only if `self` denies it.
+    #[allow(clippy::should_implement_trait)]
+    fn not(self) -> Not<Self> {
+        Not(self)
+    }
+}
+
+impl<M, P: Policy<M>> PolicyExt<M> for P {}
+
+/// A policy allowing a message only if both inner policies allow it; see [PolicyExt::and].
+#[derive(Clone, Debug)]
+pub struct And<A, B>(A, B);
+
+impl<M, A: Policy<M>, B: Policy<M>> Policy<M> for And<A, B> {
+    fn allows(&self, principal: &Principal, message: &M) -> bool {
+        self.0.allows(principal, message) && self.1.allows(principal, message)
+    }
+}
+
+/// A policy allowing a message if either inner policy allows it; see [PolicyExt::or].
+#[derive(Clone, Debug)]
+pub struct Or<A, B>(A, B);
+
+impl<M, A: Policy<M>, B: Policy<M>> Policy<M> for Or<A, B> {
+    fn allows(&self, principal: &Principal, message: &M) -> bool {
+        self.0.allows(principal, message) || self.1.allows(principal, message)
+    }
+}
+
+/// A policy inverting its inner policy; see [PolicyExt::not].
+#[derive(Clone, Debug)]
+pub struct Not<A>(A);
+
+impl<M, A: Policy<M>> Policy<M> for Not<A> {
+    fn allows(&self, principal: &Principal, message: &M) -> bool {
+        !self.0.allows(principal, message)
+    }
+}
+
+/// A policy allowing a message only if every inner policy allows it; see [all_of].
+pub struct AllOf<M>(Vec<Box<dyn Policy<M>>>);
+
+impl<M> Policy<M> for AllOf<M> {
+    fn allows(&self, principal: &Principal, message: &M) -> bool {
+        self.0
+            .iter()
+            .all(|policy| policy.allows(principal, message))
+    }
+}
+
+/// A policy allowing a message if any inner policy allows it; see [any_of].
+pub struct AnyOf<M>(Vec<Box<dyn Policy<M>>>);
+
+impl<M> Policy<M> for AnyOf<M> {
+    fn allows(&self, principal: &Principal, message: &M) -> bool {
+        self.0
+            .iter()
+            .any(|policy| policy.allows(principal, message))
+    }
+}
+
+/// Allows a message only if every policy of `policies` allows it.
+///
+/// An empty list allows every message.
+pub fn all_of<M>(policies: impl IntoIterator<Item = Box<dyn Policy<M>>>) -> AllOf<M> {
+    AllOf(policies.into_iter().collect())
+}
+
+/// Allows a message if any policy of `policies` allows it.
+///
+/// An empty list denies every message.
+pub fn any_of<M>(policies: impl IntoIterator<Item = Box<dyn Policy<M>>>) -> AnyOf<M> {
+    AnyOf(policies.into_iter().collect())
+}
+
+/// A policy allowing principals with a given role; see [has_role].
+#[derive(Clone, Debug)]
+pub struct HasRole(String);
+
+impl<M> Policy<M> for HasRole {
+    fn allows(&self, principal: &Principal, _message: &M) -> bool {
+        principal.has_role(&self.0)
+    }
+}
+
+/// Allows principals having the role `role`.
+pub fn has_role(role: impl Into<String>) -> HasRole {
+    HasRole(role.into())
+}
+
+/// A policy allowing principals with a given claim value; see [has_claim].
+#[derive(Clone, Debug)]
+pub struct HasClaim(String, String);
+
+impl<M> Policy<M> for HasClaim {
+    fn allows(&self, principal: &Principal, _message: &M) -> bool {
+        principal.claim(&self.0) == Some(self.1.as_str())
+    }
+}
+
+/// Allows principals whose claim `name` equals `value`.
+pub fn has_claim(name: impl Into<String>, value: impl Into<String>) -> HasClaim {
+    HasClaim(name.into(), value.into())
+}
+
+/// A policy allowing every authenticated principal; see [authenticated].
+#[derive(Clone, Copy, Debug)]
+pub struct Authenticated;
+
+impl<M> Policy<M> for Authenticated {
+    fn allows(&self, principal: &Principal, _message: &M) -> bool {
+        principal.is_authenticated()
+    }
+}
+
+/// Allows every principal except the anonymous one.
+pub fn authenticated() -> Authenticated {
+    Authenticated
+}
+
+/// A policy allowing every principal, including the anonymous one; see [allow_all].
+#[derive(Clone, Copy, Debug)]
+pub struct AllowAll;
+
+impl<M> Policy<M> for AllowAll {
+    fn allows(&self, _principal: &Principal, _message: &M) -> bool {
+        true
+    }
+}
+
+/// Allows every principal, including the anonymous one.
+///
+/// Useful to explicitly mark a message type as public on a bus that denies by default.
+pub fn allow_all() -> AllowAll {
+    AllowAll
+}
+
+/// A type-erased policy for a single message type.
+trait ErasedPolicy: Send + Sync {
+    fn allows(&self, principal: &Principal, message: &dyn Any) -> bool;
+}
+
+/// Adapts a `Policy<M>` to the type-erased interface.
+struct Typed<M, P>(P, PhantomData<fn(&M)>);
+
+impl<M: 'static, P: Policy<M>> ErasedPolicy for Typed<M, P> {
+    fn allows(&self, principal: &Principal, message: &dyn Any) -> bool {
+        let message = message
+            .downcast_ref::<M>()
+            .expect("Cannot downcast message to correct type");
+        self.0.allows(principal, message)
+    }
+}
+
+/// The authorization policies of a bus, keyed by message type.
+#[derive(Clone, Default)]
+pub(crate) struct Policies {
+    policies: HashMap<TypeId, Arc<dyn ErasedPolicy>>,
+    deny_by_default: bool,
+}
+
+impl Policies {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the policy for messages of type `T`, replacing any previous one.
+    pub(crate) fn set<T: 'static>(&mut self, policy: impl Policy<T> + 'static) {
+        self.policies
+            .insert(TypeId::of::<T>(), Arc::new(Typed(policy, PhantomData)));
+    }
+
+    /// Denies every message type without a policy.
+    pub(crate) fn deny_by_default(&mut self) {
+        self.deny_by_default = true;
+    }
+
+    /// Evaluates the policy of `message` against the principal of the current dispatch.
+    pub(crate) fn authorize<T: 'static, E>(&self, message: &T) -> Result<(), DispatchError<E>> {
+        let Some(policy) = self.policies.get(&TypeId::of::<T>()) else {
+            return match self.deny_by_default {
+                true => Err(DispatchError::Forbidden(Forbidden::NoPolicy)),
+                false => Ok(()),
+            };
+        };
+
+        let allowed = match current_principal() {
+            Some(principal) => policy.allows(&principal, message),
+            None => policy.allows(&Principal::anonymous(), message),
+        };
+        match allowed {
+            true => Ok(()),
+            false => Err(DispatchError::Forbidden(Forbidden::Denied)),
+        }
+    }
+}
+
+/// Debug implementation for `Policies`
+impl Debug for Policies {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("Policies")
+            .field("deny_by_default", &self.deny_by_default)
+            .finish()
+    }
+}
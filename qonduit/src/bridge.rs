@@ -0,0 +1,808 @@
+//! The `bridge` module distributes events between the `EventBus`es of several processes.
+//!
+//! This module is available with the `remote` feature. An `EventBus` only fans events out to the
+//! handlers of its own process. An [EventBridge] connects it to an [EventBroker], over any
+//! [Transport] of the [remote](crate::remote) module:
+//!
+//! - Events of the types the bridge [forwards](EventBridge::forward) are handed to the bridge by
+//!   a handler registered on the local bus, and sent to the broker.
+//! - Events of the types the bridge [receives](EventBridge::receive) are sent by the broker and
+//!   dispatched on the local bus.
+//!
+//! The broker routes each event to every other bridge receiving its type. It stands in for a
+//! real message broker, and runs in its own process or in one of the connected processes.
+//!
+//! Delivery is at least once. A bridge keeps each forwarded event until the broker acknowledges
+//! it, and the broker keeps each routed event until the receiving bridge acknowledges it, once
+//! the event has been dispatched on its bus. When a connection breaks, the bridge reconnects and
+//! both ends resend the events that were not acknowledged, so handlers of remote events should be
+//! idempotent. Unacknowledged events are kept in memory, and are lost if a process stops.
+//!
+//! The broker knows a bridge by its name, which must be unique and stable across restarts. Events
+//! are kept for a disconnected bridge from the moment it first subscribed to their type, up to a
+//! [maximum](EventBroker::with_max_unacknowledged) beyond which the oldest ones are dropped, until
+//! the bridge is [forgotten](EventBroker::forget). With the `signing` feature, a broker
+//! [verifying](EventBroker::with_verifier) signatures only accepts the bridges that sign their
+//! hello with a trusted key, so that no other process can take over the name of a bridge.
+//!
+//! - [EventBridge]: Connects an `EventBus` to a broker.
+//! - [EventBroker]: Routes events between bridges.
+//! - [ForwardEventHandler]: Hands the events of a type to a bridge.
+
+use std::any::type_name;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+use std::future::Future;
+use std::future::poll_fn;
+use std::marker::PhantomData;
+use std::pin::pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::task::Poll;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::sync::Notify;
+use tokio::sync::mpsc;
+
+use crate::codec::Codec;
+use crate::event::Event;
+use crate::event::EventBus;
+use crate::event::EventHandler;
+use crate::message::MessageName;
+use crate::remote::Frame;
+use crate::remote::FrameKind;
+use crate::remote::TcpTransport;
+use crate::remote::Transport;
+use crate::remote::TransportError;
+#[cfg(unix)]
+use crate::remote::UnixTransport;
+use crate::remote::accept_failed;
+#[cfg(feature = "signing")]
+use crate::signing::SignedEnvelope;
+#[cfg(feature = "signing")]
+use crate::signing::Signer;
+#[cfg(feature = "signing")]
+use crate::signing::Verifier;
+use crate::util::BoxFuture;
+
+/// The time a bridge waits before reconnecting by default.
+pub const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// The number of events a broker keeps per bridge, waiting for their acknowledgement, by default.
+pub const DEFAULT_MAX_UNACKNOWLEDGED: usize = 10_000;
+
+tokio::task_local! {
+    /// Set while a bridge dispatches a received event, so that it is not forwarded back.
+    static RECEIVING: ();
+}
+
+/// Decodes a received event and dispatches it on the local bus.
+type Route = Arc<dyn Fn(EventBus, Vec<u8>) -> BoxFuture<()> + Send + Sync>;
+
+/// The events handed to a bridge and not yet acknowledged by the broker.
+#[derive(Default)]
+struct Outbox {
+    state: Mutex<OutboxState>,
+    notify: Notify,
+}
+
+#[derive(Default)]
+struct OutboxState {
+    next_sequence: u64,
+    events: BTreeMap<u64, (&'static str, Vec<u8>)>,
+    #[cfg(feature = "signing")]
+    signer: Option<Signer>,
+}
+
+impl Outbox {
+    fn push(&self, name: &'static str, payload: Vec<u8>) {
+        let mut state = self.state.lock().unwrap();
+        // Resent events keep their signature, so that receivers recognize them
+        #[cfg(feature = "signing")]
+        let payload = match &state.signer {
+            Some(signer) => signer.sign(name, payload).to_bytes(),
+            None => payload,
+        };
+        let sequence = state.next_sequence;
+        state.next_sequence += 1;
+        state.events.insert(sequence, (name, payload));
+        drop(state);
+        self.notify.notify_one();
+    }
+
+    fn ack(&self, sequence: u64) {
+        self.state.lock().unwrap().events.remove(&sequence);
+    }
+
+    /// Returns the events from sequence number `from` onwards.
+    fn events_from(&self, from: u64) -> Vec<Frame> {
+        let state = self.state.lock().unwrap();
+        state
+            .events
+            .range(from..)
+            .map(|(sequence, (name, payload))| Frame {
+                correlation_id: *sequence,
+                kind: FrameKind::Event,
+                name: name.to_string(),
+                principal: None,
+                deadline: None,
+                payload: payload.clone(),
+            })
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.state.lock().unwrap().events.len()
+    }
+}
+
+/// The `EventBridge` connects the `EventBus` of a process to an [EventBroker].
+///
+/// Clones of the bridge share the events waiting for an acknowledgement, so a clone can be kept
+/// to [monitor](EventBridge::unacknowledged) a running bridge.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+/// # struct OrderPlacedEvent { order_id: u64 }
+/// # impl qonduit::event::Event for OrderPlacedEvent {}
+/// # impl qonduit::message::MessageName for OrderPlacedEvent {
+/// #   const NAME: &'static str = "orders.placed.v1";
+/// # }
+/// # #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+/// # struct InvoiceSentEvent { order_id: u64 }
+/// # impl qonduit::event::Event for InvoiceSentEvent {}
+/// # impl qonduit::message::MessageName for InvoiceSentEvent {
+/// #   const NAME: &'static str = "billing.invoice_sent.v1";
+/// # }
+/// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// # rt.block_on(async {
+/// use qonduit::bridge::EventBridge;
+/// use qonduit::codec::JsonCodec;
+/// use qonduit::event::EventBus;
+/// use qonduit::registry::EventHandlerRegistry;
+/// use qonduit::remote::TcpTransport;
+/// use tokio::net::TcpStream;
+///
+/// // The billing process receives the placed orders and publishes the sent invoices
+/// let bridge = EventBridge::new("billing", JsonCodec).receive::<OrderPlacedEvent>();
+///
+/// let mut registry = EventHandlerRegistry::new();
+/// registry.register::<InvoiceSentEvent>(bridge.forward::<InvoiceSentEvent>());
+/// let bus = EventBus::new(registry);
+///
+/// tokio::spawn(bridge.run(bus.clone(), || async {
+///     Ok(TcpTransport::from(TcpStream::connect("broker:7100").await?))
+/// }));
+/// # });
+/// ```
+pub struct EventBridge<K: Codec> {
+    #[doc(hidden)]
+    name: String,
+    #[doc(hidden)]
+    codec: Arc<K>,
+    #[doc(hidden)]
+    outbox: Arc<Outbox>,
+    #[doc(hidden)]
+    routes: Arc<HashMap<&'static str, Route>>,
+    #[doc(hidden)]
+    reconnect_delay: Duration,
+    #[doc(hidden)]
+    #[cfg(feature = "signing")]
+    verifier: Option<Verifier>,
+}
+
+/// Implementation of the `EventBridge`.
+impl<K: Codec> EventBridge<K> {
+    /// Creates a bridge known to the broker as `name`, encoding events with `codec`, without any
+    /// forwarded or received event type.
+    pub fn new(name: impl Into<String>, codec: K) -> Self {
+        Self {
+            name: name.into(),
+            codec: Arc::new(codec),
+            outbox: Arc::new(Outbox::default()),
+            routes: Arc::new(HashMap::new()),
+            reconnect_delay: DEFAULT_RECONNECT_DELAY,
+            #[cfg(feature = "signing")]
+            verifier: None,
+        }
+    }
+
+    /// Receives the events of type `E` published by other processes, and dispatches them on the
+    /// local bus.
+    ///
+    /// A received event is acknowledged once dispatched, even if one of its handlers fails. An
+    /// event that cannot be decoded is acknowledged and dropped.
+    pub fn receive<E: Event + MessageName + DeserializeOwned>(mut self) -> Self {
+        let codec = self.codec.clone();
+        let route: Route = Arc::new(move |bus, payload| {
+            let event = codec.decode::<E>(&payload);
+            Box::pin(async move {
+                if let Ok(event) = event {
+                    // The event is acknowledged whether or not its handlers succeed
+                    let _ = RECEIVING.scope((), bus.dispatch(event)).await;
+                }
+            })
+        });
+        Arc::make_mut(&mut self.routes).insert(E::NAME, route);
+        self
+    }
+
+    /// Returns the handler forwarding the events of type `E` to the broker, to be registered on
+    /// the local bus.
+    ///
+    /// Events of a type that is also received are not forwarded back when the bridge dispatches
+    /// them.
+    pub fn forward<E: Event + MessageName + Serialize>(&self) -> ForwardEventHandler<K, E> {
+        ForwardEventHandler {
+            codec: self.codec.clone(),
+            outbox: self.outbox.clone(),
+            event: PhantomData,
+        }
+    }
+
+    /// Waits `delay` before reconnecting, instead of [DEFAULT_RECONNECT_DELAY].
+    pub fn with_reconnect_delay(mut self, delay: Duration) -> Self {
+        self.reconnect_delay = delay;
+        self
+    }
+
+    /// Signs each forwarded event with `signer`, for receivers
+    /// [verifying](EventBridge::with_verifier) the signatures of the events they receive.
+    ///
+    /// An event is signed when it is forwarded, and resent with the same signature, so it must
+    /// reach its receivers within their maximum age. The hello introducing the bridge to the
+    /// broker is signed as well, for a broker [verifying](EventBroker::with_verifier) it. See the
+    /// [signing](crate::signing) module for details.
+    #[cfg(feature = "signing")]
+    pub fn with_signer(self, signer: Signer) -> Self {
+        self.outbox.state.lock().unwrap().signer = Some(signer);
+        self
+    }
+
+    /// Only dispatches the received events signed by a key trusted by `verifier`.
+    ///
+    /// Events that are not signed, or whose signature is rejected, are acknowledged and dropped
+    /// without reaching the local bus. An event resent after its first delivery is rejected as a
+    /// replay, since it was dispatched already.
+    #[cfg(feature = "signing")]
+    pub fn with_verifier(mut self, verifier: Verifier) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+
+    /// Returns the number of forwarded events not yet acknowledged by the broker.
+    pub fn unacknowledged(&self) -> usize {
+        self.outbox.len()
+    }
+
+    /// Connects to the broker with `connect`, dispatching received events on `bus`, and
+    /// reconnects whenever the connection breaks or cannot be established.
+    ///
+    /// This method never returns: it is meant to be spawned, and stops when its task is aborted.
+    pub async fn run<F, T, Fut>(self, bus: EventBus, mut connect: F)
+    where
+        F: FnMut() -> Fut,
+        T: Transport,
+        Fut: Future<Output = Result<T, TransportError>>,
+    {
+        loop {
+            if let Ok(transport) = connect().await {
+                // The session ends with the connection, whatever the reason
+                let _ = self.session(&bus, Arc::new(transport)).await;
+            }
+            tokio::time::sleep(self.reconnect_delay).await;
+        }
+    }
+
+    /// Exchanges events with the broker until the connection breaks.
+    async fn session(
+        &self,
+        bus: &EventBus,
+        transport: Arc<impl Transport>,
+    ) -> Result<(), TransportError> {
+        transport
+            .send(self.sign_hello(control(FrameKind::Hello, &self.name)))
+            .await?;
+        for name in self.routes.keys() {
+            transport.send(control(FrameKind::Subscribe, name)).await?;
+        }
+
+        let sender = tokio::spawn(send_outbox(self.outbox.clone(), transport.clone()));
+        let result = self.receive_events(bus, transport.as_ref()).await;
+        sender.abort();
+        result
+    }
+
+    /// Signs the hello introducing the bridge to the broker with its signer, if any.
+    #[cfg(feature = "signing")]
+    fn sign_hello(&self, hello: Frame) -> Frame {
+        match &self.outbox.state.lock().unwrap().signer {
+            Some(signer) => Frame {
+                payload: signer.sign(&self.name, Vec::new()).to_bytes(),
+                ..hello
+            },
+            None => hello,
+        }
+    }
+
+    /// Returns the hello unchanged when signatures are not supported.
+    #[cfg(not(feature = "signing"))]
+    fn sign_hello(&self, hello: Frame) -> Frame {
+        hello
+    }
+
+    /// Dispatches the received events and handles the acknowledgements of the forwarded ones.
+    async fn receive_events(
+        &self,
+        bus: &EventBus,
+        transport: &impl Transport,
+    ) -> Result<(), TransportError> {
+        while let Some(frame) = transport.recv().await? {
+            match frame.kind {
+                FrameKind::Ack => self.outbox.ack(frame.correlation_id),
+                FrameKind::Event => {
+                    let route = self.routes.get(frame.name.as_str());
+                    if let (Some(route), Some(payload)) = (route, self.authenticate(&frame)) {
+                        route(bus.clone(), payload).await;
+                    }
+                    transport
+                        .send(ack(frame.correlation_id, frame.name))
+                        .await?;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the signed event of a received frame, or `None` if its signature is rejected.
+    #[cfg(feature = "signing")]
+    fn authenticate(&self, frame: &Frame) -> Option<Vec<u8>> {
+        let Some(verifier) = &self.verifier else {
+            return Some(frame.payload.clone());
+        };
+        let envelope = SignedEnvelope::from_bytes(&frame.payload).ok()?;
+        (envelope.name == frame.name && verifier.verify(&envelope).is_ok())
+            .then_some(envelope.payload)
+    }
+
+    /// Returns the event of a received frame when signatures are not supported.
+    #[cfg(not(feature = "signing"))]
+    fn authenticate(&self, frame: &Frame) -> Option<Vec<u8>> {
+        Some(frame.payload.clone())
+    }
+}
+
+/// Clone implementation for `EventBridge`
+impl<K: Codec> Clone for EventBridge<K> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            codec: self.codec.clone(),
+            outbox: self.outbox.clone(),
+            routes: self.routes.clone(),
+            reconnect_delay: self.reconnect_delay,
+            #[cfg(feature = "signing")]
+            verifier: self.verifier.clone(),
+        }
+    }
+}
+
+/// Debug implementation for `EventBridge`
+impl<K: Codec> Debug for EventBridge<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("EventBridge")
+            .field("name", &self.name)
+            .field("content_type", &self.codec.content_type())
+            .field("received", &self.routes.keys().collect::<Vec<_>>())
+            .field("unacknowledged", &self.outbox.len())
+            .field("reconnect_delay", &self.reconnect_delay)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Sends the forwarded events to the broker, starting with those sent over a previous connection
+/// and not acknowledged.
+async fn send_outbox(outbox: Arc<Outbox>, transport: Arc<impl Transport>) {
+    let mut next = 0;
+    loop {
+        for frame in outbox.events_from(next) {
+            next = frame.correlation_id + 1;
+            if transport.send(frame).await.is_err() {
+                return;
+            }
+        }
+        outbox.notify.notified().await;
+    }
+}
+
+/// Creates a frame without payload.
+fn control(kind: FrameKind, name: &str) -> Frame {
+    Frame {
+        correlation_id: 0,
+        kind,
+        name: name.to_string(),
+        principal: None,
+        deadline: None,
+        payload: Vec::new(),
+    }
+}
+
+/// Creates the acknowledgement of an event.
+fn ack(sequence: u64, name: String) -> Frame {
+    Frame {
+        correlation_id: sequence,
+        kind: FrameKind::Ack,
+        name,
+        principal: None,
+        deadline: None,
+        payload: Vec::new(),
+    }
+}
+
+/// The `ForwardEventHandler` hands the events of type `E` to an [EventBridge].
+///
+/// It is returned by [forward](EventBridge::forward). Handling an event only encodes it and
+/// queues it for the broker, so the dispatch does not wait for the broker.
+pub struct ForwardEventHandler<K: Codec, E> {
+    #[doc(hidden)]
+    codec: Arc<K>,
+    #[doc(hidden)]
+    outbox: Arc<Outbox>,
+    #[doc(hidden)]
+    event: PhantomData<fn(E)>,
+}
+
+/// EventHandler implementation for `ForwardEventHandler`
+#[async_trait]
+impl<K, E> EventHandler<E> for ForwardEventHandler<K, E>
+where
+    K: Codec,
+    E: Event + MessageName + Serialize,
+{
+    async fn handle(&self, event: E) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if RECEIVING.try_with(|_| ()).is_ok() {
+            return Ok(());
+        }
+        let payload = self.codec.encode(&event)?;
+        self.outbox.push(E::NAME, payload);
+        Ok(())
+    }
+}
+
+/// Debug implementation for `ForwardEventHandler`
+impl<K: Codec, E> Debug for ForwardEventHandler<K, E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("ForwardEventHandler")
+            .field("event", &type_name::<E>())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A bridge known to the broker.
+#[derive(Default)]
+struct Node {
+    events: HashSet<String>,
+    next_sequence: u64,
+    unacknowledged: BTreeMap<u64, Frame>,
+    connection: Option<NodeConnection>,
+}
+
+/// The connection of a bridge to the broker.
+struct NodeConnection {
+    id: u64,
+    sender: mpsc::UnboundedSender<Frame>,
+    close: Arc<Notify>,
+}
+
+/// The `EventBroker` routes the events published by each [EventBridge] to the other bridges
+/// receiving their type.
+///
+/// Clones of the broker share its state, so it can serve many connections at once.
+///
+/// # Example
+///
+/// ```no_run
+/// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// # rt.block_on(async {
+/// use qonduit::bridge::EventBroker;
+/// use tokio::net::TcpListener;
+///
+/// let listener = TcpListener::bind("0.0.0.0:7100").await.unwrap();
+/// EventBroker::new().serve_tcp(listener).await;
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct EventBroker {
+    #[doc(hidden)]
+    nodes: Arc<Mutex<HashMap<String, Node>>>,
+    #[doc(hidden)]
+    next_connection: Arc<AtomicU64>,
+    #[doc(hidden)]
+    max_unacknowledged: usize,
+    #[doc(hidden)]
+    #[cfg(feature = "signing")]
+    verifier: Option<Verifier>,
+}
+
+/// Implementation of the `EventBroker`.
+impl EventBroker {
+    /// Creates a broker without any known bridge.
+    pub fn new() -> Self {
+        Self {
+            nodes: Arc::new(Mutex::new(HashMap::new())),
+            next_connection: Arc::new(AtomicU64::new(0)),
+            max_unacknowledged: DEFAULT_MAX_UNACKNOWLEDGED,
+            #[cfg(feature = "signing")]
+            verifier: None,
+        }
+    }
+
+    /// Keeps at most `max_unacknowledged` events per bridge waiting for their acknowledgement,
+    /// instead of [DEFAULT_MAX_UNACKNOWLEDGED].
+    ///
+    /// When a bridge falls behind, for example while it is disconnected, the oldest events kept
+    /// for it are dropped to make room for the new ones, and never delivered to it.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `max_unacknowledged` is zero.
+    pub fn with_max_unacknowledged(mut self, max_unacknowledged: usize) -> Self {
+        assert!(
+            max_unacknowledged > 0,
+            "max_unacknowledged must be positive"
+        );
+        self.max_unacknowledged = max_unacknowledged;
+        self
+    }
+
+    /// Only accepts the bridges whose hello is signed by a key trusted by `verifier`.
+    ///
+    /// Each bridge must be [signing](EventBridge::with_signer) with a key trusted by the
+    /// verifier. A connection whose hello is not signed, or whose signature is rejected, is
+    /// closed with an [Unauthenticated](TransportError::Unauthenticated) error.
+    #[cfg(feature = "signing")]
+    pub fn with_verifier(mut self, verifier: Verifier) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+
+    /// Forgets the bridge named `name`, with its subscriptions and the events kept for it.
+    ///
+    /// The bridge is disconnected if it is connected, and known again from its next hello. Returns
+    /// whether the bridge was known.
+    pub fn forget(&self, name: &str) -> bool {
+        let node = self.nodes.lock().unwrap().remove(name);
+        if let Some(connection) = node.as_ref().and_then(|node| node.connection.as_ref()) {
+            connection.close.notify_one();
+        }
+        node.is_some()
+    }
+
+    /// Returns the number of events kept for the bridge named `name`, waiting for their
+    /// acknowledgement.
+    pub fn unacknowledged(&self, name: &str) -> usize {
+        let nodes = self.nodes.lock().unwrap();
+        nodes.get(name).map_or(0, |node| node.unacknowledged.len())
+    }
+
+    /// Serves the bridge connected over `transport` until the connection is closed, or the bridge
+    /// is [forgotten](EventBroker::forget).
+    ///
+    /// # Errors
+    ///
+    /// Returns the `TransportError` that ended the connection, if it did not close cleanly.
+    pub async fn serve(&self, transport: impl Transport) -> Result<(), TransportError> {
+        let transport = Arc::new(transport);
+        let connection = self.next_connection.fetch_add(1, Ordering::Relaxed);
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Frame>();
+        let writer = tokio::spawn({
+            let transport = transport.clone();
+            async move {
+                while let Some(frame) = receiver.recv().await {
+                    if transport.send(frame).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        let mut node = None;
+        let close = Arc::new(Notify::new());
+        let connection = NodeConnection {
+            id: connection,
+            sender,
+            close: close.clone(),
+        };
+        let result = {
+            let mut closed = pin!(close.notified());
+            let mut routing = pin!(self.route_frames(transport.as_ref(), &connection, &mut node));
+            poll_fn(|cx| match closed.as_mut().poll(cx) {
+                Poll::Ready(()) => Poll::Ready(Ok(())),
+                Poll::Pending => routing.as_mut().poll(cx),
+            })
+            .await
+        };
+        if let Some(node) = node {
+            let mut nodes = self.nodes.lock().unwrap();
+            if let Some(node) = nodes.get_mut(&node)
+                && node.connection.as_ref().map(|other| other.id) == Some(connection.id)
+            {
+                node.connection = None;
+            }
+        }
+        writer.abort();
+        result
+    }
+
+    /// Returns the names of the bridges receiving the events of the given stable name, sorted,
+    /// whether or not they are connected.
+    pub fn receivers(&self, event: &str) -> Vec<String> {
+        let nodes = self.nodes.lock().unwrap();
+        let mut receivers: Vec<_> = nodes
+            .iter()
+            .filter(|(_, node)| node.events.contains(event))
+            .map(|(name, _)| name.clone())
+            .collect();
+        receivers.sort();
+        receivers
+    }
+
+    /// Accepts TCP connections on `listener` and serves each of them on its own task, forever.
+    ///
+    /// Failures to accept a connection are handled as with
+    /// [RemoteServer::serve_tcp](crate::remote::RemoteServer::serve_tcp).
+    pub async fn serve_tcp(self, listener: TcpListener) {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let broker = self.clone();
+                    tokio::spawn(async move { broker.serve(TcpTransport::from(stream)).await });
+                }
+                Err(error) => accept_failed(error).await,
+            }
+        }
+    }
+
+    /// Accepts connections on the Unix domain socket `listener` and serves each of them on its
+    /// own task, forever.
+    ///
+    /// Failures to accept a connection are handled as with
+    /// [RemoteServer::serve_tcp](crate::remote::RemoteServer::serve_tcp).
+    #[cfg(unix)]
+    pub async fn serve_unix(self, listener: UnixListener) {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let broker = self.clone();
+                    tokio::spawn(async move { broker.serve(UnixTransport::from(stream)).await });
+                }
+                Err(error) => accept_failed(error).await,
+            }
+        }
+    }
+
+    /// Handles the frames of `connection`, whose bridge is `node` once it said hello.
+    async fn route_frames(
+        &self,
+        transport: &impl Transport,
+        connection: &NodeConnection,
+        node: &mut Option<String>,
+    ) -> Result<(), TransportError> {
+        while let Some(frame) = transport.recv().await? {
+            if frame.kind == FrameKind::Hello {
+                self.authenticate(&frame)?;
+                let mut nodes = self.nodes.lock().unwrap();
+                let entry = nodes.entry(frame.name.clone()).or_default();
+                entry.connection = Some(NodeConnection {
+                    id: connection.id,
+                    sender: connection.sender.clone(),
+                    close: connection.close.clone(),
+                });
+                for event in entry.unacknowledged.values() {
+                    let _ = connection.sender.send(event.clone());
+                }
+                *node = Some(frame.name);
+                continue;
+            }
+            // Frames are ignored until the bridge said hello
+            let Some(name) = node.as_ref() else {
+                continue;
+            };
+            let mut nodes = self.nodes.lock().unwrap();
+            // The bridge was forgotten while the frame was on its way
+            let Some(entry) = nodes.get_mut(name) else {
+                return Ok(());
+            };
+            match frame.kind {
+                FrameKind::Subscribe => {
+                    entry.events.insert(frame.name);
+                }
+                FrameKind::Ack => {
+                    entry.unacknowledged.remove(&frame.correlation_id);
+                }
+                FrameKind::Event => {
+                    let receivers = nodes.iter_mut().filter(|(other, entry)| {
+                        *other != name && entry.events.contains(&frame.name)
+                    });
+                    for (_, entry) in receivers {
+                        let sequence = entry.next_sequence;
+                        entry.next_sequence += 1;
+                        let event = Frame {
+                            correlation_id: sequence,
+                            ..frame.clone()
+                        };
+                        if let Some(connection) = &entry.connection {
+                            let _ = connection.sender.send(event.clone());
+                        }
+                        entry.unacknowledged.insert(sequence, event);
+                        if entry.unacknowledged.len() > self.max_unacknowledged {
+                            entry.unacknowledged.pop_first();
+                        }
+                    }
+                    let _ = connection
+                        .sender
+                        .send(ack(frame.correlation_id, frame.name));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Verifies the signature of the hello of a bridge.
+    #[cfg(feature = "signing")]
+    fn authenticate(&self, hello: &Frame) -> Result<(), TransportError> {
+        let Some(verifier) = &self.verifier else {
+            return Ok(());
+        };
+        let envelope = SignedEnvelope::from_bytes(&hello.payload)
+            .and_then(|envelope| verifier.verify(&envelope).map(|()| envelope));
+        match envelope {
+            Ok(envelope) if envelope.name == hello.name => Ok(()),
+            Ok(envelope) => Err(TransportError::Unauthenticated(format!(
+                "hello signed as {:?}",
+                envelope.name
+            ))),
+            Err(error) => Err(TransportError::Unauthenticated(error.to_string())),
+        }
+    }
+
+    /// Accepts every bridge when signatures are not supported.
+    #[cfg(not(feature = "signing"))]
+    fn authenticate(&self, _hello: &Frame) -> Result<(), TransportError> {
+        Ok(())
+    }
+}
+
+/// Default implementation for `EventBroker`
+impl Default for EventBroker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Debug implementation for `EventBroker`
+impl Debug for EventBroker {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        let nodes = self.nodes.lock().unwrap();
+        f.debug_struct("EventBroker")
+            .field("nodes", &nodes.keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
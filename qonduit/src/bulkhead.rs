@@ -0,0 +1,222 @@
+//! The `bulkhead` module caps how many messages of a type are processed concurrently.
+//!
+//! A bulkhead is configured per message type on a bus. Up to `max_concurrent` dispatches of that
+//! type run at once; further dispatches wait in a queue of at most `max_queued` entries, and
+//! dispatches arriving while the queue is full fail immediately with
+//! [DispatchError::BulkheadFull]. This keeps a single hot message type, or a handler backed by a
+//! small connection pool, from starving the rest of the process.
+//!
+//! On the event bus, each handler of the event type has its own bulkhead with the configured
+//! limits, so that a slow handler does not take the slots of the other handlers of the event.
+//!
+//! Time spent waiting in the queue counts towards the dispatch timeout.
+//!
+//! - [BulkheadConfig]: Concurrency and queue limits of a bulkhead.
+//! - [BulkheadStats]: A snapshot of the in-flight and queued dispatches of a bulkhead.
+
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use tokio::sync::Semaphore;
+
+use crate::error::DispatchError;
+
+/// The `BulkheadConfig` defines the concurrency and queue limits of a bulkhead.
+///
+/// # Example
+///
+/// ```
+/// use qonduit::bulkhead::BulkheadConfig;
+///
+/// // Four concurrent executions, with up to sixteen more waiting for a slot.
+/// let config = BulkheadConfig::new(4).with_max_queued(16);
+/// # drop(config);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BulkheadConfig {
+    #[doc(hidden)]
+    max_concurrent: usize,
+    #[doc(hidden)]
+    max_queued: usize,
+}
+
+/// Implementation of the `BulkheadConfig`.
+impl BulkheadConfig {
+    /// Creates a configuration allowing `max_concurrent` executions at once and no queueing.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `max_concurrent` is zero.
+    pub fn new(max_concurrent: usize) -> Self {
+        assert!(
+            max_concurrent > 0,
+            "Bulkhead must allow at least one execution"
+        );
+        Self {
+            max_concurrent,
+            max_queued: 0,
+        }
+    }
+
+    /// Sets how many dispatches may wait for a free slot before new ones are rejected.
+    pub fn with_max_queued(mut self, max_queued: usize) -> Self {
+        self.max_queued = max_queued;
+        self
+    }
+}
+
+/// A snapshot of the dispatches currently held by a bulkhead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BulkheadStats {
+    /// Dispatches currently executing their handler.
+    pub in_flight: usize,
+    /// Dispatches currently waiting for a free slot.
+    pub queued: usize,
+}
+
+/// A bulkhead limiting the concurrent dispatches of a single message type or handler.
+#[derive(Debug)]
+struct Bulkhead {
+    config: BulkheadConfig,
+    semaphore: Semaphore,
+    queued: AtomicUsize,
+}
+
+impl Bulkhead {
+    fn new(config: BulkheadConfig) -> Self {
+        Self {
+            config,
+            semaphore: Semaphore::new(config.max_concurrent),
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    fn stats(&self) -> BulkheadStats {
+        BulkheadStats {
+            in_flight: self.config.max_concurrent - self.semaphore.available_permits(),
+            queued: self.queued.load(Ordering::SeqCst),
+        }
+    }
+}
+
+/// Releases a queue slot when the waiting dispatch obtains a permit or is cancelled.
+struct QueueSlot<'a>(&'a AtomicUsize);
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The bulkheads of a bus, keyed by message type.
+///
+/// A message type has one bulkhead per handler: a single one for commands and queries, and one
+/// for each registered handler of an event, so that a slow handler does not hold the slots of the
+/// other handlers of the same event.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Bulkheads {
+    bulkheads: HashMap<TypeId, Vec<Arc<Bulkhead>>>,
+}
+
+impl Bulkheads {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Installs a bulkhead for messages of type `T`, replacing any previous one.
+    pub(crate) fn set<T: 'static>(&mut self, config: BulkheadConfig) {
+        self.set_per_handler::<T>(1, config);
+    }
+
+    /// Installs a separate bulkhead for each of the `handlers` handlers of messages of type `T`,
+    /// replacing any previous ones.
+    pub(crate) fn set_per_handler<T: 'static>(&mut self, handlers: usize, config: BulkheadConfig) {
+        let bulkheads = (0..handlers)
+            .map(|_| Arc::new(Bulkhead::new(config)))
+            .collect();
+        self.bulkheads.insert(TypeId::of::<T>(), bulkheads);
+    }
+
+    /// Returns the current counters of the bulkheads for messages of type `T`, summed over their
+    /// handlers, if any.
+    pub(crate) fn stats<T: 'static>(&self) -> Option<BulkheadStats> {
+        let bulkheads = self.bulkheads.get(&TypeId::of::<T>())?;
+        let stats = bulkheads.iter().map(|bulkhead| bulkhead.stats()).fold(
+            BulkheadStats {
+                in_flight: 0,
+                queued: 0,
+            },
+            |total, stats| BulkheadStats {
+                in_flight: total.in_flight + stats.in_flight,
+                queued: total.queued + stats.queued,
+            },
+        );
+        Some(stats)
+    }
+
+    /// Returns the current counters of the bulkhead of the handler at `index` for messages of type
+    /// `T`, if any.
+    pub(crate) fn handler_stats<T: 'static>(&self, index: usize) -> Option<BulkheadStats> {
+        self.bulkheads
+            .get(&TypeId::of::<T>())
+            .and_then(|bulkheads| bulkheads.get(index))
+            .map(|bulkhead| bulkhead.stats())
+    }
+
+    /// Runs `future` once the bulkhead for messages of type `T`, if any, has a free slot.
+    pub(crate) async fn run<T, R, E, F>(&self, future: F) -> Result<R, DispatchError<E>>
+    where
+        T: 'static,
+        F: Future<Output = Result<R, DispatchError<E>>>,
+    {
+        self.run_handler::<T, R, E, F>(0, future).await
+    }
+
+    /// Runs `future` once the bulkhead of the handler at `index` for messages of type `T`, if any,
+    /// has a free slot.
+    pub(crate) async fn run_handler<T, R, E, F>(
+        &self,
+        index: usize,
+        future: F,
+    ) -> Result<R, DispatchError<E>>
+    where
+        T: 'static,
+        F: Future<Output = Result<R, DispatchError<E>>>,
+    {
+        let Some(bulkhead) = self
+            .bulkheads
+            .get(&TypeId::of::<T>())
+            .and_then(|bulkheads| bulkheads.get(index))
+        else {
+            return future.await;
+        };
+
+        let _permit = match bulkhead.semaphore.try_acquire() {
+            Ok(permit) => permit,
+            Err(_) => {
+                let max_queued = bulkhead.config.max_queued;
+                let reserved =
+                    bulkhead
+                        .queued
+                        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |queued| {
+                            (queued < max_queued).then_some(queued + 1)
+                        });
+                if reserved.is_err() {
+                    return Err(DispatchError::BulkheadFull);
+                }
+
+                let _slot = QueueSlot(&bulkhead.queued);
+                bulkhead
+                    .semaphore
+                    .acquire()
+                    .await
+                    .expect("Bulkhead semaphore is never closed")
+            }
+        };
+        future.await
+    }
+}
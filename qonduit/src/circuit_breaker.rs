@@ -0,0 +1,414 @@
+//! The `circuit_breaker` module stops dispatching to handlers whose dependencies are failing.
+//!
+//! A circuit breaker is configured per message type on the `CommandBus` and `QueryBus`. It tracks
+//! the outcome of every dispatch over a rolling time window and moves through three states:
+//!
+//! - [Closed](CircuitState::Closed): dispatches reach the handler. When the failure rate over the
+//!   window reaches the threshold, the circuit opens.
+//! - [Open](CircuitState::Open): dispatches fail fast with [DispatchError::CircuitOpen] without
+//!   invoking the handler. After the open duration elapses, the circuit becomes half-open.
+//! - [HalfOpen](CircuitState::HalfOpen): a limited number of trial dispatches reach the handler.
+//!   If all of them succeed the circuit closes, and a single failure opens it again.
+//!
+//! Handler errors and timeouts count as failures, while dispatches rejected by the bus for other
+//! reasons are not recorded. Transitions can be observed with
+//! [CircuitBreakerConfig::on_state_change], for example to raise an alert.
+//!
+//! - [CircuitBreakerConfig]: Thresholds and callbacks of a circuit breaker.
+//! - [CircuitState]: The state of a circuit breaker.
+//! - [StateTransition]: Describes a change of state passed to the callbacks.
+
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::error::DispatchError;
+
+/// The state of a circuit breaker.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CircuitState {
+    /// Dispatches reach the handler and their outcomes are recorded.
+    Closed,
+    /// Dispatches fail fast without reaching the handler.
+    Open,
+    /// A limited number of trial dispatches reach the handler.
+    HalfOpen,
+}
+
+/// Describes a change of state of a circuit breaker.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateTransition {
+    /// The stable name of the message the circuit breaker protects, or its type name if it has
+    /// none.
+    pub message_type: &'static str,
+    /// The state before the transition.
+    pub from: CircuitState,
+    /// The state after the transition.
+    pub to: CircuitState,
+}
+
+type StateChangeCallback = Arc<dyn Fn(&StateTransition) + Send + Sync>;
+
+/// The `CircuitBreakerConfig` defines when a circuit breaker opens and how it recovers.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use qonduit::circuit_breaker::CircuitBreakerConfig;
+///
+/// let config = CircuitBreakerConfig::new()
+///     .with_failure_rate_threshold(0.5)
+///     .with_minimum_calls(20)
+///     .with_window(Duration::from_secs(60))
+///     .with_open_duration(Duration::from_secs(30))
+///     .on_state_change(|transition| {
+///         eprintln!(
+///             "circuit for {} moved from {:?} to {:?}",
+///             transition.message_type, transition.from, transition.to
+///         );
+///     });
+/// # drop(config);
+/// ```
+#[derive(Clone)]
+pub struct CircuitBreakerConfig {
+    #[doc(hidden)]
+    failure_rate_threshold: f64,
+    #[doc(hidden)]
+    minimum_calls: usize,
+    #[doc(hidden)]
+    window: Duration,
+    #[doc(hidden)]
+    open_duration: Duration,
+    #[doc(hidden)]
+    half_open_calls: usize,
+    #[doc(hidden)]
+    callbacks: Vec<StateChangeCallback>,
+}
+
+/// Implementation of the `CircuitBreakerConfig`.
+impl CircuitBreakerConfig {
+    /// Creates a configuration that opens when half of at least 10 calls within the last minute
+    /// failed, stays open for 30 seconds, then lets a single trial call through.
+    pub fn new() -> Self {
+        Self {
+            failure_rate_threshold: 0.5,
+            minimum_calls: 10,
+            window: Duration::from_secs(60),
+            open_duration: Duration::from_secs(30),
+            half_open_calls: 1,
+            callbacks: Vec::new(),
+        }
+    }
+
+    /// Sets the failure rate, between `0.0` and `1.0`, at which the circuit opens.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the threshold is outside of `0.0..=1.0`.
+    pub fn with_failure_rate_threshold(mut self, threshold: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&threshold),
+            "Failure rate threshold must be between 0.0 and 1.0, got {threshold}"
+        );
+        self.failure_rate_threshold = threshold;
+        self
+    }
+
+    /// Sets the number of calls the window must contain before the failure rate is evaluated.
+    pub fn with_minimum_calls(mut self, calls: usize) -> Self {
+        self.minimum_calls = calls.max(1);
+        self
+    }
+
+    /// Sets the rolling time window over which the failure rate is computed.
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Sets how long the circuit stays open before letting trial calls through.
+    pub fn with_open_duration(mut self, duration: Duration) -> Self {
+        self.open_duration = duration;
+        self
+    }
+
+    /// Sets how many consecutive trial calls must succeed in the half-open state to close the circuit.
+    pub fn with_half_open_calls(mut self, calls: usize) -> Self {
+        self.half_open_calls = calls.max(1);
+        self
+    }
+
+    /// Registers a callback invoked on every state transition.
+    ///
+    /// Callbacks run synchronously on the dispatching task, so they should return quickly.
+    pub fn on_state_change(
+        mut self,
+        callback: impl Fn(&StateTransition) + Send + Sync + 'static,
+    ) -> Self {
+        self.callbacks.push(Arc::new(callback));
+        self
+    }
+}
+
+/// Default implementation for `CircuitBreakerConfig`
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Debug implementation for `CircuitBreakerConfig`
+impl Debug for CircuitBreakerConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("CircuitBreakerConfig")
+            .field("failure_rate_threshold", &self.failure_rate_threshold)
+            .field("minimum_calls", &self.minimum_calls)
+            .field("window", &self.window)
+            .field("open_duration", &self.open_duration)
+            .field("half_open_calls", &self.half_open_calls)
+            .finish()
+    }
+}
+
+/// The mutable part of a circuit breaker, guarded by a mutex.
+#[derive(Debug)]
+struct Inner {
+    state: CircuitState,
+    /// Outcomes recorded while closed, as `(completed at, succeeded)`.
+    outcomes: VecDeque<(Instant, bool)>,
+    opened_at: Instant,
+    /// Trial calls started in the current half-open period.
+    trials_started: usize,
+    /// Trial calls that succeeded in the current half-open period.
+    trials_succeeded: usize,
+}
+
+/// A circuit breaker protecting the handler of a single message type.
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    message_type: &'static str,
+    config: CircuitBreakerConfig,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    fn new(message_type: &'static str, config: CircuitBreakerConfig) -> Self {
+        Self {
+            message_type,
+            config,
+            inner: Mutex::new(Inner {
+                state: CircuitState::Closed,
+                outcomes: VecDeque::new(),
+                opened_at: Instant::now(),
+                trials_started: 0,
+                trials_succeeded: 0,
+            }),
+        }
+    }
+
+    /// Returns the current state, moving from open to half-open if the open duration elapsed.
+    fn state(&self) -> CircuitState {
+        let mut inner = self.inner.lock().unwrap();
+        let transition = self.expire_open(&mut inner);
+        let state = inner.state;
+        drop(inner);
+        self.notify(transition);
+        state
+    }
+
+    /// Decides whether a call may proceed, returning whether it is a half-open trial.
+    fn try_acquire(&self) -> Option<bool> {
+        let mut inner = self.inner.lock().unwrap();
+        let transition = self.expire_open(&mut inner);
+        let permit = match inner.state {
+            CircuitState::Closed => Some(false),
+            CircuitState::Open => None,
+            CircuitState::HalfOpen if inner.trials_started < self.config.half_open_calls => {
+                inner.trials_started += 1;
+                Some(true)
+            }
+            CircuitState::HalfOpen => None,
+        };
+        drop(inner);
+        self.notify(transition);
+        permit
+    }
+
+    /// Records the outcome of a call that was allowed by [CircuitBreaker::try_acquire].
+    fn record(&self, trial: bool, success: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        let now = Instant::now();
+        let transition = match inner.state {
+            CircuitState::Closed if !trial => {
+                inner.outcomes.push_back((now, success));
+                self.prune(&mut inner, now);
+                let calls = inner.outcomes.len();
+                let failures = inner.outcomes.iter().filter(|(_, ok)| !ok).count();
+                let rate = failures as f64 / calls as f64;
+                if calls >= self.config.minimum_calls && rate >= self.config.failure_rate_threshold
+                {
+                    self.transition(&mut inner, CircuitState::Open)
+                } else {
+                    None
+                }
+            }
+            CircuitState::HalfOpen if trial && !success => {
+                self.transition(&mut inner, CircuitState::Open)
+            }
+            CircuitState::HalfOpen if trial => {
+                inner.trials_succeeded += 1;
+                if inner.trials_succeeded >= self.config.half_open_calls {
+                    self.transition(&mut inner, CircuitState::Closed)
+                } else {
+                    None
+                }
+            }
+            // Outcomes of calls started in an earlier state are stale.
+            _ => None,
+        };
+        drop(inner);
+        self.notify(transition);
+    }
+
+    /// Gives back a trial slot of a call that was cancelled before completing.
+    fn release(&self, trial: bool) {
+        let mut inner = self.inner.lock().unwrap();
+        if trial && inner.state == CircuitState::HalfOpen {
+            inner.trials_started = inner.trials_started.saturating_sub(1);
+        }
+    }
+
+    fn expire_open(&self, inner: &mut Inner) -> Option<StateTransition> {
+        if inner.state == CircuitState::Open
+            && inner.opened_at.elapsed() >= self.config.open_duration
+        {
+            self.transition(inner, CircuitState::HalfOpen)
+        } else {
+            None
+        }
+    }
+
+    fn prune(&self, inner: &mut Inner, now: Instant) {
+        while let Some((at, _)) = inner.outcomes.front() {
+            if now.saturating_duration_since(*at) > self.config.window {
+                inner.outcomes.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn transition(&self, inner: &mut Inner, to: CircuitState) -> Option<StateTransition> {
+        let from = inner.state;
+        inner.state = to;
+        inner.outcomes.clear();
+        inner.trials_started = 0;
+        inner.trials_succeeded = 0;
+        if to == CircuitState::Open {
+            inner.opened_at = Instant::now();
+        }
+        Some(StateTransition {
+            message_type: self.message_type,
+            from,
+            to,
+        })
+    }
+
+    fn notify(&self, transition: Option<StateTransition>) {
+        if let Some(transition) = transition {
+            for callback in &self.config.callbacks {
+                callback(&transition);
+            }
+        }
+    }
+}
+
+/// Tracks a call allowed by a circuit breaker until its outcome is recorded.
+struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    trial: bool,
+    recorded: bool,
+}
+
+impl Permit<'_> {
+    fn record(mut self, success: bool) {
+        self.recorded = true;
+        self.breaker.record(self.trial, success);
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if !self.recorded {
+            self.breaker.release(self.trial);
+        }
+    }
+}
+
+/// The circuit breakers of a bus, keyed by message type.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CircuitBreakers {
+    breakers: HashMap<TypeId, Arc<CircuitBreaker>>,
+}
+
+impl CircuitBreakers {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Installs a circuit breaker for messages of type `T`, named `message_type` in its state
+    /// transitions, replacing any previous one.
+    pub(crate) fn set<T: 'static>(
+        &mut self,
+        message_type: &'static str,
+        config: CircuitBreakerConfig,
+    ) {
+        let breaker = CircuitBreaker::new(message_type, config);
+        self.breakers.insert(TypeId::of::<T>(), Arc::new(breaker));
+    }
+
+    /// Returns the state of the circuit breaker for messages of type `T`, if any.
+    pub(crate) fn state<T: 'static>(&self) -> Option<CircuitState> {
+        self.breakers
+            .get(&TypeId::of::<T>())
+            .map(|breaker| breaker.state())
+    }
+
+    /// Runs `future` through the circuit breaker for messages of type `T`, if any.
+    pub(crate) async fn run<T, R, E, F>(&self, future: F) -> Result<R, DispatchError<E>>
+    where
+        T: 'static,
+        F: Future<Output = Result<R, DispatchError<E>>>,
+    {
+        let Some(breaker) = self.breakers.get(&TypeId::of::<T>()) else {
+            return future.await;
+        };
+        let Some(trial) = breaker.try_acquire() else {
+            return Err(DispatchError::CircuitOpen);
+        };
+
+        let permit = Permit {
+            breaker,
+            trial,
+            recorded: false,
+        };
+        let result = future.await;
+        match &result {
+            Ok(_) => permit.record(true),
+            Err(DispatchError::Handler(_) | DispatchError::Timeout(_)) => permit.record(false),
+            // Rejections by other parts of the bus say nothing about the handler's health.
+            Err(_) => drop(permit),
+        }
+        result
+    }
+}
@@ -0,0 +1,232 @@
+//! The `codec` module converts messages and their outcomes to and from bytes.
+//!
+//! This module is available with the `serde` feature. A [Codec] encodes any `serde::Serialize`
+//! value, such as a command, query or event, its response, its handler error or a whole
+//! `DispatchError`, and decodes it back. Anything persisting or transporting messages is generic
+//! over the codec, so the wire format can be chosen per use case:
+//!
+//! - [JsonCodec]: Human-readable JSON, always available with the `serde` feature.
+//! - [MessagePackCodec]: Compact MessagePack, with the `msgpack` feature.
+//! - [BincodeCodec]: Fast bincode, with the `bincode` feature. Bincode is not self-describing, so
+//!   it cannot decode types relying on `deserialize_any`, such as `serde_json::Value` or untagged
+//!   enums.
+//! - [CborCodec]: Self-describing binary CBOR, with the `cbor` feature.
+//!
+//! The [round_trip] and [assert_round_trip] helpers check in tests that a type survives encoding
+//! and decoding with a given codec.
+
+use std::error::Error;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+/// The `CodecError` is returned when a value cannot be encoded or decoded.
+#[derive(Debug)]
+pub enum CodecError {
+    /// The value could not be encoded.
+    Encode(Box<dyn Error + Send + Sync>),
+
+    /// The bytes could not be decoded into a value of the requested type.
+    Decode(Box<dyn Error + Send + Sync>),
+}
+
+/// Display implementation for `CodecError`
+impl Display for CodecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        match self {
+            CodecError::Encode(error) => write!(f, "cannot encode value: {error}"),
+            CodecError::Decode(error) => write!(f, "cannot decode value: {error}"),
+        }
+    }
+}
+
+/// Error implementation for `CodecError`
+impl Error for CodecError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CodecError::Encode(error) | CodecError::Decode(error) => Some(error.as_ref()),
+        }
+    }
+}
+
+/// The `Codec` trait defines a wire format for messages and their outcomes.
+///
+/// # Example
+///
+/// ```
+/// use qonduit::codec::{Codec, JsonCodec};
+///
+/// #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+/// struct AddProductCommand {
+///     name: String,
+///     price: f64,
+/// }
+///
+/// let command = AddProductCommand { name: "Keyboard".to_string(), price: 59.99 };
+/// let bytes = JsonCodec.encode(&command).unwrap();
+/// assert_eq!(bytes, br#"{"name":"Keyboard","price":59.99}"#);
+///
+/// let decoded: AddProductCommand = JsonCodec.decode(&bytes).unwrap();
+/// assert_eq!(decoded, command);
+/// ```
+pub trait Codec: Send + Sync + 'static {
+    /// The media type of the encoded bytes, for example `application/json`.
+    fn content_type(&self) -> &'static str;
+
+    /// Encodes `value` into bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns [Encode](CodecError::Encode) if `value` cannot be represented in the format.
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, CodecError>;
+
+    /// Decodes a value of type `T` from `bytes`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [Decode](CodecError::Decode) if `bytes` are not a valid encoding of a `T`.
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError>;
+}
+
+/// A codec encoding values as JSON.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonCodec;
+
+/// Codec implementation for `JsonCodec`
+impl Codec for JsonCodec {
+    fn content_type(&self) -> &'static str {
+        "application/json"
+    }
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        serde_json::to_vec(value).map_err(|error| CodecError::Encode(error.into()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        serde_json::from_slice(bytes).map_err(|error| CodecError::Decode(error.into()))
+    }
+}
+
+/// A codec encoding values as MessagePack.
+///
+/// Structs are encoded as maps keyed by field name, so fields can be added or reordered without
+/// breaking previously encoded values.
+#[cfg(feature = "msgpack")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MessagePackCodec;
+
+/// Codec implementation for `MessagePackCodec`
+#[cfg(feature = "msgpack")]
+impl Codec for MessagePackCodec {
+    fn content_type(&self) -> &'static str {
+        "application/msgpack"
+    }
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        rmp_serde::to_vec_named(value).map_err(|error| CodecError::Encode(error.into()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        rmp_serde::from_slice(bytes).map_err(|error| CodecError::Decode(error.into()))
+    }
+}
+
+/// A codec encoding values with bincode, using its standard configuration.
+#[cfg(feature = "bincode")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BincodeCodec;
+
+/// Codec implementation for `BincodeCodec`
+#[cfg(feature = "bincode")]
+impl Codec for BincodeCodec {
+    fn content_type(&self) -> &'static str {
+        "application/x-bincode"
+    }
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        bincode::serde::encode_to_vec(value, bincode::config::standard())
+            .map_err(|error| CodecError::Encode(error.into()))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        bincode::serde::decode_from_slice(bytes, bincode::config::standard())
+            .map(|(value, _)| value)
+            .map_err(|error| CodecError::Decode(error.into()))
+    }
+}
+
+/// A codec encoding values as CBOR.
+#[cfg(feature = "cbor")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CborCodec;
+
+/// Codec implementation for `CborCodec`
+#[cfg(feature = "cbor")]
+impl Codec for CborCodec {
+    fn content_type(&self) -> &'static str {
+        "application/cbor"
+    }
+
+    fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(value, &mut bytes)
+            .map_err(|error| CodecError::Encode(error.into()))?;
+        Ok(bytes)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, CodecError> {
+        ciborium::from_reader(bytes).map_err(|error| CodecError::Decode(error.into()))
+    }
+}
+
+/// Encodes `value` with `codec` and decodes the result back.
+///
+/// # Errors
+///
+/// Returns the error of the failed encoding or decoding.
+pub fn round_trip<C, T>(codec: &C, value: &T) -> Result<T, CodecError>
+where
+    C: Codec,
+    T: Serialize + DeserializeOwned,
+{
+    codec.decode(&codec.encode(value)?)
+}
+
+/// Asserts that `value` is unchanged after encoding and decoding it with `codec`.
+///
+/// # Panics
+///
+/// This function will panic if `value` cannot be encoded or decoded, or if the decoded value is
+/// not equal to `value`.
+///
+/// # Example
+///
+/// ```
+/// use qonduit::codec::{JsonCodec, assert_round_trip};
+/// use qonduit::error::DispatchError;
+///
+/// let error: DispatchError<String> = DispatchError::Handler("out of stock".to_string());
+/// assert_round_trip(&JsonCodec, &error);
+/// ```
+pub fn assert_round_trip<C, T>(codec: &C, value: &T)
+where
+    C: Codec,
+    T: Serialize + DeserializeOwned + PartialEq + Debug,
+{
+    match round_trip(codec, value) {
+        Ok(decoded) => assert_eq!(
+            &decoded,
+            value,
+            "value changed after a round trip through {}",
+            codec.content_type()
+        ),
+        Err(error) => panic!(
+            "round trip through {} failed: {error}",
+            codec.content_type()
+        ),
+    }
+}
@@ -16,11 +16,76 @@
 //! - [CommandHandlerRegistry]: Manages the collection of command handlers.
 
 use std::any::Any;
+#[cfg(any(feature = "audit", feature = "dead-letter", feature = "queue"))]
+use std::error::Error;
 use std::fmt::Debug;
+use std::hash::Hash;
 use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(any(feature = "dead-letter", feature = "queue", feature = "remote"))]
+use serde::Serialize;
+#[cfg(any(feature = "dead-letter", feature = "remote"))]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "dead-letter")]
+use serde_json::Value;
 
 use crate::async_trait;
+#[cfg(feature = "audit")]
+use crate::audit;
+#[cfg(feature = "audit")]
+use crate::audit::AuditRecord;
+#[cfg(feature = "audit")]
+use crate::audit::AuditSink;
+#[cfg(feature = "audit")]
+use crate::audit::Auditor;
+use crate::authorization;
+use crate::authorization::Policies;
+use crate::authorization::Policy;
+use crate::authorization::Principal;
+use crate::bulkhead::BulkheadConfig;
+use crate::bulkhead::BulkheadStats;
+use crate::bulkhead::Bulkheads;
+use crate::circuit_breaker::CircuitBreakerConfig;
+use crate::circuit_breaker::CircuitBreakers;
+use crate::circuit_breaker::CircuitState;
+#[cfg(feature = "remote")]
+use crate::codec::Codec;
+#[cfg(feature = "dead-letter")]
+use crate::dead_letter;
+#[cfg(feature = "dead-letter")]
+use crate::dead_letter::DeadLetterQueue;
+#[cfg(feature = "dead-letter")]
+use crate::dead_letter::DeadLetters;
+#[cfg(feature = "dead-letter")]
+use crate::dead_letter::MessageKind;
+#[cfg(feature = "dead-letter")]
+use crate::dead_letter::Replay;
+use crate::error::DispatchError;
+#[cfg(feature = "tracing")]
+use crate::instrumentation;
+#[cfg(feature = "tracing")]
+use crate::instrumentation::Instrumentation;
+#[cfg(any(feature = "dead-letter", feature = "queue", feature = "remote"))]
+use crate::message::MessageName;
+#[cfg(feature = "metrics")]
+use crate::metrics;
+#[cfg(feature = "serde")]
+use crate::named::NamedDispatchError;
+#[cfg(feature = "queue")]
+use crate::queue::CommandQueue;
+use crate::rate_limit::RateLimitConfig;
+use crate::rate_limit::RateLimiters;
 use crate::registry::CommandHandlerRegistry;
+#[cfg(feature = "remote")]
+use crate::routing::Router;
+#[cfg(feature = "remote")]
+use crate::routing::Routes;
+#[cfg(feature = "opentelemetry")]
+use crate::telemetry;
+use crate::timeout::Timeouts;
+use crate::validation::Validate;
+use crate::validation::Validators;
 
 /// The `Command` trait defines an operation that modifies the system state.
 ///
@@ -196,6 +261,33 @@ pub trait CommandHandler<C: Command>: Send + Sync {
 pub struct CommandBus {
     #[doc(hidden)]
     registry: Arc<CommandHandlerRegistry>,
+    #[doc(hidden)]
+    timeouts: Arc<Timeouts>,
+    #[doc(hidden)]
+    circuit_breakers: Arc<CircuitBreakers>,
+    #[doc(hidden)]
+    bulkheads: Arc<Bulkheads>,
+    #[doc(hidden)]
+    validators: Arc<Validators>,
+    #[doc(hidden)]
+    rate_limiters: Arc<RateLimiters>,
+    #[doc(hidden)]
+    policies: Arc<Policies>,
+    #[doc(hidden)]
+    #[cfg(feature = "tracing")]
+    instrumentation: Arc<Instrumentation>,
+    #[doc(hidden)]
+    #[cfg(feature = "audit")]
+    auditor: Arc<Auditor>,
+    #[doc(hidden)]
+    #[cfg(feature = "remote")]
+    routes: Arc<Routes>,
+    #[doc(hidden)]
+    #[cfg(feature = "queue")]
+    queue: Option<CommandQueue>,
+    #[doc(hidden)]
+    #[cfg(feature = "dead-letter")]
+    dead_letters: Arc<DeadLetters<CommandBus>>,
 }
 
 /// Implementation of the `CommandBus`.
@@ -223,9 +315,694 @@ impl CommandBus {
     pub fn new(registry: CommandHandlerRegistry) -> Self {
         Self {
             registry: Arc::new(registry),
+            timeouts: Arc::new(Timeouts::new()),
+            circuit_breakers: Arc::new(CircuitBreakers::new()),
+            bulkheads: Arc::new(Bulkheads::new()),
+            validators: Arc::new(Validators::new()),
+            rate_limiters: Arc::new(RateLimiters::new()),
+            policies: Arc::new(Policies::new()),
+            #[cfg(feature = "tracing")]
+            instrumentation: Arc::new(Instrumentation::new()),
+            #[cfg(feature = "audit")]
+            auditor: Arc::new(Auditor::new()),
+            #[cfg(feature = "remote")]
+            routes: Arc::new(Routes::new()),
+            #[cfg(feature = "queue")]
+            queue: None,
+            #[cfg(feature = "dead-letter")]
+            dead_letters: Arc::new(DeadLetters::new(MessageKind::Command)),
         }
     }
 
+    /// Sends an audit record of every dispatched command to `sink`, replacing any previous sink.
+    ///
+    /// Commands rejected before reaching their handler are recorded as well. Payloads are only
+    /// recorded for command types enabled with [with_audit_payload](CommandBus::with_audit_payload).
+    /// See the [audit](crate::audit) module for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    /// # rt.block_on(async {
+    /// # use qonduit::async_trait;
+    /// # use qonduit::command::{Command, CommandHandler};
+    /// #
+    /// # #[derive(Debug, serde::Serialize)]
+    /// # struct ChangePasswordCommand { user_id: u64, password: String }
+    /// #
+    /// # impl Command for ChangePasswordCommand {
+    /// #   type Response = ();
+    /// #   type Error = ();
+    /// # }
+    /// #
+    /// # struct ChangePasswordCommandHandler;
+    /// #
+    /// # #[async_trait]
+    /// # impl CommandHandler<ChangePasswordCommand> for ChangePasswordCommandHandler {
+    /// #   async fn handle(&self, _command: ChangePasswordCommand) -> Result<(), ()> {
+    /// #     Ok(())
+    /// #   }
+    /// # }
+    /// use serde_json::json;
+    /// use qonduit::audit::InMemoryAuditSink;
+    /// use qonduit::command::CommandBus;
+    /// use qonduit::registry::CommandHandlerRegistry;
+    ///
+    /// let mut registry = CommandHandlerRegistry::new();
+    /// registry.register::<ChangePasswordCommand>(ChangePasswordCommandHandler);
+    ///
+    /// let sink = InMemoryAuditSink::new();
+    /// let command_bus = CommandBus::new(registry)
+    ///     .with_audit_sink(sink.clone())
+    ///     .with_audit_payload::<ChangePasswordCommand>()
+    ///     .with_audit_redaction::<ChangePasswordCommand>(["password"]);
+    ///
+    /// let command = ChangePasswordCommand { user_id: 7, password: "hunter2".to_string() };
+    /// command_bus.dispatch(command).await.unwrap();
+    ///
+    /// let record = &sink.records()[0];
+    /// assert_eq!(record.outcome, "ok");
+    /// assert_eq!(record.payload, Some(json!({ "user_id": 7, "password": "[REDACTED]" })));
+    /// # });
+    /// ```
+    #[cfg(feature = "audit")]
+    pub fn with_audit_sink(mut self, sink: impl AuditSink + 'static) -> Self {
+        Arc::make_mut(&mut self.auditor).set_sink(sink);
+        self
+    }
+
+    /// Registers a callback invoked with every audit record that the sink fails to store, and the
+    /// error of the sink, replacing any previous callback.
+    ///
+    /// The dispatch does not fail when its record cannot be stored, since its command already ran.
+    /// The callback runs on the dispatching task, so it should return quickly.
+    #[cfg(feature = "audit")]
+    pub fn on_audit_failure(
+        mut self,
+        callback: impl Fn(&AuditRecord, &(dyn Error + Send + Sync)) + Send + Sync + 'static,
+    ) -> Self {
+        Arc::make_mut(&mut self.auditor).on_failure(callback);
+        self
+    }
+
+    /// Records the serialized payload of commands of type `C` in their audit records.
+    #[cfg(feature = "audit")]
+    pub fn with_audit_payload<C: Command + serde::Serialize>(mut self) -> Self {
+        Arc::make_mut(&mut self.auditor).record_payload::<C>();
+        self
+    }
+
+    /// Masks fields of the recorded payload of commands of type `C` with
+    /// [REDACTED](crate::audit::REDACTED).
+    ///
+    /// Fields are addressed by their dotted path in the serialized command, for example `password`
+    /// or `card.number`; array elements are addressed by index, for example `payees.0.iban`.
+    #[cfg(feature = "audit")]
+    pub fn with_audit_redaction<C: Command>(
+        mut self,
+        paths: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Arc::make_mut(&mut self.auditor).redact::<C>(paths.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Records the `Debug` rendering of every dispatched command in its tracing span.
+    ///
+    /// Commands may contain personal or secret data, so this is disabled by default. Use
+    /// [without_message_recording](CommandBus::without_message_recording) to withhold specific
+    /// command types. See the [instrumentation](crate::instrumentation) module for the recorded
+    /// fields.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use qonduit::command::Command;
+    /// #
+    /// # #[derive(Debug)]
+    /// # struct ChangePasswordCommand;
+    /// #
+    /// # impl Command for ChangePasswordCommand {
+    /// #   type Response = ();
+    /// #   type Error = ();
+    /// # }
+    /// use qonduit::command::CommandBus;
+    /// use qonduit::registry::CommandHandlerRegistry;
+    ///
+    /// let command_bus = CommandBus::new(CommandHandlerRegistry::new())
+    ///     .with_message_recording()
+    ///     .without_message_recording::<ChangePasswordCommand>();
+    /// # drop(command_bus);
+    /// ```
+    #[cfg(feature = "tracing")]
+    pub fn with_message_recording(mut self) -> Self {
+        Arc::make_mut(&mut self.instrumentation).record_messages();
+        self
+    }
+
+    /// Never records the `Debug` rendering of commands of type `C` in tracing spans.
+    #[cfg(feature = "tracing")]
+    pub fn without_message_recording<C: Command>(mut self) -> Self {
+        Arc::make_mut(&mut self.instrumentation).skip_message::<C>();
+        self
+    }
+
+    /// Authorizes commands of type `C` with `policy` before dispatching them to their handler.
+    ///
+    /// The policy is evaluated against the principal of the dispatch, and a command it denies is
+    /// rejected with [DispatchError::Forbidden] without invoking the handler. This replaces any
+    /// policy previously set for `C`. See the [authorization](crate::authorization) module for
+    /// details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    /// # rt.block_on(async {
+    /// # use qonduit::async_trait;
+    /// # use qonduit::command::{Command, CommandHandler};
+    /// #
+    /// # #[derive(Debug)]
+    /// # struct DeleteProductCommand { id: u64 }
+    /// #
+    /// # impl Command for DeleteProductCommand {
+    /// #   type Response = ();
+    /// #   type Error = ();
+    /// # }
+    /// #
+    /// # struct DeleteProductCommandHandler;
+    /// #
+    /// # #[async_trait]
+    /// # impl CommandHandler<DeleteProductCommand> for DeleteProductCommandHandler {
+    /// #   async fn handle(&self, _command: DeleteProductCommand) -> Result<(), ()> {
+    /// #     Ok(())
+    /// #   }
+    /// # }
+    /// use qonduit::authorization::{has_role, Forbidden, Principal};
+    /// use qonduit::command::CommandBus;
+    /// use qonduit::error::DispatchError;
+    /// use qonduit::registry::CommandHandlerRegistry;
+    ///
+    /// let mut registry = CommandHandlerRegistry::new();
+    /// registry.register::<DeleteProductCommand>(DeleteProductCommandHandler);
+    /// let command_bus = CommandBus::new(registry).with_policy::<DeleteProductCommand>(has_role("admin"));
+    ///
+    /// let admin = Principal::new("u1").with_role("admin");
+    /// let clerk = Principal::new("u2").with_role("clerk");
+    ///
+    /// assert_eq!(command_bus.dispatch_as(admin, DeleteProductCommand { id: 7 }).await, Ok(()));
+    /// assert_eq!(
+    ///     command_bus.dispatch_as(clerk, DeleteProductCommand { id: 7 }).await,
+    ///     Err(DispatchError::Forbidden(Forbidden::Denied))
+    /// );
+    /// # });
+    /// ```
+    pub fn with_policy<C: Command>(mut self, policy: impl Policy<C> + 'static) -> Self {
+        Arc::make_mut(&mut self.policies).set::<C>(policy);
+        self
+    }
+
+    /// Denies every command type without a policy.
+    ///
+    /// Dispatching a command whose type has no policy fails with
+    /// [DispatchError::Forbidden] instead of reaching its handler. Use
+    /// [allow_all](crate::authorization::allow_all) to mark command types as public.
+    pub fn with_deny_by_default(mut self) -> Self {
+        Arc::make_mut(&mut self.policies).deny_by_default();
+        self
+    }
+
+    /// Validates commands of type `C` before dispatching them to their handler.
+    ///
+    /// A command failing [Validate::validate] is rejected with [DispatchError::Invalid] and the
+    /// handler is not invoked. See the [validation](crate::validation) module for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    /// # rt.block_on(async {
+    /// # use qonduit::async_trait;
+    /// # use qonduit::command::{Command, CommandHandler};
+    /// #
+    /// # #[derive(Debug)]
+    /// # struct FindProductsByNameCommand { name: String }
+    /// #
+    /// # impl Command for FindProductsByNameCommand {
+    /// #   type Response = Vec<u64>;
+    /// #   type Error = ();
+    /// # }
+    /// #
+    /// # struct FindProductsByNameCommandHandler;
+    /// #
+    /// # #[async_trait]
+    /// # impl CommandHandler<FindProductsByNameCommand> for FindProductsByNameCommandHandler {
+    /// #   async fn handle(&self, _message: FindProductsByNameCommand) -> Result<Vec<u64>, ()> {
+    /// #     Ok(vec![])
+    /// #   }
+    /// # }
+    /// use qonduit::error::DispatchError;
+    /// use qonduit::command::CommandBus;
+    /// use qonduit::registry::CommandHandlerRegistry;
+    /// use qonduit::validation::{Validate, Violation, Violations};
+    ///
+    /// impl Validate for FindProductsByNameCommand {
+    ///     fn validate(&self) -> Result<(), Violations> {
+    ///         let mut violations = Violations::new();
+    ///         if self.name.is_empty() {
+    ///             violations.push(Violation::new("name", "required", "must not be empty"));
+    ///         }
+    ///         violations.into_result()
+    ///     }
+    /// }
+    ///
+    /// let mut registry = CommandHandlerRegistry::new();
+    /// registry.register::<FindProductsByNameCommand>(FindProductsByNameCommandHandler);
+    /// let command_bus = CommandBus::new(registry).with_validation::<FindProductsByNameCommand>();
+    ///
+    /// let result = command_bus.dispatch(FindProductsByNameCommand { name: String::new() }).await;
+    /// assert!(matches!(result, Err(DispatchError::Invalid(_))));
+    /// # });
+    /// ```
+    pub fn with_validation<C: Command + Validate>(mut self) -> Self {
+        Arc::make_mut(&mut self.validators).set::<C>();
+        self
+    }
+
+    /// Sets the timeout applied to every command type without a dedicated timeout.
+    ///
+    /// A dispatch exceeding its timeout fails with [DispatchError::Timeout] and the handler future
+    /// is dropped. See the [timeout](crate::timeout) module for how deadlines propagate to nested
+    /// dispatches.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use qonduit::command::CommandBus;
+    /// use qonduit::registry::CommandHandlerRegistry;
+    ///
+    /// let command_bus = CommandBus::new(CommandHandlerRegistry::new())
+    ///     .with_default_timeout(Duration::from_secs(30));
+    /// # drop(command_bus);
+    /// ```
+    pub fn with_default_timeout(mut self, timeout: Duration) -> Self {
+        Arc::make_mut(&mut self.timeouts).set_default(timeout);
+        self
+    }
+
+    /// Sets the timeout applied to commands of type `C`, overriding the default timeout.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use qonduit::command::Command;
+    /// #
+    /// # #[derive(Debug)]
+    /// # struct AddProductCommand;
+    /// #
+    /// # impl Command for AddProductCommand {
+    /// #   type Response = u64;
+    /// #   type Error = ();
+    /// # }
+    /// use std::time::Duration;
+    /// use qonduit::command::CommandBus;
+    /// use qonduit::registry::CommandHandlerRegistry;
+    ///
+    /// let command_bus = CommandBus::new(CommandHandlerRegistry::new())
+    ///     .with_timeout::<AddProductCommand>(Duration::from_millis(500));
+    /// # drop(command_bus);
+    /// ```
+    pub fn with_timeout<C: Command>(mut self, timeout: Duration) -> Self {
+        Arc::make_mut(&mut self.timeouts).set::<C>(timeout);
+        self
+    }
+
+    /// Protects the handler of commands of type `C` with a circuit breaker.
+    ///
+    /// While the circuit is open, dispatching a `C` fails with [DispatchError::CircuitOpen]
+    /// without invoking the handler. Clones of the bus share the state of the circuit breaker.
+    /// See the [circuit_breaker](crate::circuit_breaker) module for the state machine.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use qonduit::command::Command;
+    /// #
+    /// # #[derive(Debug)]
+    /// # struct SyncInventoryCommand;
+    /// #
+    /// # impl Command for SyncInventoryCommand {
+    /// #   type Response = ();
+    /// #   type Error = ();
+    /// # }
+    /// use std::time::Duration;
+    /// use qonduit::circuit_breaker::{CircuitBreakerConfig, CircuitState};
+    /// use qonduit::command::CommandBus;
+    /// use qonduit::registry::CommandHandlerRegistry;
+    ///
+    /// let command_bus = CommandBus::new(CommandHandlerRegistry::new()).with_circuit_breaker::<SyncInventoryCommand>(
+    ///     CircuitBreakerConfig::new()
+    ///         .with_failure_rate_threshold(0.25)
+    ///         .with_open_duration(Duration::from_secs(10)),
+    /// );
+    ///
+    /// assert_eq!(command_bus.circuit_state::<SyncInventoryCommand>(), Some(CircuitState::Closed));
+    /// ```
+    pub fn with_circuit_breaker<C: Command>(mut self, config: CircuitBreakerConfig) -> Self {
+        let message_type = self.registry.message_name::<C>();
+        Arc::make_mut(&mut self.circuit_breakers).set::<C>(message_type, config);
+        self
+    }
+
+    /// Returns the state of the circuit breaker protecting commands of type `C`.
+    ///
+    /// Returns `None` if no circuit breaker is configured for `C`.
+    pub fn circuit_state<C: Command>(&self) -> Option<CircuitState> {
+        self.circuit_breakers.state::<C>()
+    }
+
+    /// Limits how many commands of type `C` are handled concurrently.
+    ///
+    /// Dispatches beyond the limit wait in a bounded queue, and fail with
+    /// [DispatchError::BulkheadFull] once the queue is full. Clones of the bus share the bulkhead.
+    /// See the [bulkhead](crate::bulkhead) module for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use qonduit::command::Command;
+    /// #
+    /// # #[derive(Debug)]
+    /// # struct SyncInventoryCommand;
+    /// #
+    /// # impl Command for SyncInventoryCommand {
+    /// #   type Response = ();
+    /// #   type Error = ();
+    /// # }
+    /// use qonduit::bulkhead::BulkheadConfig;
+    /// use qonduit::command::CommandBus;
+    /// use qonduit::registry::CommandHandlerRegistry;
+    ///
+    /// let command_bus = CommandBus::new(CommandHandlerRegistry::new())
+    ///     .with_bulkhead::<SyncInventoryCommand>(BulkheadConfig::new(4).with_max_queued(16));
+    ///
+    /// let stats = command_bus.bulkhead_stats::<SyncInventoryCommand>().unwrap();
+    /// assert_eq!((stats.in_flight, stats.queued), (0, 0));
+    /// ```
+    pub fn with_bulkhead<C: Command>(mut self, config: BulkheadConfig) -> Self {
+        Arc::make_mut(&mut self.bulkheads).set::<C>(config);
+        self
+    }
+
+    /// Returns the in-flight and queued dispatch counts of the bulkhead for commands of type `C`.
+    ///
+    /// Returns `None` if no bulkhead is configured for `C`.
+    pub fn bulkhead_stats<C: Command>(&self) -> Option<BulkheadStats> {
+        self.bulkheads.stats::<C>()
+    }
+
+    /// Limits how often commands of type `C` may be dispatched.
+    ///
+    /// All commands of type `C` share a single token bucket. Once it is empty, dispatching fails
+    /// with [DispatchError::RateLimited] without invoking the handler. Clones of the bus share the
+    /// bucket. See the [rate_limit](crate::rate_limit) module for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use qonduit::command::Command;
+    /// #
+    /// # #[derive(Debug)]
+    /// # struct SendNewsletterCommand;
+    /// #
+    /// # impl Command for SendNewsletterCommand {
+    /// #   type Response = ();
+    /// #   type Error = ();
+    /// # }
+    /// use std::time::Duration;
+    /// use qonduit::command::CommandBus;
+    /// use qonduit::rate_limit::RateLimitConfig;
+    /// use qonduit::registry::CommandHandlerRegistry;
+    ///
+    /// let command_bus = CommandBus::new(CommandHandlerRegistry::new())
+    ///     .with_rate_limit::<SendNewsletterCommand>(RateLimitConfig::new(1, Duration::from_secs(3600)));
+    /// # drop(command_bus);
+    /// ```
+    pub fn with_rate_limit<C: Command>(mut self, config: RateLimitConfig) -> Self {
+        Arc::make_mut(&mut self.rate_limiters).set::<C>(config);
+        self
+    }
+
+    /// Limits how often commands of type `C` may be dispatched for each key extracted by `key`.
+    ///
+    /// Every distinct key gets its own token bucket, so callers identified by different keys
+    /// (for example, different users or tenants) do not share a budget. The buckets of the least
+    /// recently seen keys are dropped beyond [with_max_keys](RateLimitConfig::with_max_keys) keys.
+    /// This replaces any limit previously set for `C`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use qonduit::command::{Command, CommandBus};
+    /// use qonduit::rate_limit::RateLimitConfig;
+    /// use qonduit::registry::CommandHandlerRegistry;
+    ///
+    /// #[derive(Debug)]
+    /// struct PlaceOrderCommand {
+    ///     tenant_id: u64,
+    ///     sku: String,
+    /// }
+    ///
+    /// impl Command for PlaceOrderCommand {
+    ///     type Response = u64;
+    ///     type Error = ();
+    /// }
+    ///
+    /// let command_bus = CommandBus::new(CommandHandlerRegistry::new()).with_rate_limit_by_key(
+    ///     RateLimitConfig::new(10, Duration::from_secs(1)),
+    ///     |command: &PlaceOrderCommand| command.tenant_id,
+    /// );
+    /// # drop(command_bus);
+    /// ```
+    pub fn with_rate_limit_by_key<C, K>(
+        mut self,
+        config: RateLimitConfig,
+        key: impl Fn(&C) -> K + Send + Sync + 'static,
+    ) -> Self
+    where
+        C: Command,
+        K: Hash + Eq + Clone + Send + 'static,
+    {
+        Arc::make_mut(&mut self.rate_limiters).set_keyed(config, key);
+        self
+    }
+
+    /// Routes commands of type `C` with `router`.
+    ///
+    /// Each dispatch looks up the route of `C` in the current [RoutingTable](crate::routing::RoutingTable):
+    /// commands routed to a remote node are forwarded to it, and the others are handled locally.
+    /// A forwarded command goes through the layers of this bus, from its policy to its audit, and
+    /// is sent in place of calling the local handler, which does not need to be registered. The
+    /// principal of [dispatch_as](CommandBus::dispatch_as) and the remaining deadline are sent
+    /// with it. See the [routing](crate::routing) module for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # #[derive(Debug, serde::Serialize, serde::Deserialize)]
+    /// # struct GenerateReportCommand { month: u32 }
+    /// # impl qonduit::command::Command for GenerateReportCommand {
+    /// #   type Response = String;
+    /// #   type Error = String;
+    /// # }
+    /// # impl qonduit::message::MessageName for GenerateReportCommand {
+    /// #   const NAME: &'static str = "reports.generate.v1";
+    /// # }
+    /// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    /// # rt.block_on(async {
+    /// use qonduit::codec::JsonCodec;
+    /// use qonduit::command::CommandBus;
+    /// use qonduit::registry::CommandHandlerRegistry;
+    /// use qonduit::remote::{MemoryTransport, RemoteCommandBus};
+    /// use qonduit::routing::{Route, Router, RoutingTable};
+    ///
+    /// # let (transport, _server) = MemoryTransport::pair();
+    /// let table = RoutingTable::new()
+    ///     .with_route("reports.generate.v1", Route::Node("reports".to_string()));
+    /// let router =
+    ///     Router::new(table).with_node("reports", RemoteCommandBus::new(transport, JsonCodec));
+    ///
+    /// let command_bus = CommandBus::new(CommandHandlerRegistry::new())
+    ///     .with_routing::<GenerateReportCommand, _>(&router);
+    /// # drop(command_bus);
+    /// # });
+    /// ```
+    #[cfg(feature = "remote")]
+    pub fn with_routing<C, K>(mut self, router: &Router<K>) -> Self
+    where
+        C: Command + MessageName + Serialize,
+        C::Response: DeserializeOwned + 'static,
+        C::Error: DeserializeOwned + 'static,
+        K: Codec,
+    {
+        Arc::make_mut(&mut self.routes).set::<C, K>(router.clone());
+        self
+    }
+
+    /// Routes commands of type `C` with `router`, choosing among the nodes of a
+    /// [ConsistentHash](crate::routing::Route::ConsistentHash) route by the key extracted by `key`.
+    ///
+    /// Commands with the same key are handled by the same node as long as it stays in the route.
+    /// Otherwise behaves like [with_routing](CommandBus::with_routing).
+    #[cfg(feature = "remote")]
+    pub fn with_routing_by_key<C, K, H>(
+        mut self,
+        router: &Router<K>,
+        key: impl Fn(&C) -> H + Send + Sync + 'static,
+    ) -> Self
+    where
+        C: Command + MessageName + Serialize,
+        C::Response: DeserializeOwned + 'static,
+        C::Error: DeserializeOwned + 'static,
+        K: Codec,
+        H: Hash,
+    {
+        Arc::make_mut(&mut self.routes).set_keyed::<C, K, H>(router.clone(), key);
+        self
+    }
+
+    /// Saves the commands passed to [enqueue](CommandBus::enqueue) to `queue`, replacing any
+    /// previous queue.
+    ///
+    /// The commands are executed by a [WorkerPool](crate::queue::WorkerPool) reading the same
+    /// queue. See the [queue](crate::queue) module for details.
+    #[cfg(feature = "queue")]
+    pub fn with_queue(mut self, queue: CommandQueue) -> Self {
+        self.queue = Some(queue);
+        self
+    }
+
+    /// Saves `command` to the queue of the bus to be executed asynchronously, and returns the id
+    /// of its job.
+    ///
+    /// The command is dispatched later by a [WorkerPool](crate::queue::WorkerPool), through a bus
+    /// where `C` is registered with
+    /// [register_json](crate::registry::CommandHandlerRegistry::register_json), and its outcome is
+    /// polled with [status](crate::queue::CommandQueue::status). The current
+    /// [principal](crate::authorization::current_principal), if any, is saved with the command,
+    /// which is dispatched on its behalf.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be serialized or saved.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the bus has no queue, see [with_queue](CommandBus::with_queue).
+    #[cfg(feature = "queue")]
+    pub async fn enqueue<C>(&self, command: C) -> Result<String, Box<dyn Error + Send + Sync>>
+    where
+        C: Command + MessageName + Serialize,
+    {
+        let Some(queue) = &self.queue else {
+            panic!("No queue configured to enqueue command: {:?}", C::NAME);
+        };
+        queue.push(&command).await
+    }
+
+    /// Saves `command` to the queue of the bus to be executed asynchronously on behalf of
+    /// `principal`, and returns the id of its job.
+    ///
+    /// # Errors
+    ///
+    /// The same as [enqueue](CommandBus::enqueue).
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the bus has no queue, see [with_queue](CommandBus::with_queue).
+    #[cfg(feature = "queue")]
+    pub async fn enqueue_as<C>(
+        &self,
+        principal: Principal,
+        command: C,
+    ) -> Result<String, Box<dyn Error + Send + Sync>>
+    where
+        C: Command + MessageName + Serialize,
+    {
+        authorization::scope(principal, self.enqueue(command)).await
+    }
+
+    /// Records the failed dispatches of the command types enabled with
+    /// [with_dead_letters](CommandBus::with_dead_letters) to `queue`, replacing any previous
+    /// queue.
+    ///
+    /// See the [dead_letter](crate::dead_letter) module for details.
+    #[cfg(feature = "dead-letter")]
+    pub fn with_dead_letter_queue(mut self, queue: DeadLetterQueue) -> Self {
+        Arc::make_mut(&mut self.dead_letters).set_queue(queue);
+        self
+    }
+
+    /// Records the failed dispatches of commands of type `C` as dead letters.
+    ///
+    /// The command is serialized before it is dispatched, and its dead letter is saved under
+    /// [MessageName::NAME] with the debug representation of the error. Commands rejected before
+    /// reaching their handler are not recorded.
+    #[cfg(feature = "dead-letter")]
+    pub fn with_dead_letters<C>(mut self) -> Self
+    where
+        C: Command + MessageName + Serialize + DeserializeOwned,
+    {
+        Arc::make_mut(&mut self.dead_letters).set::<C>(C::NAME, Self::replay::<C>, 1);
+        self
+    }
+
+    /// Dispatches the command of the dead letter `id` again, removes the dead letter if the
+    /// dispatch succeeds, and returns whether the dead letter was found.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the dispatch if it fails again, in which case the dead letter is kept
+    /// with its attempts incremented, or an error if the dead letter cannot be loaded or its
+    /// command type is not enabled on this bus.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the bus has no dead letter queue, see
+    /// [with_dead_letter_queue](CommandBus::with_dead_letter_queue).
+    #[cfg(feature = "dead-letter")]
+    pub async fn replay_dead_letter(&self, id: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        self.dead_letters.replay(self, id).await
+    }
+
+    /// Dispatches the command of type `C` serialized in `payload`.
+    #[cfg(feature = "dead-letter")]
+    fn replay<C>(&self, _handler_index: usize, payload: Value) -> Replay<'_>
+    where
+        C: Command + DeserializeOwned,
+    {
+        Box::pin(async move {
+            let command = serde_json::from_value::<C>(payload)?;
+            match dead_letter::replaying(self.dispatch(command)).await {
+                Ok(_) => Ok(()),
+                Err(error) => Err(error.describe().into()),
+            }
+        })
+    }
+
+    /// Returns whether commands of type `C` have a handler registered, or are routed to a remote
+    /// node.
+    #[cfg(feature = "axum")]
+    pub(crate) fn handles<C: Command>(&self) -> bool {
+        #[cfg(feature = "remote")]
+        if self.routes.contains::<C>() {
+            return true;
+        }
+        self.registry.handler_type_name::<C>().is_some()
+    }
+
     /// Dispatches a command to its corresponding handler and returns the result of the command execution.
     ///
     /// # Arguments
@@ -236,6 +1013,16 @@ impl CommandBus {
     ///
     /// The result from the command handler, either containing the response data of the command execution or an error.
     ///
+    /// # Errors
+    ///
+    /// - [DispatchError::Handler] with the handler's error if the command fails.
+    /// - [DispatchError::Forbidden] if the principal of the dispatch is not allowed to dispatch the command.
+    /// - [DispatchError::Invalid] if validation is enabled for the command type and the command is invalid.
+    /// - [DispatchError::RateLimited] if the rate limit of the command type is exhausted.
+    /// - [DispatchError::CircuitOpen] if the circuit breaker of the command type is open.
+    /// - [DispatchError::Timeout] if the handler does not complete within the configured timeout.
+    /// - [DispatchError::BulkheadFull] if the bulkhead of the command type is full.
+    ///
     /// # Panics
     ///
     /// This method will panic if no handler is registered for the command type.
@@ -314,15 +1101,210 @@ impl CommandBus {
     /// }
     /// # });
     /// ```
-    pub async fn dispatch<C: Command>(&self, command: C) -> Result<C::Response, C::Error> {
-        match self.registry.get_handler::<C>() {
-            None => {
+    pub async fn dispatch<C: Command>(
+        &self,
+        command: C,
+    ) -> Result<C::Response, DispatchError<C::Error>> {
+        let handler = self.registry.get_handler::<C>();
+        #[cfg(feature = "remote")]
+        let routed = self.routes.contains::<C>();
+        #[cfg(not(feature = "remote"))]
+        let routed = false;
+        match handler {
+            None if !routed => {
+                #[cfg(feature = "metrics")]
+                metrics::missing_handler("command", self.registry.message_name::<C>());
                 panic!(
                     "No handler registered for command: {:?}",
-                    std::any::type_name::<C>()
+                    self.registry.message_name::<C>()
                 );
             }
-            Some(handler) => handler.handle(command).await,
+            handler => {
+                #[cfg(feature = "tracing")]
+                let span = self.instrumentation.span(
+                    "command",
+                    self.registry.message_name::<C>(),
+                    self.registry.handler_type_name::<C>(),
+                    &command,
+                );
+                #[cfg(feature = "audit")]
+                let pending_record = self
+                    .auditor
+                    .start(self.registry.message_name::<C>(), &command);
+                #[cfg(feature = "dead-letter")]
+                let pending_letter = self.dead_letters.capture(&command);
+                // Boxed, so that the resilience layers do not add up with the layers below in the
+                // future of every caller
+                let dispatch = Box::pin(async {
+                    self.policies.authorize(&command)?;
+                    self.validators.validate(&command)?;
+                    self.rate_limiters.acquire(&command)?;
+                    let handle = async {
+                        // A routed command is forwarded once the local layers let it through
+                        #[cfg(feature = "remote")]
+                        let command = match self.routes.forward(command) {
+                            Ok(outcome) => return outcome.await,
+                            Err(command) => command,
+                        };
+                        let Some(handler) = handler else {
+                            panic!(
+                                "No handler registered for command: {:?}",
+                                self.registry.message_name::<C>()
+                            );
+                        };
+                        handler
+                            .handle(command)
+                            .await
+                            .map_err(DispatchError::Handler)
+                    };
+                    self.circuit_breakers
+                        .run::<C, _, _, _>(
+                            self.timeouts
+                                .run::<C, _, _, _>(self.bulkheads.run::<C, _, _, _>(handle)),
+                        )
+                        .await
+                });
+                #[cfg(feature = "dead-letter")]
+                let dispatch = dead_letter::record_failure(
+                    pending_letter,
+                    self.registry.handler_type_name::<C>().unwrap_or_default(),
+                    dispatch,
+                );
+                #[cfg(feature = "audit")]
+                let dispatch = audit::audit(pending_record, dispatch);
+                #[cfg(feature = "opentelemetry")]
+                let dispatch = telemetry::trace(
+                    "command",
+                    self.registry.message_name::<C>(),
+                    self.registry.handler_type_name::<C>(),
+                    dispatch,
+                );
+                #[cfg(feature = "metrics")]
+                let dispatch =
+                    metrics::measure("command", self.registry.message_name::<C>(), dispatch);
+                #[cfg(feature = "tracing")]
+                let dispatch = instrumentation::instrument(span, dispatch);
+                dispatch.await
+            }
         }
     }
+
+    /// Dispatches a command on behalf of `principal`.
+    ///
+    /// The policy of the command type is evaluated against `principal`, which is also visible to
+    /// the handler through [current_principal](crate::authorization::current_principal) and
+    /// inherited by every dispatch made while handling the command.
+    ///
+    /// # Errors
+    ///
+    /// The same as [dispatch](CommandBus::dispatch).
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if no handler is registered for the command type.
+    pub async fn dispatch_as<C: Command>(
+        &self,
+        principal: Principal,
+        command: C,
+    ) -> Result<C::Response, DispatchError<C::Error>> {
+        authorization::scope(principal, self.dispatch(command)).await
+    }
+
+    /// Dispatches the JSON `payload` as the command type registered under `name` with
+    /// [register_json](crate::registry::CommandHandlerRegistry::register_json).
+    ///
+    /// The command goes through the same checks as with [dispatch](CommandBus::dispatch), and its
+    /// response is returned as JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns [UnknownName](NamedDispatchError::UnknownName) if no command type is registered under
+    /// `name`, [InvalidPayload](NamedDispatchError::InvalidPayload) if `payload` cannot be
+    /// deserialized into it, and [Dispatch](NamedDispatchError::Dispatch) with the handler error
+    /// serialized as JSON if the dispatch fails.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    /// # rt.block_on(async {
+    /// # use qonduit::async_trait;
+    /// # use qonduit::command::{Command, CommandHandler};
+    /// #
+    /// # #[derive(Debug, serde::Deserialize)]
+    /// # struct AddProductCommand { name: String }
+    /// #
+    /// # impl Command for AddProductCommand {
+    /// #   type Response = u64;
+    /// #   type Error = String;
+    /// # }
+    /// #
+    /// # impl qonduit::message::MessageName for AddProductCommand {
+    /// #   const NAME: &'static str = "products.add";
+    /// # }
+    /// #
+    /// # struct AddProductCommandHandler;
+    /// #
+    /// # #[async_trait]
+    /// # impl CommandHandler<AddProductCommand> for AddProductCommandHandler {
+    /// #   async fn handle(&self, command: AddProductCommand) -> Result<u64, String> {
+    /// #     match command.name.is_empty() {
+    /// #       true => Err("name is empty".to_string()),
+    /// #       false => Ok(42),
+    /// #     }
+    /// #   }
+    /// # }
+    /// use serde_json::json;
+    /// use qonduit::command::CommandBus;
+    /// use qonduit::error::DispatchError;
+    /// use qonduit::named::NamedDispatchError;
+    /// use qonduit::registry::CommandHandlerRegistry;
+    ///
+    /// let mut registry = CommandHandlerRegistry::new();
+    /// registry.register_json::<AddProductCommand>(AddProductCommandHandler);
+    /// let command_bus = CommandBus::new(registry);
+    ///
+    /// let response = command_bus
+    ///     .dispatch_json("products.add", json!({ "name": "Keyboard" }))
+    ///     .await
+    ///     .unwrap();
+    /// assert_eq!(response, json!(42));
+    ///
+    /// let error = command_bus
+    ///     .dispatch_json("products.add", json!({ "name": "" }))
+    ///     .await
+    ///     .unwrap_err();
+    /// assert!(matches!(
+    ///     error,
+    ///     NamedDispatchError::Dispatch(DispatchError::Handler(error)) if error == json!("name is empty")
+    /// ));
+    /// # });
+    /// ```
+    #[cfg(feature = "serde")]
+    pub async fn dispatch_json(
+        &self,
+        name: &str,
+        payload: serde_json::Value,
+    ) -> Result<serde_json::Value, NamedDispatchError> {
+        let Some(dispatch) = self.registry.named.get(name) else {
+            return Err(NamedDispatchError::UnknownName(name.to_string()));
+        };
+        dispatch(self, payload).await
+    }
+
+    /// Dispatches the JSON `payload` as the command type registered under `name`, on behalf of
+    /// `principal`.
+    ///
+    /// # Errors
+    ///
+    /// The same as [dispatch_json](CommandBus::dispatch_json).
+    #[cfg(feature = "serde")]
+    pub async fn dispatch_json_as(
+        &self,
+        principal: Principal,
+        name: &str,
+        payload: serde_json::Value,
+    ) -> Result<serde_json::Value, NamedDispatchError> {
+        authorization::scope(principal, self.dispatch_json(name, payload)).await
+    }
 }
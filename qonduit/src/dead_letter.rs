@@ -0,0 +1,828 @@
+//! The `dead_letter` module keeps the events and commands whose handlers failed, so that they can
+//! be replayed once the cause of the failure is fixed.
+//!
+//! This module is available with the `dead-letter` feature. A bus given a [DeadLetterQueue] with
+//! `with_dead_letter_queue` records a [DeadLetter] for the message types enabled with
+//! `with_dead_letters`, holding the message serialized as JSON, the handler that failed, the error
+//! and the number of attempts:
+//!
+//! - On the `EventBus`, each handler of an event is attempted up to the maximum number of attempts
+//!   of its type. An event whose handler still fails is recorded, and the dispatch goes on with the
+//!   remaining handlers instead of failing.
+//! - On the `CommandBus`, a failed dispatch is recorded, and its error is still returned to the
+//!   caller. Commands rejected before reaching their handler, because they are invalid, forbidden
+//!   or rate limited, are not recorded, since they would be rejected again.
+//!
+//! Dead letters are listed, inspected and discarded through the [DeadLetterQueue], and replayed
+//! with `replay_dead_letter` on the bus that recorded them: an event is handed to the handler that
+//! failed only, found by its position in the registration order of the handlers of its type, and a
+//! command is dispatched again. A replayed message is removed from the queue
+//! once handled, and kept with its attempts incremented if it fails again. The message is replayed
+//! on behalf of the [Principal] of its original dispatch, if any, so that the policy of its type
+//! is evaluated against the same identity, whoever replays it.
+//!
+//! A dead letter that cannot be saved is lost, and the failure is logged with the `tracing`
+//! feature: the dispatch of an event then fails with the error of its handler, and the dispatch
+//! of a command returns its error as usual.
+//!
+//! Dead letters are kept in a [DeadLetterStore]:
+//!
+//! - [InMemoryDeadLetterStore]: Keeps dead letters in memory, mainly for tests.
+//! - [FileDeadLetterStore]: Keeps dead letters as JSON files in a directory.
+//! - `SqliteDeadLetterStore`: Keeps dead letters in a SQLite database. It is available with the
+//!   `sqlite` feature.
+//!
+//! - [DeadLetterQueue]: Records the failed messages, and lists, inspects and discards them.
+//! - [DeadLetter]: A failed message.
+//! - [MessageKind]: Whether a dead letter is an event or a command.
+
+use std::any::Any;
+use std::any::TypeId;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::SystemTime;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::async_trait;
+use crate::authorization;
+use crate::authorization::Principal;
+use crate::error::DispatchError;
+use crate::util;
+use crate::util::blocking;
+use crate::util::unix_millis;
+
+tokio::task_local! {
+    static REPLAYING: Cell<bool>;
+}
+
+/// The `MessageKind` tells whether a [DeadLetter] is an event or a command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageKind {
+    /// An event whose handler failed.
+    Event,
+
+    /// A command whose dispatch failed.
+    Command,
+}
+
+/// Display implementation for `MessageKind`
+impl Display for MessageKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        match self {
+            MessageKind::Event => write!(f, "event"),
+            MessageKind::Command => write!(f, "command"),
+        }
+    }
+}
+
+/// The `DeadLetter` is a message whose handler failed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// The unique id of the dead letter. Ids sort by the time of the first failure.
+    pub id: String,
+
+    /// Whether the message is an event or a command.
+    pub kind: MessageKind,
+
+    /// The stable name of the message type.
+    pub name: String,
+
+    /// The message serialized as JSON.
+    pub payload: Value,
+
+    /// The type name of the handler that failed, for information.
+    pub handler: String,
+
+    /// The position of the handler that failed among the handlers of the message type, in
+    /// registration order, which identifies it when the message is replayed. Always 0 for a
+    /// command.
+    #[serde(default)]
+    pub handler_index: usize,
+
+    /// The error of the last attempt.
+    pub error: String,
+
+    /// The number of attempts made so far, including replays.
+    pub attempts: u32,
+
+    /// When the last attempt failed, in milliseconds since the Unix epoch.
+    pub failed_at: u64,
+
+    /// The principal on whose behalf the message was dispatched, if any, restored when the
+    /// message is replayed.
+    #[serde(default)]
+    pub principal: Option<Principal>,
+}
+
+/// The `DeadLetterStore` saves the dead letters until they are replayed or discarded.
+#[async_trait]
+pub trait DeadLetterStore: Send + Sync {
+    /// Saves `letter`, replacing any dead letter of the same id.
+    async fn save(&self, letter: &DeadLetter) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Returns the dead letter `id`, if any.
+    async fn get(&self, id: &str) -> Result<Option<DeadLetter>, Box<dyn Error + Send + Sync>>;
+
+    /// Removes the dead letter `id`, and returns whether it was found.
+    async fn remove(&self, id: &str) -> Result<bool, Box<dyn Error + Send + Sync>>;
+
+    /// Returns the dead letters, ordered by id.
+    async fn load(&self) -> Result<Vec<DeadLetter>, Box<dyn Error + Send + Sync>>;
+}
+
+/// A dead letter store keeping dead letters in memory.
+///
+/// Clones share the same dead letters.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryDeadLetterStore {
+    #[doc(hidden)]
+    letters: Arc<Mutex<BTreeMap<String, DeadLetter>>>,
+}
+
+/// Implementation of the `InMemoryDeadLetterStore`.
+impl InMemoryDeadLetterStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// DeadLetterStore implementation for `InMemoryDeadLetterStore`
+#[async_trait]
+impl DeadLetterStore for InMemoryDeadLetterStore {
+    async fn save(&self, letter: &DeadLetter) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut letters = self.letters.lock().unwrap();
+        letters.insert(letter.id.clone(), letter.clone());
+        Ok(())
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<DeadLetter>, Box<dyn Error + Send + Sync>> {
+        Ok(self.letters.lock().unwrap().get(id).cloned())
+    }
+
+    async fn remove(&self, id: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        Ok(self.letters.lock().unwrap().remove(id).is_some())
+    }
+
+    async fn load(&self) -> Result<Vec<DeadLetter>, Box<dyn Error + Send + Sync>> {
+        Ok(self.letters.lock().unwrap().values().cloned().collect())
+    }
+}
+
+/// A dead letter store keeping each dead letter as a JSON file in a directory.
+///
+/// Files are written to a temporary name then renamed, so that a process stopping while saving
+/// does not leave a truncated dead letter behind. File operations run on the blocking threads of
+/// the runtime.
+///
+/// # Example
+///
+/// ```no_run
+/// use qonduit::dead_letter::{DeadLetterQueue, FileDeadLetterStore};
+///
+/// let store = FileDeadLetterStore::open("/var/lib/orders/dead-letters").unwrap();
+/// let dead_letters = DeadLetterQueue::new(store);
+/// # drop(dead_letters);
+/// ```
+#[derive(Clone, Debug)]
+pub struct FileDeadLetterStore {
+    #[doc(hidden)]
+    directory: PathBuf,
+}
+
+/// Implementation of the `FileDeadLetterStore`.
+impl FileDeadLetterStore {
+    /// Opens the store kept in `directory`, creating the directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created.
+    pub fn open(directory: impl AsRef<Path>) -> io::Result<Self> {
+        let directory = directory.as_ref().to_path_buf();
+        std::fs::create_dir_all(&directory)?;
+        Ok(Self { directory })
+    }
+
+    fn path(&self, id: &str) -> io::Result<PathBuf> {
+        util::json_file(&self.directory, id)
+    }
+}
+
+/// DeadLetterStore implementation for `FileDeadLetterStore`
+#[async_trait]
+impl DeadLetterStore for FileDeadLetterStore {
+    async fn save(&self, letter: &DeadLetter) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let path = self.path(&letter.id)?;
+        let bytes = serde_json::to_vec(letter)?;
+        blocking(move || {
+            let temporary = path.with_extension("json.tmp");
+            std::fs::write(&temporary, bytes)?;
+            std::fs::rename(temporary, path)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<DeadLetter>, Box<dyn Error + Send + Sync>> {
+        let path = self.path(id)?;
+        blocking(move || match std::fs::read(path) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        })
+        .await
+    }
+
+    async fn remove(&self, id: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let path = self.path(id)?;
+        blocking(move || match std::fs::remove_file(path) {
+            Ok(()) => Ok(true),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(error) => Err(error.into()),
+        })
+        .await
+    }
+
+    async fn load(&self) -> Result<Vec<DeadLetter>, Box<dyn Error + Send + Sync>> {
+        let directory = self.directory.clone();
+        blocking(move || {
+            let mut letters = Vec::new();
+            for entry in std::fs::read_dir(directory)? {
+                let path = entry?.path();
+                if path
+                    .extension()
+                    .is_some_and(|extension| extension == "json")
+                {
+                    letters.push(serde_json::from_slice::<DeadLetter>(&std::fs::read(path)?)?);
+                }
+            }
+            letters.sort_by(|a, b| a.id.cmp(&b.id));
+            Ok(letters)
+        })
+        .await
+    }
+}
+
+/// A dead letter store keeping dead letters in a SQLite database.
+///
+/// Dead letters are kept in the `qonduit_dead_letters` table, created when the store is opened.
+/// Clones share the same connection. Queries run on the blocking threads of the runtime.
+///
+/// # Example
+///
+/// ```no_run
+/// use qonduit::dead_letter::{DeadLetterQueue, SqliteDeadLetterStore};
+///
+/// let store = SqliteDeadLetterStore::open("/var/lib/orders/dead-letters.db").unwrap();
+/// let dead_letters = DeadLetterQueue::new(store);
+/// # drop(dead_letters);
+/// ```
+#[cfg(feature = "sqlite")]
+#[derive(Clone)]
+pub struct SqliteDeadLetterStore {
+    #[doc(hidden)]
+    connection: Arc<Mutex<rusqlite::Connection>>,
+}
+
+/// The columns of a dead letter, in the order read by `SqliteDeadLetterStore::letter`.
+#[cfg(feature = "sqlite")]
+const LETTER_COLUMNS: &str =
+    "id, kind, name, payload, handler, error, attempts, failed_at, principal, handler_index";
+
+/// A dead letter read by `SqliteDeadLetterStore::letter`, with its kind, payload and principal
+/// still to parse.
+#[cfg(feature = "sqlite")]
+type LetterRow = (DeadLetter, String, String, Option<String>);
+
+/// Implementation of the `SqliteDeadLetterStore`.
+#[cfg(feature = "sqlite")]
+impl SqliteDeadLetterStore {
+    /// Opens the database at `path`, creating it and its table if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened or the table cannot be created.
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        let connection = rusqlite::Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS qonduit_dead_letters (
+                id TEXT PRIMARY KEY,
+                kind TEXT NOT NULL,
+                name TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                handler TEXT NOT NULL,
+                error TEXT NOT NULL,
+                attempts INTEGER NOT NULL,
+                failed_at INTEGER NOT NULL,
+                principal TEXT,
+                handler_index INTEGER NOT NULL DEFAULT 0
+            );",
+        )?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Reads a dead letter from a row of the columns of `LETTER_COLUMNS`.
+    fn letter(row: &rusqlite::Row<'_>) -> rusqlite::Result<LetterRow> {
+        let letter = DeadLetter {
+            id: row.get(0)?,
+            kind: MessageKind::Event,
+            name: row.get(2)?,
+            payload: Value::Null,
+            handler: row.get(4)?,
+            error: row.get(5)?,
+            attempts: row.get(6)?,
+            failed_at: row.get::<_, i64>(7)? as u64,
+            principal: None,
+            handler_index: row.get::<_, i64>(9)? as usize,
+        };
+        Ok((letter, row.get(1)?, row.get(3)?, row.get(8)?))
+    }
+
+    /// Parses the kind, the payload and the principal of a dead letter read by `letter`.
+    fn parse(
+        (mut letter, kind, payload, principal): LetterRow,
+    ) -> Result<DeadLetter, Box<dyn Error + Send + Sync>> {
+        letter.kind = serde_json::from_value(Value::String(kind))?;
+        letter.payload = serde_json::from_str(&payload)?;
+        letter.principal = principal.as_deref().map(serde_json::from_str).transpose()?;
+        Ok(letter)
+    }
+}
+
+/// DeadLetterStore implementation for `SqliteDeadLetterStore`
+#[cfg(feature = "sqlite")]
+#[async_trait]
+impl DeadLetterStore for SqliteDeadLetterStore {
+    async fn save(&self, letter: &DeadLetter) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let connection = self.connection.clone();
+        let params = (
+            letter.id.clone(),
+            letter.kind.to_string(),
+            letter.name.clone(),
+            serde_json::to_string(&letter.payload)?,
+            letter.handler.clone(),
+            letter.error.clone(),
+            letter.attempts,
+            letter.failed_at as i64,
+            letter
+                .principal
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+            letter.handler_index as i64,
+        );
+        blocking(move || {
+            connection.lock().unwrap().execute(
+                &format!(
+                    "INSERT OR REPLACE INTO qonduit_dead_letters ({LETTER_COLUMNS})
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"
+                ),
+                params,
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<DeadLetter>, Box<dyn Error + Send + Sync>> {
+        use rusqlite::OptionalExtension;
+
+        let connection = self.connection.clone();
+        let id = id.to_string();
+        blocking(move || {
+            let row = connection
+                .lock()
+                .unwrap()
+                .query_row(
+                    &format!("SELECT {LETTER_COLUMNS} FROM qonduit_dead_letters WHERE id = ?1"),
+                    [id],
+                    Self::letter,
+                )
+                .optional()?;
+            row.map(Self::parse).transpose()
+        })
+        .await
+    }
+
+    async fn remove(&self, id: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let connection = self.connection.clone();
+        let id = id.to_string();
+        blocking(move || {
+            let removed = connection
+                .lock()
+                .unwrap()
+                .execute("DELETE FROM qonduit_dead_letters WHERE id = ?1", [id])?;
+            Ok(removed > 0)
+        })
+        .await
+    }
+
+    async fn load(&self) -> Result<Vec<DeadLetter>, Box<dyn Error + Send + Sync>> {
+        let connection = self.connection.clone();
+        blocking(move || {
+            let rows = {
+                let connection = connection.lock().unwrap();
+                let mut statement = connection.prepare(&format!(
+                    "SELECT {LETTER_COLUMNS} FROM qonduit_dead_letters ORDER BY id"
+                ))?;
+                statement
+                    .query_map([], Self::letter)?
+                    .collect::<rusqlite::Result<Vec<_>>>()?
+            };
+            rows.into_iter().map(Self::parse).collect()
+        })
+        .await
+    }
+}
+
+/// Debug implementation for `SqliteDeadLetterStore`
+#[cfg(feature = "sqlite")]
+impl Debug for SqliteDeadLetterStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("SqliteDeadLetterStore")
+            .finish_non_exhaustive()
+    }
+}
+
+/// The `DeadLetterQueue` records the failed messages of the buses it is given to, and lists,
+/// inspects and discards them.
+///
+/// Clones share the same store. Dead letters are replayed with `replay_dead_letter` on the bus
+/// that recorded them.
+///
+/// # Example
+///
+/// ```
+/// # #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+/// # struct OrderPaidEvent { order_id: u64 }
+/// # impl qonduit::event::Event for OrderPaidEvent {}
+/// # impl qonduit::message::MessageName for OrderPaidEvent {
+/// #   const NAME: &'static str = "orders.paid.v1";
+/// # }
+/// # struct ShipOrderEventHandler;
+/// # #[qonduit::async_trait]
+/// # impl qonduit::event::EventHandler<OrderPaidEvent> for ShipOrderEventHandler {
+/// #   async fn handle(&self, _event: OrderPaidEvent) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+/// #     Err("the warehouse is unreachable".into())
+/// #   }
+/// # }
+/// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// # rt.block_on(async {
+/// use qonduit::dead_letter::{DeadLetterQueue, InMemoryDeadLetterStore};
+/// use qonduit::event::EventBus;
+/// use qonduit::registry::EventHandlerRegistry;
+///
+/// let mut registry = EventHandlerRegistry::new();
+/// registry.register::<OrderPaidEvent>(ShipOrderEventHandler);
+/// let dead_letters = DeadLetterQueue::new(InMemoryDeadLetterStore::new());
+/// let event_bus = EventBus::new(registry)
+///     .with_dead_letter_queue(dead_letters.clone())
+///     .with_dead_letters::<OrderPaidEvent>(3);
+///
+/// // The handler failed three times, and the event was kept
+/// event_bus.dispatch(OrderPaidEvent { order_id: 42 }).await.unwrap();
+/// let letter = &dead_letters.list().await.unwrap()[0];
+/// assert_eq!((letter.name.as_str(), letter.attempts), ("orders.paid.v1", 3));
+///
+/// // Once the warehouse is reachable again
+/// # let _ = event_bus.replay_dead_letter(&letter.id).await;
+/// # dead_letters.discard(&letter.id).await.unwrap();
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct DeadLetterQueue {
+    #[doc(hidden)]
+    store: Arc<dyn DeadLetterStore>,
+    #[doc(hidden)]
+    next_id: Arc<AtomicU64>,
+}
+
+/// Implementation of the `DeadLetterQueue`.
+impl DeadLetterQueue {
+    /// Creates a queue keeping its dead letters in `store`.
+    pub fn new(store: impl DeadLetterStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+            next_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Returns the dead letters, ordered by id.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the store if the dead letters cannot be loaded.
+    pub async fn list(&self) -> Result<Vec<DeadLetter>, Box<dyn Error + Send + Sync>> {
+        self.store.load().await
+    }
+
+    /// Returns the dead letter `id`, or `None` if there is no such dead letter.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the store if the dead letter cannot be loaded.
+    pub async fn get(&self, id: &str) -> Result<Option<DeadLetter>, Box<dyn Error + Send + Sync>> {
+        self.store.get(id).await
+    }
+
+    /// Discards the dead letter `id` without replaying it, and returns whether it was found.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the store if the dead letter cannot be removed.
+    pub async fn discard(&self, id: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        self.store.remove(id).await
+    }
+
+    /// Returns a new dead letter id at `now`, in milliseconds since the Unix epoch.
+    fn next_id(&self, now: u64) -> String {
+        let sequence = self.next_id.fetch_add(1, Ordering::Relaxed);
+        // Sorts by creation, and stays unique across processes and restarts
+        format!("{now:013}-{}-{sequence:06}", std::process::id())
+    }
+}
+
+/// Debug implementation for `DeadLetterQueue`
+impl Debug for DeadLetterQueue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("DeadLetterQueue").finish_non_exhaustive()
+    }
+}
+
+/// The future of a replay.
+pub(crate) type Replay<'a> =
+    Pin<Box<dyn Future<Output = Result<(), Box<dyn Error + Send + Sync>>> + Send + 'a>>;
+
+/// Replays a payload to the handler at the given position through a bus.
+pub(crate) type Replayer<B> = fn(&B, usize, Value) -> Replay<'_>;
+
+/// Serializes a message of a dead-lettered type.
+type Serializer = fn(&dyn Any) -> serde_json::Result<Value>;
+
+/// A message type whose failures are recorded.
+struct Message<B> {
+    name: &'static str,
+    serialize: Serializer,
+    replay: Replayer<B>,
+    max_attempts: u32,
+}
+
+/// Clone implementation for `Message`
+impl<B> Clone for Message<B> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name,
+            serialize: self.serialize,
+            replay: self.replay,
+            max_attempts: self.max_attempts,
+        }
+    }
+}
+
+/// The dead letter settings of a bus of type `B`.
+pub(crate) struct DeadLetters<B> {
+    kind: MessageKind,
+    queue: Option<DeadLetterQueue>,
+    messages: HashMap<TypeId, Message<B>>,
+}
+
+/// A failed dispatch whose dead letter is pending until its outcome is known.
+pub(crate) struct PendingLetter {
+    queue: DeadLetterQueue,
+    kind: MessageKind,
+    name: &'static str,
+    payload: Value,
+    principal: Option<Principal>,
+}
+
+impl<B> DeadLetters<B> {
+    pub(crate) fn new(kind: MessageKind) -> Self {
+        Self {
+            kind,
+            queue: None,
+            messages: HashMap::new(),
+        }
+    }
+
+    /// Records the dead letters to `queue`, replacing any previous queue.
+    pub(crate) fn set_queue(&mut self, queue: DeadLetterQueue) {
+        self.queue = Some(queue);
+    }
+
+    /// Records the failures of messages of type `T`, named `name`, after `max_attempts` attempts.
+    pub(crate) fn set<T: Serialize + 'static>(
+        &mut self,
+        name: &'static str,
+        replay: Replayer<B>,
+        max_attempts: u32,
+    ) {
+        let serialize: Serializer = |message| {
+            serde_json::to_value(
+                message
+                    .downcast_ref::<T>()
+                    .expect("Cannot downcast message to correct type"),
+            )
+        };
+        let message = Message {
+            name,
+            serialize,
+            replay,
+            max_attempts,
+        };
+        self.messages.insert(TypeId::of::<T>(), message);
+    }
+
+    /// Returns how many times a handler of messages of type `T` is attempted.
+    pub(crate) fn max_attempts<T: 'static>(&self) -> u32 {
+        self.messages
+            .get(&TypeId::of::<T>())
+            .map_or(1, |message| message.max_attempts)
+    }
+
+    /// Captures `message` before it is dispatched, if the failures of its type are recorded.
+    ///
+    /// The replayed message of a dead letter is not captured, so that a replay failing again
+    /// updates its dead letter instead of recording another one. The messages dispatched while
+    /// handling it are captured as usual.
+    pub(crate) fn capture<T: 'static>(&self, message: &T) -> Option<PendingLetter> {
+        let replayed = REPLAYING
+            .try_with(|replaying| replaying.replace(false))
+            .unwrap_or(false);
+        if replayed {
+            return None;
+        }
+        let queue = self.queue.clone()?;
+        let captured = self.messages.get(&TypeId::of::<T>())?;
+        Some(PendingLetter {
+            queue,
+            kind: self.kind,
+            name: captured.name,
+            payload: (captured.serialize)(message).ok()?,
+            principal: authorization::current_principal().map(|principal| (*principal).clone()),
+        })
+    }
+
+    /// Replays the dead letter `id` through `bus`, and returns whether it was found.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if no queue is configured.
+    pub(crate) async fn replay(
+        &self,
+        bus: &B,
+        id: &str,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let Some(queue) = &self.queue else {
+            panic!("No dead letter queue configured to replay: {id:?}");
+        };
+        let Some(mut letter) = queue.store.get(id).await? else {
+            return Ok(false);
+        };
+        let message = self
+            .messages
+            .values()
+            .find(|message| letter.kind == self.kind && message.name == letter.name)
+            .ok_or_else(|| {
+                format!(
+                    "no {} type named {:?} is dead-lettered on this bus",
+                    letter.kind, letter.name
+                )
+            })?;
+        let replay = (message.replay)(bus, letter.handler_index, letter.payload.clone());
+        let outcome = match letter.principal.clone() {
+            Some(principal) => authorization::scope(principal, replay).await,
+            None => replay.await,
+        };
+        match outcome {
+            Ok(()) => {
+                queue.store.remove(id).await?;
+                Ok(true)
+            }
+            Err(error) => {
+                letter.attempts += 1;
+                letter.error = error.to_string();
+                letter.failed_at = unix_millis(SystemTime::now());
+                queue.store.save(&letter).await?;
+                Err(error)
+            }
+        }
+    }
+}
+
+/// Clone implementation for `DeadLetters`
+impl<B> Clone for DeadLetters<B> {
+    fn clone(&self) -> Self {
+        Self {
+            kind: self.kind,
+            queue: self.queue.clone(),
+            messages: self.messages.clone(),
+        }
+    }
+}
+
+/// Debug implementation for `DeadLetters`
+impl<B> Debug for DeadLetters<B> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("DeadLetters")
+            .field("enabled", &self.queue.is_some())
+            .finish()
+    }
+}
+
+impl PendingLetter {
+    /// Saves the dead letter of the message after `attempts` attempts of `handler`, registered at
+    /// `handler_index`, failing with `error`, and returns whether it was saved.
+    ///
+    /// A dead letter that cannot be saved is logged with the `tracing` feature.
+    pub(crate) async fn record(
+        self,
+        handler_index: usize,
+        handler: &str,
+        error: String,
+        attempts: u32,
+    ) -> bool {
+        let now = unix_millis(SystemTime::now());
+        let letter = DeadLetter {
+            id: self.queue.next_id(now),
+            kind: self.kind,
+            name: self.name.to_string(),
+            payload: self.payload,
+            handler: handler.to_string(),
+            error,
+            attempts,
+            failed_at: now,
+            principal: self.principal,
+            handler_index,
+        };
+        match self.queue.store.save(&letter).await {
+            Ok(()) => true,
+            Err(error) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!(
+                    kind = %letter.kind,
+                    message_type = letter.name,
+                    handler = letter.handler,
+                    error = %error,
+                    "failed to save dead letter"
+                );
+                #[cfg(not(feature = "tracing"))]
+                let _ = error;
+                false
+            }
+        }
+    }
+}
+
+/// Runs `future`, the dispatch of the message of a dead letter, so that the message is not
+/// captured again by [DeadLetters::capture].
+pub(crate) async fn replaying<F: Future>(future: F) -> F::Output {
+    REPLAYING.scope(Cell::new(true), future).await
+}
+
+/// Runs `future`, the dispatch of a command handled by `handler`, and records its failure once it
+/// completes.
+pub(crate) async fn record_failure<R, E, F>(
+    pending: Option<PendingLetter>,
+    handler: &str,
+    future: F,
+) -> Result<R, DispatchError<E>>
+where
+    E: Debug,
+    F: Future<Output = Result<R, DispatchError<E>>>,
+{
+    let result = future.await;
+    if let (Some(pending), Err(error)) = (pending, &result) {
+        // Rejected commands would be rejected again when replayed
+        let rejected = matches!(
+            error,
+            DispatchError::Invalid(_)
+                | DispatchError::Forbidden(_)
+                | DispatchError::RateLimited { .. }
+        );
+        if !rejected {
+            pending.record(0, handler, error.describe(), 1).await;
+        }
+    }
+    result
+}
@@ -0,0 +1,191 @@
+//! The `error` module defines the error type returned by the buses when dispatching a message.
+//!
+//! Every bus wraps the error produced by the handler into a [DispatchError], which additionally
+//! describes failures raised by the bus itself (for example, a dispatch exceeding its time budget)
+//! before or instead of the handler's own outcome.
+//!
+//! - [DispatchError]: The error returned by `CommandBus`, `QueryBus` and `EventBus` dispatches.
+
+use std::error::Error;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+use std::time::Duration;
+
+use crate::authorization::Forbidden;
+use crate::validation::Violations;
+
+/// The `DispatchError` is returned when a message could not be processed successfully.
+///
+/// The [Handler](DispatchError::Handler) variant carries the error returned by the handler itself,
+/// while the remaining variants describe failures detected by the bus.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use qonduit::error::DispatchError;
+///
+/// #[derive(Debug)]
+/// enum AddProductError {
+///     SkuAlreadyExists,
+/// }
+///
+/// fn describe(error: DispatchError<AddProductError>) -> String {
+///     match error {
+///         DispatchError::Handler(AddProductError::SkuAlreadyExists) => "duplicate sku".to_string(),
+///         DispatchError::Timeout(budget) => format!("timed out after {budget:?}"),
+///         other => format!("{other:?}"),
+///     }
+/// }
+///
+/// assert_eq!(describe(DispatchError::Timeout(Duration::from_secs(1))), "timed out after 1s");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum DispatchError<E> {
+    /// The handler processed the message and returned an error.
+    Handler(E),
+
+    /// The dispatch did not complete within its time budget.
+    ///
+    /// The duration is the budget that was available when the dispatch started.
+    Timeout(Duration),
+
+    /// The circuit breaker of the message type is open, so the handler was not invoked.
+    CircuitOpen,
+
+    /// The bulkhead of the message type has no free execution slot and its queue is full.
+    BulkheadFull,
+
+    /// The rate limit of the message type is exhausted, so the handler was not invoked.
+    RateLimited {
+        /// How long to wait before a dispatch may be allowed again.
+        retry_after: Duration,
+    },
+
+    /// The message failed validation, so the handler was not invoked.
+    Invalid(Violations),
+
+    /// The principal of the dispatch is not allowed to dispatch the message, so the handler was
+    /// not invoked.
+    Forbidden(Forbidden),
+
+    /// The message could not be delivered to a remote bus, or its outcome could not be read back.
+    ///
+    /// The string describes the failure. The message may or may not have been handled.
+    Transport(String),
+
+    /// The message is not signed by a trusted key, or its signature is invalid, expired or
+    /// replayed, so the handler was not invoked.
+    ///
+    /// The string describes the failure. See the `signing` module for details.
+    Unauthenticated(String),
+}
+
+/// Implementation of the `DispatchError`.
+impl<E> DispatchError<E> {
+    /// Returns the handler error, or `None` if the failure was raised by the bus.
+    pub fn into_handler_error(self) -> Option<E> {
+        match self {
+            DispatchError::Handler(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the dispatch exceeded its time budget.
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, DispatchError::Timeout(_))
+    }
+
+    /// Returns `true` if the failure may not happen again when the message is dispatched later:
+    /// `timeout`, `circuit_open`, `bulkhead_full`, `rate_limited` or `transport`.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            DispatchError::Timeout(_)
+                | DispatchError::CircuitOpen
+                | DispatchError::BulkheadFull
+                | DispatchError::RateLimited { .. }
+                | DispatchError::Transport(_)
+        )
+    }
+
+    /// Returns `true` if the principal of the dispatch was not allowed to dispatch the message.
+    pub fn is_forbidden(&self) -> bool {
+        matches!(self, DispatchError::Forbidden(_))
+    }
+
+    /// Returns a short, stable name of the kind of failure, suitable as a log field or metric
+    /// label: `handler_error`, `timeout`, `circuit_open`, `bulkhead_full`, `rate_limited`,
+    /// `invalid`, `forbidden`, `transport` or `unauthenticated`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DispatchError::Handler(_) => "handler_error",
+            DispatchError::Timeout(_) => "timeout",
+            DispatchError::CircuitOpen => "circuit_open",
+            DispatchError::BulkheadFull => "bulkhead_full",
+            DispatchError::RateLimited { .. } => "rate_limited",
+            DispatchError::Invalid(_) => "invalid",
+            DispatchError::Forbidden(_) => "forbidden",
+            DispatchError::Transport(_) => "transport",
+            DispatchError::Unauthenticated(_) => "unauthenticated",
+        }
+    }
+
+    /// Formats the failure, with the handler error formatted by `handler`.
+    fn fmt_with(
+        &self,
+        f: &mut Formatter<'_>,
+        handler: impl FnOnce(&E, &mut Formatter<'_>) -> FormatterResult,
+    ) -> FormatterResult {
+        match self {
+            DispatchError::Handler(error) => handler(error, f),
+            DispatchError::Timeout(budget) => write!(f, "dispatch timed out after {budget:?}"),
+            DispatchError::CircuitOpen => write!(f, "circuit breaker is open"),
+            DispatchError::BulkheadFull => write!(f, "bulkhead is full"),
+            DispatchError::RateLimited { retry_after } => {
+                write!(f, "rate limit exceeded, retry after {retry_after:?}")
+            }
+            DispatchError::Invalid(violations) => write!(f, "validation failed: {violations}"),
+            DispatchError::Forbidden(Forbidden::Denied) => write!(f, "dispatch is forbidden"),
+            DispatchError::Forbidden(Forbidden::NoPolicy) => {
+                write!(f, "dispatch is forbidden, no policy is defined")
+            }
+            DispatchError::Transport(reason) => write!(f, "remote dispatch failed: {reason}"),
+            DispatchError::Unauthenticated(reason) => {
+                write!(f, "message authentication failed: {reason}")
+            }
+        }
+    }
+}
+
+/// Crate-private implementation of the `DispatchError`.
+#[cfg(feature = "dead-letter")]
+impl<E: Debug> DispatchError<E> {
+    /// Describes the failure as its `Display` implementation would, with the handler error,
+    /// which may only implement `Debug`, in its `Debug` format.
+    pub(crate) fn describe(&self) -> String {
+        struct Described<'a, E>(&'a DispatchError<E>);
+
+        impl<E: Debug> Display for Described<'_, E> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+                self.0.fmt_with(f, |error, f| Debug::fmt(error, f))
+            }
+        }
+
+        Described(self).to_string()
+    }
+}
+
+/// Display implementation for `DispatchError`
+impl<E: Display> Display for DispatchError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        self.fmt_with(f, |error, f| Display::fmt(error, f))
+    }
+}
+
+/// Error implementation for `DispatchError`
+impl<E: Debug + Display> Error for DispatchError<E> {}
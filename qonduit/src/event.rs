@@ -1,9 +1,42 @@
+use crate::bulkhead::BulkheadConfig;
+use crate::bulkhead::BulkheadStats;
+use crate::bulkhead::Bulkheads;
+#[cfg(feature = "dead-letter")]
+use crate::dead_letter::DeadLetterQueue;
+#[cfg(feature = "dead-letter")]
+use crate::dead_letter::DeadLetters;
+#[cfg(feature = "dead-letter")]
+use crate::dead_letter::MessageKind;
+#[cfg(feature = "dead-letter")]
+use crate::dead_letter::Replay;
+use crate::error::DispatchError;
+#[cfg(feature = "tracing")]
+use crate::instrumentation;
+#[cfg(feature = "tracing")]
+use crate::instrumentation::Instrumentation;
+#[cfg(feature = "dead-letter")]
+use crate::message::MessageName;
+#[cfg(feature = "metrics")]
+use crate::metrics;
 use crate::registry::EventHandlerRegistry;
+use crate::subscription::Subscribers;
+use crate::subscription::Subscription;
+#[cfg(feature = "opentelemetry")]
+use crate::telemetry;
+use crate::timeout::Timeouts;
 use async_trait::async_trait;
+#[cfg(feature = "dead-letter")]
+use serde::Serialize;
+#[cfg(feature = "dead-letter")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "dead-letter")]
+use serde_json::Value;
 use std::any::Any;
 use std::error::Error;
 use std::fmt::Debug;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
 
 /// A domain/event-sourcing style notification that has occurred in the system.
 ///
@@ -130,6 +163,18 @@ pub trait EventHandler<E: Event>: Send + Sync {
 pub struct EventBus {
     #[doc(hidden)]
     registry: Arc<EventHandlerRegistry>,
+    #[doc(hidden)]
+    timeouts: Arc<Timeouts>,
+    #[doc(hidden)]
+    bulkheads: Arc<Bulkheads>,
+    #[doc(hidden)]
+    subscribers: Arc<Subscribers>,
+    #[doc(hidden)]
+    #[cfg(feature = "tracing")]
+    instrumentation: Arc<Instrumentation>,
+    #[doc(hidden)]
+    #[cfg(feature = "dead-letter")]
+    dead_letters: Arc<DeadLetters<EventBus>>,
 }
 
 impl EventBus {
@@ -137,17 +182,216 @@ impl EventBus {
     pub fn new(registry: EventHandlerRegistry) -> Self {
         Self {
             registry: Arc::new(registry),
+            timeouts: Arc::new(Timeouts::new()),
+            bulkheads: Arc::new(Bulkheads::new()),
+            subscribers: Arc::new(Subscribers::new()),
+            #[cfg(feature = "tracing")]
+            instrumentation: Arc::new(Instrumentation::new()),
+            #[cfg(feature = "dead-letter")]
+            dead_letters: Arc::new(DeadLetters::new(MessageKind::Event)),
         }
     }
 
+    /// Records the `Debug` rendering of every dispatched event in its tracing span.
+    ///
+    /// Events may contain personal or secret data, so this is disabled by default. Use
+    /// [without_message_recording](EventBus::without_message_recording) to withhold specific
+    /// event types. See the [instrumentation](crate::instrumentation) module for the recorded
+    /// fields.
+    #[cfg(feature = "tracing")]
+    pub fn with_message_recording(mut self) -> Self {
+        Arc::make_mut(&mut self.instrumentation).record_messages();
+        self
+    }
+
+    /// Never records the `Debug` rendering of events of type `E` in tracing spans.
+    #[cfg(feature = "tracing")]
+    pub fn without_message_recording<E: Event>(mut self) -> Self {
+        Arc::make_mut(&mut self.instrumentation).skip_message::<E>();
+        self
+    }
+
+    /// Sets the timeout applied to every event type without a dedicated timeout.
+    ///
+    /// The timeout bounds the whole fan-out: all handlers of the event must complete
+    /// within it, otherwise dispatching fails with [`DispatchError::Timeout`] and the
+    /// remaining handlers are not invoked.
+    pub fn with_default_timeout(mut self, timeout: Duration) -> Self {
+        Arc::make_mut(&mut self.timeouts).set_default(timeout);
+        self
+    }
+
+    /// Sets the timeout applied to events of type `E`, overriding the default timeout.
+    pub fn with_timeout<E: Event>(mut self, timeout: Duration) -> Self {
+        Arc::make_mut(&mut self.timeouts).set::<E>(timeout);
+        self
+    }
+
+    /// Limits how many events of type `E` each of their handlers processes concurrently.
+    ///
+    /// Every handler registered for `E` gets its own bulkhead with the limits of `config`, so a
+    /// slow handler does not take the slots of the other handlers of the event. Invocations beyond
+    /// the limit of a handler wait in a bounded queue, and the dispatch fails with
+    /// [`DispatchError::BulkheadFull`] once the queue is full. Clones of the bus share the
+    /// bulkheads.
+    ///
+    /// # Example
+    /// ```
+    /// use qonduit::bulkhead::BulkheadConfig;
+    /// use qonduit::event::{Event, EventBus};
+    /// use qonduit::registry::EventHandlerRegistry;
+    ///
+    /// #[derive(Clone, Debug)]
+    /// struct ProductCreatedEvent { id: u64 }
+    /// impl Event for ProductCreatedEvent {}
+    ///
+    /// let bus = EventBus::new(EventHandlerRegistry::new())
+    ///     .with_bulkhead::<ProductCreatedEvent>(BulkheadConfig::new(2).with_max_queued(8));
+    ///
+    /// let stats = bus.bulkhead_stats::<ProductCreatedEvent>().unwrap();
+    /// assert_eq!((stats.in_flight, stats.queued), (0, 0));
+    /// ```
+    pub fn with_bulkhead<E: Event>(mut self, config: BulkheadConfig) -> Self {
+        let handlers = self.registry.handler_type_names::<E>().len();
+        Arc::make_mut(&mut self.bulkheads).set_per_handler::<E>(handlers, config);
+        self
+    }
+
+    /// Returns the in-flight and queued invocation counts of the bulkheads for events of type
+    /// `E`, summed over their handlers.
+    ///
+    /// Returns `None` if no bulkhead is configured for `E`.
+    pub fn bulkhead_stats<E: Event>(&self) -> Option<BulkheadStats> {
+        self.bulkheads.stats::<E>()
+    }
+
+    /// Returns the type name and the in-flight and queued invocation counts of the bulkhead of
+    /// each handler of events of type `E`, in registration order.
+    ///
+    /// Returns an empty list if no bulkhead is configured for `E`.
+    pub fn handler_bulkhead_stats<E: Event>(&self) -> Vec<(&'static str, BulkheadStats)> {
+        self.registry
+            .handler_type_names::<E>()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, handler_type)| {
+                let stats = self.bulkheads.handler_stats::<E>(index)?;
+                Some((*handler_type, stats))
+            })
+            .collect()
+    }
+
+    /// Sets how many events each new [Subscription] buffers before its subscriber lags behind.
+    ///
+    /// Defaults to 256 events. The capacity is fixed for an event type by its first subscription.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `capacity` is zero.
+    pub fn with_subscriber_capacity(mut self, capacity: usize) -> Self {
+        Arc::make_mut(&mut self.subscribers).set_capacity(capacity);
+        self
+    }
+
+    /// Subscribes to every event of type `E` dispatched from now on, by this bus or its clones.
+    ///
+    /// The subscription receives each event as it is dispatched, before the registered handlers
+    /// run, whatever their outcome. It stops receiving events when dropped. See the
+    /// [subscription](crate::subscription) module for how slow subscribers are handled.
+    pub fn subscribe<E: Event>(&self) -> Subscription<E> {
+        self.subscribers.subscribe::<E>()
+    }
+
+    /// Returns the number of live subscriptions to events of type `E`.
+    pub fn subscriber_count<E: Event>(&self) -> usize {
+        self.subscribers.count::<E>()
+    }
+
+    /// Records the events whose handlers fail, for the event types enabled with
+    /// [with_dead_letters](EventBus::with_dead_letters), to `queue`, replacing any previous queue.
+    ///
+    /// See the [dead_letter](crate::dead_letter) module for details.
+    #[cfg(feature = "dead-letter")]
+    pub fn with_dead_letter_queue(mut self, queue: DeadLetterQueue) -> Self {
+        Arc::make_mut(&mut self.dead_letters).set_queue(queue);
+        self
+    }
+
+    /// Attempts each handler of events of type `E` up to `max_attempts` times, then records the
+    /// event as a dead letter instead of failing the dispatch.
+    ///
+    /// The dead letter is saved under [MessageName::NAME], with the position and the type name of
+    /// the handler that failed and the error of its last attempt. The remaining handlers still run.
+    /// Since the handler is found again by its position, the handlers of `E` should be registered
+    /// in the same order by the process replaying the dead letter.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `max_attempts` is zero.
+    #[cfg(feature = "dead-letter")]
+    pub fn with_dead_letters<E>(mut self, max_attempts: u32) -> Self
+    where
+        E: Event + MessageName + Serialize + DeserializeOwned,
+    {
+        assert!(max_attempts > 0, "max_attempts must be at least 1");
+        Arc::make_mut(&mut self.dead_letters).set::<E>(E::NAME, Self::replay::<E>, max_attempts);
+        self
+    }
+
+    /// Hands the event of the dead letter `id` to the handler that failed, removes the dead
+    /// letter if the handler succeeds, and returns whether the dead letter was found.
+    ///
+    /// The other handlers of the event, which succeeded when it was dispatched, are not run again.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the handler if it fails again, in which case the dead letter is kept
+    /// with its attempts incremented, or an error if the dead letter cannot be loaded, or its
+    /// event type or handler is not registered on this bus.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the bus has no dead letter queue, see
+    /// [with_dead_letter_queue](EventBus::with_dead_letter_queue).
+    #[cfg(feature = "dead-letter")]
+    pub async fn replay_dead_letter(&self, id: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        self.dead_letters.replay(self, id).await
+    }
+
+    /// Hands the event of type `E` serialized in `payload` to the handler registered at
+    /// `handler_index`.
+    #[cfg(feature = "dead-letter")]
+    fn replay<E>(&self, handler_index: usize, payload: Value) -> Replay<'_>
+    where
+        E: Event + DeserializeOwned,
+    {
+        Box::pin(async move {
+            let event = serde_json::from_value::<E>(payload)?;
+            let Some(handler) = self
+                .registry
+                .get_handlers::<E>()
+                .into_iter()
+                .nth(handler_index)
+            else {
+                return Err(format!("no handler is registered at position {handler_index}").into());
+            };
+            handler.handle(event).await
+        })
+    }
+
     /// Dispatches (publishes) an event to every registered handler for its type.
     ///
     /// Handlers are invoked sequentially in registration order. If a handler
-    /// returns an error, processing stops and that error is returned.
+    /// returns an error, processing stops and that error is returned, unless the event type is
+    /// enabled with [with_dead_letters](EventBus::with_dead_letters) (with the `dead-letter`
+    /// feature), in which case the handler is attempted again then the event recorded as a dead
+    /// letter.
     ///
     /// # Errors
     ///
-    /// Returns the first handler error encountered (if any).
+    /// Returns the first handler error encountered (if any) as [`DispatchError::Handler`],
+    /// [`DispatchError::Timeout`] if the handlers do not complete within the configured
+    /// timeout, or [`DispatchError::BulkheadFull`] if the bulkhead of one of its handlers is full.
     ///
     /// # Example
     /// ```
@@ -177,10 +421,121 @@ impl EventBus {
     /// bus.dispatch(OrderPaidEvent { order_id: 42 }).await.unwrap();
     /// # });
     /// ```
-    pub async fn dispatch<E: Event>(&self, event: E) -> Result<(), Box<dyn Error + Send + Sync>> {
-        for handler in self.registry.get_handlers::<E>() {
-            handler.handle(event.clone()).await?;
-        }
-        Ok(())
+    pub async fn dispatch<E: Event>(
+        &self,
+        event: E,
+    ) -> Result<(), DispatchError<Box<dyn Error + Send + Sync>>> {
+        self.subscribers.publish(&event);
+        #[cfg(feature = "tracing")]
+        let span =
+            self.instrumentation
+                .span("event", self.registry.message_name::<E>(), None, &event);
+        #[cfg(feature = "opentelemetry")]
+        let origin = telemetry::origin();
+        #[cfg_attr(
+            not(any(feature = "tracing", feature = "opentelemetry")),
+            allow(unused_variables)
+        )]
+        let fan_out = async {
+            let handlers = self.registry.get_handlers::<E>();
+            #[cfg(feature = "metrics")]
+            if handlers.is_empty() {
+                metrics::missing_handler("event", self.registry.message_name::<E>());
+            }
+            let handler_types = self.registry.handler_type_names::<E>();
+            #[cfg(feature = "dead-letter")]
+            let max_attempts = self.dead_letters.max_attempts::<E>();
+            #[cfg(not(feature = "dead-letter"))]
+            let max_attempts = 1;
+            for (index, (handler, handler_type)) in
+                handlers.into_iter().zip(handler_types).enumerate()
+            {
+                let mut attempts = 0;
+                let error = loop {
+                    attempts += 1;
+                    let handle = handler.handle(event.clone());
+                    #[cfg(feature = "opentelemetry")]
+                    let handle = telemetry::trace_event_handler(handler_type, &origin, handle);
+                    #[cfg(feature = "tracing")]
+                    let handle = instrumentation::instrument_event_handler(handler_type, handle);
+                    let handle = async { handle.await.map_err(DispatchError::Handler) };
+                    match self
+                        .bulkheads
+                        .run_handler::<E, _, _, _>(index, handle)
+                        .await
+                    {
+                        Ok(()) => break None,
+                        Err(DispatchError::Handler(error)) if attempts >= max_attempts => {
+                            break Some(error);
+                        }
+                        Err(DispatchError::Handler(_)) => {}
+                        Err(error) => return Err(error),
+                    }
+                };
+                if let Some(error) = error {
+                    #[cfg(feature = "dead-letter")]
+                    if let Some(pending) = self.dead_letters.capture(&event)
+                        && pending
+                            .record(index, handler_type, error.to_string(), attempts)
+                            .await
+                    {
+                        continue;
+                    }
+                    return Err(DispatchError::Handler(error));
+                }
+            }
+            Ok(())
+        };
+        let dispatch = self.timeouts.run::<E, _, _, _>(fan_out);
+        #[cfg(feature = "opentelemetry")]
+        let dispatch = telemetry::trace("event", self.registry.message_name::<E>(), None, dispatch);
+        #[cfg(feature = "metrics")]
+        let dispatch = metrics::measure("event", self.registry.message_name::<E>(), dispatch);
+        #[cfg(feature = "tracing")]
+        let dispatch = instrumentation::instrument(span, dispatch);
+        dispatch.await
+    }
+
+    /// Dispatches an event in a background task and returns a handle to the result.
+    ///
+    /// Handlers run exactly as with [dispatch](EventBus::dispatch), but the caller does not wait
+    /// for them. Unlike spawning a `dispatch` with `tokio::spawn`, this carries the current
+    /// `tracing` span and OpenTelemetry context (with the `tracing` and `opentelemetry` features)
+    /// over to the background task, so the handlers remain part of the caller's trace.
+    ///
+    /// The deadline of the enclosing dispatch is not carried over, since background handlers are
+    /// meant to outlive their caller; they are only bounded by the timeouts of this bus.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if called outside of a Tokio runtime.
+    ///
+    /// # Example
+    /// ```
+    /// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    /// # rt.block_on(async {
+    /// use qonduit::event::{Event, EventBus};
+    /// use qonduit::registry::EventHandlerRegistry;
+    ///
+    /// #[derive(Clone, Debug)]
+    /// struct OrderShippedEvent { order_id: u64 }
+    /// impl Event for OrderShippedEvent {}
+    ///
+    /// let bus = EventBus::new(EventHandlerRegistry::new());
+    /// let handle = bus.spawn_dispatch(OrderShippedEvent { order_id: 42 });
+    /// handle.await.unwrap().unwrap();
+    /// # });
+    /// ```
+    pub fn spawn_dispatch<E: Event>(
+        &self,
+        event: E,
+    ) -> JoinHandle<Result<(), DispatchError<Box<dyn Error + Send + Sync>>>> {
+        let bus = self.clone();
+        let dispatch = async move { bus.dispatch(event).await };
+        #[cfg(feature = "opentelemetry")]
+        let dispatch = telemetry::propagate(dispatch);
+        #[cfg(feature = "tracing")]
+        let dispatch = tracing::Instrument::in_current_span(dispatch);
+        tokio::spawn(dispatch)
     }
 }
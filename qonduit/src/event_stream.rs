@@ -0,0 +1,308 @@
+//! The `event_stream` module streams domain events to HTTP clients as server-sent events.
+//!
+//! This module is available with the `axum` feature. An [EventStream] turns an `EventBus` into an
+//! `axum::Router` serving `GET /events`. Each connection [subscribes](crate::event::EventBus::subscribe)
+//! to the exposed event types for as long as it stays open, and receives every dispatched event as
+//! a server-sent event named after the [stable name](crate::message::MessageName) of its type, with
+//! the event serialized as JSON in its data:
+//!
+//! ```text
+//! event: orders.shipped.v1
+//! data: {"order_id":42}
+//! ```
+//!
+//! Connections choose the events they want with the `events` query parameter, a comma-separated
+//! list of stable names, and receive every exposed type without it. Unknown names are rejected
+//! with `400 Bad Request` and an `unknown_event` error. A [filter](EventStream::with_filter) can
+//! further restrict the events sent to a connection, for example to those of the
+//! [Principal] found in the request extensions.
+//!
+//! A client reading slower than events are dispatched falls behind its subscription buffer, sized
+//! with [with_subscriber_capacity](crate::event::EventBus::with_subscriber_capacity). It then
+//! misses the oldest events and receives a `lagged` event with the number of events it missed,
+//! `{"skipped":3}`, before the stream resumes, or before the stream ends if the stream
+//! [disconnects lagging clients](EventStream::with_disconnect_on_lag) so they can reload their
+//! state and reconnect.
+//!
+//! - [EventStream]: Builds the router streaming the exposed event types.
+
+use std::convert::Infallible;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::Json;
+use axum::Router;
+use axum::extract::Query as QueryString;
+use axum::extract::Request;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::response::Sse;
+use axum::response::sse::Event as SseEvent;
+use axum::response::sse::KeepAlive;
+use axum::routing::get;
+use futures_util::StreamExt;
+use futures_util::stream;
+use futures_util::stream::BoxStream;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use serde_json::json;
+
+use crate::authorization::Principal;
+use crate::event::Event;
+use crate::event::EventBus;
+use crate::message::MessageName;
+use crate::subscription::SubscriptionError;
+
+/// An item of the stream sent to a connection, before its conversion to a server-sent event.
+enum Item {
+    Event { name: &'static str, data: Value },
+    Lagged(u64),
+}
+
+/// Subscribes to an exposed event type, yielding its events serialized as JSON.
+type Subscribe = fn(&EventBus) -> BoxStream<'static, Item>;
+
+/// Decides whether an event is sent to a connection.
+type Filter = dyn Fn(Option<&Principal>, &str, &Value) -> bool + Send + Sync;
+
+/// The query string of a connection.
+#[derive(Deserialize)]
+struct Params {
+    events: Option<String>,
+}
+
+/// The `EventStream` builds an `axum::Router` streaming domain events as server-sent events.
+///
+/// # Example
+///
+/// ```
+/// use qonduit::event::{Event, EventBus};
+/// use qonduit::event_stream::EventStream;
+/// use qonduit::message::MessageName;
+/// use qonduit::registry::EventHandlerRegistry;
+/// use std::time::Duration;
+///
+/// #[derive(Clone, Debug, serde::Serialize)]
+/// struct OrderShippedEvent { order_id: u64 }
+/// impl Event for OrderShippedEvent {}
+///
+/// impl MessageName for OrderShippedEvent {
+///     const NAME: &'static str = "orders.shipped.v1";
+/// }
+///
+/// let bus = EventBus::new(EventHandlerRegistry::new());
+///
+/// // Serves `GET /events` and `GET /events?events=orders.shipped.v1`
+/// let router: axum::Router = EventStream::new(bus)
+///     .event::<OrderShippedEvent>()
+///     .with_keep_alive(Duration::from_secs(15))
+///     .into_router();
+/// ```
+#[derive(Clone)]
+pub struct EventStream {
+    #[doc(hidden)]
+    bus: EventBus,
+    #[doc(hidden)]
+    events: Vec<(&'static str, Subscribe)>,
+    #[doc(hidden)]
+    filter: Option<Arc<Filter>>,
+    #[doc(hidden)]
+    disconnect_on_lag: bool,
+    #[doc(hidden)]
+    keep_alive: Option<Duration>,
+}
+
+/// Implementation of the `EventStream`.
+impl EventStream {
+    /// Creates a stream of the events dispatched on `bus`, without any exposed event type.
+    pub fn new(bus: EventBus) -> Self {
+        Self {
+            bus,
+            events: Vec::new(),
+            filter: None,
+            disconnect_on_lag: false,
+            keep_alive: None,
+        }
+    }
+
+    /// Exposes the event type `E` under its [stable name](MessageName).
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if another exposed event type has the same name.
+    pub fn event<E: Event + MessageName + Serialize>(mut self) -> Self {
+        assert!(
+            self.events.iter().all(|(name, _)| *name != E::NAME),
+            "Event name {:?} is already exposed",
+            E::NAME
+        );
+        self.events.push((E::NAME, subscribe::<E>));
+        self
+    }
+
+    /// Only sends the events for which `filter` returns `true`.
+    ///
+    /// The filter receives the principal found in the request extensions, if any, and the stable
+    /// name and JSON data of the event.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use qonduit::event::EventBus;
+    /// use qonduit::event_stream::EventStream;
+    /// use qonduit::registry::EventHandlerRegistry;
+    ///
+    /// // Only streams the events concerning the customer of the connection
+    /// let stream = EventStream::new(EventBus::new(EventHandlerRegistry::new()))
+    ///     .with_filter(|principal, _name, data| {
+    ///         principal
+    ///             .and_then(|principal| principal.id())
+    ///             .is_some_and(|id| data["customer_id"] == id)
+    ///     });
+    /// # drop(stream);
+    /// ```
+    pub fn with_filter(
+        mut self,
+        filter: impl Fn(Option<&Principal>, &str, &Value) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+
+    /// Ends the stream of a connection after sending it a `lagged` event, instead of resuming it.
+    pub fn with_disconnect_on_lag(mut self) -> Self {
+        self.disconnect_on_lag = true;
+        self
+    }
+
+    /// Sends a comment to every connection that stayed idle for `interval`, so that idle
+    /// connections are not closed by proxies.
+    pub fn with_keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+
+    /// Returns the router serving `GET /events`.
+    ///
+    /// The router can be nested under a prefix or merged with other routes of the application.
+    pub fn into_router(self) -> Router {
+        let stream = Arc::new(self);
+        let handler = move |request: Request| async move { stream.respond(request) };
+        Router::new().route("/events", get(handler))
+    }
+
+    /// Subscribes a new connection to the requested event types.
+    fn respond(&self, request: Request) -> Response {
+        let names = match QueryString::<Params>::try_from_uri(request.uri()) {
+            Ok(QueryString(params)) => params.events.unwrap_or_default(),
+            Err(rejection) => {
+                return bad_request(
+                    json!({ "error": "invalid_query", "message": rejection.body_text() }),
+                );
+            }
+        };
+        let mut selected = Vec::new();
+        for name in names.split(',').filter(|name| !name.is_empty()) {
+            match self.events.iter().find(|(exposed, _)| *exposed == name) {
+                Some(event) => selected.push(event),
+                None => return bad_request(json!({ "error": "unknown_event", "name": name })),
+            }
+        }
+        if selected.is_empty() {
+            selected.extend(&self.events);
+        }
+
+        let items: Vec<BoxStream<'static, Item>> = selected
+            .into_iter()
+            .map(|(_, subscribe)| subscribe(&self.bus))
+            .collect();
+
+        let principal = request.extensions().get::<Principal>().cloned();
+        let filter = self.filter.clone();
+        let disconnect_on_lag = self.disconnect_on_lag;
+        let events = stream::unfold(
+            (stream::select_all(items), false),
+            move |(mut items, done)| {
+                let principal = principal.clone();
+                let filter = filter.clone();
+                async move {
+                    if done {
+                        return None;
+                    }
+                    loop {
+                        let event = match items.next().await? {
+                            Item::Event { name, data } => {
+                                let allowed = filter
+                                    .as_ref()
+                                    .is_none_or(|filter| filter(principal.as_ref(), name, &data));
+                                if !allowed {
+                                    continue;
+                                }
+                                SseEvent::default().event(name).data(data.to_string())
+                            }
+                            Item::Lagged(skipped) => {
+                                let event = SseEvent::default()
+                                    .event("lagged")
+                                    .data(json!({ "skipped": skipped }).to_string());
+                                return Some((Ok(event), (items, disconnect_on_lag)));
+                            }
+                        };
+                        return Some((Ok::<_, Infallible>(event), (items, false)));
+                    }
+                }
+            },
+        );
+        match self.keep_alive {
+            Some(interval) => Sse::new(events)
+                .keep_alive(KeepAlive::new().interval(interval))
+                .into_response(),
+            None => Sse::new(events).into_response(),
+        }
+    }
+}
+
+/// Debug implementation for `EventStream`
+impl Debug for EventStream {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("EventStream")
+            .field("bus", &self.bus)
+            .field(
+                "events",
+                &self.events.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            )
+            .field("disconnect_on_lag", &self.disconnect_on_lag)
+            .field("keep_alive", &self.keep_alive)
+            .finish_non_exhaustive()
+    }
+}
+
+fn subscribe<E: Event + MessageName + Serialize>(bus: &EventBus) -> BoxStream<'static, Item> {
+    stream::unfold(bus.subscribe::<E>(), |mut subscription| async move {
+        loop {
+            let item = match subscription.recv().await {
+                Ok(event) => match serde_json::to_value(&event) {
+                    Ok(data) => Item::Event {
+                        name: E::NAME,
+                        data,
+                    },
+                    // An event that cannot be represented as JSON is not streamed
+                    Err(_) => continue,
+                },
+                Err(SubscriptionError::Lagged(skipped)) => Item::Lagged(skipped),
+                Err(SubscriptionError::Closed) => return None,
+            };
+            return Some((item, subscription));
+        }
+    })
+    .boxed()
+}
+
+/// Rejects a connection whose query string is invalid.
+fn bad_request(body: Value) -> Response {
+    (StatusCode::BAD_REQUEST, Json(body)).into_response()
+}
@@ -0,0 +1,346 @@
+//! The `gateway` module exposes commands and queries over HTTP with [axum](https://docs.rs/axum).
+//!
+//! This module is available with the `axum` feature. A [Gateway] turns a `CommandBus` and a
+//! `QueryBus` into an `axum::Router` with one route per registered message type, named after its
+//! [stable name](crate::message::MessageName):
+//!
+//! - `POST /commands/{name}`: Dispatches the command deserialized from the JSON body.
+//! - `GET /queries/{name}`: Dispatches the query deserialized from the query string.
+//! - `POST /queries/{name}`: Dispatches the query deserialized from the JSON body.
+//!
+//! A successful dispatch responds with `200 OK` and the response serialized as JSON. A failed
+//! dispatch responds with a JSON object whose `error` field is the
+//! [kind](crate::error::DispatchError::kind) of failure, and whose status code is given by the
+//! [HttpStatus] trait: handler errors choose their own status code, while failures raised by the
+//! bus are mapped as follows:
+//!
+//! - `timeout`: `504 Gateway Timeout`.
+//! - `circuit_open` and `bulkhead_full`: `503 Service Unavailable`.
+//! - `rate_limited`: `429 Too Many Requests`, with a `Retry-After` header.
+//! - `invalid`: `422 Unprocessable Entity`, with the `violations`.
+//! - `forbidden`: `403 Forbidden`.
+//! - `transport`: `502 Bad Gateway`.
+//! - `unauthenticated`: `401 Unauthorized`.
+//!
+//! A body that cannot be deserialized into the message type is rejected with `400 Bad Request`
+//! and an `invalid_payload` error, and names that are not exposed with `404 Not Found` and an
+//! `unknown_message` error.
+//!
+//! If the request carries a [Principal] in its extensions,
+//! typically inserted by an authentication middleware, the message is dispatched on its behalf.
+//!
+//! - [Gateway]: Builds the router exposing the registered message types.
+//! - [HttpStatus]: Maps errors to HTTP status codes.
+
+use axum::Json;
+use axum::Router;
+use axum::extract::FromRequest;
+use axum::extract::Path;
+use axum::extract::Query as QueryString;
+use axum::extract::Request;
+use axum::http::HeaderValue;
+use axum::http::StatusCode;
+use axum::http::header::RETRY_AFTER;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::routing::get;
+use axum::routing::post;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use serde_json::json;
+
+use crate::authorization::Principal;
+use crate::command::Command;
+use crate::command::CommandBus;
+use crate::error::DispatchError;
+use crate::message::MessageName;
+use crate::query::Query;
+use crate::query::QueryBus;
+
+/// The `HttpStatus` trait maps an error to the status code of its HTTP response.
+///
+/// It must be implemented by the `Error` type of every command and query exposed by a [Gateway].
+///
+/// # Example
+///
+/// ```
+/// use axum::http::StatusCode;
+/// use qonduit::gateway::HttpStatus;
+///
+/// #[derive(Debug, serde::Serialize)]
+/// enum PlaceOrderError {
+///     OutOfStock,
+///     UnknownCustomer,
+/// }
+///
+/// impl HttpStatus for PlaceOrderError {
+///     fn status(&self) -> StatusCode {
+///         match self {
+///             PlaceOrderError::OutOfStock => StatusCode::CONFLICT,
+///             PlaceOrderError::UnknownCustomer => StatusCode::NOT_FOUND,
+///         }
+///     }
+/// }
+/// ```
+pub trait HttpStatus {
+    /// Returns the status code of the HTTP response reporting the error.
+    fn status(&self) -> StatusCode;
+}
+
+/// HttpStatus implementation for `DispatchError`
+impl<E: HttpStatus> HttpStatus for DispatchError<E> {
+    fn status(&self) -> StatusCode {
+        match self {
+            DispatchError::Handler(error) => error.status(),
+            DispatchError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            DispatchError::CircuitOpen | DispatchError::BulkheadFull => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            DispatchError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            DispatchError::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            DispatchError::Forbidden(_) => StatusCode::FORBIDDEN,
+            DispatchError::Transport(_) => StatusCode::BAD_GATEWAY,
+            DispatchError::Unauthenticated(_) => StatusCode::UNAUTHORIZED,
+        }
+    }
+}
+
+/// The `Gateway` builds an `axum::Router` exposing commands and queries over HTTP.
+///
+/// # Example
+///
+/// ```
+/// # use qonduit::async_trait;
+/// # use qonduit::command::{Command, CommandHandler};
+/// # use qonduit::message::MessageName;
+/// # use qonduit::query::{Query, QueryHandler};
+/// #
+/// # #[derive(Debug, serde::Serialize)]
+/// # struct PlaceOrderError;
+/// #
+/// # impl qonduit::gateway::HttpStatus for PlaceOrderError {
+/// #   fn status(&self) -> axum::http::StatusCode { axum::http::StatusCode::CONFLICT }
+/// # }
+/// #
+/// # #[derive(Debug, serde::Deserialize)]
+/// # struct PlaceOrderCommand { sku: String }
+/// #
+/// # impl Command for PlaceOrderCommand {
+/// #   type Response = u64;
+/// #   type Error = PlaceOrderError;
+/// # }
+/// #
+/// # impl MessageName for PlaceOrderCommand {
+/// #   const NAME: &'static str = "orders.place.v1";
+/// # }
+/// #
+/// # struct PlaceOrderCommandHandler;
+/// #
+/// # #[async_trait]
+/// # impl CommandHandler<PlaceOrderCommand> for PlaceOrderCommandHandler {
+/// #   async fn handle(&self, _command: PlaceOrderCommand) -> Result<u64, PlaceOrderError> {
+/// #     Ok(1)
+/// #   }
+/// # }
+/// use qonduit::command::CommandBus;
+/// use qonduit::gateway::Gateway;
+/// use qonduit::query::QueryBus;
+/// use qonduit::registry::{CommandHandlerRegistry, QueryHandlerRegistry};
+///
+/// let mut registry = CommandHandlerRegistry::new();
+/// registry.register_named::<PlaceOrderCommand>(PlaceOrderCommandHandler);
+/// let command_bus = CommandBus::new(registry);
+/// let query_bus = QueryBus::new(QueryHandlerRegistry::new());
+///
+/// // Serves `POST /commands/orders.place.v1`
+/// let router: axum::Router = Gateway::new(command_bus, query_bus)
+///     .command::<PlaceOrderCommand>()
+///     .into_router();
+/// ```
+#[derive(Clone, Debug)]
+pub struct Gateway {
+    #[doc(hidden)]
+    command_bus: CommandBus,
+    #[doc(hidden)]
+    query_bus: QueryBus,
+    #[doc(hidden)]
+    router: Router,
+}
+
+/// Implementation of the `Gateway`.
+impl Gateway {
+    /// Creates a gateway dispatching on `command_bus` and `query_bus`, without any route.
+    pub fn new(command_bus: CommandBus, query_bus: QueryBus) -> Self {
+        Self {
+            command_bus,
+            query_bus,
+            router: Router::new(),
+        }
+    }
+
+    /// Exposes the command type `C` as `POST /commands/{name}`, where `name` is its
+    /// [stable name](MessageName).
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if no handler is registered for `C` on the command bus, and `C` is
+    /// not routed to a remote node.
+    pub fn command<C>(mut self) -> Self
+    where
+        C: Command + MessageName + DeserializeOwned,
+        C::Response: Serialize,
+        C::Error: Serialize + HttpStatus,
+    {
+        assert!(
+            self.command_bus.handles::<C>(),
+            "No handler registered for command: {:?}",
+            C::NAME
+        );
+        let bus = self.command_bus.clone();
+        let handler = move |request: Request| async move {
+            let principal = request.extensions().get::<Principal>().cloned();
+            let command = match Json::<C>::from_request(request, &()).await {
+                Ok(Json(command)) => command,
+                Err(rejection) => return invalid_payload(rejection.body_text()),
+            };
+            let result = match principal {
+                Some(principal) => bus.dispatch_as(principal, command).await,
+                None => bus.dispatch(command).await,
+            };
+            respond(result)
+        };
+        self.router = self
+            .router
+            .route(&format!("/commands/{}", C::NAME), post(handler));
+        self
+    }
+
+    /// Exposes the query type `Q` as `GET /queries/{name}`, reading the query from the query
+    /// string, and as `POST /queries/{name}`, reading it from the JSON body, where `name` is its
+    /// [stable name](MessageName).
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if no handler is registered for `Q` on the query bus.
+    pub fn query<Q>(mut self) -> Self
+    where
+        Q: Query + MessageName + DeserializeOwned,
+        Q::Response: Serialize,
+        Q::Error: Serialize + HttpStatus,
+    {
+        assert!(
+            self.query_bus.handles::<Q>(),
+            "No handler registered for query: {:?}",
+            Q::NAME
+        );
+        let get_bus = self.query_bus.clone();
+        let get_handler = move |request: Request| async move {
+            let principal = request.extensions().get::<Principal>().cloned();
+            let query = match QueryString::<Q>::try_from_uri(request.uri()) {
+                Ok(QueryString(query)) => query,
+                Err(rejection) => return invalid_payload(rejection.body_text()),
+            };
+            dispatch_query(&get_bus, principal, query).await
+        };
+        let post_bus = self.query_bus.clone();
+        let post_handler = move |request: Request| async move {
+            let principal = request.extensions().get::<Principal>().cloned();
+            let query = match Json::<Q>::from_request(request, &()).await {
+                Ok(Json(query)) => query,
+                Err(rejection) => return invalid_payload(rejection.body_text()),
+            };
+            dispatch_query(&post_bus, principal, query).await
+        };
+        self.router = self.router.route(
+            &format!("/queries/{}", Q::NAME),
+            get(get_handler).post(post_handler),
+        );
+        self
+    }
+
+    /// Returns the router serving the registered routes.
+    ///
+    /// The router can be nested under a prefix or merged with other routes of the application.
+    pub fn into_router(self) -> Router {
+        self.router
+            .route("/commands/{name}", post(unknown_message))
+            .route(
+                "/queries/{name}",
+                get(unknown_message).post(unknown_message),
+            )
+    }
+}
+
+async fn dispatch_query<Q>(bus: &QueryBus, principal: Option<Principal>, query: Q) -> Response
+where
+    Q: Query,
+    Q::Response: Serialize,
+    Q::Error: Serialize + HttpStatus,
+{
+    let result = match principal {
+        Some(principal) => bus.dispatch_as(principal, query).await,
+        None => bus.dispatch(query).await,
+    };
+    respond(result)
+}
+
+/// Converts the outcome of a dispatch into a response.
+fn respond<R, E>(result: Result<R, DispatchError<E>>) -> Response
+where
+    R: Serialize,
+    E: Serialize + HttpStatus,
+{
+    let error = match result {
+        Ok(response) => return Json(response).into_response(),
+        Err(error) => error,
+    };
+
+    let status = error.status();
+    let mut body = json!({ "error": error.kind() });
+    let mut retry_after = None;
+    match error {
+        DispatchError::Handler(error) => {
+            body["details"] = serde_json::to_value(error).unwrap_or(Value::Null);
+        }
+        DispatchError::Timeout(budget) => {
+            body["timeout_ms"] = json!(budget.as_millis() as u64);
+        }
+        DispatchError::RateLimited {
+            retry_after: duration,
+        } => {
+            body["retry_after_ms"] = json!(duration.as_millis() as u64);
+            retry_after = Some(duration.as_secs_f64().ceil() as u64);
+        }
+        DispatchError::Invalid(violations) => {
+            body["violations"] = serde_json::to_value(violations).unwrap_or(Value::Null);
+        }
+        _ => {}
+    }
+
+    let mut response = (status, Json(body)).into_response();
+    if let Some(seconds) = retry_after {
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(seconds));
+    }
+    response
+}
+
+/// Responds to a request for a message type that is not exposed.
+async fn unknown_message(Path(name): Path<String>) -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({ "error": "unknown_message", "name": name })),
+    )
+        .into_response()
+}
+
+/// Responds to a request whose payload cannot be deserialized into the message type.
+fn invalid_payload(message: String) -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({ "error": "invalid_payload", "message": message })),
+    )
+        .into_response()
+}
@@ -0,0 +1,405 @@
+//! The `graphql` module exposes commands, queries and events through an
+//! [async-graphql](https://docs.rs/async-graphql) schema.
+//!
+//! This module is available with the `async-graphql` feature. The buses are added to the schema
+//! data. A [DynamicSchema] generates the whole schema from the exposed message types, without any
+//! resolver to write: each query becomes a field, each command a mutation and each event type a
+//! subscription, taking the message as a `JSON` input and returning the response or event as
+//! `JSON`. Schemas written with the async-graphql macros forward their arguments to the buses with
+//! a single call instead:
+//!
+//! - [dispatch_query]: Resolves a query field by dispatching a `Query` on the `QueryBus`.
+//! - [dispatch_command]: Resolves a mutation by dispatching a `Command` on the `CommandBus`.
+//! - [subscribe]: Resolves a subscription with the events of a type published on the `EventBus`.
+//!
+//! If the schema or request data holds a [Principal], messages are dispatched on its behalf.
+//!
+//! A failed dispatch is converted into a GraphQL error by [into_graphql_error]. Its message is
+//! the `Display` rendering of the failure, and its `code` extension the
+//! [kind](crate::error::DispatchError::kind) of failure, along with the `timeout_ms`,
+//! `retry_after_ms` or `violations` of the failure, if any:
+//!
+//! ```json
+//! {
+//!   "message": "validation failed: quantity: must be positive",
+//!   "extensions": {
+//!     "code": "invalid",
+//!     "violations": [{ "field": "quantity", "code": "not_positive", "message": "must be positive" }]
+//!   }
+//! }
+//! ```
+
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+
+use async_graphql::Context;
+use async_graphql::Error;
+use async_graphql::ErrorExtensions;
+use async_graphql::Result;
+use async_graphql::dynamic;
+use async_graphql::dynamic::FieldFuture;
+use async_graphql::dynamic::FieldValue;
+use async_graphql::dynamic::InputValue;
+use async_graphql::dynamic::ResolverContext;
+use async_graphql::dynamic::SubscriptionFieldFuture;
+use async_graphql::dynamic::TypeRef;
+use futures_util::Stream;
+use futures_util::StreamExt;
+use futures_util::stream;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::authorization::Principal;
+use crate::command::Command;
+use crate::command::CommandBus;
+use crate::error::DispatchError;
+use crate::event::Event;
+use crate::event::EventBus;
+use crate::query::Query;
+use crate::query::QueryBus;
+use crate::subscription::SubscriptionError;
+
+/// Dispatches `query` on the `QueryBus` of the schema data and returns its response.
+///
+/// # Errors
+///
+/// Returns an error if the schema data holds no `QueryBus`, or the
+/// [converted](into_graphql_error) `DispatchError` if the dispatch fails.
+///
+/// # Example
+///
+/// ```
+/// # use qonduit::async_trait;
+/// # use qonduit::query::{Query, QueryHandler};
+/// #
+/// # #[derive(Debug)]
+/// # struct FindProductQuery { id: u64 }
+/// #
+/// # impl Query for FindProductQuery {
+/// #   type Response = Option<String>;
+/// #   type Error = String;
+/// # }
+/// #
+/// # struct FindProductQueryHandler;
+/// #
+/// # #[async_trait]
+/// # impl QueryHandler<FindProductQuery> for FindProductQueryHandler {
+/// #   async fn handle(&self, query: FindProductQuery) -> Result<Option<String>, String> {
+/// #     Ok((query.id == 1).then(|| "Keyboard".to_string()))
+/// #   }
+/// # }
+/// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// # rt.block_on(async {
+/// use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Schema};
+/// use qonduit::graphql::dispatch_query;
+/// use qonduit::query::QueryBus;
+/// use qonduit::registry::QueryHandlerRegistry;
+///
+/// struct QueryRoot;
+///
+/// #[Object]
+/// impl QueryRoot {
+///     async fn product_name(&self, ctx: &Context<'_>, id: u64) -> async_graphql::Result<Option<String>> {
+///         dispatch_query(ctx, FindProductQuery { id }).await
+///     }
+/// }
+///
+/// let mut registry = QueryHandlerRegistry::new();
+/// registry.register::<FindProductQuery>(FindProductQueryHandler);
+/// let schema = Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+///     .data(QueryBus::new(registry))
+///     .finish();
+///
+/// let response = schema.execute("{ productName(id: 1) }").await;
+/// assert_eq!(response.data.to_string(), r#"{productName: "Keyboard"}"#);
+/// # });
+/// ```
+pub async fn dispatch_query<Q>(ctx: &Context<'_>, query: Q) -> Result<Q::Response>
+where
+    Q: Query,
+    Q::Error: Display,
+{
+    let bus = ctx.data::<QueryBus>()?;
+    let result = match ctx.data_opt::<Principal>() {
+        Some(principal) => bus.dispatch_as(principal.clone(), query).await,
+        None => bus.dispatch(query).await,
+    };
+    result.map_err(into_graphql_error)
+}
+
+/// Dispatches `command` on the `CommandBus` of the schema data and returns its response.
+///
+/// # Errors
+///
+/// Returns an error if the schema data holds no `CommandBus`, or the
+/// [converted](into_graphql_error) `DispatchError` if the dispatch fails.
+pub async fn dispatch_command<C>(ctx: &Context<'_>, command: C) -> Result<C::Response>
+where
+    C: Command,
+    C::Error: Display,
+{
+    let bus = ctx.data::<CommandBus>()?;
+    let result = match ctx.data_opt::<Principal>() {
+        Some(principal) => bus.dispatch_as(principal.clone(), command).await,
+        None => bus.dispatch(command).await,
+    };
+    result.map_err(into_graphql_error)
+}
+
+/// Subscribes to the events of type `E` published on the `EventBus` of the schema data.
+///
+/// The stream yields every event dispatched while the GraphQL subscription is active. A
+/// subscriber lagging behind receives an error whose `code` extension is `lagged` and whose
+/// `skipped` extension is the number of events it missed, then the stream resumes. See the
+/// [subscription](crate::subscription) module.
+///
+/// # Errors
+///
+/// Returns an error if the schema data holds no `EventBus`.
+///
+/// # Example
+///
+/// ```
+/// use async_graphql::{Context, SimpleObject, Subscription};
+/// use futures_util::Stream;
+/// use qonduit::event::Event;
+/// use qonduit::graphql::subscribe;
+///
+/// #[derive(Clone, Debug, SimpleObject)]
+/// struct OrderShippedEvent { order_id: u64 }
+/// impl Event for OrderShippedEvent {}
+///
+/// struct SubscriptionRoot;
+///
+/// #[Subscription]
+/// impl SubscriptionRoot {
+///     async fn order_shipped(
+///         &self,
+///         ctx: &Context<'_>,
+///     ) -> async_graphql::Result<impl Stream<Item = async_graphql::Result<OrderShippedEvent>>> {
+///         subscribe::<OrderShippedEvent>(ctx)
+///     }
+/// }
+/// ```
+pub fn subscribe<E: Event>(ctx: &Context<'_>) -> Result<impl Stream<Item = Result<E>> + use<E>> {
+    let subscription = ctx.data::<EventBus>()?.subscribe::<E>();
+    Ok(stream::unfold(
+        subscription,
+        |mut subscription| async move {
+            let item = match subscription.recv().await {
+                Ok(event) => Ok(event),
+                Err(SubscriptionError::Lagged(skipped)) => Err(Error::new(format!(
+                    "subscriber lagged behind and missed {skipped} events"
+                ))
+                .extend_with(|_, extensions| {
+                    extensions.set("code", "lagged");
+                    extensions.set("skipped", skipped);
+                })),
+                Err(SubscriptionError::Closed) => return None,
+            };
+            Some((item, subscription))
+        },
+    ))
+}
+
+/// The name of the scalar type of the inputs and outputs of a [DynamicSchema].
+const JSON: &str = "JSON";
+
+/// The `DynamicSchema` generates a GraphQL schema exposing queries, commands and events.
+///
+/// Every exposed query is a field of the `Query` type, every command a field of the `Mutation`
+/// type, and every event type a field of the `Subscription` type. Queries and commands take the
+/// message as their `input` argument, a `JSON` value deserialized into the message type (`null`
+/// when omitted), and return the response serialized as `JSON`. Subscriptions yield each event
+/// serialized as `JSON`. Failures are reported as described in the [graphql](crate::graphql)
+/// module.
+///
+/// # Example
+///
+/// ```
+/// # use qonduit::async_trait;
+/// # use qonduit::query::{Query, QueryHandler};
+/// #
+/// # #[derive(Debug, serde::Deserialize)]
+/// # struct FindProductQuery { id: u64 }
+/// #
+/// # impl Query for FindProductQuery {
+/// #   type Response = Option<String>;
+/// #   type Error = String;
+/// # }
+/// #
+/// # struct FindProductQueryHandler;
+/// #
+/// # #[async_trait]
+/// # impl QueryHandler<FindProductQuery> for FindProductQueryHandler {
+/// #   async fn handle(&self, query: FindProductQuery) -> Result<Option<String>, String> {
+/// #     Ok((query.id == 1).then(|| "Keyboard".to_string()))
+/// #   }
+/// # }
+/// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// # rt.block_on(async {
+/// use qonduit::graphql::DynamicSchema;
+/// use qonduit::query::QueryBus;
+/// use qonduit::registry::QueryHandlerRegistry;
+///
+/// let mut registry = QueryHandlerRegistry::new();
+/// registry.register::<FindProductQuery>(FindProductQueryHandler);
+/// let schema = DynamicSchema::new()
+///     .query::<FindProductQuery>("productName")
+///     .into_builder()
+///     .data(QueryBus::new(registry))
+///     .finish()
+///     .unwrap();
+///
+/// let response = schema.execute("{ productName(input: { id: 1 }) }").await;
+/// assert_eq!(response.data.to_string(), r#"{productName: "Keyboard"}"#);
+/// # });
+/// ```
+pub struct DynamicSchema {
+    #[doc(hidden)]
+    query: dynamic::Object,
+    #[doc(hidden)]
+    mutation: Option<dynamic::Object>,
+    #[doc(hidden)]
+    subscription: Option<dynamic::Subscription>,
+}
+
+/// Implementation of the `DynamicSchema`.
+impl DynamicSchema {
+    /// Creates a schema without any field.
+    pub fn new() -> Self {
+        Self {
+            query: dynamic::Object::new("Query"),
+            mutation: None,
+            subscription: None,
+        }
+    }
+
+    /// Exposes the query type `Q` as the field `name` of the `Query` type.
+    pub fn query<Q>(mut self, name: &str) -> Self
+    where
+        Q: Query + DeserializeOwned,
+        Q::Response: Serialize,
+        Q::Error: Display,
+    {
+        let field = dynamic::Field::new(name, TypeRef::named(JSON), |ctx| {
+            FieldFuture::new(async move {
+                let query = input::<Q>(&ctx)?;
+                let response = dispatch_query(ctx.ctx, query).await?;
+                Ok(Some(FieldValue::value(async_graphql::to_value(response)?)))
+            })
+        });
+        self.query = self.query.field(field.argument(input_argument()));
+        self
+    }
+
+    /// Exposes the command type `C` as the field `name` of the `Mutation` type.
+    pub fn command<C>(mut self, name: &str) -> Self
+    where
+        C: Command + DeserializeOwned,
+        C::Response: Serialize,
+        C::Error: Display,
+    {
+        let field = dynamic::Field::new(name, TypeRef::named(JSON), |ctx| {
+            FieldFuture::new(async move {
+                let command = input::<C>(&ctx)?;
+                let response = dispatch_command(ctx.ctx, command).await?;
+                Ok(Some(FieldValue::value(async_graphql::to_value(response)?)))
+            })
+        });
+        let mutation = self
+            .mutation
+            .unwrap_or_else(|| dynamic::Object::new("Mutation"));
+        self.mutation = Some(mutation.field(field.argument(input_argument())));
+        self
+    }
+
+    /// Exposes the events of type `E` as the field `name` of the `Subscription` type.
+    pub fn event<E: Event + Serialize>(mut self, name: &str) -> Self {
+        let field = dynamic::SubscriptionField::new(name, TypeRef::named_nn(JSON), |ctx| {
+            SubscriptionFieldFuture::new(async move {
+                let events = subscribe::<E>(ctx.ctx)?;
+                Ok(events.map(|event| Ok(FieldValue::value(async_graphql::to_value(event?)?))))
+            })
+        });
+        let subscription = self
+            .subscription
+            .unwrap_or_else(|| dynamic::Subscription::new("Subscription"));
+        self.subscription = Some(subscription.field(field));
+        self
+    }
+
+    /// Returns the builder of the schema, to add the buses to its data and finish it.
+    ///
+    /// The schema must expose at least one query for the builder to finish.
+    pub fn into_builder(self) -> dynamic::SchemaBuilder {
+        let mut builder = dynamic::Schema::build(
+            self.query.type_name(),
+            self.mutation.as_ref().map(dynamic::Object::type_name),
+            self.subscription
+                .as_ref()
+                .map(dynamic::Subscription::type_name),
+        )
+        .register(dynamic::Scalar::new(JSON))
+        .register(self.query);
+        if let Some(mutation) = self.mutation {
+            builder = builder.register(mutation);
+        }
+        if let Some(subscription) = self.subscription {
+            builder = builder.register(subscription);
+        }
+        builder
+    }
+}
+
+/// Default implementation for `DynamicSchema`
+impl Default for DynamicSchema {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Debug implementation for `DynamicSchema`
+impl Debug for DynamicSchema {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("DynamicSchema").finish_non_exhaustive()
+    }
+}
+
+/// Returns the `input` argument of the fields of a [DynamicSchema].
+fn input_argument() -> InputValue {
+    InputValue::new("input", TypeRef::named(JSON))
+}
+
+/// Deserializes the `input` argument of a field into the message type `T`.
+fn input<T: DeserializeOwned>(ctx: &ResolverContext<'_>) -> Result<T> {
+    match ctx.args.get("input") {
+        Some(input) => input.deserialize(),
+        None => Ok(serde_json::from_value(serde_json::Value::Null)?),
+    }
+}
+
+/// Converts a `DispatchError` into a GraphQL error.
+///
+/// See the [graphql](crate::graphql) module for the extensions of the error.
+pub fn into_graphql_error<E: Display>(error: DispatchError<E>) -> Error {
+    Error::new(error.to_string()).extend_with(|_, extensions| {
+        extensions.set("code", error.kind());
+        match &error {
+            DispatchError::Timeout(budget) => {
+                extensions.set("timeout_ms", budget.as_millis() as u64);
+            }
+            DispatchError::RateLimited { retry_after } => {
+                extensions.set("retry_after_ms", retry_after.as_millis() as u64);
+            }
+            DispatchError::Invalid(violations) => {
+                if let Ok(violations) = async_graphql::to_value(violations) {
+                    extensions.set("violations", violations);
+                }
+            }
+            _ => {}
+        }
+    })
+}
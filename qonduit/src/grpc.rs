@@ -0,0 +1,556 @@
+//! The `grpc` module exposes commands and queries over gRPC with [tonic](https://docs.rs/tonic),
+//! and dispatches them to a remote process.
+//!
+//! This module is available with the `tonic` feature. Rather than generating a gRPC method per
+//! message type, a single generic service routes envelopes carrying the
+//! [stable name](crate::message::MessageName) of a message and its payload, encoded with a
+//! [Codec]:
+//!
+//! ```protobuf
+//! syntax = "proto3";
+//!
+//! package qonduit.v1;
+//!
+//! service Gateway {
+//!   rpc Command(Envelope) returns (Reply);
+//!   rpc Query(Envelope) returns (Reply);
+//! }
+//!
+//! message Envelope {
+//!   string name = 1;
+//!   bytes payload = 2;
+//! }
+//!
+//! message Reply {
+//!   bytes payload = 1;
+//! }
+//! ```
+//!
+//! A successful dispatch replies with the encoded response. A failed dispatch replies with a
+//! status whose details hold the encoded `DispatchError`, and whose code is given by the
+//! [GrpcCode] trait: handler errors choose their own code, while failures raised by the bus are
+//! mapped as follows:
+//!
+//! - `timeout`: `DEADLINE_EXCEEDED`.
+//! - `circuit_open`, `bulkhead_full` and `transport`: `UNAVAILABLE`.
+//! - `rate_limited`: `RESOURCE_EXHAUSTED`.
+//! - `invalid`: `INVALID_ARGUMENT`.
+//! - `forbidden`: `PERMISSION_DENIED`.
+//! - `unauthenticated`: `UNAUTHENTICATED`.
+//!
+//! An envelope whose name is not exposed is rejected with `NOT_FOUND`, and a payload that cannot
+//! be decoded into the message type with `INVALID_ARGUMENT`, both without details.
+//!
+//! If the request carries a [Principal] in its extensions,
+//! typically inserted by an interceptor authenticating the caller, the message is dispatched on
+//! its behalf.
+//!
+//! On the client side, [GrpcCommandBus] and [GrpcQueryBus] dispatch messages to a remote
+//! [GrpcGateway] with the same typed signature as the local buses, decoding the outcome back
+//! into the response or `DispatchError` of the message type.
+//!
+//! - [GrpcGateway]: The gRPC service dispatching envelopes on a `CommandBus` and a `QueryBus`.
+//! - [GrpcCommandBus]: Dispatches commands to a remote gateway.
+//! - [GrpcQueryBus]: Dispatches queries to a remote gateway.
+//! - [GrpcCode]: Maps errors to gRPC status codes.
+//! - [Envelope]: A message and its stable name.
+//! - [Reply]: The response to a message.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tonic::Code;
+use tonic::Request;
+use tonic::Response;
+use tonic::Status;
+use tonic::body::Body as GrpcBody;
+use tonic::codegen::Body;
+use tonic::codegen::Bytes;
+use tonic::codegen::Service;
+use tonic::codegen::StdError;
+use tonic::codegen::http;
+use tonic::server::NamedService;
+use tonic::server::UnaryService;
+use tonic::transport::Channel;
+use tonic_prost::ProstCodec;
+
+use crate::authorization::Principal;
+use crate::codec::Codec;
+use crate::command::Command;
+use crate::command::CommandBus;
+use crate::error::DispatchError;
+use crate::message::MessageName;
+use crate::query::Query;
+use crate::query::QueryBus;
+use crate::util::BoxFuture;
+
+/// The fully qualified name of the gRPC service.
+pub const SERVICE_NAME: &str = "qonduit.v1.Gateway";
+
+const COMMAND_PATH: &str = "/qonduit.v1.Gateway/Command";
+const QUERY_PATH: &str = "/qonduit.v1.Gateway/Query";
+
+/// The `Envelope` carries an encoded message and the stable name of its type.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Envelope {
+    /// The stable name of the message type.
+    #[prost(string, tag = "1")]
+    pub name: String,
+
+    /// The message, encoded with the codec of the gateway.
+    #[prost(bytes = "vec", tag = "2")]
+    pub payload: Vec<u8>,
+}
+
+/// The `Reply` carries the encoded response to a message.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Reply {
+    /// The response, encoded with the codec of the gateway.
+    #[prost(bytes = "vec", tag = "1")]
+    pub payload: Vec<u8>,
+}
+
+/// The `GrpcCode` trait maps an error to the code of its gRPC status.
+///
+/// It must be implemented by the `Error` type of every command and query exposed by a
+/// [GrpcGateway].
+///
+/// # Example
+///
+/// ```
+/// use qonduit::grpc::GrpcCode;
+/// use tonic::Code;
+///
+/// #[derive(Debug, serde::Serialize)]
+/// enum PlaceOrderError {
+///     OutOfStock,
+///     UnknownCustomer,
+/// }
+///
+/// impl GrpcCode for PlaceOrderError {
+///     fn code(&self) -> Code {
+///         match self {
+///             PlaceOrderError::OutOfStock => Code::FailedPrecondition,
+///             PlaceOrderError::UnknownCustomer => Code::NotFound,
+///         }
+///     }
+/// }
+/// ```
+pub trait GrpcCode {
+    /// Returns the code of the gRPC status reporting the error.
+    fn code(&self) -> Code;
+}
+
+/// GrpcCode implementation for `DispatchError`
+impl<E: GrpcCode> GrpcCode for DispatchError<E> {
+    fn code(&self) -> Code {
+        match self {
+            DispatchError::Handler(error) => error.code(),
+            DispatchError::Timeout(_) => Code::DeadlineExceeded,
+            DispatchError::CircuitOpen
+            | DispatchError::BulkheadFull
+            | DispatchError::Transport(_) => Code::Unavailable,
+            DispatchError::RateLimited { .. } => Code::ResourceExhausted,
+            DispatchError::Invalid(_) => Code::InvalidArgument,
+            DispatchError::Forbidden(_) => Code::PermissionDenied,
+            DispatchError::Unauthenticated(_) => Code::Unauthenticated,
+        }
+    }
+}
+
+/// Decodes, dispatches and encodes the outcome of a message of an exposed type.
+type Route =
+    Arc<dyn Fn(Option<Principal>, Vec<u8>) -> BoxFuture<Result<Reply, Status>> + Send + Sync>;
+
+/// The `GrpcGateway` is a gRPC service dispatching envelopes on a `CommandBus` and a `QueryBus`.
+///
+/// It can be served by `tonic::transport::Server` like any generated service.
+///
+/// # Example
+///
+/// ```
+/// # use qonduit::async_trait;
+/// # use qonduit::command::{Command, CommandHandler};
+/// # use qonduit::message::MessageName;
+/// #
+/// # #[derive(Debug, serde::Serialize, serde::Deserialize)]
+/// # struct PlaceOrderError;
+/// #
+/// # impl qonduit::grpc::GrpcCode for PlaceOrderError {
+/// #   fn code(&self) -> tonic::Code { tonic::Code::FailedPrecondition }
+/// # }
+/// #
+/// # #[derive(Debug, serde::Serialize, serde::Deserialize)]
+/// # struct PlaceOrderCommand { sku: String }
+/// #
+/// # impl Command for PlaceOrderCommand {
+/// #   type Response = u64;
+/// #   type Error = PlaceOrderError;
+/// # }
+/// #
+/// # impl MessageName for PlaceOrderCommand {
+/// #   const NAME: &'static str = "orders.place.v1";
+/// # }
+/// #
+/// # struct PlaceOrderCommandHandler;
+/// #
+/// # #[async_trait]
+/// # impl CommandHandler<PlaceOrderCommand> for PlaceOrderCommandHandler {
+/// #   async fn handle(&self, _command: PlaceOrderCommand) -> Result<u64, PlaceOrderError> {
+/// #     Ok(1)
+/// #   }
+/// # }
+/// use qonduit::codec::JsonCodec;
+/// use qonduit::command::CommandBus;
+/// use qonduit::grpc::GrpcGateway;
+/// use qonduit::query::QueryBus;
+/// use qonduit::registry::{CommandHandlerRegistry, QueryHandlerRegistry};
+///
+/// let mut registry = CommandHandlerRegistry::new();
+/// registry.register_named::<PlaceOrderCommand>(PlaceOrderCommandHandler);
+/// let command_bus = CommandBus::new(registry);
+/// let query_bus = QueryBus::new(QueryHandlerRegistry::new());
+///
+/// let gateway = GrpcGateway::new(command_bus, query_bus, JsonCodec)
+///     .command::<PlaceOrderCommand>();
+/// let server = tonic::transport::Server::builder().add_service(gateway);
+/// # drop(server);
+/// ```
+#[derive(Clone)]
+pub struct GrpcGateway<K: Codec> {
+    #[doc(hidden)]
+    command_bus: CommandBus,
+    #[doc(hidden)]
+    query_bus: QueryBus,
+    #[doc(hidden)]
+    codec: Arc<K>,
+    #[doc(hidden)]
+    commands: Arc<HashMap<&'static str, Route>>,
+    #[doc(hidden)]
+    queries: Arc<HashMap<&'static str, Route>>,
+}
+
+/// Implementation of the `GrpcGateway`.
+impl<K: Codec> GrpcGateway<K> {
+    /// Creates a gateway dispatching on `command_bus` and `query_bus`, encoding messages and
+    /// their outcomes with `codec`, without any exposed message type.
+    pub fn new(command_bus: CommandBus, query_bus: QueryBus, codec: K) -> Self {
+        Self {
+            command_bus,
+            query_bus,
+            codec: Arc::new(codec),
+            commands: Arc::new(HashMap::new()),
+            queries: Arc::new(HashMap::new()),
+        }
+    }
+
+    /// Exposes the command type `C` under its [stable name](MessageName).
+    pub fn command<C>(mut self) -> Self
+    where
+        C: Command + MessageName + DeserializeOwned,
+        C::Response: Serialize,
+        C::Error: Serialize + GrpcCode,
+    {
+        let bus = self.command_bus.clone();
+        let codec = self.codec.clone();
+        let route: Route = Arc::new(move |principal, payload| {
+            let bus = bus.clone();
+            let codec = codec.clone();
+            Box::pin(async move {
+                let command = decode::<C>(codec.as_ref(), &payload)?;
+                let result = match principal {
+                    Some(principal) => bus.dispatch_as(principal, command).await,
+                    None => bus.dispatch(command).await,
+                };
+                respond(codec.as_ref(), result)
+            })
+        });
+        Arc::make_mut(&mut self.commands).insert(C::NAME, route);
+        self
+    }
+
+    /// Exposes the query type `Q` under its [stable name](MessageName).
+    pub fn query<Q>(mut self) -> Self
+    where
+        Q: Query + MessageName + DeserializeOwned,
+        Q::Response: Serialize,
+        Q::Error: Serialize + GrpcCode,
+    {
+        let bus = self.query_bus.clone();
+        let codec = self.codec.clone();
+        let route: Route = Arc::new(move |principal, payload| {
+            let bus = bus.clone();
+            let codec = codec.clone();
+            Box::pin(async move {
+                let query = decode::<Q>(codec.as_ref(), &payload)?;
+                let result = match principal {
+                    Some(principal) => bus.dispatch_as(principal, query).await,
+                    None => bus.dispatch(query).await,
+                };
+                respond(codec.as_ref(), result)
+            })
+        });
+        Arc::make_mut(&mut self.queries).insert(Q::NAME, route);
+        self
+    }
+}
+
+/// Debug implementation for `GrpcGateway`
+impl<K: Codec> Debug for GrpcGateway<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("GrpcGateway")
+            .field("content_type", &self.codec.content_type())
+            .field("commands", &self.commands.keys().collect::<Vec<_>>())
+            .field("queries", &self.queries.keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
+
+/// NamedService implementation for `GrpcGateway`
+impl<K: Codec> NamedService for GrpcGateway<K> {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+/// Service implementation for `GrpcGateway`
+impl<K, B> Service<http::Request<B>> for GrpcGateway<K>
+where
+    K: Codec,
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<GrpcBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let routes = match request.uri().path() {
+            COMMAND_PATH => self.commands.clone(),
+            QUERY_PATH => self.queries.clone(),
+            _ => return Box::pin(async { Ok(Status::unimplemented("").into_http()) }),
+        };
+        Box::pin(async move {
+            let mut grpc = tonic::server::Grpc::new(ProstCodec::default());
+            Ok(grpc.unary(Routes(routes), request).await)
+        })
+    }
+}
+
+/// Routes the envelopes of a gRPC method to the exposed message types.
+struct Routes(Arc<HashMap<&'static str, Route>>);
+
+/// UnaryService implementation for `Routes`
+impl UnaryService<Envelope> for Routes {
+    type Response = Reply;
+    type Future = BoxFuture<Result<Response<Reply>, Status>>;
+
+    fn call(&mut self, request: Request<Envelope>) -> Self::Future {
+        let principal = request.extensions().get::<Principal>().cloned();
+        let envelope = request.into_inner();
+        let Some(route) = self.0.get(envelope.name.as_str()) else {
+            let status = Status::not_found(format!("unknown message name {:?}", envelope.name));
+            return Box::pin(async { Err(status) });
+        };
+        let reply = route(principal, envelope.payload);
+        Box::pin(async move { reply.await.map(Response::new) })
+    }
+}
+
+/// Decodes the payload of an envelope into a message of type `T`.
+fn decode<T: DeserializeOwned>(codec: &impl Codec, payload: &[u8]) -> Result<T, Status> {
+    codec
+        .decode(payload)
+        .map_err(|error| Status::invalid_argument(format!("invalid payload: {error}")))
+}
+
+/// Converts the outcome of a dispatch into a reply or a status.
+fn respond<R, E>(codec: &impl Codec, result: Result<R, DispatchError<E>>) -> Result<Reply, Status>
+where
+    R: Serialize,
+    E: Serialize + GrpcCode,
+{
+    let encoded = match &result {
+        Ok(response) => codec.encode(response),
+        Err(error) => codec.encode(error),
+    };
+    let bytes = encoded.map_err(|error| Status::internal(error.to_string()))?;
+    match result {
+        Ok(_) => Ok(Reply { payload: bytes }),
+        Err(error) => Err(Status::with_details(
+            error.code(),
+            error.kind(),
+            Bytes::from(bytes),
+        )),
+    }
+}
+
+/// A client sending envelopes to a remote [GrpcGateway].
+#[derive(Clone, Debug)]
+struct Client<K: Codec> {
+    grpc: tonic::client::Grpc<Channel>,
+    codec: Arc<K>,
+}
+
+impl<K: Codec> Client<K> {
+    fn new(channel: Channel, codec: K) -> Self {
+        Self {
+            grpc: tonic::client::Grpc::new(channel),
+            codec: Arc::new(codec),
+        }
+    }
+
+    /// Sends a message to the gRPC method at `path` and decodes its outcome.
+    async fn send<M, R, E>(&self, path: &'static str, message: M) -> Result<R, DispatchError<E>>
+    where
+        M: MessageName + Serialize,
+        R: DeserializeOwned,
+        E: DeserializeOwned,
+    {
+        let payload = self
+            .codec
+            .encode(&message)
+            .map_err(|error| DispatchError::Transport(error.to_string()))?;
+        let envelope = Envelope {
+            name: M::NAME.to_string(),
+            payload,
+        };
+
+        let mut grpc = self.grpc.clone();
+        grpc.ready()
+            .await
+            .map_err(|error| DispatchError::Transport(error.to_string()))?;
+        let path = http::uri::PathAndQuery::from_static(path);
+        let reply = grpc
+            .unary(
+                Request::new(envelope),
+                path,
+                ProstCodec::<Envelope, Reply>::default(),
+            )
+            .await;
+
+        match reply {
+            Ok(reply) => self
+                .codec
+                .decode(&reply.into_inner().payload)
+                .map_err(|error| DispatchError::Transport(error.to_string())),
+            Err(status) => Err(self
+                .codec
+                .decode(status.details())
+                .unwrap_or_else(|_| DispatchError::Transport(status.to_string()))),
+        }
+    }
+}
+
+/// The `GrpcCommandBus` dispatches commands to the [GrpcGateway] of a remote process.
+///
+/// The command type must be exposed by the gateway, and the codec must match the one of the
+/// gateway. Failures to reach the gateway, or to decode its reply, are reported as
+/// [Transport](DispatchError::Transport) errors.
+///
+/// # Example
+///
+/// ```no_run
+/// # use qonduit::command::Command;
+/// # use qonduit::message::MessageName;
+/// #
+/// # #[derive(Debug, serde::Serialize, serde::Deserialize)]
+/// # struct PlaceOrderCommand { sku: String }
+/// #
+/// # impl Command for PlaceOrderCommand {
+/// #   type Response = u64;
+/// #   type Error = String;
+/// # }
+/// #
+/// # impl MessageName for PlaceOrderCommand {
+/// #   const NAME: &'static str = "orders.place.v1";
+/// # }
+/// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// # rt.block_on(async {
+/// use qonduit::codec::JsonCodec;
+/// use qonduit::grpc::GrpcCommandBus;
+/// use tonic::transport::Channel;
+///
+/// let channel = Channel::from_static("http://orders:50051").connect().await.unwrap();
+/// let bus = GrpcCommandBus::new(channel, JsonCodec);
+///
+/// let order_id = bus.dispatch(PlaceOrderCommand { sku: "kb-01".to_string() }).await;
+/// # drop(order_id);
+/// # });
+/// ```
+#[derive(Clone, Debug)]
+pub struct GrpcCommandBus<K: Codec> {
+    #[doc(hidden)]
+    client: Client<K>,
+}
+
+/// Implementation of the `GrpcCommandBus`.
+impl<K: Codec> GrpcCommandBus<K> {
+    /// Creates a bus sending commands over `channel`, encoded with `codec`.
+    pub fn new(channel: Channel, codec: K) -> Self {
+        Self {
+            client: Client::new(channel, codec),
+        }
+    }
+
+    /// Dispatches a command to the remote gateway and returns its response.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `DispatchError` reported by the remote bus, or
+    /// [Transport](DispatchError::Transport) if the gateway cannot be reached or its reply cannot
+    /// be decoded.
+    pub async fn dispatch<C>(&self, command: C) -> Result<C::Response, DispatchError<C::Error>>
+    where
+        C: Command + MessageName + Serialize,
+        C::Response: DeserializeOwned,
+        C::Error: DeserializeOwned,
+    {
+        self.client.send(COMMAND_PATH, command).await
+    }
+}
+
+/// The `GrpcQueryBus` dispatches queries to the [GrpcGateway] of a remote process.
+///
+/// It behaves like the [GrpcCommandBus], for queries.
+#[derive(Clone, Debug)]
+pub struct GrpcQueryBus<K: Codec> {
+    #[doc(hidden)]
+    client: Client<K>,
+}
+
+/// Implementation of the `GrpcQueryBus`.
+impl<K: Codec> GrpcQueryBus<K> {
+    /// Creates a bus sending queries over `channel`, encoded with `codec`.
+    pub fn new(channel: Channel, codec: K) -> Self {
+        Self {
+            client: Client::new(channel, codec),
+        }
+    }
+
+    /// Dispatches a query to the remote gateway and returns its response.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `DispatchError` reported by the remote bus, or
+    /// [Transport](DispatchError::Transport) if the gateway cannot be reached or its reply cannot
+    /// be decoded.
+    pub async fn dispatch<Q>(&self, query: Q) -> Result<Q::Response, DispatchError<Q::Error>>
+    where
+        Q: Query + MessageName + Serialize,
+        Q::Response: DeserializeOwned,
+        Q::Error: DeserializeOwned,
+    {
+        self.client.send(QUERY_PATH, query).await
+    }
+}
@@ -0,0 +1,131 @@
+//! The `instrumentation` module reports dispatches to [tracing](https://docs.rs/tracing).
+//!
+//! This module is available with the `tracing` feature. Once enabled, every dispatch on the
+//! `CommandBus`, `QueryBus` and `EventBus` runs inside a `qonduit.dispatch` span, and every event
+//! handler invoked during an `EventBus` fan-out runs inside a `qonduit.event_handler` child span.
+//! Spans emitted by the handlers themselves are nested below them.
+//!
+//! The `qonduit.dispatch` span has the following fields:
+//!
+//! - `bus`: The kind of bus, one of `command`, `query` or `event`.
+//! - `message_type`: The [stable name](crate::message::MessageName) of the dispatched message,
+//!   or its type name.
+//! - `handler_type`: The type name of the handler; not recorded for events, which may have several.
+//! - `message`: The `Debug` rendering of the message, only recorded when enabled on the bus.
+//! - `outcome`: `ok`, or the [kind](crate::error::DispatchError::kind) of failure, such as `timeout`.
+//! - `duration_ms`: How long the dispatch took, in milliseconds.
+//!
+//! The `qonduit.event_handler` span records the `handler_type`, `outcome` and `duration_ms` of a
+//! single event handler.
+//!
+//! Messages may contain personal or secret data, so their `Debug` rendering is only recorded after
+//! calling `with_message_recording` on the bus, and can still be withheld for specific message
+//! types with `without_message_recording`.
+
+use std::any::TypeId;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::future::Future;
+
+use tokio::time::Instant;
+use tracing::Instrument;
+use tracing::Span;
+use tracing::field;
+
+use crate::error::DispatchError;
+
+/// The tracing settings of a bus.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Instrumentation {
+    record_messages: bool,
+    unrecorded: HashSet<TypeId>,
+}
+
+impl Instrumentation {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the `Debug` rendering of dispatched messages.
+    pub(crate) fn record_messages(&mut self) {
+        self.record_messages = true;
+    }
+
+    /// Never records the `Debug` rendering of messages of type `T`.
+    pub(crate) fn skip_message<T: 'static>(&mut self) {
+        self.unrecorded.insert(TypeId::of::<T>());
+    }
+
+    /// Creates the span of a dispatch of `message`.
+    pub(crate) fn span<T: Debug + 'static>(
+        &self,
+        bus: &'static str,
+        message_type: &'static str,
+        handler_type: Option<&'static str>,
+        message: &T,
+    ) -> Span {
+        let span = tracing::info_span!(
+            "qonduit.dispatch",
+            bus,
+            message_type,
+            handler_type,
+            message = field::Empty,
+            outcome = field::Empty,
+            duration_ms = field::Empty,
+        );
+        if self.record_messages && !self.unrecorded.contains(&TypeId::of::<T>()) {
+            span.record("message", field::debug(message));
+        }
+        span
+    }
+}
+
+/// Runs `future`, a dispatch, inside `span` and records its outcome and duration.
+pub(crate) async fn instrument<R, E, F>(span: Span, future: F) -> Result<R, DispatchError<E>>
+where
+    F: Future<Output = Result<R, DispatchError<E>>>,
+{
+    let started_at = Instant::now();
+    let result = future.instrument(span.clone()).await;
+    span.record("outcome", outcome(&result));
+    span.record("duration_ms", elapsed_ms(started_at));
+    result
+}
+
+/// Runs `future`, a single event handler, inside a child span of the current dispatch.
+pub(crate) async fn instrument_event_handler<E, F>(
+    handler_type: &'static str,
+    future: F,
+) -> Result<(), E>
+where
+    F: Future<Output = Result<(), E>>,
+{
+    let span = tracing::info_span!(
+        "qonduit.event_handler",
+        handler_type,
+        outcome = field::Empty,
+        duration_ms = field::Empty,
+    );
+    let started_at = Instant::now();
+    let result = future.instrument(span.clone()).await;
+    span.record(
+        "outcome",
+        match result {
+            Ok(()) => "ok",
+            Err(_) => "handler_error",
+        },
+    );
+    span.record("duration_ms", elapsed_ms(started_at));
+    result
+}
+
+fn outcome<R, E>(result: &Result<R, DispatchError<E>>) -> &'static str {
+    match result {
+        Ok(_) => "ok",
+        Err(error) => error.kind(),
+    }
+}
+
+fn elapsed_ms(started_at: Instant) -> f64 {
+    started_at.elapsed().as_secs_f64() * 1000.0
+}
@@ -293,12 +293,66 @@
 //! - **Projections**: Update read models and caches when data changes
 //! - **Integration**: Trigger external system integrations (emails, webhooks, etc.)
 
+#[cfg(feature = "audit")]
+pub mod audit;
+pub mod authorization;
+#[cfg(feature = "remote")]
+pub mod bridge;
+pub mod bulkhead;
+pub mod circuit_breaker;
+#[cfg(feature = "serde")]
+pub mod codec;
 pub mod command;
+#[cfg(feature = "dead-letter")]
+pub mod dead_letter;
+pub mod error;
 pub mod event;
+#[cfg(feature = "axum")]
+pub mod event_stream;
+#[cfg(feature = "axum")]
+pub mod gateway;
+#[cfg(feature = "async-graphql")]
+pub mod graphql;
+#[cfg(feature = "tonic")]
+pub mod grpc;
+#[cfg(feature = "tracing")]
+pub mod instrumentation;
 #[cfg(feature = "macros")]
 pub mod macros;
+pub mod message;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "serde")]
+pub mod named;
 pub mod query;
+#[cfg(feature = "queue")]
+pub mod queue;
+pub mod rate_limit;
 pub mod registry;
+#[cfg(feature = "remote")]
+pub mod remote;
+#[cfg(feature = "remote")]
+pub mod routing;
+#[cfg(feature = "schedule")]
+pub mod schedule;
+#[cfg(feature = "signing")]
+pub mod signing;
+pub mod subscription;
+#[cfg(feature = "opentelemetry")]
+pub mod telemetry;
+pub mod timeout;
+#[cfg(any(
+    feature = "dead-letter",
+    feature = "queue",
+    feature = "remote",
+    feature = "schedule",
+    feature = "tonic",
+    feature = "webhook"
+))]
+mod util;
+pub mod validation;
+#[cfg(feature = "webhook")]
+pub mod webhook;
 
 /// Re-exports the `async_trait` crate.
 ///
@@ -510,7 +510,7 @@ macro_rules! query_registry {
 /// ```
 ///
 /// 2. **Handlers only** – relies on type inference (each handler's implemented `EventHandler<E>`
-/// trait determines the event type):
+///    trait determines the event type):
 /// ```
 /// use qonduit::{async_trait, event_bus};
 /// use qonduit::event::{Event, EventHandler};
@@ -0,0 +1,110 @@
+//! The `message` module gives commands, queries and events stable names.
+//!
+//! By default, messages are identified by their `std::any::type_name`, which changes whenever a
+//! type is renamed or moved to another module. Implementing [MessageName] gives a message a name
+//! that is independent of its Rust path, for example `orders.place.v2`, so it can be versioned
+//! explicitly and kept stable across refactorings.
+//!
+//! Messages registered with `register_named` are known to their registry under their stable name,
+//! which must be unique among the messages of the registry. The buses then use it in place of the
+//! type name in spans, metrics and audit records, and the `serde` feature uses it to dispatch
+//! messages by name.
+//!
+//! With the `derive` feature, `MessageName` can be derived with a `#[message(...)]` attribute
+//! holding the `name` and an optional `version`, which is appended as a `.v<version>` suffix.
+//!
+//! - [MessageName]: Gives a message a stable name.
+
+use std::any::TypeId;
+use std::collections::HashMap;
+
+/// The `MessageName` trait gives a command, query or event a stable name.
+///
+/// # Example
+///
+/// ```
+/// use qonduit::command::Command;
+/// use qonduit::message::MessageName;
+///
+/// #[derive(Debug)]
+/// struct PlaceOrderCommand;
+///
+/// impl Command for PlaceOrderCommand {
+///     type Response = ();
+///     type Error = ();
+/// }
+///
+/// impl MessageName for PlaceOrderCommand {
+///     const NAME: &'static str = "orders.place.v2";
+/// }
+/// ```
+///
+/// With the `derive` feature, the same name can be derived:
+///
+/// ```
+/// # #[cfg(feature = "derive")]
+/// # {
+/// use qonduit::message::MessageName;
+///
+/// #[derive(Debug, MessageName)]
+/// #[message(name = "orders.place", version = 2)]
+/// struct PlaceOrderCommand;
+///
+/// assert_eq!(PlaceOrderCommand::NAME, "orders.place.v2");
+/// # }
+/// ```
+pub trait MessageName {
+    /// The stable name of the message.
+    const NAME: &'static str;
+}
+
+/// Derives [MessageName](trait@MessageName) from a `#[message(name = "...", version = N)]`
+/// attribute.
+#[cfg(feature = "derive")]
+pub use qonduit_derive::MessageName;
+
+/// The stable names of the messages known to a registry.
+#[derive(Default)]
+pub(crate) struct MessageNames {
+    by_type: HashMap<TypeId, &'static str>,
+    by_name: HashMap<&'static str, TypeId>,
+    type_names: HashMap<&'static str, &'static str>,
+}
+
+impl MessageNames {
+    /// Records the stable name of the message type `T`.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if the name of `T` is already used by another message type.
+    pub(crate) fn insert<T: MessageName + 'static>(&mut self) {
+        let type_id = TypeId::of::<T>();
+        let type_name = std::any::type_name::<T>();
+        if let Some(&other) = self.by_name.get(T::NAME)
+            && other != type_id
+        {
+            panic!(
+                "Message name {:?} of {} is already used by {}",
+                T::NAME,
+                type_name,
+                self.type_names[T::NAME]
+            );
+        }
+        self.by_type.insert(type_id, T::NAME);
+        self.by_name.insert(T::NAME, type_id);
+        self.type_names.insert(T::NAME, type_name);
+    }
+
+    /// Returns the stable name of the message type `T`, or its type name if it has none.
+    pub(crate) fn get<T: 'static>(&self) -> &'static str {
+        self.by_type
+            .get(&TypeId::of::<T>())
+            .copied()
+            .unwrap_or_else(std::any::type_name::<T>)
+    }
+
+    /// Returns the stable names of the known message types.
+    pub(crate) fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.by_name.keys().copied()
+    }
+}
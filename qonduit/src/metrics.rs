@@ -0,0 +1,91 @@
+//! The `metrics` module records dispatch metrics through the [metrics](https://docs.rs/metrics)
+//! facade.
+//!
+//! This module is available with the `metrics` feature. Once enabled, every dispatch on the
+//! `CommandBus`, `QueryBus` and `EventBus` is recorded with whichever recorder is installed, for
+//! example a Prometheus exporter in production or an in-process recorder in tests. Nothing is
+//! recorded while no recorder is installed.
+//!
+//! Every metric is labelled with `bus` (one of `command`, `query` or `event`) and `message_type`
+//! (the [stable name](crate::message::MessageName) of the dispatched message, or its type name):
+//!
+//! - [DISPATCHED]: Counter of started dispatches.
+//! - [SUCCEEDED]: Counter of successful dispatches.
+//! - [FAILED]: Counter of failed dispatches, additionally labelled with the `reason`, which is
+//!   the [kind](crate::error::DispatchError::kind) of failure.
+//! - [MISSING_HANDLER]: Counter of messages dispatched without a registered handler.
+//! - [DURATION]: Histogram of dispatch durations, in seconds.
+//! - [IN_FLIGHT]: Gauge of dispatches currently in progress.
+
+use std::future::Future;
+
+use ::metrics::Gauge;
+use ::metrics::counter;
+use ::metrics::gauge;
+use ::metrics::histogram;
+use tokio::time::Instant;
+
+use crate::error::DispatchError;
+
+/// The name of the counter of started dispatches.
+pub const DISPATCHED: &str = "qonduit_dispatched_total";
+
+/// The name of the counter of successful dispatches.
+pub const SUCCEEDED: &str = "qonduit_succeeded_total";
+
+/// The name of the counter of failed dispatches.
+pub const FAILED: &str = "qonduit_failed_total";
+
+/// The name of the counter of messages dispatched without a registered handler.
+pub const MISSING_HANDLER: &str = "qonduit_missing_handler_total";
+
+/// The name of the histogram of dispatch durations, in seconds.
+pub const DURATION: &str = "qonduit_dispatch_duration_seconds";
+
+/// The name of the gauge of dispatches in progress.
+pub const IN_FLIGHT: &str = "qonduit_in_flight";
+
+/// Decrements the in-flight gauge when the dispatch completes or is cancelled.
+struct InFlight(Gauge);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.decrement(1.0);
+    }
+}
+
+/// Runs `future`, a dispatch of a message named `message_type`, and records its metrics.
+pub(crate) async fn measure<R, E, F>(
+    bus: &'static str,
+    message_type: &'static str,
+    future: F,
+) -> Result<R, DispatchError<E>>
+where
+    F: Future<Output = Result<R, DispatchError<E>>>,
+{
+    counter!(DISPATCHED, "bus" => bus, "message_type" => message_type).increment(1);
+    let in_flight = gauge!(IN_FLIGHT, "bus" => bus, "message_type" => message_type);
+    in_flight.increment(1.0);
+    let _in_flight = InFlight(in_flight);
+
+    let started_at = Instant::now();
+    let result = future.await;
+    histogram!(DURATION, "bus" => bus, "message_type" => message_type).record(started_at.elapsed());
+
+    match &result {
+        Ok(_) => counter!(SUCCEEDED, "bus" => bus, "message_type" => message_type).increment(1),
+        Err(error) => counter!(
+            FAILED,
+            "bus" => bus,
+            "message_type" => message_type,
+            "reason" => error.kind()
+        )
+        .increment(1),
+    }
+    result
+}
+
+/// Records that a message named `message_type` was dispatched without a registered handler.
+pub(crate) fn missing_handler(bus: &'static str, message_type: &'static str) {
+    counter!(MISSING_HANDLER, "bus" => bus, "message_type" => message_type).increment(1);
+}
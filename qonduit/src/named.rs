@@ -0,0 +1,148 @@
+//! The `named` module dispatches messages identified by a string name and carried as JSON.
+//!
+//! This module is available with the `serde` feature. Registries are keyed by Rust types, which
+//! is not enough for messages arriving over the network, where only a name and a payload are
+//! known. A command or query whose type implements [MessageName](crate::message::MessageName) and
+//! is registered with `register_json` can be dispatched by its name with `dispatch_json`: the
+//! payload is deserialized into the message type, dispatched through the bus like any other
+//! message, and the response or handler error is serialized back to JSON.
+//!
+//! - [NamedDispatchError]: The error returned by `dispatch_json`.
+
+use std::error::Error;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+use std::future::Future;
+use std::pin::Pin;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::command::Command;
+use crate::command::CommandBus;
+use crate::error::DispatchError;
+use crate::query::Query;
+use crate::query::QueryBus;
+
+/// The `NamedDispatchError` is returned when a message dispatched by name could not be processed
+/// successfully.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum NamedDispatchError {
+    /// No message type is registered under the name.
+    UnknownName(String),
+
+    /// The payload could not be deserialized into the message type.
+    InvalidPayload(serde_json::Error),
+
+    /// The response or the handler error could not be serialized.
+    Serialization(serde_json::Error),
+
+    /// The dispatch failed, with the handler error serialized as JSON.
+    Dispatch(DispatchError<Value>),
+}
+
+/// Implementation of the `NamedDispatchError`.
+impl NamedDispatchError {
+    /// Returns a short, stable name of the kind of failure: `unknown_name`, `invalid_payload`,
+    /// `serialization`, or the [kind](DispatchError::kind) of the failed dispatch.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NamedDispatchError::UnknownName(_) => "unknown_name",
+            NamedDispatchError::InvalidPayload(_) => "invalid_payload",
+            NamedDispatchError::Serialization(_) => "serialization",
+            NamedDispatchError::Dispatch(error) => error.kind(),
+        }
+    }
+
+    /// Returns the dispatch error, or `None` if the message never reached the bus.
+    pub fn into_dispatch_error(self) -> Option<DispatchError<Value>> {
+        match self {
+            NamedDispatchError::Dispatch(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Display implementation for `NamedDispatchError`
+impl Display for NamedDispatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        match self {
+            NamedDispatchError::UnknownName(name) => {
+                write!(f, "no message is registered under the name {name:?}")
+            }
+            NamedDispatchError::InvalidPayload(error) => write!(f, "invalid payload: {error}"),
+            NamedDispatchError::Serialization(error) => {
+                write!(f, "cannot serialize the outcome: {error}")
+            }
+            NamedDispatchError::Dispatch(error) => Display::fmt(error, f),
+        }
+    }
+}
+
+/// Error implementation for `NamedDispatchError`
+impl Error for NamedDispatchError {}
+
+/// The future of a dispatch by name.
+pub(crate) type NamedDispatch<'a> =
+    Pin<Box<dyn Future<Output = Result<Value, NamedDispatchError>> + Send + 'a>>;
+
+/// Dispatches a JSON payload as the command type registered under a name.
+pub(crate) type NamedCommand = for<'a> fn(&'a CommandBus, Value) -> NamedDispatch<'a>;
+
+/// Dispatches a JSON payload as the query type registered under a name.
+pub(crate) type NamedQuery = for<'a> fn(&'a QueryBus, Value) -> NamedDispatch<'a>;
+
+/// Dispatches `payload` as a command of type `C`.
+pub(crate) fn command<C>(bus: &CommandBus, payload: Value) -> NamedDispatch<'_>
+where
+    C: Command + DeserializeOwned,
+    C::Response: Serialize,
+    C::Error: Serialize,
+{
+    Box::pin(async move {
+        let command =
+            serde_json::from_value::<C>(payload).map_err(NamedDispatchError::InvalidPayload)?;
+        outcome(bus.dispatch(command).await)
+    })
+}
+
+/// Dispatches `payload` as a query of type `Q`.
+pub(crate) fn query<Q>(bus: &QueryBus, payload: Value) -> NamedDispatch<'_>
+where
+    Q: Query + DeserializeOwned,
+    Q::Response: Serialize,
+    Q::Error: Serialize,
+{
+    Box::pin(async move {
+        let query =
+            serde_json::from_value::<Q>(payload).map_err(NamedDispatchError::InvalidPayload)?;
+        outcome(bus.dispatch(query).await)
+    })
+}
+
+/// Serializes the outcome of a dispatch.
+fn outcome<R: Serialize, E: Serialize>(
+    result: Result<R, DispatchError<E>>,
+) -> Result<Value, NamedDispatchError> {
+    match result {
+        Ok(response) => serde_json::to_value(response).map_err(NamedDispatchError::Serialization),
+        Err(error) => Err(NamedDispatchError::Dispatch(match error {
+            DispatchError::Handler(error) => DispatchError::Handler(
+                serde_json::to_value(error).map_err(NamedDispatchError::Serialization)?,
+            ),
+            DispatchError::Timeout(budget) => DispatchError::Timeout(budget),
+            DispatchError::CircuitOpen => DispatchError::CircuitOpen,
+            DispatchError::BulkheadFull => DispatchError::BulkheadFull,
+            DispatchError::RateLimited { retry_after } => {
+                DispatchError::RateLimited { retry_after }
+            }
+            DispatchError::Invalid(violations) => DispatchError::Invalid(violations),
+            DispatchError::Forbidden(forbidden) => DispatchError::Forbidden(forbidden),
+            DispatchError::Transport(reason) => DispatchError::Transport(reason),
+            DispatchError::Unauthenticated(reason) => DispatchError::Unauthenticated(reason),
+        })),
+    }
+}
@@ -18,9 +18,34 @@
 use std::any::Any;
 use std::fmt::Debug;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::async_trait;
+use crate::authorization;
+use crate::authorization::Policies;
+use crate::authorization::Policy;
+use crate::authorization::Principal;
+use crate::bulkhead::BulkheadConfig;
+use crate::bulkhead::BulkheadStats;
+use crate::bulkhead::Bulkheads;
+use crate::circuit_breaker::CircuitBreakerConfig;
+use crate::circuit_breaker::CircuitBreakers;
+use crate::circuit_breaker::CircuitState;
+use crate::error::DispatchError;
+#[cfg(feature = "tracing")]
+use crate::instrumentation;
+#[cfg(feature = "tracing")]
+use crate::instrumentation::Instrumentation;
+#[cfg(feature = "metrics")]
+use crate::metrics;
+#[cfg(feature = "serde")]
+use crate::named::NamedDispatchError;
 use crate::registry::QueryHandlerRegistry;
+#[cfg(feature = "opentelemetry")]
+use crate::telemetry;
+use crate::timeout::Timeouts;
+use crate::validation::Validate;
+use crate::validation::Validators;
 
 /// The `Query` trait defines a query for retrieving data from the system.
 ///
@@ -179,6 +204,7 @@ pub trait QueryHandler<Q: Query>: Send + Sync {
 /// #   }
 /// # }
 /// use qonduit::registry::QueryHandlerRegistry;
+/// use qonduit::error::DispatchError;
 /// use qonduit::query::QueryBus;
 ///
 /// // First, create a registry to store our query handlers
@@ -210,8 +236,9 @@ pub trait QueryHandler<Q: Query>: Send + Sync {
 ///     Err(err) => {
 ///         # assert!(false);
 ///         match err {
-///             FindProductError::ProductNotFound => println!("Product not found"),
-///             FindProductError::InvalidQuery => println!("Invalid product query"),
+///             DispatchError::Handler(FindProductError::ProductNotFound) => println!("Product not found"),
+///             DispatchError::Handler(FindProductError::InvalidQuery) => println!("Invalid product query"),
+///             other => println!("Query failed: {:?}", other),
 ///         }
 ///     }
 /// }
@@ -221,6 +248,19 @@ pub trait QueryHandler<Q: Query>: Send + Sync {
 pub struct QueryBus {
     #[doc(hidden)]
     registry: Arc<QueryHandlerRegistry>,
+    #[doc(hidden)]
+    timeouts: Arc<Timeouts>,
+    #[doc(hidden)]
+    circuit_breakers: Arc<CircuitBreakers>,
+    #[doc(hidden)]
+    bulkheads: Arc<Bulkheads>,
+    #[doc(hidden)]
+    validators: Arc<Validators>,
+    #[doc(hidden)]
+    policies: Arc<Policies>,
+    #[doc(hidden)]
+    #[cfg(feature = "tracing")]
+    instrumentation: Arc<Instrumentation>,
 }
 
 /// Implementation of the `QueryBus`.
@@ -248,9 +288,298 @@ impl QueryBus {
     pub fn new(registry: QueryHandlerRegistry) -> Self {
         Self {
             registry: Arc::new(registry),
+            timeouts: Arc::new(Timeouts::new()),
+            circuit_breakers: Arc::new(CircuitBreakers::new()),
+            bulkheads: Arc::new(Bulkheads::new()),
+            validators: Arc::new(Validators::new()),
+            policies: Arc::new(Policies::new()),
+            #[cfg(feature = "tracing")]
+            instrumentation: Arc::new(Instrumentation::new()),
         }
     }
 
+    /// Records the `Debug` rendering of every dispatched query in its tracing span.
+    ///
+    /// Queries may contain personal or secret data, so this is disabled by default. Use
+    /// [without_message_recording](QueryBus::without_message_recording) to withhold specific
+    /// query types. See the [instrumentation](crate::instrumentation) module for the recorded
+    /// fields.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use qonduit::query::Query;
+    /// #
+    /// # #[derive(Debug)]
+    /// # struct FindPaymentMethodsQuery;
+    /// #
+    /// # impl Query for FindPaymentMethodsQuery {
+    /// #   type Response = ();
+    /// #   type Error = ();
+    /// # }
+    /// use qonduit::query::QueryBus;
+    /// use qonduit::registry::QueryHandlerRegistry;
+    ///
+    /// let query_bus = QueryBus::new(QueryHandlerRegistry::new())
+    ///     .with_message_recording()
+    ///     .without_message_recording::<FindPaymentMethodsQuery>();
+    /// # drop(query_bus);
+    /// ```
+    #[cfg(feature = "tracing")]
+    pub fn with_message_recording(mut self) -> Self {
+        Arc::make_mut(&mut self.instrumentation).record_messages();
+        self
+    }
+
+    /// Never records the `Debug` rendering of querys of type `Q` in tracing spans.
+    #[cfg(feature = "tracing")]
+    pub fn without_message_recording<Q: Query>(mut self) -> Self {
+        Arc::make_mut(&mut self.instrumentation).skip_message::<Q>();
+        self
+    }
+
+    /// Authorizes queries of type `Q` with `policy` before dispatching them to their handler.
+    ///
+    /// The policy is evaluated against the principal of the dispatch, and a query it denies is
+    /// rejected with [DispatchError::Forbidden] without invoking the handler. This replaces any
+    /// policy previously set for `Q`. See the [authorization](crate::authorization) module for
+    /// details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use qonduit::query::Query;
+    /// #
+    /// # #[derive(Debug)]
+    /// # struct FindOrdersByTenantQuery { tenant: String }
+    /// #
+    /// # impl Query for FindOrdersByTenantQuery {
+    /// #   type Response = Vec<u64>;
+    /// #   type Error = ();
+    /// # }
+    /// use qonduit::authorization::{authenticated, Principal, PolicyExt};
+    /// use qonduit::query::QueryBus;
+    /// use qonduit::registry::QueryHandlerRegistry;
+    ///
+    /// // Authenticated principals may only list the orders of their own tenant.
+    /// let query_bus = QueryBus::new(QueryHandlerRegistry::new()).with_policy::<FindOrdersByTenantQuery>(
+    ///     authenticated().and(|principal: &Principal, query: &FindOrdersByTenantQuery| {
+    ///         principal.claim("tenant") == Some(query.tenant.as_str())
+    ///     }),
+    /// );
+    /// # drop(query_bus);
+    /// ```
+    pub fn with_policy<Q: Query>(mut self, policy: impl Policy<Q> + 'static) -> Self {
+        Arc::make_mut(&mut self.policies).set::<Q>(policy);
+        self
+    }
+
+    /// Denies every query type without a policy.
+    ///
+    /// Dispatching a query whose type has no policy fails with [DispatchError::Forbidden] instead
+    /// of reaching its handler. Use [allow_all](crate::authorization::allow_all) to mark query
+    /// types as public.
+    pub fn with_deny_by_default(mut self) -> Self {
+        Arc::make_mut(&mut self.policies).deny_by_default();
+        self
+    }
+
+    /// Validates queries of type `Q` before dispatching them to their handler.
+    ///
+    /// A query failing [Validate::validate] is rejected with [DispatchError::Invalid] and the
+    /// handler is not invoked. See the [validation](crate::validation) module for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    /// # rt.block_on(async {
+    /// # use qonduit::async_trait;
+    /// # use qonduit::query::{Query, QueryHandler};
+    /// #
+    /// # #[derive(Debug)]
+    /// # struct FindProductsByNameQuery { name: String }
+    /// #
+    /// # impl Query for FindProductsByNameQuery {
+    /// #   type Response = Vec<u64>;
+    /// #   type Error = ();
+    /// # }
+    /// #
+    /// # struct FindProductsByNameQueryHandler;
+    /// #
+    /// # #[async_trait]
+    /// # impl QueryHandler<FindProductsByNameQuery> for FindProductsByNameQueryHandler {
+    /// #   async fn handle(&self, _message: FindProductsByNameQuery) -> Result<Vec<u64>, ()> {
+    /// #     Ok(vec![])
+    /// #   }
+    /// # }
+    /// use qonduit::error::DispatchError;
+    /// use qonduit::query::QueryBus;
+    /// use qonduit::registry::QueryHandlerRegistry;
+    /// use qonduit::validation::{Validate, Violation, Violations};
+    ///
+    /// impl Validate for FindProductsByNameQuery {
+    ///     fn validate(&self) -> Result<(), Violations> {
+    ///         let mut violations = Violations::new();
+    ///         if self.name.is_empty() {
+    ///             violations.push(Violation::new("name", "required", "must not be empty"));
+    ///         }
+    ///         violations.into_result()
+    ///     }
+    /// }
+    ///
+    /// let mut registry = QueryHandlerRegistry::new();
+    /// registry.register::<FindProductsByNameQuery>(FindProductsByNameQueryHandler);
+    /// let query_bus = QueryBus::new(registry).with_validation::<FindProductsByNameQuery>();
+    ///
+    /// let result = query_bus.dispatch(FindProductsByNameQuery { name: String::new() }).await;
+    /// assert!(matches!(result, Err(DispatchError::Invalid(_))));
+    /// # });
+    /// ```
+    pub fn with_validation<Q: Query + Validate>(mut self) -> Self {
+        Arc::make_mut(&mut self.validators).set::<Q>();
+        self
+    }
+
+    /// Sets the timeout applied to every query type without a dedicated timeout.
+    ///
+    /// A dispatch exceeding its timeout fails with [DispatchError::Timeout] and the handler future
+    /// is dropped. See the [timeout](crate::timeout) module for how deadlines propagate to nested
+    /// dispatches.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use qonduit::query::QueryBus;
+    /// use qonduit::registry::QueryHandlerRegistry;
+    ///
+    /// let query_bus = QueryBus::new(QueryHandlerRegistry::new())
+    ///     .with_default_timeout(Duration::from_secs(2));
+    /// # drop(query_bus);
+    /// ```
+    pub fn with_default_timeout(mut self, timeout: Duration) -> Self {
+        Arc::make_mut(&mut self.timeouts).set_default(timeout);
+        self
+    }
+
+    /// Sets the timeout applied to queries of type `Q`, overriding the default timeout.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use qonduit::query::Query;
+    /// #
+    /// # #[derive(Debug)]
+    /// # struct FindProductQuery;
+    /// #
+    /// # impl Query for FindProductQuery {
+    /// #   type Response = String;
+    /// #   type Error = ();
+    /// # }
+    /// use std::time::Duration;
+    /// use qonduit::query::QueryBus;
+    /// use qonduit::registry::QueryHandlerRegistry;
+    ///
+    /// let query_bus = QueryBus::new(QueryHandlerRegistry::new())
+    ///     .with_timeout::<FindProductQuery>(Duration::from_millis(200));
+    /// # drop(query_bus);
+    /// ```
+    pub fn with_timeout<Q: Query>(mut self, timeout: Duration) -> Self {
+        Arc::make_mut(&mut self.timeouts).set::<Q>(timeout);
+        self
+    }
+
+    /// Protects the handler of querys of type `Q` with a circuit breaker.
+    ///
+    /// While the circuit is open, dispatching a `Q` fails with [DispatchError::CircuitOpen]
+    /// without invoking the handler. Clones of the bus share the state of the circuit breaker.
+    /// See the [circuit_breaker](crate::circuit_breaker) module for the state machine.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use qonduit::query::Query;
+    /// #
+    /// # #[derive(Debug)]
+    /// # struct SyncInventoryQuery;
+    /// #
+    /// # impl Query for SyncInventoryQuery {
+    /// #   type Response = ();
+    /// #   type Error = ();
+    /// # }
+    /// use std::time::Duration;
+    /// use qonduit::circuit_breaker::{CircuitBreakerConfig, CircuitState};
+    /// use qonduit::query::QueryBus;
+    /// use qonduit::registry::QueryHandlerRegistry;
+    ///
+    /// let query_bus = QueryBus::new(QueryHandlerRegistry::new()).with_circuit_breaker::<SyncInventoryQuery>(
+    ///     CircuitBreakerConfig::new()
+    ///         .with_failure_rate_threshold(0.25)
+    ///         .with_open_duration(Duration::from_secs(10)),
+    /// );
+    ///
+    /// assert_eq!(query_bus.circuit_state::<SyncInventoryQuery>(), Some(CircuitState::Closed));
+    /// ```
+    pub fn with_circuit_breaker<Q: Query>(mut self, config: CircuitBreakerConfig) -> Self {
+        let message_type = self.registry.message_name::<Q>();
+        Arc::make_mut(&mut self.circuit_breakers).set::<Q>(message_type, config);
+        self
+    }
+
+    /// Returns the state of the circuit breaker protecting querys of type `Q`.
+    ///
+    /// Returns `None` if no circuit breaker is configured for `Q`.
+    pub fn circuit_state<Q: Query>(&self) -> Option<CircuitState> {
+        self.circuit_breakers.state::<Q>()
+    }
+
+    /// Limits how many querys of type `Q` are handled concurrently.
+    ///
+    /// Dispatches beyond the limit wait in a bounded queue, and fail with
+    /// [DispatchError::BulkheadFull] once the queue is full. Clones of the bus share the bulkhead.
+    /// See the [bulkhead](crate::bulkhead) module for details.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use qonduit::query::Query;
+    /// #
+    /// # #[derive(Debug)]
+    /// # struct SyncInventoryQuery;
+    /// #
+    /// # impl Query for SyncInventoryQuery {
+    /// #   type Response = ();
+    /// #   type Error = ();
+    /// # }
+    /// use qonduit::bulkhead::BulkheadConfig;
+    /// use qonduit::query::QueryBus;
+    /// use qonduit::registry::QueryHandlerRegistry;
+    ///
+    /// let query_bus = QueryBus::new(QueryHandlerRegistry::new())
+    ///     .with_bulkhead::<SyncInventoryQuery>(BulkheadConfig::new(4).with_max_queued(16));
+    ///
+    /// let stats = query_bus.bulkhead_stats::<SyncInventoryQuery>().unwrap();
+    /// assert_eq!((stats.in_flight, stats.queued), (0, 0));
+    /// ```
+    pub fn with_bulkhead<Q: Query>(mut self, config: BulkheadConfig) -> Self {
+        Arc::make_mut(&mut self.bulkheads).set::<Q>(config);
+        self
+    }
+
+    /// Returns the in-flight and queued dispatch counts of the bulkhead for querys of type `Q`.
+    ///
+    /// Returns `None` if no bulkhead is configured for `Q`.
+    pub fn bulkhead_stats<Q: Query>(&self) -> Option<BulkheadStats> {
+        self.bulkheads.stats::<Q>()
+    }
+
+    /// Returns whether queries of type `Q` have a handler registered.
+    #[cfg(feature = "axum")]
+    pub(crate) fn handles<Q: Query>(&self) -> bool {
+        self.registry.handler_type_name::<Q>().is_some()
+    }
+
     /// Dispatches a query to its corresponding handler and returns the result.
     ///
     /// # Arguments
@@ -261,6 +590,15 @@ impl QueryBus {
     ///
     /// The result from the query handler, either containing the Response data or an error.
     ///
+    /// # Errors
+    ///
+    /// - [DispatchError::Handler] with the handler's error if the query fails.
+    /// - [DispatchError::Forbidden] if the principal of the dispatch is not allowed to dispatch the query.
+    /// - [DispatchError::Invalid] if validation is enabled for the query type and the query is invalid.
+    /// - [DispatchError::CircuitOpen] if the circuit breaker of the query type is open.
+    /// - [DispatchError::Timeout] if the handler does not complete within the configured timeout.
+    /// - [DispatchError::BulkheadFull] if the bulkhead of the query type is full.
+    ///
     /// # Panics
     ///
     /// This method will panic if no handler is registered for the query type.
@@ -310,6 +648,7 @@ impl QueryBus {
     /// #   }
     /// # }
     /// use qonduit::registry::QueryHandlerRegistry;
+    /// use qonduit::error::DispatchError;
     /// use qonduit::query::QueryBus;
     ///
     /// // Create and set up a registry with our query handlers
@@ -336,22 +675,122 @@ impl QueryBus {
     ///     Err(err) => {
     ///         # assert!(false);
     ///         match err {
-    ///             FindProductError::ProductNotFound => println!("Product not found"),
-    ///             FindProductError::InvalidQuery => println!("Invalid product query"),
+    ///             DispatchError::Handler(FindProductError::ProductNotFound) => println!("Product not found"),
+    ///             DispatchError::Handler(FindProductError::InvalidQuery) => println!("Invalid product query"),
+    ///             other => println!("Query failed: {:?}", other),
     ///         }
     ///     }
     /// }
     /// # });
     /// ```
-    pub async fn dispatch<Q: Query>(&self, query: Q) -> Result<Q::Response, Q::Error> {
+    pub async fn dispatch<Q: Query>(
+        &self,
+        query: Q,
+    ) -> Result<Q::Response, DispatchError<Q::Error>> {
         match self.registry.get_handler::<Q>() {
-            Some(handler) => handler.handle(query).await,
+            Some(handler) => {
+                #[cfg(feature = "tracing")]
+                let span = self.instrumentation.span(
+                    "query",
+                    self.registry.message_name::<Q>(),
+                    self.registry.handler_type_name::<Q>(),
+                    &query,
+                );
+                let dispatch = async {
+                    self.policies.authorize(&query)?;
+                    self.validators.validate(&query)?;
+                    let handle =
+                        async { handler.handle(query).await.map_err(DispatchError::Handler) };
+                    self.circuit_breakers
+                        .run::<Q, _, _, _>(
+                            self.timeouts
+                                .run::<Q, _, _, _>(self.bulkheads.run::<Q, _, _, _>(handle)),
+                        )
+                        .await
+                };
+                #[cfg(feature = "opentelemetry")]
+                let dispatch = telemetry::trace(
+                    "query",
+                    self.registry.message_name::<Q>(),
+                    self.registry.handler_type_name::<Q>(),
+                    dispatch,
+                );
+                #[cfg(feature = "metrics")]
+                let dispatch =
+                    metrics::measure("query", self.registry.message_name::<Q>(), dispatch);
+                #[cfg(feature = "tracing")]
+                let dispatch = instrumentation::instrument(span, dispatch);
+                dispatch.await
+            }
             None => {
+                #[cfg(feature = "metrics")]
+                metrics::missing_handler("query", self.registry.message_name::<Q>());
                 panic!(
                     "No handler registered for query: {:?}",
-                    std::any::type_name::<Q>()
+                    self.registry.message_name::<Q>()
                 );
             }
         }
     }
+
+    /// Dispatches a query on behalf of `principal`.
+    ///
+    /// The policy of the query type is evaluated against `principal`, which is also visible to
+    /// the handler through [current_principal](crate::authorization::current_principal) and
+    /// inherited by every dispatch made while handling the query.
+    ///
+    /// # Errors
+    ///
+    /// The same as [dispatch](QueryBus::dispatch).
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if no handler is registered for the query type.
+    pub async fn dispatch_as<Q: Query>(
+        &self,
+        principal: Principal,
+        query: Q,
+    ) -> Result<Q::Response, DispatchError<Q::Error>> {
+        authorization::scope(principal, self.dispatch(query)).await
+    }
+
+    /// Dispatches the JSON `payload` as the query type registered under `name` with
+    /// [register_json](crate::registry::QueryHandlerRegistry::register_json).
+    ///
+    /// The query goes through the same checks as with [dispatch](QueryBus::dispatch), and its
+    /// response is returned as JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns [UnknownName](NamedDispatchError::UnknownName) if no query type is registered under
+    /// `name`, [InvalidPayload](NamedDispatchError::InvalidPayload) if `payload` cannot be
+    /// deserialized into it, and [Dispatch](NamedDispatchError::Dispatch) with the handler error
+    /// serialized as JSON if the dispatch fails.
+    #[cfg(feature = "serde")]
+    pub async fn dispatch_json(
+        &self,
+        name: &str,
+        payload: serde_json::Value,
+    ) -> Result<serde_json::Value, NamedDispatchError> {
+        let Some(dispatch) = self.registry.named.get(name) else {
+            return Err(NamedDispatchError::UnknownName(name.to_string()));
+        };
+        dispatch(self, payload).await
+    }
+
+    /// Dispatches the JSON `payload` as the query type registered under `name`, on behalf of
+    /// `principal`.
+    ///
+    /// # Errors
+    ///
+    /// The same as [dispatch_json](QueryBus::dispatch_json).
+    #[cfg(feature = "serde")]
+    pub async fn dispatch_json_as(
+        &self,
+        principal: Principal,
+        name: &str,
+        payload: serde_json::Value,
+    ) -> Result<serde_json::Value, NamedDispatchError> {
+        authorization::scope(principal, self.dispatch_json(name, payload)).await
+    }
 }
@@ -0,0 +1,1088 @@
+//! The `queue` module executes commands asynchronously from a durable queue.
+//!
+//! This module is available with the `queue` feature. [enqueue](crate::command::CommandBus::enqueue)
+//! serializes a command as JSON and saves it as a [Job] to the [CommandQueue] of the bus, then
+//! returns the id of the job. A [WorkerPool] claims the queued jobs, dispatches them by name
+//! through its `CommandBus` on behalf of the principal that enqueued them, and records their
+//! [JobStatus], which callers poll with [status](CommandQueue::status). Command types executed
+//! from the queue must be registered with
+//! [register_json](crate::registry::CommandHandlerRegistry::register_json) on the bus of the
+//! workers.
+//!
+//! Delivery is at least once. A claimed job stays invisible to other workers for the visibility
+//! timeout of the pool, and is acknowledged by recording its outcome. A job whose worker stops
+//! before acknowledging it, because the process crashed or the handler took too long, is claimed
+//! again once its visibility timeout expires, so handlers of queued commands should be idempotent.
+//! A job claimed more times than the maximum number of attempts is failed without being dispatched
+//! again. A dispatch failing with a [transient](DispatchError::is_transient) error, such as a
+//! timeout or an open circuit breaker, is retried after a backoff until the job runs out of
+//! attempts. A command whose handler returns an error, or that is rejected by the bus, is not
+//! retried.
+//!
+//! Jobs are kept in a [JobStore]:
+//!
+//! - [InMemoryJobStore]: Keeps jobs in memory, mainly for tests.
+//! - [FileJobStore]: Keeps jobs as JSON files in a directory, for a single process.
+//! - `SqliteJobStore`: Keeps jobs in a SQLite database, which several processes may share. It is
+//!   available with the `sqlite` feature.
+//!
+//! - [CommandQueue]: Saves enqueued commands and reports their status.
+//! - [WorkerPool]: Executes the queued commands.
+//! - [Job]: A command waiting to be executed, or executed.
+//! - [JobStatus]: Where a job stands.
+//! - [JobFailure]: Why a job failed.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::task::JoinSet;
+
+use crate::async_trait;
+use crate::authorization;
+use crate::authorization::Principal;
+use crate::command::Command;
+use crate::command::CommandBus;
+use crate::error::DispatchError;
+use crate::message::MessageName;
+use crate::named::NamedDispatchError;
+use crate::util;
+use crate::util::blocking;
+use crate::util::unix_millis;
+
+/// How long a claimed job stays invisible to other workers by default.
+pub const DEFAULT_VISIBILITY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long an idle worker waits before looking for jobs again by default.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long a job waits before its first retry by default.
+pub const DEFAULT_RETRY_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The longest a job waits between two retries by default.
+pub const DEFAULT_MAX_RETRY_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The `JobStatus` tells where a [Job] stands.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// The job waits for a worker.
+    Queued,
+
+    /// A worker claimed the job and did not acknowledge it yet.
+    Running,
+
+    /// The command was handled, with its response serialized as JSON.
+    Succeeded(Value),
+
+    /// The command failed, or the job ran out of attempts.
+    Failed(JobFailure),
+}
+
+/// Implementation of the `JobStatus`.
+impl JobStatus {
+    /// Returns `true` if the job succeeded or failed.
+    pub fn is_complete(&self) -> bool {
+        matches!(self, JobStatus::Succeeded(_) | JobStatus::Failed(_))
+    }
+}
+
+/// The `JobFailure` describes why a [Job] failed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JobFailure {
+    /// The [kind](NamedDispatchError::kind) of the failed dispatch, or `attempts_exhausted` if
+    /// the job ran out of attempts.
+    pub kind: String,
+
+    /// A description of the failure.
+    pub message: String,
+
+    /// The error returned by the handler, serialized as JSON, if the handler failed.
+    pub error: Option<Value>,
+}
+
+/// Implementation of the `JobFailure`.
+impl JobFailure {
+    fn dispatch(error: NamedDispatchError) -> Self {
+        let kind = error.kind().to_string();
+        let message = error.to_string();
+        Self {
+            kind,
+            message,
+            error: error
+                .into_dispatch_error()
+                .and_then(DispatchError::into_handler_error),
+        }
+    }
+}
+
+/// The `Job` is an enqueued command.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    /// The unique id of the job. Ids sort by enqueue time.
+    pub id: String,
+
+    /// The stable name of the command type.
+    pub name: String,
+
+    /// The command serialized as JSON.
+    pub payload: Value,
+
+    /// Where the job stands.
+    pub status: JobStatus,
+
+    /// The number of times the job was claimed by a worker.
+    pub attempts: u32,
+
+    /// When the job was enqueued, in milliseconds since the Unix epoch.
+    pub enqueued_at: u64,
+
+    /// When a queued or running job may be claimed, in milliseconds since the Unix epoch.
+    pub visible_at: u64,
+
+    /// The principal on whose behalf the command was enqueued, if any, restored when it is
+    /// dispatched.
+    #[serde(default)]
+    pub principal: Option<Principal>,
+}
+
+/// Implementation of the `Job`.
+impl Job {
+    /// Returns `true` if a worker may claim the job at `now`.
+    fn is_visible(&self, now: u64) -> bool {
+        !self.status.is_complete() && self.visible_at <= now
+    }
+
+    /// Makes the job running for the attempt of a worker, until `until`.
+    fn claim(&mut self, until: u64) {
+        self.status = JobStatus::Running;
+        self.attempts += 1;
+        self.visible_at = until;
+    }
+
+    /// Puts the running job back in the queue, visible again from `visible_at`.
+    fn release(&mut self, visible_at: u64) {
+        self.status = JobStatus::Queued;
+        self.visible_at = visible_at;
+    }
+
+    /// Returns `true` if the job is still held by the claim of `attempt`.
+    fn is_held_by(&self, attempt: u32) -> bool {
+        self.status == JobStatus::Running && self.attempts == attempt
+    }
+}
+
+/// The `JobStore` keeps the jobs of a [CommandQueue].
+///
+/// Claims must be exclusive: a job is claimed by one worker at a time, until its visibility
+/// timeout expires.
+#[async_trait]
+pub trait JobStore: Send + Sync {
+    /// Saves the new `job`.
+    async fn push(&self, job: &Job) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Claims the visible job of lowest id at `now`, making it running and invisible until
+    /// `until`, and increments its attempts. Returns `None` if no job is visible.
+    async fn claim(
+        &self,
+        now: u64,
+        until: u64,
+    ) -> Result<Option<Job>, Box<dyn Error + Send + Sync>>;
+
+    /// Records the `status` of the job `id` at the end of the claim of `attempt`, and returns
+    /// `false` without changing the job if it was claimed again since.
+    async fn complete(
+        &self,
+        id: &str,
+        attempt: u32,
+        status: JobStatus,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>>;
+
+    /// Puts the job `id` back in the queue at the end of the claim of `attempt`, visible again
+    /// from `visible_at`, and returns `false` without changing the job if it was claimed again
+    /// since.
+    async fn release(
+        &self,
+        id: &str,
+        attempt: u32,
+        visible_at: u64,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>>;
+
+    /// Returns the job `id`, if any.
+    async fn get(&self, id: &str) -> Result<Option<Job>, Box<dyn Error + Send + Sync>>;
+
+    /// Removes the job `id`, if any.
+    async fn remove(&self, id: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
+}
+
+/// A job store keeping jobs in memory.
+///
+/// Clones share the same jobs.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryJobStore {
+    #[doc(hidden)]
+    jobs: Arc<Mutex<BTreeMap<String, Job>>>,
+}
+
+/// Implementation of the `InMemoryJobStore`.
+impl InMemoryJobStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// JobStore implementation for `InMemoryJobStore`
+#[async_trait]
+impl JobStore for InMemoryJobStore {
+    async fn push(&self, job: &Job) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .insert(job.id.clone(), job.clone());
+        Ok(())
+    }
+
+    async fn claim(
+        &self,
+        now: u64,
+        until: u64,
+    ) -> Result<Option<Job>, Box<dyn Error + Send + Sync>> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.values_mut().find(|job| job.is_visible(now));
+        Ok(job.map(|job| {
+            job.claim(until);
+            job.clone()
+        }))
+    }
+
+    async fn complete(
+        &self,
+        id: &str,
+        attempt: u32,
+        status: JobStatus,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let mut jobs = self.jobs.lock().unwrap();
+        match jobs.get_mut(id) {
+            Some(job) if job.is_held_by(attempt) => {
+                job.status = status;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn release(
+        &self,
+        id: &str,
+        attempt: u32,
+        visible_at: u64,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let mut jobs = self.jobs.lock().unwrap();
+        match jobs.get_mut(id) {
+            Some(job) if job.is_held_by(attempt) => {
+                job.release(visible_at);
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Job>, Box<dyn Error + Send + Sync>> {
+        Ok(self.jobs.lock().unwrap().get(id).cloned())
+    }
+
+    async fn remove(&self, id: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.jobs.lock().unwrap().remove(id);
+        Ok(())
+    }
+}
+
+/// A job store keeping each job as a JSON file in a directory.
+///
+/// Files are written to a temporary name then renamed, so that a process stopping while saving
+/// does not leave a truncated job behind. Claims are only exclusive within a process, so the
+/// directory must not be shared by the workers of several processes. Every claim reads the jobs
+/// waiting in the directory, while completed jobs are moved to its `completed` subdirectory,
+/// where they stay until [removed](CommandQueue::remove). File operations run on the blocking
+/// threads of the runtime, off the threads executing the commands.
+///
+/// # Example
+///
+/// ```no_run
+/// use qonduit::queue::{CommandQueue, FileJobStore};
+///
+/// let store = FileJobStore::open("/var/lib/orders/jobs").unwrap();
+/// let queue = CommandQueue::new(store);
+/// # drop(queue);
+/// ```
+#[derive(Clone, Debug)]
+pub struct FileJobStore {
+    #[doc(hidden)]
+    directory: PathBuf,
+    #[doc(hidden)]
+    lock: Arc<Mutex<()>>,
+}
+
+/// Implementation of the `FileJobStore`.
+impl FileJobStore {
+    /// Opens the store kept in `directory`, creating the directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created.
+    pub fn open(directory: impl AsRef<Path>) -> io::Result<Self> {
+        let directory = directory.as_ref().to_path_buf();
+        std::fs::create_dir_all(directory.join(COMPLETED))?;
+        Ok(Self {
+            directory,
+            lock: Arc::new(Mutex::new(())),
+        })
+    }
+
+    /// Returns the path of the job `id` while it waits or runs.
+    fn path(&self, id: &str) -> io::Result<PathBuf> {
+        util::json_file(&self.directory, id)
+    }
+
+    /// Returns the path of the job `id` once completed.
+    fn completed_path(&self, id: &str) -> io::Result<PathBuf> {
+        util::json_file(&self.directory.join(COMPLETED), id)
+    }
+
+    fn read(&self, path: &Path) -> Result<Option<Job>, Box<dyn Error + Send + Sync>> {
+        match std::fs::read(path) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn write(&self, path: PathBuf, job: &Job) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let temporary = path.with_extension("json.tmp");
+        std::fs::write(&temporary, serde_json::to_vec(job)?)?;
+        std::fs::rename(temporary, path)?;
+        Ok(())
+    }
+}
+
+/// The subdirectory of a `FileJobStore` keeping the completed jobs.
+const COMPLETED: &str = "completed";
+
+/// Removes the file at `path`, if any.
+fn remove_file(path: PathBuf) -> io::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+/// JobStore implementation for `FileJobStore`
+#[async_trait]
+impl JobStore for FileJobStore {
+    async fn push(&self, job: &Job) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        let path = self.path(&job.id)?;
+        let job = job.clone();
+        blocking(move || store.write(path, &job)).await
+    }
+
+    async fn claim(
+        &self,
+        now: u64,
+        until: u64,
+    ) -> Result<Option<Job>, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        blocking(move || {
+            let _lock = store.lock.lock().unwrap();
+            let mut paths = Vec::new();
+            for entry in std::fs::read_dir(&store.directory)? {
+                let path = entry?.path();
+                if path
+                    .extension()
+                    .is_some_and(|extension| extension == "json")
+                {
+                    paths.push(path);
+                }
+            }
+            paths.sort();
+            for path in paths {
+                let Some(mut job) = store.read(&path)? else {
+                    continue;
+                };
+                // The process stopped while moving the job to the completed jobs
+                if store.completed_path(&job.id)?.try_exists()? {
+                    remove_file(path)?;
+                    continue;
+                }
+                if job.is_visible(now) {
+                    job.claim(until);
+                    store.write(path, &job)?;
+                    return Ok(Some(job));
+                }
+            }
+            Ok(None)
+        })
+        .await
+    }
+
+    async fn complete(
+        &self,
+        id: &str,
+        attempt: u32,
+        status: JobStatus,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        let path = self.path(id)?;
+        let completed_path = self.completed_path(id)?;
+        blocking(move || {
+            let _lock = store.lock.lock().unwrap();
+            match store.read(&path)? {
+                Some(mut job) if job.is_held_by(attempt) => {
+                    job.status = status;
+                    store.write(completed_path, &job)?;
+                    remove_file(path)?;
+                    Ok(true)
+                }
+                _ => Ok(false),
+            }
+        })
+        .await
+    }
+
+    async fn release(
+        &self,
+        id: &str,
+        attempt: u32,
+        visible_at: u64,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        let path = self.path(id)?;
+        blocking(move || {
+            let _lock = store.lock.lock().unwrap();
+            match store.read(&path)? {
+                Some(mut job) if job.is_held_by(attempt) => {
+                    job.release(visible_at);
+                    store.write(path, &job)?;
+                    Ok(true)
+                }
+                _ => Ok(false),
+            }
+        })
+        .await
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Job>, Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        let path = self.path(id)?;
+        let completed_path = self.completed_path(id)?;
+        blocking(move || match store.read(&completed_path)? {
+            Some(job) => Ok(Some(job)),
+            None => store.read(&path),
+        })
+        .await
+    }
+
+    async fn remove(&self, id: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let store = self.clone();
+        let path = self.path(id)?;
+        let completed_path = self.completed_path(id)?;
+        blocking(move || {
+            let _lock = store.lock.lock().unwrap();
+            remove_file(path)?;
+            remove_file(completed_path)?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// A job store keeping jobs in a SQLite database.
+///
+/// Jobs are kept in the `qonduit_jobs` table, created when the store is opened. Claims are
+/// exclusive across processes, so several processes may share the database. Clones share the
+/// same connection. Queries run on the blocking threads of the runtime.
+///
+/// # Example
+///
+/// ```no_run
+/// use qonduit::queue::{CommandQueue, SqliteJobStore};
+///
+/// let store = SqliteJobStore::open("/var/lib/orders/jobs.db").unwrap();
+/// let queue = CommandQueue::new(store);
+/// # drop(queue);
+/// ```
+#[cfg(feature = "sqlite")]
+#[derive(Clone)]
+pub struct SqliteJobStore {
+    #[doc(hidden)]
+    connection: Arc<Mutex<rusqlite::Connection>>,
+}
+
+/// The columns of a job, in the order read by `SqliteJobStore::job`.
+#[cfg(feature = "sqlite")]
+const JOB_COLUMNS: &str = "id, name, payload, status, attempts, enqueued_at, visible_at, principal";
+
+/// A job read by `SqliteJobStore::job`, with its payload, status and principal still to parse.
+#[cfg(feature = "sqlite")]
+type JobRow = (Job, String, String, Option<String>);
+
+/// Implementation of the `SqliteJobStore`.
+#[cfg(feature = "sqlite")]
+impl SqliteJobStore {
+    /// Opens the database at `path`, creating it and its table if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened or the table cannot be created.
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        let connection = rusqlite::Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS qonduit_jobs (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                payload TEXT NOT NULL,
+                state TEXT NOT NULL,
+                status TEXT NOT NULL,
+                attempts INTEGER NOT NULL,
+                enqueued_at INTEGER NOT NULL,
+                visible_at INTEGER NOT NULL,
+                principal TEXT
+            );
+            CREATE INDEX IF NOT EXISTS qonduit_jobs_visible ON qonduit_jobs (state, visible_at);",
+        )?;
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Reads a job from a row of the columns of `JOB_COLUMNS`.
+    fn job(row: &rusqlite::Row<'_>) -> rusqlite::Result<JobRow> {
+        let job = Job {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            payload: Value::Null,
+            status: JobStatus::Queued,
+            attempts: row.get(4)?,
+            enqueued_at: row.get::<_, i64>(5)? as u64,
+            visible_at: row.get::<_, i64>(6)? as u64,
+            principal: None,
+        };
+        Ok((job, row.get(2)?, row.get(3)?, row.get(7)?))
+    }
+
+    /// Parses the payload, the status and the principal of a job read by `job`.
+    fn parse(
+        (mut job, payload, status, principal): JobRow,
+    ) -> Result<Job, Box<dyn Error + Send + Sync>> {
+        job.payload = serde_json::from_str(&payload)?;
+        job.status = serde_json::from_str(&status)?;
+        job.principal = principal.as_deref().map(serde_json::from_str).transpose()?;
+        Ok(job)
+    }
+}
+
+/// Returns the value of the `state` column for `status`.
+#[cfg(feature = "sqlite")]
+fn state(status: &JobStatus) -> &'static str {
+    match status {
+        JobStatus::Queued => "queued",
+        JobStatus::Running => "running",
+        JobStatus::Succeeded(_) => "succeeded",
+        JobStatus::Failed(_) => "failed",
+    }
+}
+
+/// JobStore implementation for `SqliteJobStore`
+#[cfg(feature = "sqlite")]
+#[async_trait]
+impl JobStore for SqliteJobStore {
+    async fn push(&self, job: &Job) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let connection = self.connection.clone();
+        let params = (
+            job.id.clone(),
+            job.name.clone(),
+            serde_json::to_string(&job.payload)?,
+            state(&job.status),
+            serde_json::to_string(&job.status)?,
+            job.attempts,
+            job.enqueued_at as i64,
+            job.visible_at as i64,
+            job.principal
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+        );
+        blocking(move || {
+            connection.lock().unwrap().execute(
+                "INSERT INTO qonduit_jobs (id, name, payload, state, status, attempts, enqueued_at, visible_at, principal)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params,
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn claim(
+        &self,
+        now: u64,
+        until: u64,
+    ) -> Result<Option<Job>, Box<dyn Error + Send + Sync>> {
+        use rusqlite::OptionalExtension;
+
+        let connection = self.connection.clone();
+        let running = serde_json::to_string(&JobStatus::Running)?;
+        blocking(move || {
+            let row = connection
+                .lock()
+                .unwrap()
+                .query_row(
+                    &format!(
+                        "UPDATE qonduit_jobs
+                        SET state = 'running', status = ?3, attempts = attempts + 1, visible_at = ?2
+                        WHERE id = (
+                            SELECT id FROM qonduit_jobs
+                            WHERE state IN ('queued', 'running') AND visible_at <= ?1
+                            ORDER BY id LIMIT 1
+                        )
+                        RETURNING {JOB_COLUMNS}"
+                    ),
+                    rusqlite::params![now as i64, until as i64, running],
+                    Self::job,
+                )
+                .optional()?;
+            row.map(Self::parse).transpose()
+        })
+        .await
+    }
+
+    async fn complete(
+        &self,
+        id: &str,
+        attempt: u32,
+        status: JobStatus,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let connection = self.connection.clone();
+        let params = (
+            id.to_string(),
+            attempt,
+            state(&status),
+            serde_json::to_string(&status)?,
+        );
+        blocking(move || {
+            let updated = connection.lock().unwrap().execute(
+                "UPDATE qonduit_jobs SET state = ?3, status = ?4
+                WHERE id = ?1 AND state = 'running' AND attempts = ?2",
+                params,
+            )?;
+            Ok(updated > 0)
+        })
+        .await
+    }
+
+    async fn release(
+        &self,
+        id: &str,
+        attempt: u32,
+        visible_at: u64,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let connection = self.connection.clone();
+        let params = (
+            id.to_string(),
+            attempt,
+            serde_json::to_string(&JobStatus::Queued)?,
+            visible_at as i64,
+        );
+        blocking(move || {
+            let updated = connection.lock().unwrap().execute(
+                "UPDATE qonduit_jobs SET state = 'queued', status = ?3, visible_at = ?4
+                WHERE id = ?1 AND state = 'running' AND attempts = ?2",
+                params,
+            )?;
+            Ok(updated > 0)
+        })
+        .await
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Job>, Box<dyn Error + Send + Sync>> {
+        use rusqlite::OptionalExtension;
+
+        let connection = self.connection.clone();
+        let id = id.to_string();
+        blocking(move || {
+            let row = connection
+                .lock()
+                .unwrap()
+                .query_row(
+                    &format!("SELECT {JOB_COLUMNS} FROM qonduit_jobs WHERE id = ?1"),
+                    [id],
+                    Self::job,
+                )
+                .optional()?;
+            row.map(Self::parse).transpose()
+        })
+        .await
+    }
+
+    async fn remove(&self, id: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let connection = self.connection.clone();
+        let id = id.to_string();
+        blocking(move || {
+            connection
+                .lock()
+                .unwrap()
+                .execute("DELETE FROM qonduit_jobs WHERE id = ?1", [id])?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// Debug implementation for `SqliteJobStore`
+#[cfg(feature = "sqlite")]
+impl Debug for SqliteJobStore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("SqliteJobStore").finish_non_exhaustive()
+    }
+}
+
+/// The `CommandQueue` saves enqueued commands as jobs, and reports their status.
+///
+/// Clones share the same store.
+///
+/// # Example
+///
+/// ```
+/// # #[derive(Debug, serde::Serialize, serde::Deserialize)]
+/// # struct SendInvoiceCommand { invoice_id: u64 }
+/// # impl qonduit::command::Command for SendInvoiceCommand {
+/// #   type Response = ();
+/// #   type Error = String;
+/// # }
+/// # impl qonduit::message::MessageName for SendInvoiceCommand {
+/// #   const NAME: &'static str = "invoices.send.v1";
+/// # }
+/// # struct SendInvoiceCommandHandler;
+/// # #[qonduit::async_trait]
+/// # impl qonduit::command::CommandHandler<SendInvoiceCommand> for SendInvoiceCommandHandler {
+/// #   async fn handle(&self, _command: SendInvoiceCommand) -> Result<(), String> { Ok(()) }
+/// # }
+/// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// # rt.block_on(async {
+/// use qonduit::command::CommandBus;
+/// use qonduit::queue::{CommandQueue, InMemoryJobStore, JobStatus, WorkerPool};
+/// use qonduit::registry::CommandHandlerRegistry;
+///
+/// let mut registry = CommandHandlerRegistry::new();
+/// registry.register_json::<SendInvoiceCommand>(SendInvoiceCommandHandler);
+/// let queue = CommandQueue::new(InMemoryJobStore::new());
+/// let command_bus = CommandBus::new(registry).with_queue(queue.clone());
+/// tokio::spawn(WorkerPool::new(command_bus.clone(), queue.clone()).run());
+///
+/// let id = command_bus.enqueue(SendInvoiceCommand { invoice_id: 42 }).await.unwrap();
+/// while !queue.status(&id).await.unwrap().is_some_and(|status| status.is_complete()) {
+///     tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+/// }
+/// assert_eq!(queue.status(&id).await.unwrap(), Some(JobStatus::Succeeded(serde_json::json!(null))));
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct CommandQueue {
+    #[doc(hidden)]
+    store: Arc<dyn JobStore>,
+    #[doc(hidden)]
+    next_id: Arc<AtomicU64>,
+}
+
+/// Implementation of the `CommandQueue`.
+impl CommandQueue {
+    /// Creates a queue keeping its jobs in `store`.
+    pub fn new(store: impl JobStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+            next_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Returns the status of the job `id`, or `None` if there is no such job.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the store if the job cannot be loaded.
+    pub async fn status(
+        &self,
+        id: &str,
+    ) -> Result<Option<JobStatus>, Box<dyn Error + Send + Sync>> {
+        Ok(self.store.get(id).await?.map(|job| job.status))
+    }
+
+    /// Returns the job `id`, or `None` if there is no such job.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the store if the job cannot be loaded.
+    pub async fn job(&self, id: &str) -> Result<Option<Job>, Box<dyn Error + Send + Sync>> {
+        self.store.get(id).await
+    }
+
+    /// Removes the job `id`, typically once its outcome was read.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the store if the job cannot be removed.
+    pub async fn remove(&self, id: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.store.remove(id).await
+    }
+
+    /// Saves `command` as a new job, and returns its id.
+    pub(crate) async fn push<C>(&self, command: &C) -> Result<String, Box<dyn Error + Send + Sync>>
+    where
+        C: Command + MessageName + Serialize,
+    {
+        let now = unix_millis(SystemTime::now());
+        let sequence = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let job = Job {
+            // Sorts by creation, and stays unique across processes and restarts
+            id: format!("{now:013}-{}-{sequence:06}", std::process::id()),
+            name: C::NAME.to_string(),
+            payload: serde_json::to_value(command)?,
+            status: JobStatus::Queued,
+            attempts: 0,
+            enqueued_at: now,
+            visible_at: now,
+            principal: authorization::current_principal().map(|principal| (*principal).clone()),
+        };
+        self.store.push(&job).await?;
+        Ok(job.id)
+    }
+}
+
+/// Default implementation for `CommandQueue`
+impl Default for CommandQueue {
+    /// Creates a queue keeping its jobs in memory.
+    fn default() -> Self {
+        Self::new(InMemoryJobStore::new())
+    }
+}
+
+/// Debug implementation for `CommandQueue`
+impl Debug for CommandQueue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("CommandQueue").finish_non_exhaustive()
+    }
+}
+
+/// The `WorkerPool` executes the jobs of a [CommandQueue] through a `CommandBus`.
+///
+/// [run](WorkerPool::run) runs the workers until the returned future is dropped. Several pools,
+/// in one or several processes, may execute the jobs of the same queue if its store supports it.
+///
+/// # Example
+///
+/// ```no_run
+/// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// # rt.block_on(async {
+/// use std::time::Duration;
+/// use qonduit::command::CommandBus;
+/// use qonduit::queue::{CommandQueue, FileJobStore, WorkerPool};
+/// use qonduit::registry::CommandHandlerRegistry;
+///
+/// let queue = CommandQueue::new(FileJobStore::open("/var/lib/orders/jobs").unwrap());
+/// let command_bus = CommandBus::new(CommandHandlerRegistry::new()).with_queue(queue.clone());
+///
+/// WorkerPool::new(command_bus, queue)
+///     .with_workers(8)
+///     .with_visibility_timeout(Duration::from_secs(60))
+///     .run()
+///     .await;
+/// # });
+/// ```
+#[derive(Clone, Debug)]
+pub struct WorkerPool {
+    #[doc(hidden)]
+    command_bus: CommandBus,
+    #[doc(hidden)]
+    queue: CommandQueue,
+    #[doc(hidden)]
+    workers: usize,
+    #[doc(hidden)]
+    visibility_timeout: Duration,
+    #[doc(hidden)]
+    poll_interval: Duration,
+    #[doc(hidden)]
+    max_attempts: u32,
+    #[doc(hidden)]
+    retry_backoff: Duration,
+    #[doc(hidden)]
+    max_retry_backoff: Duration,
+}
+
+/// Implementation of the `WorkerPool`.
+impl WorkerPool {
+    /// Creates a pool of 4 workers executing the jobs of `queue` through `command_bus`.
+    pub fn new(command_bus: CommandBus, queue: CommandQueue) -> Self {
+        Self {
+            command_bus,
+            queue,
+            workers: 4,
+            visibility_timeout: DEFAULT_VISIBILITY_TIMEOUT,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            max_attempts: 5,
+            retry_backoff: DEFAULT_RETRY_BACKOFF,
+            max_retry_backoff: DEFAULT_MAX_RETRY_BACKOFF,
+        }
+    }
+
+    /// Sets the number of jobs executed concurrently.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `workers` is zero.
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        assert!(workers > 0, "A worker pool needs at least one worker");
+        self.workers = workers;
+        self
+    }
+
+    /// Keeps a claimed job invisible to other workers for `timeout`, instead of
+    /// [DEFAULT_VISIBILITY_TIMEOUT].
+    ///
+    /// A job taking longer is claimed again, so the timeout should exceed the time its handler
+    /// may take.
+    pub fn with_visibility_timeout(mut self, timeout: Duration) -> Self {
+        self.visibility_timeout = timeout;
+        self
+    }
+
+    /// Waits `interval` before looking for jobs again when the queue is empty, instead of
+    /// [DEFAULT_POLL_INTERVAL].
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Fails a job claimed more than `max_attempts` times, 5 by default, instead of dispatching
+    /// it again, and a job whose dispatch fails with a transient error on its last attempt instead
+    /// of retrying it.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `max_attempts` is zero.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        assert!(max_attempts > 0, "Jobs must be attempted at least once");
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Waits `backoff` before the first retry of a job failing with a transient error, instead of
+    /// [DEFAULT_RETRY_BACKOFF], doubling the wait after each attempt up to `max_backoff`, instead
+    /// of [DEFAULT_MAX_RETRY_BACKOFF].
+    ///
+    /// A job rejected by a rate limit waits at least as long as the limit requires.
+    pub fn with_retry_backoff(mut self, backoff: Duration, max_backoff: Duration) -> Self {
+        self.retry_backoff = backoff;
+        self.max_retry_backoff = max_backoff;
+        self
+    }
+
+    /// Returns the wait before retrying a job whose attempt number `attempt` failed with `error`.
+    fn backoff(&self, attempt: u32, error: &DispatchError<Value>) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        let backoff = self
+            .retry_backoff
+            .saturating_mul(factor)
+            .min(self.max_retry_backoff);
+        match error {
+            DispatchError::RateLimited { retry_after } => backoff.max(*retry_after),
+            _ => backoff,
+        }
+    }
+
+    /// Runs the workers on Tokio tasks until the returned future is dropped.
+    ///
+    /// A worker whose handler panics is replaced, and its job is claimed again once its
+    /// visibility timeout expires.
+    pub async fn run(self) {
+        let mut workers = JoinSet::new();
+        for _ in 0..self.workers {
+            workers.spawn(self.clone().work());
+        }
+        // Dropping the set aborts the workers
+        while workers.join_next().await.is_some() {
+            workers.spawn(self.clone().work());
+        }
+    }
+
+    /// Claims and executes jobs, one at a time.
+    async fn work(self) {
+        loop {
+            let now = unix_millis(SystemTime::now());
+            let until = now.saturating_add(self.visibility_timeout.as_millis() as u64);
+            let Ok(Some(job)) = self.queue.store.claim(now, until).await else {
+                tokio::time::sleep(self.poll_interval).await;
+                continue;
+            };
+            let status = if job.attempts > self.max_attempts {
+                JobStatus::Failed(JobFailure {
+                    kind: "attempts_exhausted".to_string(),
+                    message: format!(
+                        "the job was claimed {} times without completing",
+                        job.attempts
+                    ),
+                    error: None,
+                })
+            } else {
+                let outcome = match job.principal.clone() {
+                    Some(principal) => {
+                        self.command_bus
+                            .dispatch_json_as(principal, &job.name, job.payload.clone())
+                            .await
+                    }
+                    None => {
+                        self.command_bus
+                            .dispatch_json(&job.name, job.payload.clone())
+                            .await
+                    }
+                };
+                match outcome {
+                    Ok(response) => JobStatus::Succeeded(response),
+                    Err(NamedDispatchError::Dispatch(error))
+                        if error.is_transient() && job.attempts < self.max_attempts =>
+                    {
+                        let backoff = self.backoff(job.attempts, &error).as_millis() as u64;
+                        let visible_at = unix_millis(SystemTime::now()).saturating_add(backoff);
+                        // A job left claimed is retried once its visibility timeout expires
+                        let _ = self
+                            .queue
+                            .store
+                            .release(&job.id, job.attempts, visible_at)
+                            .await;
+                        continue;
+                    }
+                    Err(error) => JobStatus::Failed(JobFailure::dispatch(error)),
+                }
+            };
+            // A job left unacknowledged is claimed again once its visibility timeout expires
+            let _ = self
+                .queue
+                .store
+                .complete(&job.id, job.attempts, status)
+                .await;
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! The `rate_limit` module throttles how often commands of a type may be dispatched.
+//!
+//! Rate limits are token buckets configured per command type on the `CommandBus`. Each dispatch
+//! takes one token; tokens refill continuously at the configured rate up to the burst size. A
+//! dispatch finding the bucket empty fails with [DispatchError::RateLimited], which tells the
+//! caller how long to wait before a token becomes available.
+//!
+//! A limit can apply to the command type as a whole, or separately to every key extracted from the
+//! command (for example, a user or tenant id), so one noisy caller cannot exhaust the budget of the
+//! others. The number of keys tracked at once is capped: beyond the cap, the bucket of the least
+//! recently seen key is dropped, and that key starts over with a full bucket.
+//!
+//! - [RateLimitConfig]: The rate and burst size of a token bucket.
+
+use std::any::Any;
+use std::any::TypeId;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::error::DispatchError;
+
+/// Default number of keys whose buckets a keyed rate limit tracks at once.
+const DEFAULT_MAX_KEYS: usize = 10_000;
+
+/// The `RateLimitConfig` defines the refill rate and burst size of a token bucket.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use qonduit::rate_limit::RateLimitConfig;
+///
+/// // 100 dispatches per minute on average, with bursts of up to 20 at once.
+/// let config = RateLimitConfig::new(100, Duration::from_secs(60)).with_burst(20);
+/// # drop(config);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimitConfig {
+    #[doc(hidden)]
+    tokens_per_second: f64,
+    #[doc(hidden)]
+    burst: f64,
+    #[doc(hidden)]
+    max_keys: usize,
+}
+
+/// Implementation of the `RateLimitConfig`.
+impl RateLimitConfig {
+    /// Creates a configuration allowing `rate` dispatches per `period`, all of which may happen
+    /// in a single burst.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `rate` or `period` is zero.
+    pub fn new(rate: u32, period: Duration) -> Self {
+        assert!(rate > 0, "Rate limit must allow at least one dispatch");
+        assert!(!period.is_zero(), "Rate limit period must not be zero");
+        Self {
+            tokens_per_second: f64::from(rate) / period.as_secs_f64(),
+            burst: f64::from(rate),
+            max_keys: DEFAULT_MAX_KEYS,
+        }
+    }
+
+    /// Sets how many dispatches may happen in a burst after the bucket had time to refill.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `burst` is zero.
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        assert!(
+            burst > 0,
+            "Rate limit burst must allow at least one dispatch"
+        );
+        self.burst = f64::from(burst);
+        self
+    }
+
+    /// Sets how many keys a limit [by key](crate::command::CommandBus::with_rate_limit_by_key)
+    /// tracks at once.
+    ///
+    /// Defaults to 10,000 keys. Once the cap is reached, the bucket of the least recently seen key
+    /// is dropped to make room for a new key. Limits of a whole command type ignore this setting.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `max_keys` is zero.
+    pub fn with_max_keys(mut self, max_keys: usize) -> Self {
+        assert!(max_keys > 0, "Rate limit must track at least one key");
+        self.max_keys = max_keys;
+        self
+    }
+}
+
+/// A token bucket refilled lazily whenever it is accessed.
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn full(config: &RateLimitConfig) -> Self {
+        Self {
+            tokens: config.burst,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, config: &RateLimitConfig, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.refilled_at)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * config.tokens_per_second).min(config.burst);
+        self.refilled_at = now;
+    }
+
+    /// Takes a token, or returns how long to wait until one is available.
+    fn try_take(&mut self, config: &RateLimitConfig) -> Result<(), Duration> {
+        self.refill(config, Instant::now());
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            let missing = 1.0 - self.tokens;
+            Err(Duration::from_secs_f64(missing / config.tokens_per_second))
+        }
+    }
+}
+
+/// A type-erased rate limiter for a single message type.
+trait Limiter: Send + Sync {
+    /// Takes a token for `message`, or returns how long to wait until one is available.
+    fn try_acquire(&self, message: &dyn Any) -> Result<(), Duration>;
+}
+
+/// Limits all messages of a type with a single bucket.
+struct TypeLimiter {
+    config: RateLimitConfig,
+    bucket: Mutex<TokenBucket>,
+}
+
+impl Limiter for TypeLimiter {
+    fn try_acquire(&self, _message: &dyn Any) -> Result<(), Duration> {
+        self.bucket.lock().unwrap().try_take(&self.config)
+    }
+}
+
+/// The buckets of a keyed limiter, with the order in which their keys were last seen.
+struct KeyedBuckets<K> {
+    /// The bucket of each key, with the sequence number of its last use.
+    buckets: HashMap<K, (u64, TokenBucket)>,
+    /// The keys by sequence number of their last use, least recent first.
+    recency: BTreeMap<u64, K>,
+    /// The sequence number of the next use.
+    sequence: u64,
+}
+
+/// Limits messages of type `T` with one bucket per extracted key.
+struct KeyedLimiter<T, K> {
+    config: RateLimitConfig,
+    key: Box<dyn Fn(&T) -> K + Send + Sync>,
+    buckets: Mutex<KeyedBuckets<K>>,
+}
+
+impl<T, K> Limiter for KeyedLimiter<T, K>
+where
+    T: 'static,
+    K: Hash + Eq + Clone + Send + 'static,
+{
+    fn try_acquire(&self, message: &dyn Any) -> Result<(), Duration> {
+        let message = message
+            .downcast_ref::<T>()
+            .expect("Cannot downcast message to correct type");
+        let key = (self.key)(message);
+
+        let mut guard = self.buckets.lock().unwrap();
+        let KeyedBuckets {
+            buckets,
+            recency,
+            sequence,
+        } = &mut *guard;
+        *sequence += 1;
+        let bucket = match buckets.get_mut(&key) {
+            Some((used, bucket)) => {
+                recency.remove(used);
+                *used = *sequence;
+                bucket
+            }
+            None => {
+                if buckets.len() >= self.config.max_keys
+                    && let Some((_, evicted)) = recency.pop_first()
+                {
+                    buckets.remove(&evicted);
+                }
+                let bucket = TokenBucket::full(&self.config);
+                &mut buckets.entry(key.clone()).or_insert((*sequence, bucket)).1
+            }
+        };
+        recency.insert(*sequence, key);
+        bucket.try_take(&self.config)
+    }
+}
+
+/// The rate limiters of a bus, keyed by message type.
+#[derive(Clone, Default)]
+pub(crate) struct RateLimiters {
+    limiters: HashMap<TypeId, Arc<dyn Limiter>>,
+}
+
+impl RateLimiters {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits messages of type `T` with a single bucket, replacing any previous limit.
+    pub(crate) fn set<T: 'static>(&mut self, config: RateLimitConfig) {
+        let limiter = TypeLimiter {
+            config,
+            bucket: Mutex::new(TokenBucket::full(&config)),
+        };
+        self.limiters.insert(TypeId::of::<T>(), Arc::new(limiter));
+    }
+
+    /// Limits messages of type `T` with one bucket per key, replacing any previous limit.
+    pub(crate) fn set_keyed<T, K>(
+        &mut self,
+        config: RateLimitConfig,
+        key: impl Fn(&T) -> K + Send + Sync + 'static,
+    ) where
+        T: 'static,
+        K: Hash + Eq + Clone + Send + 'static,
+    {
+        let limiter = KeyedLimiter {
+            config,
+            key: Box::new(key),
+            buckets: Mutex::new(KeyedBuckets {
+                buckets: HashMap::new(),
+                recency: BTreeMap::new(),
+                sequence: 0,
+            }),
+        };
+        self.limiters.insert(TypeId::of::<T>(), Arc::new(limiter));
+    }
+
+    /// Takes a token for `message`, failing with [DispatchError::RateLimited] if none is left.
+    pub(crate) fn acquire<T: 'static, E>(&self, message: &T) -> Result<(), DispatchError<E>> {
+        match self.limiters.get(&TypeId::of::<T>()) {
+            Some(limiter) => limiter
+                .try_acquire(message)
+                .map_err(|retry_after| DispatchError::RateLimited { retry_after }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Debug implementation for `RateLimiters`
+impl Debug for RateLimiters {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("RateLimiters").finish()
+    }
+}
@@ -20,6 +20,14 @@ use std::sync::Arc;
 use crate::command::Command;
 use crate::command::CommandHandler;
 use crate::event::{Event, EventHandler};
+use crate::message::MessageName;
+use crate::message::MessageNames;
+#[cfg(feature = "serde")]
+use crate::named;
+#[cfg(feature = "serde")]
+use crate::named::NamedCommand;
+#[cfg(feature = "serde")]
+use crate::named::NamedQuery;
 use crate::query::Query;
 use crate::query::QueryHandler;
 use crate::registry::wrapper::QueryHandlerWrapper;
@@ -33,6 +41,13 @@ use crate::registry::wrapper::{CommandHandlerWrapper, EventHandlerWrapper};
 pub struct CommandHandlerRegistry {
     #[doc(hidden)]
     pub(crate) handlers: HashMap<TypeId, Arc<dyn CommandHandlerWrapper>>,
+    #[doc(hidden)]
+    pub(crate) handler_names: HashMap<TypeId, &'static str>,
+    #[doc(hidden)]
+    pub(crate) message_names: MessageNames,
+    #[doc(hidden)]
+    #[cfg(feature = "serde")]
+    pub(crate) named: HashMap<&'static str, NamedCommand>,
 }
 
 /// Implementation for `CommandHandlerRegistry`
@@ -41,6 +56,10 @@ impl CommandHandlerRegistry {
     pub fn new() -> Self {
         Self {
             handlers: HashMap::new(),
+            handler_names: HashMap::new(),
+            message_names: MessageNames::default(),
+            #[cfg(feature = "serde")]
+            named: HashMap::new(),
         }
     }
 
@@ -81,6 +100,8 @@ impl CommandHandlerRegistry {
     /// # assert!(true);
     /// ```
     pub fn register<C: Command>(&mut self, handler: impl CommandHandler<C> + 'static) {
+        self.handler_names
+            .insert(TypeId::of::<C>(), std::any::type_name_of_val(&handler));
         self.handlers.insert(
             TypeId::of::<C>(),
             Arc::new(Box::new(handler) as Box<dyn CommandHandler<C>>),
@@ -130,6 +151,95 @@ impl CommandHandlerRegistry {
             .cloned()
             .map(|handler| Box::new(handler) as Box<dyn CommandHandler<C>>)
     }
+
+    /// Returns the type name of the handler registered for the command type `C`.
+    ///
+    /// Returns `None` if no handler is registered for `C`.
+    pub fn handler_type_name<C: Command>(&self) -> Option<&'static str> {
+        self.handler_names.get(&TypeId::of::<C>()).copied()
+    }
+
+    /// Registers a command handler for a command type with a stable [name](MessageName).
+    ///
+    /// The command type is then known to the registry by its name, which the bus uses in place of
+    /// its type name in spans, metrics and diagnostics.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if another command type is already registered under the same name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use qonduit::command::{Command, CommandHandler};
+    /// # use qonduit::async_trait;
+    /// # use qonduit::registry::CommandHandlerRegistry;
+    /// use qonduit::message::MessageName;
+    ///
+    /// #[derive(Debug)]
+    /// struct AddProductCommand;
+    /// #
+    /// # impl Command for AddProductCommand {
+    /// #   type Response = ();
+    /// #   type Error = ();
+    /// # }
+    ///
+    /// impl MessageName for AddProductCommand {
+    ///     const NAME: &'static str = "products.add.v1";
+    /// }
+    /// #
+    /// # struct AddProductCommandHandler;
+    /// #
+    /// # #[async_trait]
+    /// # impl CommandHandler<AddProductCommand> for AddProductCommandHandler {
+    /// #   async fn handle(&self, _command: AddProductCommand) -> Result<(), ()> {
+    /// #     Ok(())
+    /// #   }
+    /// # }
+    ///
+    /// let mut registry = CommandHandlerRegistry::new();
+    /// registry.register_named::<AddProductCommand>(AddProductCommandHandler);
+    ///
+    /// assert_eq!(registry.message_name::<AddProductCommand>(), "products.add.v1");
+    /// ```
+    pub fn register_named<C: Command + MessageName>(
+        &mut self,
+        handler: impl CommandHandler<C> + 'static,
+    ) {
+        self.message_names.insert::<C>();
+        self.register::<C>(handler);
+    }
+
+    /// Returns the stable name of the command type `C` if it was registered with
+    /// [register_named](CommandHandlerRegistry::register_named), or its type name otherwise.
+    pub fn message_name<C: Command>(&self) -> &'static str {
+        self.message_names.get::<C>()
+    }
+
+    /// Returns the stable names of the registered commands.
+    pub fn message_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.message_names.names()
+    }
+
+    /// Registers a command handler for a command type with a stable [name](MessageName), under which
+    /// it can also be dispatched with [dispatch_json](crate::command::CommandBus::dispatch_json).
+    ///
+    /// The command is deserialized from the JSON payload, and its response or handler error
+    /// serialized back to JSON.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if another command type is already registered under the same name.
+    #[cfg(feature = "serde")]
+    pub fn register_json<C>(&mut self, handler: impl CommandHandler<C> + 'static)
+    where
+        C: Command + MessageName + serde::de::DeserializeOwned,
+        C::Response: serde::Serialize,
+        C::Error: serde::Serialize,
+    {
+        self.register_named::<C>(handler);
+        self.named.insert(C::NAME, named::command::<C>);
+    }
 }
 
 /// Debug implementation for `CommandHandlerRegistry`
@@ -147,6 +257,13 @@ impl Debug for CommandHandlerRegistry {
 pub struct QueryHandlerRegistry {
     #[doc(hidden)]
     pub(crate) handlers: HashMap<TypeId, Arc<dyn QueryHandlerWrapper>>,
+    #[doc(hidden)]
+    pub(crate) handler_names: HashMap<TypeId, &'static str>,
+    #[doc(hidden)]
+    pub(crate) message_names: MessageNames,
+    #[doc(hidden)]
+    #[cfg(feature = "serde")]
+    pub(crate) named: HashMap<&'static str, NamedQuery>,
 }
 
 /// Implementation for `QueryHandlerRegistry`.
@@ -155,6 +272,10 @@ impl QueryHandlerRegistry {
     pub fn new() -> Self {
         Self {
             handlers: HashMap::new(),
+            handler_names: HashMap::new(),
+            message_names: MessageNames::default(),
+            #[cfg(feature = "serde")]
+            named: HashMap::new(),
         }
     }
 
@@ -195,6 +316,8 @@ impl QueryHandlerRegistry {
     /// # assert!(true);
     /// ```
     pub fn register<Q: Query>(&mut self, handler: impl QueryHandler<Q> + 'static) {
+        self.handler_names
+            .insert(TypeId::of::<Q>(), std::any::type_name_of_val(&handler));
         self.handlers.insert(
             TypeId::of::<Q>(),
             Arc::new(Box::new(handler) as Box<dyn QueryHandler<Q>>),
@@ -244,6 +367,60 @@ impl QueryHandlerRegistry {
             .cloned()
             .map(|handler| Box::new(handler) as Box<dyn QueryHandler<Q>>)
     }
+
+    /// Returns the type name of the handler registered for the query type `Q`.
+    ///
+    /// Returns `None` if no handler is registered for `Q`.
+    pub fn handler_type_name<Q: Query>(&self) -> Option<&'static str> {
+        self.handler_names.get(&TypeId::of::<Q>()).copied()
+    }
+
+    /// Registers a query handler for a query type with a stable [name](MessageName).
+    ///
+    /// The query type is then known to the registry by its name, which the bus uses in place of
+    /// its type name in spans, metrics and diagnostics.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if another query type is already registered under the same name.
+    pub fn register_named<Q: Query + MessageName>(
+        &mut self,
+        handler: impl QueryHandler<Q> + 'static,
+    ) {
+        self.message_names.insert::<Q>();
+        self.register::<Q>(handler);
+    }
+
+    /// Returns the stable name of the query type `Q` if it was registered with
+    /// [register_named](QueryHandlerRegistry::register_named), or its type name otherwise.
+    pub fn message_name<Q: Query>(&self) -> &'static str {
+        self.message_names.get::<Q>()
+    }
+
+    /// Returns the stable names of the registered queries.
+    pub fn message_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.message_names.names()
+    }
+
+    /// Registers a query handler for a query type with a stable [name](MessageName), under which
+    /// it can also be dispatched with [dispatch_json](crate::query::QueryBus::dispatch_json).
+    ///
+    /// The query is deserialized from the JSON payload, and its response or handler error
+    /// serialized back to JSON.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if another query type is already registered under the same name.
+    #[cfg(feature = "serde")]
+    pub fn register_json<Q>(&mut self, handler: impl QueryHandler<Q> + 'static)
+    where
+        Q: Query + MessageName + serde::de::DeserializeOwned,
+        Q::Response: serde::Serialize,
+        Q::Error: serde::Serialize,
+    {
+        self.register_named::<Q>(handler);
+        self.named.insert(Q::NAME, named::query::<Q>);
+    }
 }
 
 /// Debug implementation for `QueryHandlerRegistry`
@@ -257,6 +434,10 @@ impl Debug for QueryHandlerRegistry {
 pub struct EventHandlerRegistry {
     #[doc(hidden)]
     pub(crate) handlers: HashMap<TypeId, Vec<Arc<dyn EventHandlerWrapper>>>,
+    #[doc(hidden)]
+    pub(crate) handler_names: HashMap<TypeId, Vec<&'static str>>,
+    #[doc(hidden)]
+    pub(crate) message_names: MessageNames,
 }
 
 /// A registry that stores lists of event handlers keyed by concrete event type.
@@ -314,6 +495,8 @@ impl EventHandlerRegistry {
     pub fn new() -> Self {
         Self {
             handlers: HashMap::new(),
+            handler_names: HashMap::new(),
+            message_names: MessageNames::default(),
         }
     }
 
@@ -348,6 +531,10 @@ impl EventHandlerRegistry {
     /// registry.register::<SomethingHappened>(Audit);
     /// ```
     pub fn register<E: Event>(&mut self, handler: impl EventHandler<E> + 'static) {
+        self.handler_names
+            .entry(TypeId::of::<E>())
+            .or_default()
+            .push(std::any::type_name_of_val(&handler));
         let handlers = self.handlers.entry(TypeId::of::<E>()).or_default();
         handlers.push(Arc::new(Box::new(handler) as Box<dyn EventHandler<E>>));
     }
@@ -364,6 +551,42 @@ impl EventHandlerRegistry {
             .map(|handler| Arc::new(handler) as Arc<dyn EventHandler<E>>)
             .collect()
     }
+
+    /// Returns the type names of the handlers registered for the event type `E`, in registration
+    /// order.
+    pub fn handler_type_names<E: Event>(&self) -> &[&'static str] {
+        self.handler_names
+            .get(&TypeId::of::<E>())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Registers an event handler for an event type with a stable [name](MessageName).
+    ///
+    /// Multiple handlers may be registered for the same event type, but no other event type may
+    /// use the same name.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if another event type is already registered under the same name.
+    pub fn register_named<E: Event + MessageName>(
+        &mut self,
+        handler: impl EventHandler<E> + 'static,
+    ) {
+        self.message_names.insert::<E>();
+        self.register::<E>(handler);
+    }
+
+    /// Returns the stable name of the event type `E` if it was registered with
+    /// [register_named](EventHandlerRegistry::register_named), or its type name otherwise.
+    pub fn message_name<E: Event>(&self) -> &'static str {
+        self.message_names.get::<E>()
+    }
+
+    /// Returns the stable names of the registered events.
+    pub fn message_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.message_names.names()
+    }
 }
 
 impl Debug for EventHandlerRegistry {
@@ -0,0 +1,1163 @@
+//! The `remote` module dispatches commands and queries to the buses of another process.
+//!
+//! This module is available with the `remote` feature. A [Transport] carries [Frame]s between two
+//! processes. Each frame holds the [stable name](crate::message::MessageName) of a message and its
+//! payload, encoded with a [Codec], along with a correlation id matching replies to their
+//! requests, so that many requests can be in flight on a single connection. A request also carries
+//! the budget left before the deadline of the dispatch that sent it, and the
+//! [current principal](crate::authorization::current_principal) of the sender, if any.
+//!
+//! On the calling side, [RemoteCommandBus] and [RemoteQueryBus] dispatch messages with the same
+//! typed signature as the local buses, so that a module can be moved to another process without
+//! changing the code dispatching to it. A dispatch waits for the reply for at most the
+//! [timeout](RemoteCommandBus::with_timeout) of the bus, or the budget left to the dispatch
+//! sending it if shorter, then fails with a [Timeout](DispatchError::Timeout) error. Failures to reach the other process, or to decode its
+//! reply, are reported as [Transport](DispatchError::Transport) errors.
+//!
+//! On the serving side, a [RemoteServer] decodes the received messages, dispatches them on its
+//! local buses within the deadline of their sender, and sends back their outcome. Transports also
+//! carry the events distributed between processes by the [bridge](crate::bridge) module.
+//!
+//! Three transports are provided:
+//!
+//! - [MemoryTransport]: Connects two ends within the same process, for tests.
+//! - [TcpTransport]: Sends length-prefixed frames over a TCP connection.
+//! - [UnixTransport]: Sends length-prefixed frames over a Unix domain socket, on Unix platforms.
+//!
+//! The other items of the module are:
+//!
+//! - [RemoteServer]: Dispatches received messages on a `CommandBus` and a `QueryBus`.
+//! - [RemoteCommandBus]: Dispatches commands to a remote server.
+//! - [RemoteQueryBus]: Dispatches queries to a remote server.
+//! - [StreamTransport]: Sends length-prefixed frames over any byte stream.
+//! - [TransportError]: The error returned by a transport.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+use std::io;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tokio::net::tcp;
+#[cfg(unix)]
+use tokio::net::unix;
+use tokio::sync::Mutex as AsyncMutex;
+use tokio::sync::Semaphore;
+use tokio::sync::mpsc;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+
+use crate::authorization;
+use crate::authorization::Principal;
+use crate::codec::Codec;
+use crate::command::Command;
+use crate::command::CommandBus;
+use crate::error::DispatchError;
+use crate::message::MessageName;
+use crate::query::Query;
+use crate::query::QueryBus;
+#[cfg(feature = "signing")]
+use crate::signing::SignedEnvelope;
+#[cfg(feature = "signing")]
+use crate::signing::Signer;
+#[cfg(feature = "signing")]
+use crate::signing::Verifier;
+use crate::timeout;
+use crate::util::BoxFuture;
+
+/// The time a remote bus waits for a reply by default.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The largest frame a [StreamTransport] accepts by default, in bytes.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// The number of requests a [RemoteServer] dispatches concurrently per connection by default.
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 256;
+
+/// The time a [RemoteServer] waits before accepting connections again after a failed accept.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// The `FrameKind` tells what a [Frame] carries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameKind {
+    /// A command to dispatch.
+    Command,
+
+    /// A query to dispatch.
+    Query,
+
+    /// The response of a successful dispatch.
+    Reply,
+
+    /// The `DispatchError` of a failed dispatch.
+    Error,
+
+    /// The name of an [event bridge](crate::bridge::EventBridge) connecting to a broker.
+    Hello,
+
+    /// The stable name of an event type a bridge receives.
+    Subscribe,
+
+    /// An event to deliver.
+    Event,
+
+    /// The acknowledgement of the event whose sequence number is the correlation id.
+    Ack,
+}
+
+/// Implementation of the `FrameKind`.
+impl FrameKind {
+    fn to_byte(self) -> u8 {
+        match self {
+            FrameKind::Command => 0,
+            FrameKind::Query => 1,
+            FrameKind::Reply => 2,
+            FrameKind::Error => 3,
+            FrameKind::Hello => 4,
+            FrameKind::Subscribe => 5,
+            FrameKind::Event => 6,
+            FrameKind::Ack => 7,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(FrameKind::Command),
+            1 => Some(FrameKind::Query),
+            2 => Some(FrameKind::Reply),
+            3 => Some(FrameKind::Error),
+            4 => Some(FrameKind::Hello),
+            5 => Some(FrameKind::Subscribe),
+            6 => Some(FrameKind::Event),
+            7 => Some(FrameKind::Ack),
+            _ => None,
+        }
+    }
+}
+
+/// The `Frame` is the unit of data exchanged over a [Transport].
+///
+/// A request carries a message and the stable name of its type, along with the principal and the
+/// remaining deadline of its sender. Its reply carries the same correlation id and name, and the
+/// encoded response or `DispatchError`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// The id matching a reply to its request.
+    pub correlation_id: u64,
+
+    /// What the frame carries.
+    pub kind: FrameKind,
+
+    /// The stable name of the message type.
+    pub name: String,
+
+    /// The principal on whose behalf a request is sent, if any.
+    pub principal: Option<Principal>,
+
+    /// The time left to complete a request, if its sender has a deadline.
+    pub deadline: Option<Duration>,
+
+    /// The message or its outcome, encoded with the codec of the buses.
+    pub payload: Vec<u8>,
+}
+
+/// The `TransportError` is returned when a frame cannot be sent or received.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TransportError {
+    /// The connection is closed.
+    Closed,
+
+    /// The underlying connection failed.
+    Io(io::Error),
+
+    /// A received frame is malformed or too large.
+    InvalidFrame(String),
+
+    /// The other end could not be authenticated.
+    Unauthenticated(String),
+}
+
+/// Display implementation for `TransportError`
+impl Display for TransportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        match self {
+            TransportError::Closed => write!(f, "connection closed"),
+            TransportError::Io(error) => write!(f, "connection failed: {error}"),
+            TransportError::InvalidFrame(reason) => write!(f, "invalid frame: {reason}"),
+            TransportError::Unauthenticated(reason) => write!(f, "unauthenticated: {reason}"),
+        }
+    }
+}
+
+/// Error implementation for `TransportError`
+impl Error for TransportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TransportError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// From implementation for `TransportError`
+impl From<io::Error> for TransportError {
+    fn from(error: io::Error) -> Self {
+        TransportError::Io(error)
+    }
+}
+
+/// The `Transport` trait carries frames to and from another process.
+///
+/// A transport is one end of a connection. Frames can be sent concurrently, while they are
+/// received by a single task at a time.
+#[async_trait]
+pub trait Transport: Send + Sync + 'static {
+    /// Sends a frame to the other end.
+    ///
+    /// # Errors
+    ///
+    /// Returns [Closed](TransportError::Closed) if the connection is closed, or another
+    /// `TransportError` if the frame cannot be sent.
+    async fn send(&self, frame: Frame) -> Result<(), TransportError>;
+
+    /// Receives the next frame sent by the other end, or `None` once the other end closed the
+    /// connection.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TransportError` if the connection failed or a malformed frame was received.
+    async fn recv(&self) -> Result<Option<Frame>, TransportError>;
+}
+
+/// The `MemoryTransport` connects two ends within the same process.
+///
+/// # Example
+///
+/// ```
+/// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// # rt.block_on(async {
+/// use qonduit::remote::{Frame, FrameKind, MemoryTransport, Transport};
+///
+/// let (client, server) = MemoryTransport::pair();
+/// let frame = Frame {
+///     correlation_id: 1,
+///     kind: FrameKind::Command,
+///     name: "orders.place.v1".to_string(),
+///     principal: None,
+///     deadline: None,
+///     payload: b"{}".to_vec(),
+/// };
+/// client.send(frame.clone()).await.unwrap();
+/// assert_eq!(server.recv().await.unwrap(), Some(frame));
+///
+/// drop(client);
+/// assert_eq!(server.recv().await.unwrap(), None);
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct MemoryTransport {
+    #[doc(hidden)]
+    sender: mpsc::UnboundedSender<Frame>,
+    #[doc(hidden)]
+    receiver: AsyncMutex<mpsc::UnboundedReceiver<Frame>>,
+}
+
+/// Implementation of the `MemoryTransport`.
+impl MemoryTransport {
+    /// Creates the two connected ends of an in-memory connection.
+    pub fn pair() -> (Self, Self) {
+        let (left_sender, left_receiver) = mpsc::unbounded_channel();
+        let (right_sender, right_receiver) = mpsc::unbounded_channel();
+        let left = Self {
+            sender: right_sender,
+            receiver: AsyncMutex::new(left_receiver),
+        };
+        let right = Self {
+            sender: left_sender,
+            receiver: AsyncMutex::new(right_receiver),
+        };
+        (left, right)
+    }
+}
+
+/// Transport implementation for `MemoryTransport`
+#[async_trait]
+impl Transport for MemoryTransport {
+    async fn send(&self, frame: Frame) -> Result<(), TransportError> {
+        self.sender.send(frame).map_err(|_| TransportError::Closed)
+    }
+
+    async fn recv(&self) -> Result<Option<Frame>, TransportError> {
+        Ok(self.receiver.lock().await.recv().await)
+    }
+}
+
+/// The `StreamTransport` sends length-prefixed frames over a byte stream.
+///
+/// Each frame is written as its length in bytes, excluding the length itself, followed by its
+/// correlation id, kind, deadline, name length, name, principal length, principal and payload.
+/// Integers are big-endian. The length and the principal length take 4 bytes, and the name length
+/// 2 bytes. The deadline is the number of milliseconds left, `u64::MAX` without deadline. The
+/// principal is encoded as JSON, and is empty without principal.
+#[derive(Debug)]
+pub struct StreamTransport<R, W> {
+    #[doc(hidden)]
+    reader: AsyncMutex<R>,
+    #[doc(hidden)]
+    writer: AsyncMutex<W>,
+    #[doc(hidden)]
+    max_frame_len: usize,
+}
+
+/// A transport over a TCP connection.
+///
+/// It is created from a connected `tokio::net::TcpStream`:
+///
+/// ```no_run
+/// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// # rt.block_on(async {
+/// use qonduit::remote::TcpTransport;
+/// use tokio::net::TcpStream;
+///
+/// let transport = TcpTransport::from(TcpStream::connect("orders:7000").await.unwrap());
+/// # drop(transport);
+/// # });
+/// ```
+pub type TcpTransport = StreamTransport<tcp::OwnedReadHalf, tcp::OwnedWriteHalf>;
+
+/// A transport over a Unix domain socket.
+///
+/// It is created from a connected `tokio::net::UnixStream`, like the [TcpTransport].
+#[cfg(unix)]
+pub type UnixTransport = StreamTransport<unix::OwnedReadHalf, unix::OwnedWriteHalf>;
+
+/// Implementation of the `StreamTransport`.
+impl<R, W> StreamTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    /// Creates a transport reading frames from `reader` and writing frames to `writer`.
+    pub fn new(reader: R, writer: W) -> Self {
+        Self {
+            reader: AsyncMutex::new(reader),
+            writer: AsyncMutex::new(writer),
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+        }
+    }
+
+    /// Rejects received frames longer than `max_frame_len` bytes, instead of
+    /// [DEFAULT_MAX_FRAME_LEN].
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+}
+
+/// From implementation for `TcpTransport`
+impl From<TcpStream> for TcpTransport {
+    fn from(stream: TcpStream) -> Self {
+        let (reader, writer) = stream.into_split();
+        Self::new(reader, writer)
+    }
+}
+
+/// From implementation for `UnixTransport`
+#[cfg(unix)]
+impl From<UnixStream> for UnixTransport {
+    fn from(stream: UnixStream) -> Self {
+        let (reader, writer) = stream.into_split();
+        Self::new(reader, writer)
+    }
+}
+
+/// Transport implementation for `StreamTransport`
+#[async_trait]
+impl<R, W> Transport for StreamTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    async fn send(&self, frame: Frame) -> Result<(), TransportError> {
+        let name_len = u16::try_from(frame.name.len())
+            .map_err(|_| TransportError::InvalidFrame("name too long".to_string()))?;
+        let principal = match &frame.principal {
+            Some(principal) => serde_json::to_vec(principal)
+                .map_err(|error| TransportError::InvalidFrame(error.to_string()))?,
+            None => Vec::new(),
+        };
+        let deadline = frame.deadline.map_or(u64::MAX, |deadline| {
+            u64::try_from(deadline.as_millis()).unwrap_or(u64::MAX - 1)
+        });
+        let len = HEADER_LEN + frame.name.len() + 4 + principal.len() + frame.payload.len();
+        let len = u32::try_from(len)
+            .map_err(|_| TransportError::InvalidFrame("frame too long".to_string()))?;
+
+        let mut bytes = Vec::with_capacity(4 + len as usize);
+        bytes.extend_from_slice(&len.to_be_bytes());
+        bytes.extend_from_slice(&frame.correlation_id.to_be_bytes());
+        bytes.push(frame.kind.to_byte());
+        bytes.extend_from_slice(&deadline.to_be_bytes());
+        bytes.extend_from_slice(&name_len.to_be_bytes());
+        bytes.extend_from_slice(frame.name.as_bytes());
+        bytes.extend_from_slice(&(principal.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&principal);
+        bytes.extend_from_slice(&frame.payload);
+
+        let mut writer = self.writer.lock().await;
+        writer.write_all(&bytes).await?;
+        writer.flush().await?;
+        Ok(())
+    }
+
+    async fn recv(&self) -> Result<Option<Frame>, TransportError> {
+        let mut reader = self.reader.lock().await;
+        let len = match reader.read_u32().await {
+            Ok(len) => len as usize,
+            Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+        if len > self.max_frame_len {
+            return Err(TransportError::InvalidFrame(format!(
+                "frame of {len} bytes exceeds the limit of {} bytes",
+                self.max_frame_len
+            )));
+        }
+        let mut bytes = vec![0; len];
+        reader.read_exact(&mut bytes).await?;
+        drop(reader);
+        parse_frame(bytes).map(Some)
+    }
+}
+
+/// The length of the fixed part of a frame, from its correlation id to its name length.
+const HEADER_LEN: usize = 8 + 1 + 8 + 2;
+
+/// Parses a frame from the bytes following its length.
+fn parse_frame(mut bytes: Vec<u8>) -> Result<Frame, TransportError> {
+    let invalid = |reason: &str| TransportError::InvalidFrame(reason.to_string());
+    if bytes.len() < HEADER_LEN {
+        return Err(invalid("truncated header"));
+    }
+    let correlation_id = u64::from_be_bytes(bytes[..8].try_into().unwrap());
+    let kind = FrameKind::from_byte(bytes[8]).ok_or_else(|| invalid("unknown kind"))?;
+    let deadline = match u64::from_be_bytes(bytes[9..17].try_into().unwrap()) {
+        u64::MAX => None,
+        millis => Some(Duration::from_millis(millis)),
+    };
+    let name_len = u16::from_be_bytes([bytes[17], bytes[18]]) as usize;
+    let principal_at = HEADER_LEN + name_len;
+    if bytes.len() < principal_at + 4 {
+        return Err(invalid("truncated name"));
+    }
+    let principal_len =
+        u32::from_be_bytes(bytes[principal_at..principal_at + 4].try_into().unwrap()) as usize;
+    let payload_at = principal_at + 4 + principal_len;
+    if bytes.len() < payload_at {
+        return Err(invalid("truncated principal"));
+    }
+    let principal = match principal_len {
+        0 => None,
+        _ => Some(
+            serde_json::from_slice(&bytes[principal_at + 4..payload_at])
+                .map_err(|_| invalid("invalid principal"))?,
+        ),
+    };
+    let payload = bytes.split_off(payload_at);
+    bytes.truncate(principal_at);
+    let name =
+        String::from_utf8(bytes.split_off(HEADER_LEN)).map_err(|_| invalid("name is not UTF-8"))?;
+    Ok(Frame {
+        correlation_id,
+        kind,
+        name,
+        principal,
+        deadline,
+        payload,
+    })
+}
+
+/// Decodes, dispatches and encodes the outcome of a message of a served type.
+type Route = Arc<dyn Fn(Vec<u8>) -> BoxFuture<(FrameKind, Vec<u8>)> + Send + Sync>;
+
+/// The `RemoteServer` dispatches the messages received over transports on a `CommandBus` and a
+/// `QueryBus`, and sends back their outcome.
+///
+/// A message whose type is not served, or whose payload cannot be decoded, is answered with a
+/// [Transport](DispatchError::Transport) error.
+///
+/// # Example
+///
+/// ```
+/// # use qonduit::async_trait;
+/// # use qonduit::command::{Command, CommandHandler};
+/// # use qonduit::message::MessageName;
+/// #
+/// # #[derive(Debug, serde::Serialize, serde::Deserialize)]
+/// # struct PlaceOrderCommand { sku: String }
+/// #
+/// # impl Command for PlaceOrderCommand {
+/// #   type Response = u64;
+/// #   type Error = String;
+/// # }
+/// #
+/// # impl MessageName for PlaceOrderCommand {
+/// #   const NAME: &'static str = "orders.place.v1";
+/// # }
+/// #
+/// # struct PlaceOrderCommandHandler;
+/// #
+/// # #[async_trait]
+/// # impl CommandHandler<PlaceOrderCommand> for PlaceOrderCommandHandler {
+/// #   async fn handle(&self, _command: PlaceOrderCommand) -> Result<u64, String> {
+/// #     Ok(42)
+/// #   }
+/// # }
+/// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// # rt.block_on(async {
+/// use qonduit::codec::JsonCodec;
+/// use qonduit::command::CommandBus;
+/// use qonduit::query::QueryBus;
+/// use qonduit::registry::{CommandHandlerRegistry, QueryHandlerRegistry};
+/// use qonduit::remote::{MemoryTransport, RemoteCommandBus, RemoteServer};
+///
+/// let mut registry = CommandHandlerRegistry::new();
+/// registry.register::<PlaceOrderCommand>(PlaceOrderCommandHandler);
+/// let server = RemoteServer::new(
+///     CommandBus::new(registry),
+///     QueryBus::new(QueryHandlerRegistry::new()),
+///     JsonCodec,
+/// )
+/// .command::<PlaceOrderCommand>();
+///
+/// let (client, connection) = MemoryTransport::pair();
+/// tokio::spawn(async move { server.serve(connection).await });
+///
+/// let bus = RemoteCommandBus::new(client, JsonCodec);
+/// let order_id = bus.dispatch(PlaceOrderCommand { sku: "kb-01".to_string() }).await;
+/// assert_eq!(order_id, Ok(42));
+/// # });
+/// ```
+pub struct RemoteServer<K: Codec> {
+    #[doc(hidden)]
+    command_bus: CommandBus,
+    #[doc(hidden)]
+    query_bus: QueryBus,
+    #[doc(hidden)]
+    codec: Arc<K>,
+    #[doc(hidden)]
+    commands: Arc<HashMap<&'static str, Route>>,
+    #[doc(hidden)]
+    queries: Arc<HashMap<&'static str, Route>>,
+    #[doc(hidden)]
+    max_in_flight: usize,
+    #[doc(hidden)]
+    trusted_principals: bool,
+    #[doc(hidden)]
+    #[cfg(feature = "signing")]
+    verifier: Option<Verifier>,
+}
+
+/// Implementation of the `RemoteServer`.
+impl<K: Codec> RemoteServer<K> {
+    /// Creates a server dispatching on `command_bus` and `query_bus`, decoding messages and
+    /// encoding their outcomes with `codec`, without any served message type.
+    pub fn new(command_bus: CommandBus, query_bus: QueryBus, codec: K) -> Self {
+        Self {
+            command_bus,
+            query_bus,
+            codec: Arc::new(codec),
+            commands: Arc::new(HashMap::new()),
+            queries: Arc::new(HashMap::new()),
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            trusted_principals: false,
+            #[cfg(feature = "signing")]
+            verifier: None,
+        }
+    }
+
+    /// Dispatches at most `max_in_flight` requests concurrently per connection, instead of
+    /// [DEFAULT_MAX_IN_FLIGHT].
+    ///
+    /// Once the limit is reached, the server stops reading the connection until a dispatch
+    /// completes.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `max_in_flight` is zero.
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        assert!(max_in_flight > 0, "max_in_flight must be positive");
+        self.max_in_flight = max_in_flight;
+        self
+    }
+
+    /// Dispatches each request on behalf of the principal sent with it.
+    ///
+    /// By default, requests are dispatched without principal, so that a client cannot claim the
+    /// roles of another one. The principal is not part of the signature of a message, so it
+    /// should only be trusted on connections from trusted processes.
+    pub fn with_trusted_principals(mut self) -> Self {
+        self.trusted_principals = true;
+        self
+    }
+
+    /// Only dispatches the messages signed by a key trusted by `verifier`.
+    ///
+    /// Each message must be sent as a [SignedEnvelope], for example by a remote bus
+    /// [with a signer](RemoteCommandBus::with_signer). A message that is not signed, or whose
+    /// signature is rejected by the verifier, is answered with an
+    /// [Unauthenticated](DispatchError::Unauthenticated) error and never reaches the buses. See
+    /// the [signing](crate::signing) module for details.
+    #[cfg(feature = "signing")]
+    pub fn with_verifier(mut self, verifier: Verifier) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+
+    /// Serves the command type `C` under its [stable name](MessageName).
+    pub fn command<C>(mut self) -> Self
+    where
+        C: Command + MessageName + DeserializeOwned,
+        C::Response: Serialize,
+        C::Error: Serialize,
+    {
+        let bus = self.command_bus.clone();
+        let codec = self.codec.clone();
+        let route: Route = Arc::new(move |payload| {
+            let bus = bus.clone();
+            let codec = codec.clone();
+            Box::pin(async move {
+                match codec.decode::<C>(&payload) {
+                    Ok(command) => respond(codec.as_ref(), bus.dispatch(command).await),
+                    Err(error) => invalid_payload(codec.as_ref(), error),
+                }
+            })
+        });
+        Arc::make_mut(&mut self.commands).insert(C::NAME, route);
+        self
+    }
+
+    /// Serves the query type `Q` under its [stable name](MessageName).
+    pub fn query<Q>(mut self) -> Self
+    where
+        Q: Query + MessageName + DeserializeOwned,
+        Q::Response: Serialize,
+        Q::Error: Serialize,
+    {
+        let bus = self.query_bus.clone();
+        let codec = self.codec.clone();
+        let route: Route = Arc::new(move |payload| {
+            let bus = bus.clone();
+            let codec = codec.clone();
+            Box::pin(async move {
+                match codec.decode::<Q>(&payload) {
+                    Ok(query) => respond(codec.as_ref(), bus.dispatch(query).await),
+                    Err(error) => invalid_payload(codec.as_ref(), error),
+                }
+            })
+        });
+        Arc::make_mut(&mut self.queries).insert(Q::NAME, route);
+        self
+    }
+
+    /// Serves the messages received over `transport` until the other end closes the connection.
+    ///
+    /// Messages are dispatched concurrently, up to the
+    /// [maximum in flight](RemoteServer::with_max_in_flight), and each reply is sent as soon as
+    /// its dispatch completes. A message is dispatched within the deadline sent with it. The
+    /// dispatches still running when the connection ends, or when the returned future is dropped,
+    /// are cancelled.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `TransportError` that ended the connection, if it did not close cleanly.
+    pub async fn serve(&self, transport: impl Transport) -> Result<(), TransportError> {
+        let transport = Arc::new(transport);
+        let permits = Arc::new(Semaphore::new(self.max_in_flight));
+        // Dropping the set aborts the dispatches in flight
+        let mut tasks = JoinSet::new();
+        while let Some(mut frame) = transport.recv().await? {
+            while tasks.try_join_next().is_some() {}
+            let routes = match frame.kind {
+                FrameKind::Command => &self.commands,
+                FrameKind::Query => &self.queries,
+                // Only requests are expected from the other end
+                _ => continue,
+            };
+            let outcome = match (
+                self.authenticate(&mut frame),
+                routes.get(frame.name.as_str()),
+            ) {
+                (Err(outcome), _) => Box::pin(async move { outcome }),
+                (Ok(()), Some(route)) => route(frame.payload),
+                (Ok(()), None) => {
+                    let reason = format!("unknown message name {:?}", frame.name);
+                    let outcome = transport_error(self.codec.as_ref(), reason);
+                    Box::pin(async move { outcome })
+                }
+            };
+            let outcome = match (frame.principal.take(), self.trusted_principals) {
+                (Some(principal), true) => Box::pin(authorization::scope(principal, outcome)),
+                _ => outcome,
+            };
+            let outcome = match frame.deadline {
+                Some(deadline) => Box::pin(timeout::scope(Instant::now() + deadline, outcome)),
+                None => outcome,
+            };
+            let permit = permits.clone().acquire_owned().await.unwrap();
+            let transport = transport.clone();
+            tasks.spawn(async move {
+                let (kind, payload) = outcome.await;
+                drop(permit);
+                let reply = Frame {
+                    correlation_id: frame.correlation_id,
+                    kind,
+                    name: frame.name,
+                    principal: None,
+                    deadline: None,
+                    payload,
+                };
+                // The other end no longer waits for the reply if the connection is closed
+                let _ = transport.send(reply).await;
+            });
+        }
+        Ok(())
+    }
+
+    /// Verifies the signature of a request and replaces its payload with the signed message, or
+    /// returns the reply rejecting it.
+    #[cfg(feature = "signing")]
+    fn authenticate(&self, frame: &mut Frame) -> Result<(), (FrameKind, Vec<u8>)> {
+        let Some(verifier) = &self.verifier else {
+            return Ok(());
+        };
+        let envelope = SignedEnvelope::from_bytes(&frame.payload)
+            .and_then(|envelope| verifier.verify(&envelope).map(|()| envelope));
+        let reason = match envelope {
+            Ok(envelope) if envelope.name == frame.name => {
+                frame.payload = envelope.payload;
+                return Ok(());
+            }
+            Ok(envelope) => format!("message signed as {:?}", envelope.name),
+            Err(error) => error.to_string(),
+        };
+        // The error does not depend on the handler error type, so any type decodes it
+        let error = DispatchError::<()>::Unauthenticated(reason);
+        let payload = self.codec.encode(&error).unwrap_or_default();
+        Err((FrameKind::Error, payload))
+    }
+
+    /// Accepts every request when signatures are not supported.
+    #[cfg(not(feature = "signing"))]
+    fn authenticate(&self, _frame: &mut Frame) -> Result<(), (FrameKind, Vec<u8>)> {
+        Ok(())
+    }
+
+    /// Accepts TCP connections on `listener` and serves each of them on its own task, forever.
+    ///
+    /// A failure to accept a connection, for example when the process runs out of file
+    /// descriptors, is logged with the `tracing` feature, and connections are accepted again
+    /// after a short pause.
+    pub async fn serve_tcp(self, listener: TcpListener) {
+        let server = Arc::new(self);
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let server = server.clone();
+                    tokio::spawn(async move { server.serve(TcpTransport::from(stream)).await });
+                }
+                Err(error) => accept_failed(error).await,
+            }
+        }
+    }
+
+    /// Accepts connections on the Unix domain socket `listener` and serves each of them on its
+    /// own task, forever.
+    ///
+    /// Failures to accept a connection are handled as with [serve_tcp](RemoteServer::serve_tcp).
+    #[cfg(unix)]
+    pub async fn serve_unix(self, listener: UnixListener) {
+        let server = Arc::new(self);
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let server = server.clone();
+                    tokio::spawn(async move { server.serve(UnixTransport::from(stream)).await });
+                }
+                Err(error) => accept_failed(error).await,
+            }
+        }
+    }
+}
+
+/// Debug implementation for `RemoteServer`
+impl<K: Codec> Debug for RemoteServer<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("RemoteServer")
+            .field("content_type", &self.codec.content_type())
+            .field("commands", &self.commands.keys().collect::<Vec<_>>())
+            .field("queries", &self.queries.keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Logs a failure to accept a connection, and pauses before the next attempt.
+pub(crate) async fn accept_failed(error: io::Error) {
+    #[cfg(feature = "tracing")]
+    tracing::error!(error = %error, "failed to accept a connection");
+    #[cfg(not(feature = "tracing"))]
+    let _ = error;
+    tokio::time::sleep(ACCEPT_BACKOFF).await;
+}
+
+/// Encodes the outcome of a dispatch into the kind and payload of its reply.
+fn respond<R, E>(codec: &impl Codec, result: Result<R, DispatchError<E>>) -> (FrameKind, Vec<u8>)
+where
+    R: Serialize,
+    E: Serialize,
+{
+    let (kind, encoded) = match &result {
+        Ok(response) => (FrameKind::Reply, codec.encode(response)),
+        Err(error) => (FrameKind::Error, codec.encode(error)),
+    };
+    match encoded {
+        Ok(payload) => (kind, payload),
+        Err(error) => transport_error(codec, error.to_string()),
+    }
+}
+
+/// Answers a message whose payload cannot be decoded.
+fn invalid_payload(codec: &impl Codec, error: impl Display) -> (FrameKind, Vec<u8>) {
+    transport_error(codec, format!("invalid payload: {error}"))
+}
+
+/// Answers a message that cannot be dispatched with a `Transport` error.
+fn transport_error(codec: &impl Codec, reason: String) -> (FrameKind, Vec<u8>) {
+    // The error does not depend on the handler error type, so any type decodes it
+    let error = DispatchError::<()>::Transport(reason);
+    (FrameKind::Error, codec.encode(&error).unwrap_or_default())
+}
+
+/// The requests awaiting their reply, or `None` once the connection is closed.
+type Pending = Arc<Mutex<Option<HashMap<u64, oneshot::Sender<Frame>>>>>;
+
+/// The connection shared by the clones of the remote buses.
+struct Connection<K: Codec> {
+    transport: Arc<dyn Transport>,
+    codec: K,
+    pending: Pending,
+    next_id: AtomicU64,
+    reader: JoinHandle<()>,
+}
+
+/// Drop implementation for `Connection`
+impl<K: Codec> Drop for Connection<K> {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+/// Removes a request from the pending requests when its dispatch ends, whatever its outcome.
+struct PendingGuard<'a> {
+    pending: &'a Pending,
+    correlation_id: u64,
+}
+
+/// Drop implementation for `PendingGuard`
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(pending) = self.pending.lock().unwrap().as_mut() {
+            pending.remove(&self.correlation_id);
+        }
+    }
+}
+
+/// A client sending requests to a remote [RemoteServer].
+struct Client<K: Codec> {
+    connection: Arc<Connection<K>>,
+    timeout: Duration,
+    #[cfg(feature = "signing")]
+    signer: Option<Signer>,
+}
+
+impl<K: Codec> Client<K> {
+    fn new(transport: impl Transport, codec: K) -> Self {
+        let transport: Arc<dyn Transport> = Arc::new(transport);
+        let pending: Pending = Arc::new(Mutex::new(Some(HashMap::new())));
+        let reader = tokio::spawn(read_replies(transport.clone(), pending.clone()));
+        Self {
+            connection: Arc::new(Connection {
+                transport,
+                codec,
+                pending,
+                next_id: AtomicU64::new(0),
+                reader,
+            }),
+            timeout: DEFAULT_TIMEOUT,
+            #[cfg(feature = "signing")]
+            signer: None,
+        }
+    }
+
+    /// Sends a message as a request of `kind` and decodes the outcome of its reply.
+    async fn send<M, R, E>(&self, kind: FrameKind, message: M) -> Result<R, DispatchError<E>>
+    where
+        M: MessageName + Serialize,
+        R: DeserializeOwned,
+        E: DeserializeOwned,
+    {
+        let connection = self.connection.as_ref();
+        let payload = connection
+            .codec
+            .encode(&message)
+            .map_err(|error| DispatchError::Transport(error.to_string()))?;
+        #[cfg(feature = "signing")]
+        let payload = match &self.signer {
+            Some(signer) => signer.sign(M::NAME, payload).to_bytes(),
+            None => payload,
+        };
+
+        let correlation_id = connection.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        match connection.pending.lock().unwrap().as_mut() {
+            Some(pending) => pending.insert(correlation_id, sender),
+            None => return Err(DispatchError::Transport(TransportError::Closed.to_string())),
+        };
+        let _guard = PendingGuard {
+            pending: &connection.pending,
+            correlation_id,
+        };
+
+        // The remote dispatch cannot outlive the dispatch sending it
+        let budget =
+            timeout::remaining().map_or(self.timeout, |remaining| remaining.min(self.timeout));
+        let frame = Frame {
+            correlation_id,
+            kind,
+            name: M::NAME.to_string(),
+            principal: authorization::current_principal().map(|principal| (*principal).clone()),
+            deadline: Some(budget),
+            payload,
+        };
+        connection
+            .transport
+            .send(frame)
+            .await
+            .map_err(|error| DispatchError::Transport(error.to_string()))?;
+
+        let reply = match tokio::time::timeout(budget, receiver).await {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(_)) => {
+                return Err(DispatchError::Transport(TransportError::Closed.to_string()));
+            }
+            Err(_) => return Err(DispatchError::Timeout(budget)),
+        };
+        match reply.kind {
+            FrameKind::Reply => connection
+                .codec
+                .decode(&reply.payload)
+                .map_err(|error| DispatchError::Transport(error.to_string())),
+            _ => Err(connection
+                .codec
+                .decode(&reply.payload)
+                .unwrap_or_else(|error| DispatchError::Transport(error.to_string()))),
+        }
+    }
+}
+
+/// Clone implementation for `Client`
+impl<K: Codec> Clone for Client<K> {
+    fn clone(&self) -> Self {
+        Self {
+            connection: self.connection.clone(),
+            timeout: self.timeout,
+            #[cfg(feature = "signing")]
+            signer: self.signer.clone(),
+        }
+    }
+}
+
+/// Debug implementation for `Client`
+impl<K: Codec> Debug for Client<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("Client")
+            .field("content_type", &self.connection.codec.content_type())
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Hands the received replies to the requests awaiting them, until the connection is closed.
+async fn read_replies(transport: Arc<dyn Transport>, pending: Pending) {
+    while let Ok(Some(reply)) = transport.recv().await {
+        let sender = match pending.lock().unwrap().as_mut() {
+            Some(pending) => pending.remove(&reply.correlation_id),
+            None => None,
+        };
+        // The request may have timed out while its reply was on its way
+        if let Some(sender) = sender {
+            let _ = sender.send(reply);
+        }
+    }
+    // Fails the requests in flight, and the ones sent from now on
+    pending.lock().unwrap().take();
+}
+
+/// The `RemoteCommandBus` dispatches commands to the [RemoteServer] of another process.
+///
+/// The command type must be served by the server, and the codec must match the one of the
+/// server. Clones of the bus share its connection.
+///
+/// A remote bus must be created within a Tokio runtime, which runs the task receiving the
+/// replies of the server.
+#[derive(Debug)]
+pub struct RemoteCommandBus<K: Codec> {
+    #[doc(hidden)]
+    client: Client<K>,
+}
+
+/// Implementation of the `RemoteCommandBus`.
+impl<K: Codec> RemoteCommandBus<K> {
+    /// Creates a bus sending commands over `transport`, encoded with `codec`.
+    pub fn new(transport: impl Transport, codec: K) -> Self {
+        Self {
+            client: Client::new(transport, codec),
+        }
+    }
+
+    /// Waits at most `timeout` for the reply to a command, instead of [DEFAULT_TIMEOUT].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client.timeout = timeout;
+        self
+    }
+
+    /// Signs each command with `signer`, for a server [verifying](RemoteServer::with_verifier)
+    /// the signatures of the messages it receives.
+    #[cfg(feature = "signing")]
+    pub fn with_signer(mut self, signer: Signer) -> Self {
+        self.client.signer = Some(signer);
+        self
+    }
+
+    /// Returns a query bus sharing the connection, timeout and signer of this bus.
+    pub fn query_bus(&self) -> RemoteQueryBus<K> {
+        RemoteQueryBus {
+            client: self.client.clone(),
+        }
+    }
+
+    /// Dispatches a command to the remote server and returns its response.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `DispatchError` reported by the remote bus,
+    /// [Timeout](DispatchError::Timeout) if no reply arrives in time, or
+    /// [Transport](DispatchError::Transport) if the server cannot be reached or its reply cannot
+    /// be decoded.
+    pub async fn dispatch<C>(&self, command: C) -> Result<C::Response, DispatchError<C::Error>>
+    where
+        C: Command + MessageName + Serialize,
+        C::Response: DeserializeOwned,
+        C::Error: DeserializeOwned,
+    {
+        self.client.send(FrameKind::Command, command).await
+    }
+}
+
+/// Clone implementation for `RemoteCommandBus`
+impl<K: Codec> Clone for RemoteCommandBus<K> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+        }
+    }
+}
+
+/// The `RemoteQueryBus` dispatches queries to the [RemoteServer] of another process.
+///
+/// It behaves like the [RemoteCommandBus], for queries.
+#[derive(Debug)]
+pub struct RemoteQueryBus<K: Codec> {
+    #[doc(hidden)]
+    client: Client<K>,
+}
+
+/// Implementation of the `RemoteQueryBus`.
+impl<K: Codec> RemoteQueryBus<K> {
+    /// Creates a bus sending queries over `transport`, encoded with `codec`.
+    pub fn new(transport: impl Transport, codec: K) -> Self {
+        Self {
+            client: Client::new(transport, codec),
+        }
+    }
+
+    /// Waits at most `timeout` for the reply to a query, instead of [DEFAULT_TIMEOUT].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client.timeout = timeout;
+        self
+    }
+
+    /// Signs each query with `signer`, for a server [verifying](RemoteServer::with_verifier) the
+    /// signatures of the messages it receives.
+    #[cfg(feature = "signing")]
+    pub fn with_signer(mut self, signer: Signer) -> Self {
+        self.client.signer = Some(signer);
+        self
+    }
+
+    /// Returns a command bus sharing the connection, timeout and signer of this bus.
+    pub fn command_bus(&self) -> RemoteCommandBus<K> {
+        RemoteCommandBus {
+            client: self.client.clone(),
+        }
+    }
+
+    /// Dispatches a query to the remote server and returns its response.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `DispatchError` reported by the remote bus,
+    /// [Timeout](DispatchError::Timeout) if no reply arrives in time, or
+    /// [Transport](DispatchError::Transport) if the server cannot be reached or its reply cannot
+    /// be decoded.
+    pub async fn dispatch<Q>(&self, query: Q) -> Result<Q::Response, DispatchError<Q::Error>>
+    where
+        Q: Query + MessageName + Serialize,
+        Q::Response: DeserializeOwned,
+        Q::Error: DeserializeOwned,
+    {
+        self.client.send(FrameKind::Query, query).await
+    }
+}
+
+/// Clone implementation for `RemoteQueryBus`
+impl<K: Codec> Clone for RemoteQueryBus<K> {
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+        }
+    }
+}
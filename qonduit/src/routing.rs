@@ -0,0 +1,404 @@
+//! The `routing` module decides, per command type, which node handles a command.
+//!
+//! This module is available with the `remote` feature. A [RoutingTable] maps the
+//! [stable name](crate::message::MessageName) of a command type to a [Route]: the local handler,
+//! a named remote node, or one of several nodes chosen by consistent hashing of a key of the
+//! command. A [Router] holds the table and the connections to the named nodes, and both can be
+//! changed while the application runs, for example when a configuration file is reloaded.
+//!
+//! A `CommandBus` consults the router for the command types enabled with
+//! [with_routing](crate::command::CommandBus::with_routing) or
+//! [with_routing_by_key](crate::command::CommandBus::with_routing_by_key). Callers keep using
+//! `dispatch`, so a heavy handler can be moved to a dedicated process by changing the routing
+//! table alone. A forwarded command goes through the layers of the local bus, such as its
+//! policies, validation, rate limits, timeouts and audit, before it is sent in place of calling
+//! the local handler. It is sent with the principal of the dispatch and the budget left before its
+//! deadline, and the bus of the remote node applies its own layers as well.
+//!
+//! Consistent hashing uses rendezvous hashing: each key goes to the node with the highest hash of
+//! the key and the node name. Adding or removing a node only moves the keys of that node, and all
+//! the processes sharing a table route a key to the same node.
+//!
+//! The table can be read from a configuration file, for example in JSON:
+//!
+//! ```json
+//! {
+//!   "reports.generate.v1": { "node": "reports" },
+//!   "orders.place.v1": { "consistent_hash": ["orders-1", "orders-2"] },
+//!   "orders.cancel.v1": "local"
+//! }
+//! ```
+//!
+//! - [RoutingTable]: Maps command types to their route.
+//! - [Route]: Where the commands of a type are handled.
+//! - [Router]: Holds the routing table and the connections to the remote nodes.
+
+use std::any::Any;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::codec::Codec;
+use crate::command::Command;
+use crate::error::DispatchError;
+use crate::message::MessageName;
+use crate::remote::RemoteCommandBus;
+use crate::util::BoxFuture;
+
+/// The `Route` tells where the commands of a type are handled.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Route {
+    /// By the handler registered on the local bus.
+    #[default]
+    Local,
+
+    /// By the remote node of the given name.
+    Node(String),
+
+    /// By one of the remote nodes of the given names, chosen by consistent hashing of the key of
+    /// the command.
+    ///
+    /// Commands of a type routed without a key all go to the same node.
+    ConsistentHash(Vec<String>),
+}
+
+/// The `RoutingTable` maps the stable names of command types to their [Route].
+///
+/// Command types without a route are handled locally.
+///
+/// # Example
+///
+/// ```
+/// use qonduit::routing::{Route, RoutingTable};
+///
+/// let table = RoutingTable::new()
+///     .with_route("reports.generate.v1", Route::Node("reports".to_string()))
+///     .with_route(
+///         "orders.place.v1",
+///         Route::ConsistentHash(vec!["orders-1".to_string(), "orders-2".to_string()]),
+///     );
+///
+/// assert_eq!(table.route("reports.generate.v1"), &Route::Node("reports".to_string()));
+/// assert_eq!(table.route("orders.cancel.v1"), &Route::Local);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct RoutingTable {
+    #[doc(hidden)]
+    routes: HashMap<String, Route>,
+}
+
+/// Implementation of the `RoutingTable`.
+impl RoutingTable {
+    /// Creates a table handling every command type locally.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Routes the commands of the type named `name` to `route`.
+    pub fn with_route(mut self, name: impl Into<String>, route: Route) -> Self {
+        self.routes.insert(name.into(), route);
+        self
+    }
+
+    /// Returns the route of the command type named `name`.
+    pub fn route(&self, name: &str) -> &Route {
+        static LOCAL: Route = Route::Local;
+        self.routes.get(name).unwrap_or(&LOCAL)
+    }
+}
+
+/// The node a command is routed to.
+enum Target<K: Codec> {
+    Local,
+    Remote(RemoteCommandBus<K>),
+    Unreachable(String),
+}
+
+/// The `Router` holds a [RoutingTable] and the connections to the remote nodes it names.
+///
+/// Clones of the router share the table and the nodes, so a clone kept by the application can
+/// [reload](Router::reload) the table or change the nodes of the buses using the router.
+///
+/// # Example
+///
+/// ```
+/// # #[derive(Debug, serde::Serialize, serde::Deserialize)]
+/// # struct GenerateReportCommand { month: u32 }
+/// # impl qonduit::command::Command for GenerateReportCommand {
+/// #   type Response = String;
+/// #   type Error = String;
+/// # }
+/// # impl qonduit::message::MessageName for GenerateReportCommand {
+/// #   const NAME: &'static str = "reports.generate.v1";
+/// # }
+/// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// # rt.block_on(async {
+/// use qonduit::codec::JsonCodec;
+/// use qonduit::command::CommandBus;
+/// use qonduit::registry::CommandHandlerRegistry;
+/// use qonduit::remote::{MemoryTransport, RemoteCommandBus};
+/// use qonduit::routing::{Route, Router, RoutingTable};
+///
+/// # let (transport, _server) = MemoryTransport::pair();
+/// let router = Router::new(RoutingTable::new())
+///     .with_node("reports", RemoteCommandBus::new(transport, JsonCodec));
+/// let command_bus = CommandBus::new(CommandHandlerRegistry::new())
+///     .with_routing::<GenerateReportCommand, _>(&router);
+///
+/// // From now on, reports are generated by the `reports` node
+/// router.reload(
+///     RoutingTable::new().with_route("reports.generate.v1", Route::Node("reports".to_string())),
+/// );
+/// # drop(command_bus);
+/// # });
+/// ```
+pub struct Router<K: Codec> {
+    #[doc(hidden)]
+    table: Arc<RwLock<RoutingTable>>,
+    #[doc(hidden)]
+    nodes: Arc<RwLock<HashMap<String, RemoteCommandBus<K>>>>,
+}
+
+/// Implementation of the `Router`.
+impl<K: Codec> Router<K> {
+    /// Creates a router following `table`, without any remote node.
+    pub fn new(table: RoutingTable) -> Self {
+        Self {
+            table: Arc::new(RwLock::new(table)),
+            nodes: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Adds the remote node `name`, reached through `bus`.
+    pub fn with_node(self, name: impl Into<String>, bus: RemoteCommandBus<K>) -> Self {
+        self.add_node(name, bus);
+        self
+    }
+
+    /// Adds the remote node `name`, reached through `bus`, replacing any node of the same name.
+    pub fn add_node(&self, name: impl Into<String>, bus: RemoteCommandBus<K>) {
+        self.nodes.write().unwrap().insert(name.into(), bus);
+    }
+
+    /// Removes the remote node `name`.
+    ///
+    /// Commands still routed to it fail with a [Transport](DispatchError::Transport) error.
+    pub fn remove_node(&self, name: &str) {
+        self.nodes.write().unwrap().remove(name);
+    }
+
+    /// Replaces the routing table.
+    ///
+    /// Dispatches already forwarded complete on their node, while the next ones follow the new
+    /// table.
+    pub fn reload(&self, table: RoutingTable) {
+        *self.table.write().unwrap() = table;
+    }
+
+    /// Returns a copy of the current routing table.
+    pub fn table(&self) -> RoutingTable {
+        self.table.read().unwrap().clone()
+    }
+
+    /// Returns the node handling the commands of type `name` with the given key hash.
+    fn resolve(&self, name: &str, key: Option<u64>) -> Target<K> {
+        let table = self.table.read().unwrap();
+        let node = match table.route(name) {
+            Route::Local => return Target::Local,
+            Route::Node(node) => node,
+            Route::ConsistentHash(nodes) => {
+                let key = key.unwrap_or_default();
+                let chosen = nodes.iter().max_by_key(|node| {
+                    let mut hasher = Fnv1a::default();
+                    key.hash(&mut hasher);
+                    node.hash(&mut hasher);
+                    hasher.finish()
+                });
+                match chosen {
+                    Some(node) => node,
+                    None => return Target::Unreachable(format!("no remote node routes {name:?}")),
+                }
+            }
+        };
+        match self.nodes.read().unwrap().get(node) {
+            Some(bus) => Target::Remote(bus.clone()),
+            None => Target::Unreachable(format!("no remote node is named {node:?}")),
+        }
+    }
+}
+
+/// Clone implementation for `Router`
+impl<K: Codec> Clone for Router<K> {
+    fn clone(&self) -> Self {
+        Self {
+            table: self.table.clone(),
+            nodes: self.nodes.clone(),
+        }
+    }
+}
+
+/// Debug implementation for `Router`
+impl<K: Codec> Debug for Router<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        let nodes = self.nodes.read().unwrap();
+        f.debug_struct("Router")
+            .field("table", &*self.table.read().unwrap())
+            .field("nodes", &nodes.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// The 64-bit FNV-1a hash, which unlike the standard hasher is stable across Rust releases, so
+/// that processes built with different toolchains agree on the node of a key.
+struct Fnv1a(u64);
+
+/// Default implementation for `Fnv1a`
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+/// Hasher implementation for `Fnv1a`
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+/// The outcome of a forwarded command.
+type Outcome<C> = BoxFuture<Result<<C as Command>::Response, DispatchError<<C as Command>::Error>>>;
+
+/// Hashes the routing key of a command.
+type KeyHash<C> = Box<dyn Fn(&C) -> u64 + Send + Sync>;
+
+/// Forwards a command to its node, or gives it back if it is handled locally.
+type Forward = Arc<
+    dyn Fn(Box<dyn Any + Send>) -> Result<BoxFuture<Box<dyn Any + Send>>, Box<dyn Any + Send>>
+        + Send
+        + Sync,
+>;
+
+/// The routed command types of a bus.
+#[derive(Clone, Default)]
+pub(crate) struct Routes {
+    forwarders: HashMap<TypeId, Forward>,
+}
+
+impl Routes {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Routes the commands of type `C` with `router`.
+    pub(crate) fn set<C, K>(&mut self, router: Router<K>)
+    where
+        C: Command + MessageName + Serialize,
+        C::Response: DeserializeOwned + 'static,
+        C::Error: DeserializeOwned + 'static,
+        K: Codec,
+    {
+        self.insert::<C, K>(router, None);
+    }
+
+    /// Routes the commands of type `C` with `router`, hashing the key extracted by `key`.
+    pub(crate) fn set_keyed<C, K, H>(
+        &mut self,
+        router: Router<K>,
+        key: impl Fn(&C) -> H + Send + Sync + 'static,
+    ) where
+        C: Command + MessageName + Serialize,
+        C::Response: DeserializeOwned + 'static,
+        C::Error: DeserializeOwned + 'static,
+        K: Codec,
+        H: Hash,
+    {
+        let key = move |command: &C| {
+            let mut hasher = Fnv1a::default();
+            key(command).hash(&mut hasher);
+            hasher.finish()
+        };
+        self.insert::<C, K>(router, Some(Box::new(key)));
+    }
+
+    fn insert<C, K>(&mut self, router: Router<K>, key: Option<KeyHash<C>>)
+    where
+        C: Command + MessageName + Serialize,
+        C::Response: DeserializeOwned + 'static,
+        C::Error: DeserializeOwned + 'static,
+        K: Codec,
+    {
+        let forward: Forward = Arc::new(move |command| {
+            let command = command
+                .downcast::<C>()
+                .expect("Cannot downcast command to correct type");
+            let key = key.as_ref().map(|key| key(&command));
+            let outcome: Result<C::Response, DispatchError<C::Error>> =
+                match router.resolve(C::NAME, key) {
+                    Target::Local => return Err(command),
+                    Target::Remote(bus) => {
+                        return Ok(Box::pin(async move {
+                            Box::new(bus.dispatch(*command).await) as Box<dyn Any + Send>
+                        }));
+                    }
+                    Target::Unreachable(reason) => Err(DispatchError::Transport(reason)),
+                };
+            Ok(Box::pin(
+                async move { Box::new(outcome) as Box<dyn Any + Send> },
+            ))
+        });
+        self.forwarders.insert(TypeId::of::<C>(), forward);
+    }
+
+    /// Returns whether commands of type `C` are routed.
+    pub(crate) fn contains<C: Command>(&self) -> bool {
+        self.forwarders.contains_key(&TypeId::of::<C>())
+    }
+
+    /// Forwards `command` to its node and returns the outcome, or gives the command back if it is
+    /// handled locally.
+    pub(crate) fn forward<C: Command>(&self, command: C) -> Result<Outcome<C>, C> {
+        let Some(forward) = self.forwarders.get(&TypeId::of::<C>()) else {
+            return Err(command);
+        };
+        match forward(Box::new(command)) {
+            Ok(outcome) => Ok(Box::pin(async move {
+                *outcome
+                    .await
+                    .downcast::<Result<C::Response, DispatchError<C::Error>>>()
+                    .expect("Cannot downcast outcome to correct type")
+            })),
+            Err(command) => Err(*command
+                .downcast::<C>()
+                .expect("Cannot downcast command to correct type")),
+        }
+    }
+}
+
+/// Debug implementation for `Routes`
+impl Debug for Routes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("Routes")
+            .field("routed", &self.forwarders.len())
+            .finish()
+    }
+}
@@ -0,0 +1,871 @@
+//! The `schedule` module dispatches commands at a given time, after a delay, or on a cron schedule.
+//!
+//! This module is available with the `schedule` feature. A [Scheduler] saves each scheduled
+//! command as JSON with the [Schedule] telling when to run it, and dispatches it by name through
+//! its `CommandBus` once due. Command types must be registered with
+//! [register_json](crate::registry::CommandHandlerRegistry::register_json) on that bus.
+//! Scheduling a command returns a [ScheduleHandle] to cancel it. The principal on whose behalf a
+//! command is scheduled is saved with it, and the command is dispatched on its behalf.
+//!
+//! Schedules are saved to a [ScheduleStore], so that the schedules of a process are resumed when
+//! it starts again. A run the scheduler could not dispatch in time, typically because the process
+//! was stopped, is misfired, and handled as configured by the [MisfirePolicy] of its schedule. A
+//! command that can no longer run, because its schedule cannot be read or its only dispatch
+//! failed, is kept as [failed](ScheduledCommand::failed) until it is cancelled.
+//!
+//! The scheduler reads the time from a [Clock]. [ManualClock] sets the time explicitly, so that
+//! tests advance it and call [tick](Scheduler::tick) instead of waiting.
+//!
+//! - [Scheduler]: Saves the scheduled commands and dispatches them once due.
+//! - [Schedule]: When to dispatch a command.
+//! - [MisfirePolicy]: What to do with the runs that could not be dispatched in time.
+//! - [ScheduledCommand]: A saved command and its next run.
+//! - [ScheduleHandle]: Cancels a scheduled command.
+//! - [ScheduleStore]: Saves the scheduled commands.
+//! - [InMemoryScheduleStore]: Keeps scheduled commands in memory, mainly for tests.
+//! - [FileScheduleStore]: Keeps scheduled commands as JSON files in a directory.
+//! - [Clock]: The source of the current time.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use chrono::DateTime;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::async_trait;
+use crate::authorization;
+use crate::authorization::Principal;
+use crate::command::Command;
+use crate::command::CommandBus;
+use crate::message::MessageName;
+use crate::util;
+use crate::util::blocking;
+use crate::util::unix_millis;
+
+/// How late a run may be dispatched before it is misfired by default.
+pub const DEFAULT_MISFIRE_THRESHOLD: Duration = Duration::from_secs(60);
+
+/// How often [run](Scheduler::run) looks for due commands by default.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The most misfired runs counted for a schedule at once.
+const MAX_MISFIRED_RUNS: u32 = 1000;
+
+/// The `Clock` tells the scheduler the current time.
+pub trait Clock: Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// A clock reading the system time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+/// Clock implementation for `SystemClock`
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock whose time only changes when set or advanced.
+///
+/// Clones share the same time.
+///
+/// # Example
+///
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use qonduit::schedule::{Clock, ManualClock};
+///
+/// let clock = ManualClock::new(UNIX_EPOCH + Duration::from_secs(1_760_000_000));
+/// clock.advance(Duration::from_secs(60));
+/// assert_eq!(clock.now(), UNIX_EPOCH + Duration::from_secs(1_760_000_060));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ManualClock {
+    #[doc(hidden)]
+    now: Arc<Mutex<SystemTime>>,
+}
+
+/// Implementation of the `ManualClock`.
+impl ManualClock {
+    /// Creates a clock set to `now`.
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(now)),
+        }
+    }
+
+    /// Sets the time to `now`.
+    pub fn set(&self, now: SystemTime) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    /// Moves the time forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+/// Clock implementation for `ManualClock`
+impl Clock for ManualClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// The `MisfirePolicy` tells what to do with the runs of a schedule that could not be dispatched
+/// in time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MisfirePolicy {
+    /// The misfired runs are not dispatched.
+    Skip,
+
+    /// The command is dispatched once for all the misfired runs.
+    #[default]
+    FireOnce,
+
+    /// The command is dispatched once for each misfired run, up to 1000 runs.
+    CatchUp,
+}
+
+/// When a [Schedule] runs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Trigger {
+    At(SystemTime),
+    After(Duration),
+    Cron(String),
+}
+
+/// The `Schedule` tells when to dispatch a command.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use qonduit::schedule::{MisfirePolicy, Schedule};
+///
+/// let reminder = Schedule::after(Duration::from_secs(3600));
+/// // At 9:00 UTC every weekday
+/// let report = Schedule::cron("0 0 9 * * Mon-Fri")
+///     .unwrap()
+///     .with_misfire(MisfirePolicy::Skip);
+/// # drop((reminder, report));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    #[doc(hidden)]
+    trigger: Trigger,
+    #[doc(hidden)]
+    misfire: MisfirePolicy,
+}
+
+/// Implementation of the `Schedule`.
+impl Schedule {
+    /// Runs once at `at`.
+    pub fn at(at: SystemTime) -> Self {
+        Self::new(Trigger::At(at))
+    }
+
+    /// Runs once, `delay` after the command is scheduled.
+    pub fn after(delay: Duration) -> Self {
+        Self::new(Trigger::After(delay))
+    }
+
+    /// Runs at the times matched by the cron `expression`, in UTC.
+    ///
+    /// The expression has six or seven fields: seconds, minutes, hours, day of month, month, day
+    /// of week and an optional year.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `expression` is not a valid cron expression.
+    pub fn cron(expression: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        cron::Schedule::from_str(expression)?;
+        Ok(Self::new(Trigger::Cron(expression.to_string())))
+    }
+
+    /// Handles the misfired runs with `misfire`, instead of [MisfirePolicy::FireOnce].
+    pub fn with_misfire(mut self, misfire: MisfirePolicy) -> Self {
+        self.misfire = misfire;
+        self
+    }
+
+    fn new(trigger: Trigger) -> Self {
+        Self {
+            trigger,
+            misfire: MisfirePolicy::default(),
+        }
+    }
+}
+
+/// The `ScheduledCommand` is a command saved by the scheduler, and its next run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledCommand {
+    /// The unique id of the scheduled command. Ids sort by scheduling time.
+    pub id: String,
+
+    /// The stable name of the command type.
+    pub name: String,
+
+    /// The command serialized as JSON.
+    pub payload: Value,
+
+    /// The cron expression of the schedule, or `None` if the command runs once.
+    pub cron: Option<String>,
+
+    /// What to do with the runs that could not be dispatched in time.
+    pub misfire: MisfirePolicy,
+
+    /// When the command runs next, in milliseconds since the Unix epoch.
+    pub next_run: u64,
+
+    /// Why the last dispatch failed, if it did.
+    pub last_error: Option<String>,
+
+    /// Whether the command stopped running, because it could not be read or its schedule could
+    /// not be evaluated, for instance a cron expression saved by another version, or because
+    /// the dispatch of a command running once failed. A failed command keeps the error in
+    /// [last_error](ScheduledCommand::last_error) until it is cancelled.
+    #[serde(default)]
+    pub failed: bool,
+
+    /// The principal on whose behalf the command was scheduled, if any, restored when it is
+    /// dispatched.
+    #[serde(default)]
+    pub principal: Option<Principal>,
+}
+
+/// Implementation of the `ScheduledCommand`.
+impl ScheduledCommand {
+    /// Returns the failed command standing for the command `id` that could not be read.
+    fn unreadable(id: &str, error: &dyn Error) -> Self {
+        Self {
+            id: id.to_string(),
+            name: String::new(),
+            payload: Value::Null,
+            cron: None,
+            misfire: MisfirePolicy::default(),
+            next_run: 0,
+            last_error: Some(format!("the scheduled command cannot be read: {error}")),
+            failed: true,
+            principal: None,
+        }
+    }
+
+    /// Returns the number of dispatches due at `now`, and the next run after them, or `None` if
+    /// the command does not run again.
+    fn due(
+        &self,
+        now: u64,
+        threshold: u64,
+    ) -> Result<(u32, Option<u64>), Box<dyn Error + Send + Sync>> {
+        let cron = match &self.cron {
+            Some(expression) => Some(cron::Schedule::from_str(expression)?),
+            None => None,
+        };
+        let after = |run: u64| {
+            let time = DateTime::from_timestamp_millis(run as i64)?;
+            let next = cron.as_ref()?.after(&time).next()?;
+            Some(next.timestamp_millis() as u64)
+        };
+        let (mut on_time, mut misfired) = (0, 0);
+        let mut next = Some(self.next_run);
+        while let Some(run) = next.filter(|run| *run <= now) {
+            if now - run > threshold {
+                misfired += 1;
+                next = match misfired {
+                    // Skips the remaining misfired runs
+                    MAX_MISFIRED_RUNS => after(now - threshold),
+                    _ => after(run),
+                };
+            } else {
+                on_time += 1;
+                next = after(run);
+            }
+        }
+        let misfired = match self.misfire {
+            MisfirePolicy::Skip => 0,
+            MisfirePolicy::FireOnce => misfired.min(1),
+            MisfirePolicy::CatchUp => misfired,
+        };
+        Ok((misfired + on_time, next))
+    }
+}
+
+/// The `ScheduleStore` saves the scheduled commands.
+///
+/// A scheduled command is saved when it is scheduled, updated after each run, and removed once
+/// it does not run again or is cancelled.
+#[async_trait]
+pub trait ScheduleStore: Send + Sync {
+    /// Saves `scheduled`, replacing any scheduled command of the same id.
+    async fn save(&self, scheduled: &ScheduledCommand) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Replaces the scheduled command of the same id as `scheduled`, if it is still saved, and
+    /// returns whether it was.
+    ///
+    /// This keeps a command cancelled while its runs were dispatched from being saved again. The
+    /// default implementation loads the scheduled commands before saving, so stores should
+    /// override it to check and save at once.
+    async fn update(
+        &self,
+        scheduled: &ScheduledCommand,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let saved = self
+            .load()
+            .await?
+            .iter()
+            .any(|saved| saved.id == scheduled.id);
+        if saved {
+            self.save(scheduled).await?;
+        }
+        Ok(saved)
+    }
+
+    /// Removes the scheduled command `id`, and returns whether it was found.
+    async fn remove(&self, id: &str) -> Result<bool, Box<dyn Error + Send + Sync>>;
+
+    /// Returns the scheduled commands, ordered by id.
+    ///
+    /// A command that cannot be read should be returned as [failed](ScheduledCommand::failed),
+    /// so that it does not keep the other commands from running.
+    async fn load(&self) -> Result<Vec<ScheduledCommand>, Box<dyn Error + Send + Sync>>;
+}
+
+/// A schedule store keeping scheduled commands in memory.
+///
+/// Clones share the same scheduled commands.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryScheduleStore {
+    #[doc(hidden)]
+    scheduled: Arc<Mutex<BTreeMap<String, ScheduledCommand>>>,
+}
+
+/// Implementation of the `InMemoryScheduleStore`.
+impl InMemoryScheduleStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// ScheduleStore implementation for `InMemoryScheduleStore`
+#[async_trait]
+impl ScheduleStore for InMemoryScheduleStore {
+    async fn save(&self, scheduled: &ScheduledCommand) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut commands = self.scheduled.lock().unwrap();
+        commands.insert(scheduled.id.clone(), scheduled.clone());
+        Ok(())
+    }
+
+    async fn update(
+        &self,
+        scheduled: &ScheduledCommand,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let mut commands = self.scheduled.lock().unwrap();
+        match commands.get_mut(&scheduled.id) {
+            Some(saved) => {
+                *saved = scheduled.clone();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn remove(&self, id: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        Ok(self.scheduled.lock().unwrap().remove(id).is_some())
+    }
+
+    async fn load(&self) -> Result<Vec<ScheduledCommand>, Box<dyn Error + Send + Sync>> {
+        Ok(self.scheduled.lock().unwrap().values().cloned().collect())
+    }
+}
+
+/// A schedule store keeping each scheduled command as a JSON file in a directory.
+///
+/// Files are written to a temporary name then renamed, so that a process stopping while saving
+/// does not leave a truncated command behind. Updates and cancellations are only exclusive
+/// within a process, so the directory must not be shared by the schedulers of several processes.
+/// A file that cannot be read is loaded as a [failed](ScheduledCommand::failed) command, removed
+/// when the command is cancelled. File operations run on the blocking threads of the runtime.
+///
+/// # Example
+///
+/// ```no_run
+/// use qonduit::command::CommandBus;
+/// use qonduit::registry::CommandHandlerRegistry;
+/// use qonduit::schedule::{FileScheduleStore, Scheduler};
+///
+/// let store = FileScheduleStore::open("/var/lib/orders/schedules").unwrap();
+/// let scheduler = Scheduler::new(CommandBus::new(CommandHandlerRegistry::new())).with_store(store);
+/// # drop(scheduler);
+/// ```
+#[derive(Clone, Debug)]
+pub struct FileScheduleStore {
+    #[doc(hidden)]
+    directory: PathBuf,
+    #[doc(hidden)]
+    lock: Arc<Mutex<()>>,
+}
+
+/// Implementation of the `FileScheduleStore`.
+impl FileScheduleStore {
+    /// Opens the store kept in `directory`, creating the directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created.
+    pub fn open(directory: impl AsRef<Path>) -> io::Result<Self> {
+        let directory = directory.as_ref().to_path_buf();
+        std::fs::create_dir_all(&directory)?;
+        Ok(Self {
+            directory,
+            lock: Arc::new(Mutex::new(())),
+        })
+    }
+
+    fn path(&self, id: &str) -> io::Result<PathBuf> {
+        util::json_file(&self.directory, id)
+    }
+}
+
+/// Writes `bytes` to `path` through a temporary file.
+fn write(path: PathBuf, bytes: Vec<u8>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let temporary = path.with_extension("json.tmp");
+    std::fs::write(&temporary, bytes)?;
+    std::fs::rename(temporary, path)?;
+    Ok(())
+}
+
+/// ScheduleStore implementation for `FileScheduleStore`
+#[async_trait]
+impl ScheduleStore for FileScheduleStore {
+    async fn save(&self, scheduled: &ScheduledCommand) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let path = self.path(&scheduled.id)?;
+        let bytes = serde_json::to_vec(scheduled)?;
+        blocking(move || write(path, bytes)).await
+    }
+
+    async fn update(
+        &self,
+        scheduled: &ScheduledCommand,
+    ) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let lock = self.lock.clone();
+        let path = self.path(&scheduled.id)?;
+        let bytes = serde_json::to_vec(scheduled)?;
+        blocking(move || {
+            let _lock = lock.lock().unwrap();
+            if !path.try_exists()? {
+                return Ok(false);
+            }
+            write(path, bytes)?;
+            Ok(true)
+        })
+        .await
+    }
+
+    async fn remove(&self, id: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let lock = self.lock.clone();
+        let path = self.path(id)?;
+        blocking(move || {
+            let _lock = lock.lock().unwrap();
+            match std::fs::remove_file(path) {
+                Ok(()) => Ok(true),
+                Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(false),
+                Err(error) => Err(error.into()),
+            }
+        })
+        .await
+    }
+
+    async fn load(&self) -> Result<Vec<ScheduledCommand>, Box<dyn Error + Send + Sync>> {
+        let directory = self.directory.clone();
+        blocking(move || {
+            let mut commands = Vec::new();
+            for entry in std::fs::read_dir(directory)? {
+                let path = entry?.path();
+                let Some(id) = path
+                    .file_stem()
+                    .filter(|_| {
+                        path.extension()
+                            .is_some_and(|extension| extension == "json")
+                    })
+                    .and_then(|stem| stem.to_str())
+                else {
+                    continue;
+                };
+                let scheduled = std::fs::read(&path)
+                    .map_err(Box::<dyn Error + Send + Sync>::from)
+                    .and_then(|bytes| Ok(serde_json::from_slice::<ScheduledCommand>(&bytes)?));
+                commands.push(match scheduled {
+                    Ok(scheduled) => scheduled,
+                    Err(error) => ScheduledCommand::unreadable(id, error.as_ref()),
+                });
+            }
+            commands.sort_by(|a, b| a.id.cmp(&b.id));
+            Ok(commands)
+        })
+        .await
+    }
+}
+
+/// The `Scheduler` dispatches scheduled commands through a `CommandBus` once due.
+///
+/// Clones share the same clock and store. [run](Scheduler::run) dispatches the due commands
+/// until the returned future is dropped, and [tick](Scheduler::tick) dispatches those due at the
+/// current time of the clock. Dispatches are at least once: a command whose run was dispatched
+/// but not saved, because the process stopped, is dispatched again when it starts.
+///
+/// # Example
+///
+/// ```
+/// # #[derive(Debug, serde::Serialize, serde::Deserialize)]
+/// # struct SendReminderCommand { customer_id: u64 }
+/// # impl qonduit::command::Command for SendReminderCommand {
+/// #   type Response = ();
+/// #   type Error = String;
+/// # }
+/// # impl qonduit::message::MessageName for SendReminderCommand {
+/// #   const NAME: &'static str = "reminders.send.v1";
+/// # }
+/// # struct SendReminderCommandHandler;
+/// # #[qonduit::async_trait]
+/// # impl qonduit::command::CommandHandler<SendReminderCommand> for SendReminderCommandHandler {
+/// #   async fn handle(&self, _command: SendReminderCommand) -> Result<(), String> { Ok(()) }
+/// # }
+/// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// # rt.block_on(async {
+/// use std::time::{Duration, UNIX_EPOCH};
+/// use qonduit::command::CommandBus;
+/// use qonduit::registry::CommandHandlerRegistry;
+/// use qonduit::schedule::{ManualClock, Schedule, Scheduler};
+///
+/// let mut registry = CommandHandlerRegistry::new();
+/// registry.register_json::<SendReminderCommand>(SendReminderCommandHandler);
+/// let clock = ManualClock::new(UNIX_EPOCH + Duration::from_secs(1_760_000_000));
+/// let scheduler = Scheduler::new(CommandBus::new(registry)).with_clock(clock.clone());
+///
+/// let command = SendReminderCommand { customer_id: 7 };
+/// let handle = scheduler
+///     .schedule(command, Schedule::after(Duration::from_secs(3600)))
+///     .await
+///     .unwrap();
+///
+/// clock.advance(Duration::from_secs(3600));
+/// assert_eq!(scheduler.tick().await.unwrap(), 1);
+/// assert!(!handle.cancel().await.unwrap());
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct Scheduler {
+    #[doc(hidden)]
+    command_bus: CommandBus,
+    #[doc(hidden)]
+    clock: Arc<dyn Clock>,
+    #[doc(hidden)]
+    store: Arc<dyn ScheduleStore>,
+    #[doc(hidden)]
+    misfire_threshold: Duration,
+    #[doc(hidden)]
+    poll_interval: Duration,
+    #[doc(hidden)]
+    next_id: Arc<AtomicU64>,
+    #[doc(hidden)]
+    on_tick_error: Option<TickErrorCallback>,
+}
+
+type TickErrorCallback = Arc<dyn Fn(&(dyn Error + Send + Sync)) + Send + Sync>;
+
+/// Implementation of the `Scheduler`.
+impl Scheduler {
+    /// Creates a scheduler dispatching through `command_bus`, reading the system time and keeping
+    /// its scheduled commands in memory.
+    pub fn new(command_bus: CommandBus) -> Self {
+        Self {
+            command_bus,
+            clock: Arc::new(SystemClock),
+            store: Arc::new(InMemoryScheduleStore::new()),
+            misfire_threshold: DEFAULT_MISFIRE_THRESHOLD,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            next_id: Arc::new(AtomicU64::new(0)),
+            on_tick_error: None,
+        }
+    }
+
+    /// Reads the current time from `clock`, instead of the system time.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Saves the scheduled commands to `store`, instead of keeping them in memory.
+    pub fn with_store(mut self, store: impl ScheduleStore + 'static) -> Self {
+        self.store = Arc::new(store);
+        self
+    }
+
+    /// Misfires the runs not dispatched within `threshold` of their time, instead of
+    /// [DEFAULT_MISFIRE_THRESHOLD].
+    pub fn with_misfire_threshold(mut self, threshold: Duration) -> Self {
+        self.misfire_threshold = threshold;
+        self
+    }
+
+    /// Looks for due commands every `interval` in [run](Scheduler::run), instead of
+    /// [DEFAULT_POLL_INTERVAL].
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Registers a callback invoked with the errors of the ticks of [run](Scheduler::run),
+    /// replacing any previous callback.
+    ///
+    /// The errors are also logged with the `tracing` feature. The callback runs on the task of
+    /// the scheduler, so it should return quickly.
+    pub fn on_tick_error(
+        mut self,
+        callback: impl Fn(&(dyn Error + Send + Sync)) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_tick_error = Some(Arc::new(callback));
+        self
+    }
+
+    /// Saves `command` to be dispatched as configured by `schedule`, and returns the handle to
+    /// cancel it.
+    ///
+    /// The current [principal](crate::authorization::current_principal), if any, is saved with
+    /// the command, which is dispatched on its behalf.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command cannot be serialized or saved, or if its schedule never
+    /// runs.
+    pub async fn schedule<C>(
+        &self,
+        command: C,
+        schedule: Schedule,
+    ) -> Result<ScheduleHandle, Box<dyn Error + Send + Sync>>
+    where
+        C: Command + MessageName + Serialize,
+    {
+        let now = self.clock.now();
+        let (cron, next_run) = match schedule.trigger {
+            Trigger::At(at) => (None, unix_millis(at)),
+            Trigger::After(delay) => (None, unix_millis(now + delay)),
+            Trigger::Cron(expression) => {
+                let time = DateTime::from_timestamp_millis(unix_millis(now) as i64)
+                    .ok_or("the current time is out of range")?;
+                let next = cron::Schedule::from_str(&expression)?
+                    .after(&time)
+                    .next()
+                    .ok_or("the cron expression has no future run")?;
+                (Some(expression), next.timestamp_millis() as u64)
+            }
+        };
+        let sequence = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let scheduled = ScheduledCommand {
+            // Sorts by creation, and stays unique across processes and restarts
+            id: format!(
+                "{:013}-{}-{sequence:06}",
+                unix_millis(now),
+                std::process::id()
+            ),
+            name: C::NAME.to_string(),
+            payload: serde_json::to_value(&command)?,
+            cron,
+            misfire: schedule.misfire,
+            next_run,
+            last_error: None,
+            failed: false,
+            principal: authorization::current_principal().map(|principal| (*principal).clone()),
+        };
+        self.store.save(&scheduled).await?;
+        Ok(self.handle(&scheduled.id))
+    }
+
+    /// Saves `command` to be dispatched on behalf of `principal` as configured by `schedule`, and
+    /// returns the handle to cancel it.
+    ///
+    /// # Errors
+    ///
+    /// The same as [schedule](Scheduler::schedule).
+    pub async fn schedule_as<C>(
+        &self,
+        principal: Principal,
+        command: C,
+        schedule: Schedule,
+    ) -> Result<ScheduleHandle, Box<dyn Error + Send + Sync>>
+    where
+        C: Command + MessageName + Serialize,
+    {
+        authorization::scope(principal, self.schedule(command, schedule)).await
+    }
+
+    /// Returns the handle of the scheduled command `id`, for instance one saved by a previous
+    /// process.
+    pub fn handle(&self, id: &str) -> ScheduleHandle {
+        ScheduleHandle {
+            id: id.to_string(),
+            scheduler: self.clone(),
+        }
+    }
+
+    /// Cancels the scheduled command `id`, and returns whether it was still scheduled.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the store if the command cannot be removed.
+    pub async fn cancel(&self, id: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        self.store.remove(id).await
+    }
+
+    /// Returns the scheduled commands, ordered by id.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the store if the commands cannot be loaded.
+    pub async fn scheduled(&self) -> Result<Vec<ScheduledCommand>, Box<dyn Error + Send + Sync>> {
+        self.store.load().await
+    }
+
+    /// Dispatches the commands due at the current time of the clock, and returns the number of
+    /// dispatches.
+    ///
+    /// The dispatches are awaited in turn. A failed dispatch is recorded as the
+    /// [last_error](ScheduledCommand::last_error) of its command, and does not stop the others. A
+    /// command whose schedule cannot be evaluated, or running once and whose dispatch failed, is
+    /// marked as [failed](ScheduledCommand::failed) and no longer run.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the store if the commands cannot be loaded or saved.
+    pub async fn tick(&self) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let now = unix_millis(self.clock.now());
+        let threshold = self.misfire_threshold.as_millis() as u64;
+        let mut dispatched = 0;
+        for mut scheduled in self.store.load().await? {
+            if scheduled.failed || scheduled.next_run > now {
+                continue;
+            }
+            let (dispatches, next_run) = match scheduled.due(now, threshold) {
+                Ok(due) => due,
+                Err(error) => {
+                    // A corrupted expression is kept with its error, and the command no longer run
+                    scheduled.last_error = Some(error.to_string());
+                    scheduled.failed = true;
+                    self.store.update(&scheduled).await?;
+                    continue;
+                }
+            };
+            for _ in 0..dispatches {
+                let payload = scheduled.payload.clone();
+                let dispatch = match scheduled.principal.clone() {
+                    Some(principal) => {
+                        self.command_bus
+                            .dispatch_json_as(principal, &scheduled.name, payload)
+                            .await
+                    }
+                    None => {
+                        self.command_bus
+                            .dispatch_json(&scheduled.name, payload)
+                            .await
+                    }
+                };
+                scheduled.last_error = dispatch.err().map(|error| error.to_string());
+                dispatched += 1;
+            }
+            match next_run {
+                Some(next_run) => {
+                    // A command cancelled during its dispatches stays cancelled
+                    scheduled.next_run = next_run;
+                    self.store.update(&scheduled).await?;
+                }
+                None if scheduled.last_error.is_some() => {
+                    // A failed run is kept with its error, instead of being lost
+                    scheduled.failed = true;
+                    self.store.update(&scheduled).await?;
+                }
+                None => {
+                    self.store.remove(&scheduled.id).await?;
+                }
+            }
+        }
+        Ok(dispatched)
+    }
+
+    /// Dispatches the due commands until the returned future is dropped.
+    ///
+    /// The commands saved by a previous process are resumed, with their misfired runs handled as
+    /// configured by their schedule. Errors of the store are passed to the callback of
+    /// [on_tick_error](Scheduler::on_tick_error), and retried on the next poll.
+    pub async fn run(self) {
+        loop {
+            if let Err(error) = self.tick().await {
+                #[cfg(feature = "tracing")]
+                tracing::error!(error = %error, "failed to dispatch the scheduled commands");
+                if let Some(on_tick_error) = &self.on_tick_error {
+                    on_tick_error(error.as_ref());
+                }
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+}
+
+/// Debug implementation for `Scheduler`
+impl Debug for Scheduler {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("Scheduler")
+            .field("misfire_threshold", &self.misfire_threshold)
+            .field("poll_interval", &self.poll_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The `ScheduleHandle` cancels a scheduled command.
+///
+/// It is returned by [schedule](Scheduler::schedule). Dropping the handle does not cancel the
+/// command.
+#[derive(Clone, Debug)]
+pub struct ScheduleHandle {
+    #[doc(hidden)]
+    id: String,
+    #[doc(hidden)]
+    scheduler: Scheduler,
+}
+
+/// Implementation of the `ScheduleHandle`.
+impl ScheduleHandle {
+    /// Returns the id of the scheduled command.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Cancels the scheduled command, and returns whether it was still scheduled.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the store if the command cannot be removed.
+    pub async fn cancel(&self) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        self.scheduler.cancel(&self.id).await
+    }
+}
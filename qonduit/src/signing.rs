@@ -0,0 +1,640 @@
+//! The `signing` module authenticates the messages exchanged between processes.
+//!
+//! This module is available with the `signing` feature. A [Signer] wraps an encoded message into a
+//! [SignedEnvelope], which carries the id of the signing key, the time of signing and a random
+//! nonce along with the message, and a signature covering all of them. A [Verifier] checks an
+//! envelope against the trusted keys of its [KeyRing], and rejects envelopes that were tampered
+//! with, signed by an unknown key, older than its maximum age, or already seen.
+//!
+//! Two algorithms are supported: HMAC-SHA256, where both ends share a secret, and Ed25519, where
+//! the receiver only knows the public key of the sender. The algorithm is part of the trusted key,
+//! never read from the envelope.
+//!
+//! The remote buses sign the commands and queries they send with
+//! [with_signer](crate::remote::RemoteCommandBus::with_signer), and the
+//! [RemoteServer](crate::remote::RemoteServer) verifies them with
+//! [with_verifier](crate::remote::RemoteServer::with_verifier) before dispatching them, answering
+//! rejected messages with an [Unauthenticated](crate::error::DispatchError::Unauthenticated)
+//! error. The [EventBridge](crate::bridge::EventBridge) signs and verifies events the same way.
+//!
+//! Keys are rotated without downtime: the new key is added to the key rings of the receivers,
+//! then the signers [rotate](Signer::rotate) to it, and the old key is removed from the key rings
+//! once the messages it signed have expired.
+//!
+//! - [Signer]: Signs messages with the current signing key.
+//! - [Verifier]: Verifies signed messages and rejects replays.
+//! - [KeyRing]: The keys trusted by a verifier.
+//! - [SigningKey]: A key signing messages.
+//! - [VerifyingKey]: A key verifying the messages signed by a signing key.
+//! - [SignedEnvelope]: A message with its signature.
+//! - [VerificationError]: The reason an envelope is rejected.
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::RwLock;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use ed25519_dalek::Signature;
+use ed25519_dalek::Signer as Ed25519Signer;
+use hmac::Hmac;
+use hmac::Mac;
+use sha2::Sha256;
+
+use crate::util::unix_millis;
+
+/// The default maximum age of a signed message, after which it is rejected as expired.
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(300);
+
+/// The default tolerance for a signing time ahead of the clock of the verifier.
+pub const DEFAULT_MAX_CLOCK_SKEW: Duration = Duration::from_secs(30);
+
+/// The version of the envelope format, and of the signed bytes.
+const VERSION: u8 = 1;
+
+/// The `SignedEnvelope` carries an encoded message and its signature.
+///
+/// The signature covers every other field, so that none of them can be changed without the
+/// envelope being rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedEnvelope {
+    /// The id of the key that signed the message.
+    pub key_id: String,
+
+    /// When the message was signed, in milliseconds since the Unix epoch.
+    pub timestamp: u64,
+
+    /// A random value making each envelope unique.
+    pub nonce: [u8; 16],
+
+    /// The stable name of the message type.
+    pub name: String,
+
+    /// The message, encoded with the codec of the buses.
+    pub payload: Vec<u8>,
+
+    /// The signature of the other fields.
+    pub signature: Vec<u8>,
+}
+
+/// Implementation of the `SignedEnvelope`.
+impl SignedEnvelope {
+    /// Encodes the envelope into bytes, for example to send it as the payload of a frame.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(64 + self.signature.len() + self.payload.len());
+        bytes.push(VERSION);
+        put_str(&mut bytes, &self.key_id);
+        bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+        bytes.extend_from_slice(&self.nonce);
+        put_str(&mut bytes, &self.name);
+        put_bytes(&mut bytes, &self.signature);
+        bytes.extend_from_slice(&self.payload);
+        bytes
+    }
+
+    /// Decodes an envelope encoded with [to_bytes](SignedEnvelope::to_bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns [VerificationError::Malformed] if the bytes are not an envelope.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, VerificationError> {
+        let mut reader = Reader(bytes);
+        if reader.take(1)? != [VERSION] {
+            return Err(VerificationError::Malformed(
+                "unsupported envelope version".to_string(),
+            ));
+        }
+        let key_id = reader.string()?;
+        let timestamp = u64::from_be_bytes(reader.take(8)?.try_into().unwrap());
+        let nonce = reader.take(16)?.try_into().unwrap();
+        let name = reader.string()?;
+        let signature = reader.bytes()?.to_vec();
+        Ok(Self {
+            key_id,
+            timestamp,
+            nonce,
+            name,
+            payload: reader.0.to_vec(),
+            signature,
+        })
+    }
+
+    /// Returns the bytes covered by the signature.
+    fn signed_bytes(&self) -> Vec<u8> {
+        signed_bytes(
+            &self.key_id,
+            self.timestamp,
+            &self.nonce,
+            &self.name,
+            &self.payload,
+        )
+    }
+}
+
+/// Returns the bytes covered by the signature of an envelope.
+fn signed_bytes(key_id: &str, timestamp: u64, nonce: &[u8], name: &str, payload: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(32 + key_id.len() + name.len() + payload.len());
+    bytes.extend_from_slice(b"qonduit-signed-envelope");
+    bytes.push(VERSION);
+    put_str(&mut bytes, key_id);
+    bytes.extend_from_slice(&timestamp.to_be_bytes());
+    bytes.extend_from_slice(nonce);
+    put_str(&mut bytes, name);
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+/// Appends a string prefixed with its length.
+fn put_str(bytes: &mut Vec<u8>, value: &str) {
+    put_bytes(bytes, value.as_bytes());
+}
+
+/// Appends bytes prefixed with their length, truncated to the largest length that fits.
+fn put_bytes(bytes: &mut Vec<u8>, value: &[u8]) {
+    let len = value.len().min(u16::MAX as usize);
+    bytes.extend_from_slice(&(len as u16).to_be_bytes());
+    bytes.extend_from_slice(&value[..len]);
+}
+
+/// Reads the fields of an envelope.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], VerificationError> {
+        if self.0.len() < len {
+            return Err(VerificationError::Malformed(
+                "truncated envelope".to_string(),
+            ));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], VerificationError> {
+        let len = u16::from_be_bytes(self.take(2)?.try_into().unwrap());
+        self.take(len as usize)
+    }
+
+    fn string(&mut self) -> Result<String, VerificationError> {
+        String::from_utf8(self.bytes()?.to_vec())
+            .map_err(|_| VerificationError::Malformed("invalid string".to_string()))
+    }
+}
+
+/// The secret part of a signing key.
+#[derive(Clone)]
+enum Secret {
+    Hmac(Vec<u8>),
+    Ed25519(ed25519_dalek::SigningKey),
+}
+
+/// The `SigningKey` signs messages under a key id.
+///
+/// Its [VerifyingKey] must be in the [KeyRing] of the receivers.
+#[derive(Clone)]
+pub struct SigningKey {
+    #[doc(hidden)]
+    id: String,
+    #[doc(hidden)]
+    secret: Secret,
+}
+
+/// Implementation of the `SigningKey`.
+impl SigningKey {
+    /// Creates an HMAC-SHA256 key from a secret shared with the receivers.
+    pub fn hmac_sha256(id: impl Into<String>, secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            id: id.into(),
+            secret: Secret::Hmac(secret.into()),
+        }
+    }
+
+    /// Creates an Ed25519 key from its 32-byte secret seed.
+    pub fn ed25519(id: impl Into<String>, seed: [u8; 32]) -> Self {
+        Self {
+            id: id.into(),
+            secret: Secret::Ed25519(ed25519_dalek::SigningKey::from_bytes(&seed)),
+        }
+    }
+
+    /// Returns the id of the key.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the key verifying the messages signed by this key.
+    pub fn verifying_key(&self) -> VerifyingKey {
+        let public = match &self.secret {
+            Secret::Hmac(secret) => Public::Hmac(secret.clone()),
+            Secret::Ed25519(key) => Public::Ed25519(key.verifying_key()),
+        };
+        VerifyingKey {
+            id: self.id.clone(),
+            public,
+        }
+    }
+
+    /// Signs `bytes`.
+    fn sign(&self, bytes: &[u8]) -> Vec<u8> {
+        match &self.secret {
+            Secret::Hmac(secret) => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+                    .expect("HMAC accepts keys of any length");
+                mac.update(bytes);
+                mac.finalize().into_bytes().to_vec()
+            }
+            Secret::Ed25519(key) => key.sign(bytes).to_bytes().to_vec(),
+        }
+    }
+}
+
+/// Debug implementation for `SigningKey`
+impl Debug for SigningKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        // The secret is never printed
+        f.debug_struct("SigningKey")
+            .field("id", &self.id)
+            .field("algorithm", &self.verifying_key().algorithm())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The public part of a verifying key.
+#[derive(Clone)]
+enum Public {
+    Hmac(Vec<u8>),
+    Ed25519(ed25519_dalek::VerifyingKey),
+}
+
+/// The `VerifyingKey` verifies the messages signed by the [SigningKey] of the same id.
+#[derive(Clone)]
+pub struct VerifyingKey {
+    #[doc(hidden)]
+    id: String,
+    #[doc(hidden)]
+    public: Public,
+}
+
+/// Implementation of the `VerifyingKey`.
+impl VerifyingKey {
+    /// Creates an HMAC-SHA256 key from a secret shared with the senders.
+    pub fn hmac_sha256(id: impl Into<String>, secret: impl Into<Vec<u8>>) -> Self {
+        Self {
+            id: id.into(),
+            public: Public::Hmac(secret.into()),
+        }
+    }
+
+    /// Creates an Ed25519 key from the 32-byte public key of the sender.
+    ///
+    /// # Errors
+    ///
+    /// Returns [VerificationError::Malformed] if the bytes are not a valid public key.
+    pub fn ed25519(id: impl Into<String>, public_key: [u8; 32]) -> Result<Self, VerificationError> {
+        let key = ed25519_dalek::VerifyingKey::from_bytes(&public_key)
+            .map_err(|_| VerificationError::Malformed("invalid Ed25519 public key".to_string()))?;
+        Ok(Self {
+            id: id.into(),
+            public: Public::Ed25519(key),
+        })
+    }
+
+    /// Returns the id of the key.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the 32-byte public key of an Ed25519 key, or `None` for an HMAC key.
+    pub fn public_key(&self) -> Option<[u8; 32]> {
+        match &self.public {
+            Public::Hmac(_) => None,
+            Public::Ed25519(key) => Some(key.to_bytes()),
+        }
+    }
+
+    /// Returns the name of the algorithm of the key: `hmac-sha256` or `ed25519`.
+    pub fn algorithm(&self) -> &'static str {
+        match &self.public {
+            Public::Hmac(_) => "hmac-sha256",
+            Public::Ed25519(_) => "ed25519",
+        }
+    }
+
+    /// Returns `true` if `signature` is a valid signature of `bytes`.
+    fn verify(&self, bytes: &[u8], signature: &[u8]) -> bool {
+        match &self.public {
+            Public::Hmac(secret) => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+                    .expect("HMAC accepts keys of any length");
+                mac.update(bytes);
+                // Compares in constant time
+                mac.verify_slice(signature).is_ok()
+            }
+            Public::Ed25519(key) => Signature::from_slice(signature)
+                .is_ok_and(|signature| key.verify_strict(bytes, &signature).is_ok()),
+        }
+    }
+}
+
+/// Debug implementation for `VerifyingKey`
+impl Debug for VerifyingKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        // The secret of an HMAC key is never printed
+        f.debug_struct("VerifyingKey")
+            .field("id", &self.id)
+            .field("algorithm", &self.algorithm())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The `Signer` signs messages with its current [SigningKey].
+///
+/// Clones of the signer share the current key, so that a clone kept by the application can
+/// [rotate](Signer::rotate) the key of the buses using the signer.
+///
+/// # Example
+///
+/// ```
+/// use qonduit::signing::{KeyRing, Signer, SigningKey, Verifier};
+///
+/// let key = SigningKey::hmac_sha256("2026-10", "shared secret");
+/// let verifier = Verifier::new(KeyRing::new().with_key(key.verifying_key()));
+/// let signer = Signer::new(key);
+///
+/// let envelope = signer.sign("orders.place.v1", b"{\"sku\":\"kb-01\"}".to_vec());
+/// assert_eq!(verifier.verify(&envelope), Ok(()));
+///
+/// // The same envelope is only accepted once
+/// assert!(verifier.verify(&envelope).is_err());
+/// ```
+#[derive(Clone)]
+pub struct Signer {
+    #[doc(hidden)]
+    key: Arc<RwLock<SigningKey>>,
+}
+
+/// Implementation of the `Signer`.
+impl Signer {
+    /// Creates a signer signing with `key`.
+    pub fn new(key: SigningKey) -> Self {
+        Self {
+            key: Arc::new(RwLock::new(key)),
+        }
+    }
+
+    /// Signs the messages from now on with `key`.
+    ///
+    /// The receivers must trust the new key before the signer rotates to it.
+    pub fn rotate(&self, key: SigningKey) {
+        *self.key.write().unwrap() = key;
+    }
+
+    /// Returns the id of the current key.
+    pub fn key_id(&self) -> String {
+        self.key.read().unwrap().id.clone()
+    }
+
+    /// Signs the message of type `name`, encoded as `payload`, with the current key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the operating system cannot provide random bytes for the nonce.
+    pub fn sign(&self, name: impl Into<String>, payload: Vec<u8>) -> SignedEnvelope {
+        let key = self.key.read().unwrap().clone();
+        let name = name.into();
+        let timestamp = unix_millis(SystemTime::now());
+        let mut nonce = [0; 16];
+        getrandom::fill(&mut nonce).expect("Cannot generate a random nonce");
+        let signature = key.sign(&signed_bytes(&key.id, timestamp, &nonce, &name, &payload));
+        SignedEnvelope {
+            key_id: key.id,
+            timestamp,
+            nonce,
+            name,
+            payload,
+            signature,
+        }
+    }
+}
+
+/// Debug implementation for `Signer`
+impl Debug for Signer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("Signer")
+            .field("key", &*self.key.read().unwrap())
+            .finish()
+    }
+}
+
+/// The `KeyRing` holds the keys trusted by a [Verifier], by key id.
+///
+/// Clones of the key ring share its keys, so that keys can be added and removed while verifiers
+/// use the ring.
+#[derive(Clone, Default)]
+pub struct KeyRing {
+    #[doc(hidden)]
+    keys: Arc<RwLock<HashMap<String, VerifyingKey>>>,
+}
+
+/// Implementation of the `KeyRing`.
+impl KeyRing {
+    /// Creates an empty key ring.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trusts `key`.
+    pub fn with_key(self, key: VerifyingKey) -> Self {
+        self.add(key);
+        self
+    }
+
+    /// Trusts `key`, replacing any key of the same id.
+    pub fn add(&self, key: VerifyingKey) {
+        self.keys.write().unwrap().insert(key.id.clone(), key);
+    }
+
+    /// Stops trusting the key `id`.
+    pub fn remove(&self, id: &str) {
+        self.keys.write().unwrap().remove(id);
+    }
+
+    /// Returns the ids of the trusted keys, sorted.
+    pub fn key_ids(&self) -> Vec<String> {
+        let mut ids: Vec<_> = self.keys.read().unwrap().keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Returns the key `id`, if trusted.
+    fn get(&self, id: &str) -> Option<VerifyingKey> {
+        self.keys.read().unwrap().get(id).cloned()
+    }
+}
+
+/// Debug implementation for `KeyRing`
+impl Debug for KeyRing {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("KeyRing")
+            .field("keys", &self.key_ids())
+            .finish()
+    }
+}
+
+/// The timestamp, key id and nonce of an accepted envelope, ordered by timestamp.
+type Seen = (u64, String, [u8; 16]);
+
+/// The `Verifier` checks [SignedEnvelope]s against the keys of a [KeyRing].
+///
+/// An envelope is accepted if it is signed by a trusted key, was signed less than its
+/// [maximum age](Verifier::with_max_age) ago, and was not accepted before. The verifier
+/// remembers the nonces of the accepted envelopes until they expire, so that replaying an
+/// envelope is rejected. Clones of the verifier share these nonces.
+#[derive(Clone)]
+pub struct Verifier {
+    #[doc(hidden)]
+    key_ring: KeyRing,
+    #[doc(hidden)]
+    max_age: Duration,
+    #[doc(hidden)]
+    max_clock_skew: Duration,
+    #[doc(hidden)]
+    seen: Arc<Mutex<BTreeSet<Seen>>>,
+}
+
+/// Implementation of the `Verifier`.
+impl Verifier {
+    /// Creates a verifier trusting the keys of `key_ring`.
+    pub fn new(key_ring: KeyRing) -> Self {
+        Self {
+            key_ring,
+            max_age: DEFAULT_MAX_AGE,
+            max_clock_skew: DEFAULT_MAX_CLOCK_SKEW,
+            seen: Arc::new(Mutex::new(BTreeSet::new())),
+        }
+    }
+
+    /// Rejects the envelopes signed more than `max_age` ago, instead of [DEFAULT_MAX_AGE].
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Accepts envelopes signed at most `max_clock_skew` ahead of the local clock, instead of
+    /// [DEFAULT_MAX_CLOCK_SKEW].
+    pub fn with_max_clock_skew(mut self, max_clock_skew: Duration) -> Self {
+        self.max_clock_skew = max_clock_skew;
+        self
+    }
+
+    /// Returns the key ring of the verifier.
+    pub fn key_ring(&self) -> &KeyRing {
+        &self.key_ring
+    }
+
+    /// Verifies `envelope` against the current time.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `VerificationError` describing why the envelope is rejected.
+    pub fn verify(&self, envelope: &SignedEnvelope) -> Result<(), VerificationError> {
+        self.verify_at(envelope, SystemTime::now())
+    }
+
+    /// Verifies `envelope` as if the current time were `now`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `VerificationError` describing why the envelope is rejected.
+    pub fn verify_at(
+        &self,
+        envelope: &SignedEnvelope,
+        now: SystemTime,
+    ) -> Result<(), VerificationError> {
+        let key = self
+            .key_ring
+            .get(&envelope.key_id)
+            .ok_or_else(|| VerificationError::UnknownKey(envelope.key_id.clone()))?;
+        if !key.verify(&envelope.signed_bytes(), &envelope.signature) {
+            return Err(VerificationError::InvalidSignature);
+        }
+
+        let now = unix_millis(now);
+        let oldest = now.saturating_sub(self.max_age.as_millis() as u64);
+        let newest = now.saturating_add(self.max_clock_skew.as_millis() as u64);
+        if envelope.timestamp < oldest {
+            return Err(VerificationError::Expired);
+        }
+        if envelope.timestamp > newest {
+            return Err(VerificationError::NotYetValid);
+        }
+
+        let mut seen = self.seen.lock().unwrap();
+        // Expired envelopes are rejected anyway, so their nonces are no longer needed
+        *seen = seen.split_off(&(oldest, String::new(), [0; 16]));
+        let entry = (envelope.timestamp, envelope.key_id.clone(), envelope.nonce);
+        if !seen.insert(entry) {
+            return Err(VerificationError::Replayed);
+        }
+        Ok(())
+    }
+}
+
+/// Debug implementation for `Verifier`
+impl Debug for Verifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("Verifier")
+            .field("key_ring", &self.key_ring)
+            .field("max_age", &self.max_age)
+            .field("max_clock_skew", &self.max_clock_skew)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The `VerificationError` is returned when a [SignedEnvelope] is rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VerificationError {
+    /// The bytes are not an envelope, or a key is invalid.
+    Malformed(String),
+
+    /// The envelope is signed by a key that is not in the key ring.
+    UnknownKey(String),
+
+    /// The signature does not match the content of the envelope.
+    InvalidSignature,
+
+    /// The envelope was signed more than the maximum age ago.
+    Expired,
+
+    /// The envelope was signed further in the future than the clock skew allows.
+    NotYetValid,
+
+    /// The envelope was already accepted.
+    Replayed,
+}
+
+/// Display implementation for `VerificationError`
+impl Display for VerificationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        match self {
+            VerificationError::Malformed(reason) => write!(f, "malformed envelope: {reason}"),
+            VerificationError::UnknownKey(id) => write!(f, "unknown key {id:?}"),
+            VerificationError::InvalidSignature => write!(f, "invalid signature"),
+            VerificationError::Expired => write!(f, "message expired"),
+            VerificationError::NotYetValid => write!(f, "message signed in the future"),
+            VerificationError::Replayed => write!(f, "message replayed"),
+        }
+    }
+}
+
+/// Error implementation for `VerificationError`
+impl Error for VerificationError {}
@@ -0,0 +1,163 @@
+//! The `subscription` module lets code observe the events published on an `EventBus` without
+//! registering a handler.
+//!
+//! Unlike handlers, which are registered once when the bus is built, subscriptions are ephemeral:
+//! they are created at any time with [EventBus::subscribe](crate::event::EventBus::subscribe) and
+//! stop receiving events when dropped. This suits consumers living as long as a client
+//! connection, such as a live update stream.
+//!
+//! Each subscription buffers up to the subscriber capacity of the bus. A subscriber falling
+//! further behind misses the oldest events, and is told how many it missed with
+//! [SubscriptionError::Lagged] before receiving the next one; slow subscribers never hold up the
+//! dispatch.
+//!
+//! - [Subscription]: Receives the events of a type published on a bus.
+//! - [SubscriptionError]: Returned when a subscription lagged behind or was closed.
+
+use std::any::Any;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::event::Event;
+
+/// The number of events buffered per subscription unless configured otherwise.
+pub(crate) const DEFAULT_CAPACITY: usize = 256;
+
+/// The `SubscriptionError` is returned when a subscription cannot yield the next event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubscriptionError {
+    /// The subscriber fell behind and the given number of events were dropped. Receiving again
+    /// yields the oldest event still buffered.
+    Lagged(u64),
+
+    /// Every clone of the bus was dropped, so no more events will be published.
+    Closed,
+}
+
+/// Display implementation for `SubscriptionError`
+impl Display for SubscriptionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        match self {
+            SubscriptionError::Lagged(skipped) => {
+                write!(f, "subscriber lagged behind and missed {skipped} events")
+            }
+            SubscriptionError::Closed => write!(f, "event bus was dropped"),
+        }
+    }
+}
+
+/// Error implementation for `SubscriptionError`
+impl Error for SubscriptionError {}
+
+/// The `Subscription` receives every event of type `E` published on a bus after it was created.
+///
+/// # Example
+/// ```
+/// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// # rt.block_on(async {
+/// use qonduit::event::{Event, EventBus};
+/// use qonduit::registry::EventHandlerRegistry;
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct OrderShippedEvent { order_id: u64 }
+/// impl Event for OrderShippedEvent {}
+///
+/// let bus = EventBus::new(EventHandlerRegistry::new());
+/// let mut subscription = bus.subscribe::<OrderShippedEvent>();
+///
+/// bus.dispatch(OrderShippedEvent { order_id: 42 }).await.unwrap();
+/// assert_eq!(subscription.recv().await, Ok(OrderShippedEvent { order_id: 42 }));
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct Subscription<E: Event> {
+    #[doc(hidden)]
+    receiver: broadcast::Receiver<E>,
+}
+
+/// Implementation of the `Subscription`.
+impl<E: Event> Subscription<E> {
+    /// Waits for the next event.
+    ///
+    /// # Errors
+    ///
+    /// Returns [Lagged](SubscriptionError::Lagged) once if events were dropped because the
+    /// subscriber fell behind, and [Closed](SubscriptionError::Closed) when the bus is gone.
+    pub async fn recv(&mut self) -> Result<E, SubscriptionError> {
+        self.receiver.recv().await.map_err(|error| match error {
+            RecvError::Lagged(skipped) => SubscriptionError::Lagged(skipped),
+            RecvError::Closed => SubscriptionError::Closed,
+        })
+    }
+}
+
+/// The subscribers of a bus, keyed by event type.
+///
+/// The channels are shared by every clone of the bus, while the capacity only applies to the
+/// event types nobody subscribed to yet.
+#[derive(Clone, Debug)]
+pub(crate) struct Subscribers {
+    capacity: usize,
+    senders: Arc<Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>>,
+}
+
+impl Subscribers {
+    pub(crate) fn new() -> Self {
+        Self {
+            capacity: DEFAULT_CAPACITY,
+            senders: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Sets how many events are buffered per subscription.
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        assert!(capacity > 0, "Subscriber capacity must be at least one");
+        self.capacity = capacity;
+    }
+
+    /// Creates a subscription to the events of type `E`.
+    pub(crate) fn subscribe<E: Event>(&self) -> Subscription<E> {
+        let mut senders = self.senders.lock().expect("Subscribers lock poisoned");
+        let sender = senders
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| Box::new(broadcast::channel::<E>(self.capacity).0));
+        let receiver = sender
+            .downcast_ref::<broadcast::Sender<E>>()
+            .expect("Subscriber channel type mismatch")
+            .subscribe();
+        Subscription { receiver }
+    }
+
+    /// Returns the number of live subscriptions to the events of type `E`.
+    pub(crate) fn count<E: Event>(&self) -> usize {
+        self.sender::<E>()
+            .map_or(0, |sender| sender.receiver_count())
+    }
+
+    /// Publishes `event` to the live subscriptions to its type, if any.
+    pub(crate) fn publish<E: Event>(&self, event: &E) {
+        if let Some(sender) = self.sender::<E>()
+            && sender.receiver_count() > 0
+        {
+            // Subscriptions may be dropped concurrently, in which case nobody misses the event
+            let _ = sender.send(event.clone());
+        }
+    }
+
+    fn sender<E: Event>(&self) -> Option<broadcast::Sender<E>> {
+        let senders = self.senders.lock().expect("Subscribers lock poisoned");
+        senders
+            .get(&TypeId::of::<E>())
+            .and_then(|sender| sender.downcast_ref::<broadcast::Sender<E>>())
+            .cloned()
+    }
+}
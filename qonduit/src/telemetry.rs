@@ -0,0 +1,143 @@
+//! The `telemetry` module propagates [OpenTelemetry](https://docs.rs/opentelemetry) trace context
+//! through dispatches.
+//!
+//! This module is available with the `opentelemetry` feature. Once enabled, every dispatch on the
+//! `CommandBus`, `QueryBus` and `EventBus` captures the current OpenTelemetry context, starts a span
+//! as its child with the globally installed tracer provider, and restores the resulting context
+//! while the handler runs. Spans started by the handler, including those of nested dispatches, are
+//! therefore part of the same trace.
+//!
+//! Every event handler runs inside its own span, which is a child of the event dispatch span and
+//! carries a link to the span that was current when the event was dispatched, typically the span
+//! of the command whose handler published the event.
+//!
+//! Handing an event to a background task with `tokio::spawn` loses the context, because it is
+//! bound to the task that dispatched the event. `EventBus::spawn_dispatch` captures the context
+//! before spawning and restores it in the background task, so events handled asynchronously stay
+//! in the trace of the command that published them.
+//!
+//! The spans have the following attributes:
+//!
+//! - `qonduit.bus`: The kind of bus, one of `command`, `query` or `event`.
+//! - `qonduit.message_type`: The [stable name](crate::message::MessageName) of the dispatched
+//!   message, or its type name.
+//! - `qonduit.handler_type`: The type name of the handler, if the span covers a single handler.
+//! - `qonduit.outcome`: `ok`, or the [kind](crate::error::DispatchError::kind) of failure.
+
+use std::future::Future;
+
+use opentelemetry::Context;
+use opentelemetry::KeyValue;
+use opentelemetry::context::FutureExt;
+use opentelemetry::global;
+use opentelemetry::trace::Link;
+use opentelemetry::trace::SpanContext;
+use opentelemetry::trace::SpanKind;
+use opentelemetry::trace::Status;
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::trace::Tracer;
+
+use crate::error::DispatchError;
+
+/// The name of the tracer reporting the spans of qonduit.
+const TRACER_NAME: &str = "qonduit";
+
+/// Runs `future`, a dispatch of a message named `message_type`, inside a child span of the
+/// current context.
+pub(crate) async fn trace<R, E, F>(
+    bus: &'static str,
+    message_type: &'static str,
+    handler_type: Option<&'static str>,
+    future: F,
+) -> Result<R, DispatchError<E>>
+where
+    F: Future<Output = Result<R, DispatchError<E>>>,
+{
+    let mut attributes = vec![
+        KeyValue::new("qonduit.bus", bus),
+        KeyValue::new("qonduit.message_type", message_type),
+    ];
+    if let Some(handler_type) = handler_type {
+        attributes.push(KeyValue::new("qonduit.handler_type", handler_type));
+    }
+
+    let parent = Context::current();
+    let tracer = global::tracer(TRACER_NAME);
+    let span = tracer
+        .span_builder(format!("{bus} {message_type}"))
+        .with_kind(SpanKind::Internal)
+        .with_attributes(attributes)
+        .start_with_context(&tracer, &parent);
+    let context = parent.with_span(span);
+
+    let result = future.with_context(context.clone()).await;
+    end(
+        &context,
+        match &result {
+            Ok(_) => None,
+            Err(error) => Some(error.kind()),
+        },
+    );
+    result
+}
+
+/// Runs `future`, a single event handler, inside a child span of the current event dispatch,
+/// linked to `origin`, the span that was current when the event was dispatched.
+pub(crate) async fn trace_event_handler<E, F>(
+    handler_type: &'static str,
+    origin: &SpanContext,
+    future: F,
+) -> Result<(), E>
+where
+    F: Future<Output = Result<(), E>>,
+{
+    let parent = Context::current();
+    let tracer = global::tracer(TRACER_NAME);
+    let mut builder = tracer
+        .span_builder(format!("event_handler {handler_type}"))
+        .with_kind(SpanKind::Consumer)
+        .with_attributes([
+            KeyValue::new("qonduit.bus", "event"),
+            KeyValue::new("qonduit.handler_type", handler_type),
+        ]);
+    if origin.is_valid() {
+        builder = builder.with_links(vec![Link::with_context(origin.clone())]);
+    }
+    let context = parent.with_span(builder.start_with_context(&tracer, &parent));
+
+    let result = future.with_context(context.clone()).await;
+    end(
+        &context,
+        match &result {
+            Ok(()) => None,
+            Err(_) => Some("handler_error"),
+        },
+    );
+    result
+}
+
+/// Returns the context of the span that is current when an event is dispatched.
+pub(crate) fn origin() -> SpanContext {
+    Context::current().span().span_context().clone()
+}
+
+/// Runs `future` with the current context, so it can be moved to a background task.
+pub(crate) fn propagate<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    future.with_current_context()
+}
+
+/// Records the outcome of the span of `context` and ends it.
+fn end(context: &Context, failure: Option<&'static str>) {
+    let span = context.span();
+    match failure {
+        None => {
+            span.set_attribute(KeyValue::new("qonduit.outcome", "ok"));
+            span.set_status(Status::Ok);
+        }
+        Some(kind) => {
+            span.set_attribute(KeyValue::new("qonduit.outcome", kind));
+            span.set_status(Status::error(kind));
+        }
+    }
+    span.end();
+}
@@ -0,0 +1,151 @@
+//! The `timeout` module bounds how long a dispatch may take.
+//!
+//! Each bus can be configured with a bus-wide default timeout and per-message-type timeouts. When a
+//! dispatch starts, the bus computes its deadline and runs the handler within it. Dispatches made
+//! while a handler is running (for example, a command handler dispatching a query) inherit the
+//! remaining budget of the outer dispatch, so nested work can never outlive its caller. Events
+//! dispatched in the background with
+//! [spawn_dispatch](crate::event::EventBus::spawn_dispatch) are the exception: they start without
+//! an inherited deadline.
+//!
+//! Deadlines are measured with [tokio::time::Instant], which means they follow a paused or
+//! manually advanced clock in tests.
+//!
+//! - [Timeouts]: Bus-wide and per-type timeout configuration.
+//! - [deadline]: Returns the deadline of the dispatch currently being handled.
+//! - [remaining]: Returns the budget left before the current deadline.
+
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::error::DispatchError;
+
+tokio::task_local! {
+    static DEADLINE: Instant;
+}
+
+/// The `Timeouts` holds the bus-wide default timeout and the per-type overrides of a bus.
+///
+/// A per-type timeout always takes precedence over the default one. A bus without any timeout
+/// configured still honors the deadline inherited from an enclosing dispatch.
+#[derive(Clone, Debug, Default)]
+pub struct Timeouts {
+    #[doc(hidden)]
+    default: Option<Duration>,
+    #[doc(hidden)]
+    per_type: HashMap<TypeId, Duration>,
+}
+
+/// Implementation of the `Timeouts`.
+impl Timeouts {
+    /// Creates a configuration without any timeout.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the timeout applied to message types without a dedicated timeout.
+    pub fn set_default(&mut self, timeout: Duration) {
+        self.default = Some(timeout);
+    }
+
+    /// Sets the timeout applied to messages of type `T`.
+    pub fn set<T: 'static>(&mut self, timeout: Duration) {
+        self.per_type.insert(TypeId::of::<T>(), timeout);
+    }
+
+    /// Returns the timeout applied to messages of type `T`, if any.
+    pub fn get<T: 'static>(&self) -> Option<Duration> {
+        self.per_type
+            .get(&TypeId::of::<T>())
+            .copied()
+            .or(self.default)
+    }
+
+    /// Runs `future` within the deadline for messages of type `T`.
+    ///
+    /// The effective deadline is the earliest of the configured timeout and the deadline
+    /// inherited from the enclosing dispatch, if any.
+    pub(crate) async fn run<T, R, E, F>(&self, future: F) -> Result<R, DispatchError<E>>
+    where
+        T: 'static,
+        F: Future<Output = Result<R, DispatchError<E>>>,
+    {
+        let now = Instant::now();
+        let deadline = match (self.get::<T>().map(|timeout| now + timeout), deadline()) {
+            (Some(own), Some(inherited)) => own.min(inherited),
+            (Some(deadline), None) | (None, Some(deadline)) => deadline,
+            (None, None) => return future.await,
+        };
+
+        let budget = deadline.saturating_duration_since(now);
+        match tokio::time::timeout_at(deadline, DEADLINE.scope(deadline, future)).await {
+            Ok(result) => result,
+            Err(_) => Err(DispatchError::Timeout(budget)),
+        }
+    }
+}
+
+/// Returns the deadline of the dispatch currently being handled.
+///
+/// Returns `None` when called outside of a handler, or when neither the current dispatch nor any
+/// enclosing one has a timeout.
+///
+/// # Example
+///
+/// ```
+/// # let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+/// # rt.block_on(async {
+/// use std::time::Duration;
+/// use qonduit::async_trait;
+/// use qonduit::command::{Command, CommandBus, CommandHandler};
+/// use qonduit::registry::CommandHandlerRegistry;
+/// use qonduit::timeout;
+///
+/// #[derive(Debug)]
+/// struct ImportCatalogCommand;
+///
+/// impl Command for ImportCatalogCommand {
+///     type Response = bool;
+///     type Error = ();
+/// }
+///
+/// struct ImportCatalogCommandHandler;
+///
+/// #[async_trait]
+/// impl CommandHandler<ImportCatalogCommand> for ImportCatalogCommandHandler {
+///     async fn handle(&self, _command: ImportCatalogCommand) -> Result<bool, ()> {
+///         // The handler can inspect its budget before starting expensive work.
+///         Ok(timeout::deadline().is_some())
+///     }
+/// }
+///
+/// let mut registry = CommandHandlerRegistry::new();
+/// registry.register::<ImportCatalogCommand>(ImportCatalogCommandHandler);
+///
+/// let command_bus = CommandBus::new(registry).with_default_timeout(Duration::from_secs(5));
+/// assert_eq!(command_bus.dispatch(ImportCatalogCommand).await, Ok(true));
+/// # });
+/// ```
+pub fn deadline() -> Option<Instant> {
+    DEADLINE.try_with(|deadline| *deadline).ok()
+}
+
+/// Runs `future` as if it was dispatched by a handler whose deadline is `at`.
+///
+/// The deadline inherited from the enclosing dispatch, if any and earlier, is kept.
+#[cfg(feature = "remote")]
+pub(crate) async fn scope<F: Future>(at: Instant, future: F) -> F::Output {
+    let at = deadline().map_or(at, |inherited| inherited.min(at));
+    DEADLINE.scope(at, future).await
+}
+
+/// Returns the budget left before the deadline of the dispatch currently being handled.
+///
+/// Returns `None` when there is no deadline; see [deadline].
+pub fn remaining() -> Option<Duration> {
+    deadline().map(|deadline| deadline.saturating_duration_since(Instant::now()))
+}
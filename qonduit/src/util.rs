@@ -0,0 +1,105 @@
+//! The `util` module holds the helpers shared by the modules of the crate.
+
+#[cfg(any(
+    feature = "dead-letter",
+    feature = "queue",
+    feature = "schedule",
+    feature = "webhook"
+))]
+use std::error::Error;
+#[cfg(any(feature = "remote", feature = "tonic"))]
+use std::future::Future;
+#[cfg(any(
+    feature = "dead-letter",
+    feature = "queue",
+    feature = "schedule",
+    feature = "webhook"
+))]
+use std::io;
+#[cfg(any(
+    feature = "dead-letter",
+    feature = "queue",
+    feature = "schedule",
+    feature = "webhook"
+))]
+use std::path::Path;
+#[cfg(any(
+    feature = "dead-letter",
+    feature = "queue",
+    feature = "schedule",
+    feature = "webhook"
+))]
+use std::path::PathBuf;
+#[cfg(any(feature = "remote", feature = "tonic"))]
+use std::pin::Pin;
+#[cfg(any(
+    feature = "dead-letter",
+    feature = "queue",
+    feature = "schedule",
+    feature = "signing",
+    feature = "webhook"
+))]
+use std::time::SystemTime;
+#[cfg(any(
+    feature = "dead-letter",
+    feature = "queue",
+    feature = "schedule",
+    feature = "signing",
+    feature = "webhook"
+))]
+use std::time::UNIX_EPOCH;
+
+/// A boxed future, sendable across threads.
+#[cfg(any(feature = "remote", feature = "tonic"))]
+pub(crate) type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// Runs `operation`, which blocks on the file system or a database, on the blocking threads of
+/// the runtime.
+#[cfg(any(
+    feature = "dead-letter",
+    feature = "queue",
+    feature = "schedule",
+    feature = "webhook"
+))]
+pub(crate) async fn blocking<T, F>(operation: F) -> Result<T, Box<dyn Error + Send + Sync>>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, Box<dyn Error + Send + Sync>> + Send + 'static,
+{
+    tokio::task::spawn_blocking(operation).await?
+}
+
+/// Returns the milliseconds elapsed between the Unix epoch and `time`, or zero for earlier times.
+#[cfg(any(
+    feature = "dead-letter",
+    feature = "queue",
+    feature = "schedule",
+    feature = "signing",
+    feature = "webhook"
+))]
+pub(crate) fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// Returns the path of the JSON file keeping the record `id` in `directory`.
+///
+/// # Errors
+///
+/// Returns an `InvalidInput` error if `id` is empty, or contains a path separator or `..`, so
+/// that no id can name a file outside of `directory`.
+#[cfg(any(
+    feature = "dead-letter",
+    feature = "queue",
+    feature = "schedule",
+    feature = "webhook"
+))]
+pub(crate) fn json_file(directory: &Path, id: &str) -> io::Result<PathBuf> {
+    if id.is_empty() || id.contains(['/', '\\']) || id.contains("..") {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid record id {id:?}"),
+        ));
+    }
+    Ok(directory.join(format!("{id}.json")))
+}
@@ -0,0 +1,207 @@
+//! The `validation` module checks commands and queries before they reach their handlers.
+//!
+//! Messages implement the [Validate] trait to describe what makes them well-formed, returning a
+//! list of field-level [Violations]. When validation is enabled for a message type on the
+//! `CommandBus` or `QueryBus`, the bus validates every dispatched message first and fails with
+//! [DispatchError::Invalid] without invoking the handler if any violation is found. Handlers can
+//! then focus on business rules and assume their input is well-formed.
+//!
+//! - [Validate]: Implemented by messages that can be validated.
+//! - [Violation]: A single failed rule on a field.
+//! - [Violations]: The list of violations found on a message.
+
+use std::any::Any;
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+
+use crate::error::DispatchError;
+
+/// The `Validate` trait checks that a message is well-formed.
+///
+/// # Example
+///
+/// ```
+/// use qonduit::validation::{Validate, Violation, Violations};
+///
+/// #[derive(Debug)]
+/// struct AddProductCommand {
+///     name: String,
+///     price: f64,
+/// }
+///
+/// impl Validate for AddProductCommand {
+///     fn validate(&self) -> Result<(), Violations> {
+///         let mut violations = Violations::new();
+///         if self.name.len() < 3 {
+///             violations.push(Violation::new("name", "too_short", "must be at least 3 characters"));
+///         }
+///         if self.price <= 0.0 {
+///             violations.push(Violation::new("price", "not_positive", "must be greater than zero"));
+///         }
+///         violations.into_result()
+///     }
+/// }
+///
+/// let command = AddProductCommand { name: "TV".to_string(), price: 0.0 };
+/// let violations = command.validate().unwrap_err();
+/// assert_eq!(violations.len(), 2);
+/// ```
+pub trait Validate {
+    /// Returns `Ok(())` if the message is well-formed, or every violation found otherwise.
+    fn validate(&self) -> Result<(), Violations>;
+}
+
+/// A single validation rule that a field of a message does not satisfy.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Violation {
+    /// The path of the offending field, for example `price` or `items[2].quantity`.
+    pub field: String,
+    /// A stable, machine-readable identifier of the rule, for example `not_positive`.
+    pub code: String,
+    /// A human-readable description of the problem.
+    pub message: String,
+}
+
+/// Implementation of the `Violation`.
+impl Violation {
+    /// Creates a violation of the rule `code` on `field`.
+    pub fn new(
+        field: impl Into<String>,
+        code: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            field: field.into(),
+            code: code.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Display implementation for `Violation`
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// The list of violations found while validating a message.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Violations(Vec<Violation>);
+
+/// Implementation of the `Violations`.
+impl Violations {
+    /// Creates an empty list of violations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a violation.
+    pub fn push(&mut self, violation: Violation) {
+        self.0.push(violation);
+    }
+
+    /// Returns `true` if no violation was found.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the number of violations.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns an iterator over the violations.
+    pub fn iter(&self) -> std::slice::Iter<'_, Violation> {
+        self.0.iter()
+    }
+
+    /// Returns the violations of the field `field`.
+    pub fn for_field<'a>(&'a self, field: &'a str) -> impl Iterator<Item = &'a Violation> {
+        self.0
+            .iter()
+            .filter(move |violation| violation.field == field)
+    }
+
+    /// Returns `Ok(())` if the list is empty, or the list itself as an error otherwise.
+    pub fn into_result(self) -> Result<(), Violations> {
+        if self.is_empty() { Ok(()) } else { Err(self) }
+    }
+}
+
+/// From implementation for `Violations`
+impl From<Vec<Violation>> for Violations {
+    fn from(violations: Vec<Violation>) -> Self {
+        Self(violations)
+    }
+}
+
+/// IntoIterator implementation for `Violations`
+impl IntoIterator for Violations {
+    type Item = Violation;
+    type IntoIter = std::vec::IntoIter<Violation>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// IntoIterator implementation for `&Violations`
+impl<'a> IntoIterator for &'a Violations {
+    type Item = &'a Violation;
+    type IntoIter = std::slice::Iter<'a, Violation>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// Display implementation for `Violations`
+impl Display for Violations {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        for (index, violation) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{violation}")?;
+        }
+        Ok(())
+    }
+}
+
+type Validator = fn(&dyn Any) -> Result<(), Violations>;
+
+/// The validators of a bus, keyed by message type.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Validators {
+    validators: HashMap<TypeId, Validator>,
+}
+
+impl Validators {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables validation of messages of type `T`.
+    pub(crate) fn set<T: Validate + 'static>(&mut self) {
+        self.validators.insert(TypeId::of::<T>(), |message| {
+            message
+                .downcast_ref::<T>()
+                .expect("Cannot downcast message to correct type")
+                .validate()
+        });
+    }
+
+    /// Validates `message`, failing with [DispatchError::Invalid] if it has violations.
+    pub(crate) fn validate<T: 'static, E>(&self, message: &T) -> Result<(), DispatchError<E>> {
+        match self.validators.get(&TypeId::of::<T>()) {
+            Some(validator) => validator(message).map_err(DispatchError::Invalid),
+            None => Ok(()),
+        }
+    }
+}
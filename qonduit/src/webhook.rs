@@ -0,0 +1,748 @@
+//! The `webhook` module delivers events to the HTTP endpoints of partners.
+//!
+//! This module is available with the `webhook` feature. [Webhooks] holds the [Endpoint]s
+//! configured for each event type, and returns the [WebhookEventHandler] to register on the
+//! `EventBus` for each of them. Handling an event serializes it as JSON and creates one
+//! [Delivery] per endpoint, which is POSTed in the background so that the dispatch does not wait
+//! for the partners.
+//!
+//! A delivery succeeds when the endpoint answers with a `2xx` status. Any other outcome is retried
+//! with an exponential backoff, as configured by a [RetryConfig]. A delivery still failing after
+//! the last attempt is marked as [Failed](DeliveryStatus::Failed), and can be
+//! [retried](Webhooks::retry) once the endpoint is fixed.
+//!
+//! Deliveries are saved to a [DeliveryStore] until they succeed, so that the deliveries pending
+//! when a process stops are [resumed](Webhooks::resume) when it starts again.
+//!
+//! Each request carries the following headers:
+//!
+//! - `X-Webhook-Event`: The stable name of the event type.
+//! - `X-Webhook-Delivery`: The id of the delivery, the same for every attempt.
+//! - `X-Webhook-Timestamp`: The time of the attempt, in seconds since the Unix epoch.
+//! - `X-Webhook-Signature`: With a secret, `sha256=` followed by the hexadecimal HMAC-SHA256 of
+//!   the timestamp, a dot and the body, which partners check with [verify_signature].
+//!
+//! - [Webhooks]: Configures the endpoints and delivers the events.
+//! - [WebhookEventHandler]: Hands the events of a type to the webhooks.
+//! - [Endpoint]: The URL receiving events, and its secret.
+//! - [RetryConfig]: How failed deliveries are retried.
+//! - [Delivery]: An event to deliver to an endpoint.
+//! - [DeliveryStore]: Saves the deliveries until they succeed.
+//! - [InMemoryDeliveryStore]: Keeps deliveries in memory, mainly for tests.
+//! - [FileDeliveryStore]: Keeps deliveries as JSON files in a directory.
+
+use std::any::type_name;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::fmt::Result as FormatterResult;
+use std::io;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use hmac::Hmac;
+use hmac::Mac;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::async_trait;
+use crate::event::Event;
+use crate::event::EventHandler;
+use crate::message::MessageName;
+use crate::util;
+use crate::util::blocking;
+use crate::util::unix_millis;
+
+/// The time a webhook request may take by default.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The `RetryConfig` defines how many times a delivery is attempted, and how long to wait between
+/// attempts.
+///
+/// The wait doubles after each failed attempt, up to the maximum backoff.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+/// use qonduit::webhook::RetryConfig;
+///
+/// // Waits 1s, 2s, 4s, 8s, then 10s between the attempts.
+/// let config = RetryConfig::new(6, Duration::from_secs(1)).with_max_backoff(Duration::from_secs(10));
+/// # drop(config);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RetryConfig {
+    #[doc(hidden)]
+    max_attempts: u32,
+    #[doc(hidden)]
+    initial_backoff: Duration,
+    #[doc(hidden)]
+    max_backoff: Duration,
+}
+
+/// Implementation of the `RetryConfig`.
+impl RetryConfig {
+    /// Creates a configuration attempting each delivery up to `max_attempts` times, waiting
+    /// `initial_backoff` after the first failure.
+    ///
+    /// # Panics
+    ///
+    /// This method will panic if `max_attempts` is zero.
+    pub fn new(max_attempts: u32, initial_backoff: Duration) -> Self {
+        assert!(
+            max_attempts > 0,
+            "Deliveries must be attempted at least once"
+        );
+        Self {
+            max_attempts,
+            initial_backoff,
+            max_backoff: Duration::from_secs(3600),
+        }
+    }
+
+    /// Sets the longest wait between two attempts.
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Returns the wait after the failed attempt number `attempt`, starting at one.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Default implementation for `RetryConfig`
+impl Default for RetryConfig {
+    /// Attempts each delivery 8 times over about four minutes.
+    fn default() -> Self {
+        Self::new(8, Duration::from_secs(1)).with_max_backoff(Duration::from_secs(120))
+    }
+}
+
+/// The `Endpoint` is a URL receiving the events of a type.
+#[derive(Clone)]
+pub struct Endpoint {
+    #[doc(hidden)]
+    url: String,
+    #[doc(hidden)]
+    secret: Option<Vec<u8>>,
+}
+
+/// Implementation of the `Endpoint`.
+impl Endpoint {
+    /// Creates an endpoint receiving unsigned requests at `url`.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            secret: None,
+        }
+    }
+
+    /// Signs the requests with `secret`, shared with the partner.
+    pub fn with_secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Returns the URL of the endpoint.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+/// Debug implementation for `Endpoint`
+impl Debug for Endpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        // The secret is never printed
+        f.debug_struct("Endpoint")
+            .field("url", &self.url)
+            .field("signed", &self.secret.is_some())
+            .finish()
+    }
+}
+
+/// The `DeliveryStatus` tells where an undelivered [Delivery] stands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// The delivery is being attempted, or waits for its next attempt.
+    Pending,
+
+    /// Every attempt failed.
+    Failed,
+}
+
+/// The `Delivery` is an event to deliver to an endpoint.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Delivery {
+    /// The unique id of the delivery.
+    pub id: String,
+
+    /// The stable name of the event type.
+    pub event: String,
+
+    /// The URL of the endpoint.
+    pub url: String,
+
+    /// The event serialized as JSON.
+    pub body: String,
+
+    /// The number of attempts made so far.
+    pub attempts: u32,
+
+    /// Where the delivery stands.
+    pub status: DeliveryStatus,
+
+    /// Why the last attempt failed, if it did.
+    pub last_error: Option<String>,
+}
+
+/// The `DeliveryStore` saves the deliveries that did not succeed yet.
+///
+/// A delivery is saved when it is created and after each failed attempt, and removed once
+/// delivered.
+#[async_trait]
+pub trait DeliveryStore: Send + Sync {
+    /// Saves `delivery`, replacing any delivery of the same id.
+    async fn save(&self, delivery: &Delivery) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Removes the delivery `id`, if any.
+    async fn remove(&self, id: &str) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// Returns the saved deliveries, ordered by id.
+    async fn load(&self) -> Result<Vec<Delivery>, Box<dyn Error + Send + Sync>>;
+}
+
+/// A delivery store keeping deliveries in memory.
+///
+/// Clones share the same deliveries.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryDeliveryStore {
+    #[doc(hidden)]
+    deliveries: Arc<Mutex<BTreeMap<String, Delivery>>>,
+}
+
+/// Implementation of the `InMemoryDeliveryStore`.
+impl InMemoryDeliveryStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// DeliveryStore implementation for `InMemoryDeliveryStore`
+#[async_trait]
+impl DeliveryStore for InMemoryDeliveryStore {
+    async fn save(&self, delivery: &Delivery) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let mut deliveries = self.deliveries.lock().unwrap();
+        deliveries.insert(delivery.id.clone(), delivery.clone());
+        Ok(())
+    }
+
+    async fn remove(&self, id: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.deliveries.lock().unwrap().remove(id);
+        Ok(())
+    }
+
+    async fn load(&self) -> Result<Vec<Delivery>, Box<dyn Error + Send + Sync>> {
+        Ok(self.deliveries.lock().unwrap().values().cloned().collect())
+    }
+}
+
+/// A delivery store keeping each delivery as a JSON file in a directory.
+///
+/// Files are written to a temporary name then renamed, so that a process stopping while saving
+/// does not leave a truncated delivery behind.
+/// File operations run on the blocking threads of the runtime, off the threads handling events.
+///
+/// # Example
+///
+/// ```no_run
+/// use qonduit::webhook::{FileDeliveryStore, Webhooks};
+///
+/// let store = FileDeliveryStore::open("/var/lib/orders/webhooks").unwrap();
+/// let webhooks = Webhooks::new().with_store(store);
+/// # drop(webhooks);
+/// ```
+#[derive(Clone, Debug)]
+pub struct FileDeliveryStore {
+    #[doc(hidden)]
+    directory: PathBuf,
+}
+
+/// Implementation of the `FileDeliveryStore`.
+impl FileDeliveryStore {
+    /// Opens the store kept in `directory`, creating the directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created.
+    pub fn open(directory: impl AsRef<Path>) -> io::Result<Self> {
+        let directory = directory.as_ref().to_path_buf();
+        std::fs::create_dir_all(&directory)?;
+        Ok(Self { directory })
+    }
+
+    fn path(&self, id: &str) -> io::Result<PathBuf> {
+        util::json_file(&self.directory, id)
+    }
+}
+
+/// DeliveryStore implementation for `FileDeliveryStore`
+#[async_trait]
+impl DeliveryStore for FileDeliveryStore {
+    async fn save(&self, delivery: &Delivery) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let path = self.path(&delivery.id)?;
+        let bytes = serde_json::to_vec(delivery)?;
+        blocking(move || {
+            let temporary = path.with_extension("json.tmp");
+            std::fs::write(&temporary, bytes)?;
+            std::fs::rename(temporary, path)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn remove(&self, id: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let path = self.path(id)?;
+        blocking(move || match std::fs::remove_file(path) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error.into()),
+            _ => Ok(()),
+        })
+        .await
+    }
+
+    async fn load(&self) -> Result<Vec<Delivery>, Box<dyn Error + Send + Sync>> {
+        let directory = self.directory.clone();
+        blocking(move || {
+            let mut deliveries = Vec::new();
+            for entry in std::fs::read_dir(directory)? {
+                let path = entry?.path();
+                if path
+                    .extension()
+                    .is_some_and(|extension| extension == "json")
+                {
+                    deliveries.push(serde_json::from_slice::<Delivery>(&std::fs::read(path)?)?);
+                }
+            }
+            deliveries.sort_by(|a, b| a.id.cmp(&b.id));
+            Ok(deliveries)
+        })
+        .await
+    }
+}
+
+/// The `DeliveryStats` counts the outcomes of the deliveries made by a process.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeliveryStats {
+    /// The deliveries accepted by their endpoint.
+    pub delivered: u64,
+
+    /// The failed attempts, including the last attempt of failed deliveries.
+    pub failed_attempts: u64,
+
+    /// The deliveries whose every attempt failed.
+    pub failed: u64,
+}
+
+/// Attempts deliveries in the background.
+#[derive(Clone)]
+struct Worker {
+    client: reqwest::Client,
+    store: Arc<dyn DeliveryStore>,
+    stats: Arc<Mutex<DeliveryStats>>,
+    retry: RetryConfig,
+}
+
+/// The `Webhooks` deliver events to the endpoints configured for their type.
+///
+/// Clones of the webhooks share their store and statistics. Deliveries are attempted on Tokio
+/// tasks, so events must be handled within a Tokio runtime.
+///
+/// # Example
+///
+/// ```
+/// # #[derive(Clone, Debug, serde::Serialize)]
+/// # struct OrderShippedEvent { order_id: u64 }
+/// # impl qonduit::event::Event for OrderShippedEvent {}
+/// # impl qonduit::message::MessageName for OrderShippedEvent {
+/// #   const NAME: &'static str = "orders.shipped.v1";
+/// # }
+/// use std::time::Duration;
+/// use qonduit::event::EventBus;
+/// use qonduit::registry::EventHandlerRegistry;
+/// use qonduit::webhook::{Endpoint, RetryConfig, Webhooks};
+///
+/// let webhooks = Webhooks::new()
+///     .with_endpoint::<OrderShippedEvent>(
+///         Endpoint::new("https://partner.example/hooks/orders").with_secret("shared secret"),
+///     )
+///     .with_retry(RetryConfig::new(5, Duration::from_secs(2)));
+///
+/// let mut registry = EventHandlerRegistry::new();
+/// registry.register::<OrderShippedEvent>(webhooks.handler::<OrderShippedEvent>());
+/// let event_bus = EventBus::new(registry);
+/// # drop(event_bus);
+/// ```
+#[derive(Clone)]
+pub struct Webhooks {
+    #[doc(hidden)]
+    endpoints: Arc<HashMap<&'static str, Vec<Endpoint>>>,
+    #[doc(hidden)]
+    retry: RetryConfig,
+    #[doc(hidden)]
+    timeout: Duration,
+    #[doc(hidden)]
+    client: reqwest::Client,
+    #[doc(hidden)]
+    store: Arc<dyn DeliveryStore>,
+    #[doc(hidden)]
+    stats: Arc<Mutex<DeliveryStats>>,
+    #[doc(hidden)]
+    next_id: Arc<AtomicU64>,
+}
+
+/// Implementation of the `Webhooks`.
+impl Webhooks {
+    /// Creates webhooks without any endpoint, keeping their deliveries in memory.
+    pub fn new() -> Self {
+        Self {
+            endpoints: Arc::new(HashMap::new()),
+            retry: RetryConfig::default(),
+            timeout: DEFAULT_TIMEOUT,
+            client: client(DEFAULT_TIMEOUT),
+            store: Arc::new(InMemoryDeliveryStore::new()),
+            stats: Arc::new(Mutex::new(DeliveryStats::default())),
+            next_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Delivers the events of type `E` to `endpoint`, in addition to the endpoints already
+    /// configured for `E`.
+    pub fn with_endpoint<E: Event + MessageName>(mut self, endpoint: Endpoint) -> Self {
+        Arc::make_mut(&mut self.endpoints)
+            .entry(E::NAME)
+            .or_default()
+            .push(endpoint);
+        self
+    }
+
+    /// Retries failed deliveries as configured by `retry`, instead of [RetryConfig::default].
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Waits at most `timeout` for an endpoint to answer, instead of [DEFAULT_TIMEOUT].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self.client = client(timeout);
+        self
+    }
+
+    /// Saves the deliveries to `store`, instead of keeping them in memory.
+    pub fn with_store(mut self, store: impl DeliveryStore + 'static) -> Self {
+        self.store = Arc::new(store);
+        self
+    }
+
+    /// Returns the handler delivering the events of type `E`, to be registered on the local bus.
+    pub fn handler<E: Event + MessageName + Serialize>(&self) -> WebhookEventHandler<E> {
+        WebhookEventHandler {
+            webhooks: self.clone(),
+            event: PhantomData,
+        }
+    }
+
+    /// Resumes the pending deliveries saved by a previous process, and returns their number.
+    ///
+    /// It should be called once, when the process starts.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the store if the deliveries cannot be loaded.
+    pub async fn resume(&self) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let pending: Vec<_> = self
+            .store
+            .load()
+            .await?
+            .into_iter()
+            .filter(|delivery| delivery.status == DeliveryStatus::Pending)
+            .collect();
+        let count = pending.len();
+        for delivery in pending {
+            self.spawn(delivery);
+        }
+        Ok(count)
+    }
+
+    /// Attempts the failed delivery `id` again, as if it were new, and returns whether it was
+    /// found.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the store if the delivery cannot be loaded or saved.
+    pub async fn retry(&self, id: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        let delivery = self
+            .store
+            .load()
+            .await?
+            .into_iter()
+            .find(|delivery| delivery.id == id && delivery.status == DeliveryStatus::Failed);
+        let Some(mut delivery) = delivery else {
+            return Ok(false);
+        };
+        delivery.attempts = 0;
+        delivery.status = DeliveryStatus::Pending;
+        self.store.save(&delivery).await?;
+        self.spawn(delivery);
+        Ok(true)
+    }
+
+    /// Returns the deliveries that did not succeed yet, pending or failed, ordered by id.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the store if the deliveries cannot be loaded.
+    pub async fn undelivered(&self) -> Result<Vec<Delivery>, Box<dyn Error + Send + Sync>> {
+        self.store.load().await
+    }
+
+    /// Returns the outcomes of the deliveries made by this process.
+    pub fn stats(&self) -> DeliveryStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /// Creates and starts the deliveries of an event of type `name` to its endpoints.
+    async fn deliver(&self, name: &str, body: String) -> Result<(), Box<dyn Error + Send + Sync>> {
+        for endpoint in self.endpoints.get(name).into_iter().flatten() {
+            let sequence = self.next_id.fetch_add(1, Ordering::Relaxed);
+            let delivery = Delivery {
+                // Sorts by creation, and stays unique across restarts
+                id: format!("{:013}-{sequence:06}", unix_millis(SystemTime::now())),
+                event: name.to_string(),
+                url: endpoint.url.clone(),
+                body: body.clone(),
+                attempts: 0,
+                status: DeliveryStatus::Pending,
+                last_error: None,
+            };
+            self.store.save(&delivery).await?;
+            self.spawn(delivery);
+        }
+        Ok(())
+    }
+
+    /// Attempts `delivery` in the background until it succeeds or runs out of attempts.
+    fn spawn(&self, delivery: Delivery) {
+        let secret = self
+            .endpoints
+            .get(delivery.event.as_str())
+            .into_iter()
+            .flatten()
+            .find(|endpoint| endpoint.url == delivery.url)
+            .map(|endpoint| endpoint.secret.clone());
+        let worker = Worker {
+            client: self.client.clone(),
+            store: self.store.clone(),
+            stats: self.stats.clone(),
+            retry: self.retry,
+        };
+        tokio::spawn(worker.run(delivery, secret));
+    }
+}
+
+/// Default implementation for `Webhooks`
+impl Default for Webhooks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Debug implementation for `Webhooks`
+impl Debug for Webhooks {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("Webhooks")
+            .field("endpoints", &self.endpoints)
+            .field("retry", &self.retry)
+            .field("timeout", &self.timeout)
+            .field("stats", &self.stats())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Worker {
+    /// Attempts `delivery` with `secret` until it succeeds or runs out of attempts, or fails it
+    /// if its endpoint is no longer configured.
+    async fn run(self, mut delivery: Delivery, secret: Option<Option<Vec<u8>>>) {
+        let Some(secret) = secret else {
+            delivery.status = DeliveryStatus::Failed;
+            delivery.last_error = Some("endpoint no longer configured".to_string());
+            self.update(|stats| stats.failed += 1);
+            let _ = self.store.save(&delivery).await;
+            return;
+        };
+        loop {
+            delivery.attempts += 1;
+            let error = match self.post(&delivery, secret.as_deref()).await {
+                Ok(()) => {
+                    self.update(|stats| stats.delivered += 1);
+                    // A delivery left in the store is attempted again on the next resume
+                    let _ = self.store.remove(&delivery.id).await;
+                    return;
+                }
+                Err(error) => error,
+            };
+            delivery.last_error = Some(error);
+            self.update(|stats| stats.failed_attempts += 1);
+            if delivery.attempts >= self.retry.max_attempts {
+                delivery.status = DeliveryStatus::Failed;
+                self.update(|stats| stats.failed += 1);
+            }
+            // A delivery that cannot be saved is still attempted by this process
+            let _ = self.store.save(&delivery).await;
+            if delivery.status == DeliveryStatus::Failed {
+                return;
+            }
+            tokio::time::sleep(self.retry.backoff(delivery.attempts)).await;
+        }
+    }
+
+    fn update(&self, change: impl FnOnce(&mut DeliveryStats)) {
+        change(&mut self.stats.lock().unwrap());
+    }
+
+    /// Makes one attempt of `delivery`, and returns why it failed, if it did.
+    async fn post(&self, delivery: &Delivery, secret: Option<&[u8]>) -> Result<(), String> {
+        let timestamp = (unix_millis(SystemTime::now()) / 1000).to_string();
+        let mut request = self
+            .client
+            .post(&delivery.url)
+            .header("Content-Type", "application/json")
+            .header("X-Webhook-Event", &delivery.event)
+            .header("X-Webhook-Delivery", &delivery.id)
+            .header("X-Webhook-Timestamp", &timestamp);
+        if let Some(secret) = secret {
+            let signature = sign(secret, &timestamp, &delivery.body);
+            request = request.header("X-Webhook-Signature", signature);
+        }
+        let response = request
+            .body(delivery.body.clone())
+            .send()
+            .await
+            .map_err(|error| error.to_string())?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            status => Err(format!("endpoint answered with status {status}")),
+        }
+    }
+}
+
+/// Creates the HTTP client sending the webhooks.
+fn client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .expect("Cannot create the HTTP client")
+}
+
+/// The `WebhookEventHandler` hands the events of type `E` to [Webhooks].
+///
+/// It is returned by [handler](Webhooks::handler). Handling an event only saves its deliveries,
+/// so the dispatch does not wait for the endpoints.
+pub struct WebhookEventHandler<E> {
+    #[doc(hidden)]
+    webhooks: Webhooks,
+    #[doc(hidden)]
+    event: PhantomData<fn(E)>,
+}
+
+/// EventHandler implementation for `WebhookEventHandler`
+#[async_trait]
+impl<E> EventHandler<E> for WebhookEventHandler<E>
+where
+    E: Event + MessageName + Serialize,
+{
+    async fn handle(&self, event: E) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let body = serde_json::to_string(&event)?;
+        self.webhooks.deliver(E::NAME, body).await
+    }
+}
+
+/// Debug implementation for `WebhookEventHandler`
+impl<E> Debug for WebhookEventHandler<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        f.debug_struct("WebhookEventHandler")
+            .field("event", &type_name::<E>())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns `true` if `signature`, the value of the `X-Webhook-Signature` header, is the signature
+/// of `body` sent at `timestamp`, the value of the `X-Webhook-Timestamp` header.
+///
+/// The comparison runs in constant time. Receivers should also reject requests whose timestamp is
+/// too old, to prevent replays.
+///
+/// # Example
+///
+/// ```
+/// use qonduit::webhook::verify_signature;
+///
+/// // The values of the headers and the body of a received request
+/// let timestamp = "1760000000";
+/// let signature = "sha256=9f3c2b1a";
+/// let body = r#"{"order_id":42}"#;
+///
+/// if !verify_signature(b"shared secret", timestamp, body, signature) {
+///     // Reject the request
+/// }
+/// ```
+pub fn verify_signature(secret: &[u8], timestamp: &str, body: &str, signature: &str) -> bool {
+    let Some(signature) = signature.strip_prefix("sha256=").and_then(decode_hex) else {
+        return false;
+    };
+    mac(secret, timestamp, body)
+        .verify_slice(&signature)
+        .is_ok()
+}
+
+/// Returns the value of the `X-Webhook-Signature` header.
+fn sign(secret: &[u8], timestamp: &str, body: &str) -> String {
+    let signature = mac(secret, timestamp, body).finalize().into_bytes();
+    let hex: String = signature.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("sha256={hex}")
+}
+
+fn mac(secret: &[u8], timestamp: &str, body: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    mac
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
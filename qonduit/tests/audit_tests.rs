@@ -0,0 +1,204 @@
+#![cfg(feature = "audit")]
+
+use qonduit::async_trait;
+use qonduit::audit::{AuditRecord, AuditSink, InMemoryAuditSink, JsonLinesFileSink, REDACTED};
+use qonduit::authorization::{Principal, has_role};
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::registry::CommandHandlerRegistry;
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Error type for tests
+#[derive(Debug, PartialEq)]
+struct TestError;
+
+#[derive(Debug, Serialize)]
+struct Card {
+    number: String,
+    holder: String,
+}
+
+// Command carrying a secret, failing when its amount is zero
+#[derive(Debug, Serialize)]
+struct ChargeCardCommand {
+    amount: u64,
+    card: Card,
+}
+
+impl Command for ChargeCardCommand {
+    type Response = ();
+    type Error = TestError;
+}
+
+struct ChargeCardCommandHandler;
+
+#[async_trait]
+impl CommandHandler<ChargeCardCommand> for ChargeCardCommandHandler {
+    async fn handle(&self, command: ChargeCardCommand) -> Result<(), TestError> {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        match command.amount {
+            0 => Err(TestError),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn command_bus() -> CommandBus {
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register::<ChargeCardCommand>(ChargeCardCommandHandler);
+    CommandBus::new(registry)
+}
+
+fn charge(amount: u64) -> ChargeCardCommand {
+    ChargeCardCommand {
+        amount,
+        card: Card {
+            number: "4242424242424242".to_string(),
+            holder: "Jane Doe".to_string(),
+        },
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_records_outcome_principal_and_duration() {
+    let sink = InMemoryAuditSink::new();
+    let bus = command_bus().with_audit_sink(sink.clone());
+
+    bus.dispatch_as(Principal::new("u1"), charge(10))
+        .await
+        .unwrap();
+    bus.dispatch(charge(0)).await.unwrap_err();
+
+    let records = sink.records();
+    assert_eq!(records.len(), 2);
+    assert_eq!(
+        records[0].message_type,
+        std::any::type_name::<ChargeCardCommand>()
+    );
+    assert_eq!(records[0].outcome, "ok");
+    assert_eq!(records[0].principal.as_deref(), Some("u1"));
+    assert_eq!(records[0].duration, Duration::from_millis(100));
+    // Payloads are only recorded for command types that opted in
+    assert_eq!(records[0].payload, None);
+
+    assert_eq!(records[1].outcome, "handler_error");
+    assert_eq!(records[1].principal, None);
+}
+
+#[tokio::test]
+async fn test_payload_redaction() {
+    let sink = InMemoryAuditSink::new();
+    let bus = command_bus()
+        .with_audit_sink(sink.clone())
+        .with_audit_payload::<ChargeCardCommand>()
+        .with_audit_redaction::<ChargeCardCommand>(["card.number", "card.missing"]);
+
+    bus.dispatch(charge(10)).await.unwrap();
+
+    assert_eq!(
+        sink.records()[0].payload,
+        Some(json!({
+            "amount": 10,
+            "card": { "number": REDACTED, "holder": "Jane Doe" },
+        }))
+    );
+}
+
+#[tokio::test]
+async fn test_rejected_commands_are_recorded() {
+    let sink = InMemoryAuditSink::new();
+    let bus = command_bus()
+        .with_audit_sink(sink.clone())
+        .with_policy::<ChargeCardCommand>(has_role("billing"));
+
+    bus.dispatch_as(Principal::new("u2"), charge(10))
+        .await
+        .unwrap_err();
+
+    let records = sink.records();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].outcome, "forbidden");
+    assert_eq!(records[0].principal.as_deref(), Some("u2"));
+}
+
+// Sink whose storage is unavailable
+struct UnavailableAuditSink;
+
+#[async_trait]
+impl AuditSink for UnavailableAuditSink {
+    async fn record(&self, _record: &AuditRecord) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Err("the audit storage is unavailable".into())
+    }
+}
+
+#[tokio::test]
+async fn test_sink_failures_are_reported() {
+    let failures = Arc::new(Mutex::new(Vec::new()));
+    let bus = command_bus()
+        .with_audit_sink(UnavailableAuditSink)
+        .on_audit_failure({
+            let failures = failures.clone();
+            move |record, error| {
+                failures
+                    .lock()
+                    .unwrap()
+                    .push((record.outcome, error.to_string()));
+            }
+        });
+
+    // The command ran, so the dispatch succeeds without its record
+    assert_eq!(bus.dispatch(charge(10)).await, Ok(()));
+    assert_eq!(
+        *failures.lock().unwrap(),
+        vec![("ok", "the audit storage is unavailable".to_string())]
+    );
+}
+
+// Returns an empty directory unique to the test
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("qonduit-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn read_lines(path: &PathBuf) -> Vec<Value> {
+    std::fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_json_lines_file_sink_rotation() {
+    let dir = temp_dir("audit-rotation");
+    let path = dir.join("audit.log");
+    let sink = JsonLinesFileSink::open(&path)
+        .unwrap()
+        .with_max_bytes(1)
+        .with_max_files(2);
+    let bus = command_bus().with_audit_sink(sink);
+
+    for amount in 1..=4 {
+        bus.dispatch_as(Principal::new(format!("u{amount}")), charge(amount))
+            .await
+            .unwrap();
+    }
+
+    // Every record exceeds the maximum size, so each file holds a single record
+    let current = read_lines(&path);
+    assert_eq!(current.len(), 1);
+    assert_eq!(current[0]["principal"], "u4");
+    assert_eq!(current[0]["outcome"], "ok");
+    assert_eq!(read_lines(&dir.join("audit.log.1"))[0]["principal"], "u3");
+    assert_eq!(read_lines(&dir.join("audit.log.2"))[0]["principal"], "u2");
+    // The oldest record was discarded
+    assert!(!dir.join("audit.log.3").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,233 @@
+use qonduit::async_trait;
+use qonduit::authorization::{
+    Forbidden, Policy, PolicyExt, Principal, all_of, allow_all, any_of, authenticated,
+    current_principal, has_claim, has_role,
+};
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::error::DispatchError;
+use qonduit::query::{Query, QueryBus, QueryHandler};
+use qonduit::registry::{CommandHandlerRegistry, QueryHandlerRegistry};
+use std::sync::Arc;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering::SeqCst;
+
+// Error type for tests
+#[derive(Debug, PartialEq)]
+struct TestError;
+
+// Command issued on behalf of a tenant
+#[derive(Debug)]
+struct ArchiveProductCommand {
+    tenant: String,
+}
+
+impl Command for ArchiveProductCommand {
+    type Response = Option<String>;
+    type Error = TestError;
+}
+
+// Handler that counts its invocations and returns the id of the acting principal
+struct ArchiveProductCommandHandler {
+    calls: Arc<AtomicU32>,
+}
+
+#[async_trait]
+impl CommandHandler<ArchiveProductCommand> for ArchiveProductCommandHandler {
+    async fn handle(&self, _command: ArchiveProductCommand) -> Result<Option<String>, TestError> {
+        self.calls.fetch_add(1, SeqCst);
+        Ok(current_principal().and_then(|principal| principal.id().map(str::to_string)))
+    }
+}
+
+fn command_bus() -> (CommandBus, Arc<AtomicU32>) {
+    let calls = Arc::new(AtomicU32::new(0));
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register::<ArchiveProductCommand>(ArchiveProductCommandHandler {
+        calls: calls.clone(),
+    });
+    (CommandBus::new(registry), calls)
+}
+
+fn archive(tenant: &str) -> ArchiveProductCommand {
+    ArchiveProductCommand {
+        tenant: tenant.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_policy_allows_and_denies() {
+    let (bus, calls) = command_bus();
+    let bus = bus.with_policy::<ArchiveProductCommand>(has_role("admin"));
+
+    let admin = Principal::new("u1").with_role("admin");
+    assert_eq!(
+        bus.dispatch_as(admin, archive("acme")).await,
+        Ok(Some("u1".to_string()))
+    );
+
+    let clerk = Principal::new("u2").with_role("clerk");
+    let result = bus.dispatch_as(clerk, archive("acme")).await;
+    assert_eq!(result, Err(DispatchError::Forbidden(Forbidden::Denied)));
+    assert!(result.unwrap_err().is_forbidden());
+
+    // Without a principal, the dispatch acts on behalf of the anonymous principal
+    assert_eq!(
+        bus.dispatch(archive("acme")).await,
+        Err(DispatchError::Forbidden(Forbidden::Denied))
+    );
+    assert_eq!(calls.load(SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_policy_depending_on_message() {
+    let (bus, _) = command_bus();
+    let bus = bus.with_policy::<ArchiveProductCommand>(
+        |principal: &Principal, command: &ArchiveProductCommand| {
+            principal.claim("tenant") == Some(command.tenant.as_str())
+        },
+    );
+
+    let principal = Principal::new("u1").with_claim("tenant", "acme");
+    assert!(
+        bus.dispatch_as(principal.clone(), archive("acme"))
+            .await
+            .is_ok()
+    );
+    assert_eq!(
+        bus.dispatch_as(principal, archive("globex")).await,
+        Err(DispatchError::Forbidden(Forbidden::Denied))
+    );
+}
+
+#[test]
+fn test_policy_composition() {
+    let command = archive("acme");
+    let policy = authenticated().and(any_of([
+        Box::new(has_role("admin")) as Box<dyn Policy<ArchiveProductCommand>>,
+        Box::new(all_of([
+            Box::new(has_role("editor")) as Box<dyn Policy<ArchiveProductCommand>>,
+            Box::new(has_claim("tenant", "acme")),
+        ])),
+    ]));
+
+    let admin = Principal::new("u1").with_role("admin");
+    let editor = Principal::new("u2")
+        .with_role("editor")
+        .with_claim("tenant", "acme");
+    let foreign_editor = Principal::new("u3")
+        .with_role("editor")
+        .with_claim("tenant", "globex");
+    let anonymous_admin = Principal::anonymous().with_role("admin");
+
+    assert!(policy.allows(&admin, &command));
+    assert!(policy.allows(&editor, &command));
+    assert!(!policy.allows(&foreign_editor, &command));
+    assert!(!policy.allows(&anonymous_admin, &command));
+
+    let not_suspended = PolicyExt::<ArchiveProductCommand>::not(has_role("suspended"));
+    assert!(not_suspended.allows(&admin, &command));
+    assert!(!not_suspended.allows(&Principal::new("u4").with_role("suspended"), &command));
+}
+
+// Query allowed for everyone on buses denying by default
+#[derive(Debug)]
+struct ListProductsQuery;
+
+impl Query for ListProductsQuery {
+    type Response = u32;
+    type Error = TestError;
+}
+
+struct ListProductsQueryHandler;
+
+#[async_trait]
+impl QueryHandler<ListProductsQuery> for ListProductsQueryHandler {
+    async fn handle(&self, _query: ListProductsQuery) -> Result<u32, TestError> {
+        Ok(3)
+    }
+}
+
+// Query without any policy
+#[derive(Debug)]
+struct ListSuppliersQuery;
+
+impl Query for ListSuppliersQuery {
+    type Response = u32;
+    type Error = TestError;
+}
+
+struct ListSuppliersQueryHandler;
+
+#[async_trait]
+impl QueryHandler<ListSuppliersQuery> for ListSuppliersQueryHandler {
+    async fn handle(&self, _query: ListSuppliersQuery) -> Result<u32, TestError> {
+        Ok(5)
+    }
+}
+
+#[tokio::test]
+async fn test_deny_by_default() {
+    let mut registry = QueryHandlerRegistry::new();
+    registry.register::<ListProductsQuery>(ListProductsQueryHandler);
+    registry.register::<ListSuppliersQuery>(ListSuppliersQueryHandler);
+    let bus = QueryBus::new(registry)
+        .with_deny_by_default()
+        .with_policy::<ListProductsQuery>(allow_all());
+
+    assert_eq!(bus.dispatch(ListProductsQuery).await, Ok(3));
+    assert_eq!(
+        bus.dispatch_as(Principal::new("u1").with_role("admin"), ListSuppliersQuery)
+            .await,
+        Err(DispatchError::Forbidden(Forbidden::NoPolicy))
+    );
+}
+
+// Command whose handler dispatches a nested command on the same bus
+#[derive(Debug)]
+struct ArchiveCatalogCommand;
+
+impl Command for ArchiveCatalogCommand {
+    type Response = Option<String>;
+    type Error = TestError;
+}
+
+struct ArchiveCatalogCommandHandler {
+    bus: CommandBus,
+}
+
+#[async_trait]
+impl CommandHandler<ArchiveCatalogCommand> for ArchiveCatalogCommandHandler {
+    async fn handle(&self, _command: ArchiveCatalogCommand) -> Result<Option<String>, TestError> {
+        self.bus
+            .dispatch(archive("acme"))
+            .await
+            .map_err(|_| TestError)
+    }
+}
+
+#[tokio::test]
+async fn test_principal_propagates_to_nested_dispatches() {
+    let (bus, calls) = command_bus();
+    let bus = bus.with_policy::<ArchiveProductCommand>(has_role("admin"));
+
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register::<ArchiveCatalogCommand>(ArchiveCatalogCommandHandler { bus });
+    let outer = CommandBus::new(registry);
+
+    assert_eq!(
+        outer
+            .dispatch_as(
+                Principal::new("u1").with_role("admin"),
+                ArchiveCatalogCommand
+            )
+            .await,
+        Ok(Some("u1".to_string()))
+    );
+    assert_eq!(
+        outer
+            .dispatch_as(Principal::new("u2"), ArchiveCatalogCommand)
+            .await,
+        Err(DispatchError::Handler(TestError))
+    );
+    assert_eq!(calls.load(SeqCst), 1);
+}
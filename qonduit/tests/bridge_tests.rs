@@ -0,0 +1,290 @@
+#![cfg(feature = "remote")]
+
+use qonduit::async_trait;
+use qonduit::bridge::{EventBridge, EventBroker};
+use qonduit::codec::JsonCodec;
+use qonduit::event::{Event, EventBus, EventHandler};
+use qonduit::message::MessageName;
+use qonduit::registry::EventHandlerRegistry;
+use qonduit::remote::{MemoryTransport, TcpTransport, TransportError};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
+use tokio::time::timeout;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct OrderPlacedEvent {
+    order_id: u64,
+}
+
+impl Event for OrderPlacedEvent {}
+
+impl MessageName for OrderPlacedEvent {
+    const NAME: &'static str = "orders.placed.v1";
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct InvoiceSentEvent {
+    order_id: u64,
+}
+
+impl Event for InvoiceSentEvent {}
+
+impl MessageName for InvoiceSentEvent {
+    const NAME: &'static str = "billing.invoice_sent.v1";
+}
+
+// Records the events it handles
+struct RecordingEventHandler<E>(UnboundedSender<E>);
+
+#[async_trait]
+impl<E: Event> EventHandler<E> for RecordingEventHandler<E> {
+    async fn handle(&self, event: E) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.0.send(event)?;
+        Ok(())
+    }
+}
+
+fn recorder<E: Event>() -> (RecordingEventHandler<E>, UnboundedReceiver<E>) {
+    let (sender, receiver) = unbounded_channel();
+    (RecordingEventHandler(sender), receiver)
+}
+
+// Returns a connector opening in-memory connections served by `broker`
+fn memory(
+    broker: &EventBroker,
+) -> impl FnMut() -> std::future::Ready<Result<MemoryTransport, TransportError>> + use<> {
+    let broker = broker.clone();
+    move || {
+        let (bridge, connection) = MemoryTransport::pair();
+        let broker = broker.clone();
+        tokio::spawn(async move { broker.serve(connection).await });
+        std::future::ready(Ok(bridge))
+    }
+}
+
+async fn wait_for_receiver(broker: &EventBroker, event: &str, node: &str) {
+    while !broker
+        .receivers(event)
+        .iter()
+        .any(|receiver| receiver == node)
+    {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+}
+
+async fn next<E>(receiver: &mut UnboundedReceiver<E>) -> E {
+    timeout(Duration::from_secs(5), receiver.recv())
+        .await
+        .expect("no event received")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_two_processes_over_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let broker = EventBroker::new();
+    tokio::spawn(broker.clone().serve_tcp(listener));
+    let connect = move || async move { Ok(TcpTransport::from(TcpStream::connect(address).await?)) };
+
+    // The orders process publishes placed orders and receives sent invoices
+    let orders = EventBridge::new("orders", JsonCodec).receive::<InvoiceSentEvent>();
+    let (invoices, mut sent_invoices) = recorder::<InvoiceSentEvent>();
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<OrderPlacedEvent>(orders.forward::<OrderPlacedEvent>());
+    registry.register::<InvoiceSentEvent>(invoices);
+    let orders_bus = EventBus::new(registry);
+    tokio::spawn(orders.run(orders_bus.clone(), connect));
+
+    // The billing process sends an invoice for each placed order
+    let billing = EventBridge::new("billing", JsonCodec).receive::<OrderPlacedEvent>();
+    let (placed, mut placed_orders) = recorder::<OrderPlacedEvent>();
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<OrderPlacedEvent>(placed);
+    registry.register::<InvoiceSentEvent>(billing.forward::<InvoiceSentEvent>());
+    let billing_bus = EventBus::new(registry);
+    tokio::spawn(billing.run(billing_bus.clone(), connect));
+
+    wait_for_receiver(&broker, OrderPlacedEvent::NAME, "billing").await;
+    wait_for_receiver(&broker, InvoiceSentEvent::NAME, "orders").await;
+
+    orders_bus
+        .dispatch(OrderPlacedEvent { order_id: 7 })
+        .await
+        .unwrap();
+    let order = next(&mut placed_orders).await;
+    assert_eq!(order, OrderPlacedEvent { order_id: 7 });
+
+    billing_bus
+        .dispatch(InvoiceSentEvent { order_id: 7 })
+        .await
+        .unwrap();
+    assert_eq!(
+        next(&mut sent_invoices).await,
+        InvoiceSentEvent { order_id: 7 }
+    );
+}
+
+#[tokio::test]
+async fn test_events_are_kept_for_disconnected_receivers() {
+    let broker = EventBroker::new();
+
+    let orders = EventBridge::new("orders", JsonCodec);
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<OrderPlacedEvent>(orders.forward::<OrderPlacedEvent>());
+    let orders_bus = EventBus::new(registry);
+    tokio::spawn(orders.run(orders_bus.clone(), memory(&broker)));
+
+    // The billing process subscribes, then stops
+    let billing = EventBridge::new("billing", JsonCodec).receive::<OrderPlacedEvent>();
+    let task =
+        tokio::spawn(billing.run(EventBus::new(EventHandlerRegistry::new()), memory(&broker)));
+    wait_for_receiver(&broker, OrderPlacedEvent::NAME, "billing").await;
+    task.abort();
+    let _ = task.await;
+
+    orders_bus
+        .dispatch(OrderPlacedEvent { order_id: 1 })
+        .await
+        .unwrap();
+    orders_bus
+        .dispatch(OrderPlacedEvent { order_id: 2 })
+        .await
+        .unwrap();
+
+    // Once restarted, it receives the events published while it was away
+    let billing = EventBridge::new("billing", JsonCodec).receive::<OrderPlacedEvent>();
+    let (placed, mut placed_orders) = recorder::<OrderPlacedEvent>();
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<OrderPlacedEvent>(placed);
+    tokio::spawn(billing.run(EventBus::new(registry), memory(&broker)));
+
+    assert_eq!(next(&mut placed_orders).await.order_id, 1);
+    assert_eq!(next(&mut placed_orders).await.order_id, 2);
+}
+
+#[tokio::test]
+async fn test_forwarded_events_wait_for_the_broker() {
+    let broker = EventBroker::new();
+    let billing = EventBridge::new("billing", JsonCodec).receive::<OrderPlacedEvent>();
+    let (placed, mut placed_orders) = recorder::<OrderPlacedEvent>();
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<OrderPlacedEvent>(placed);
+    tokio::spawn(billing.run(EventBus::new(registry), memory(&broker)));
+    wait_for_receiver(&broker, OrderPlacedEvent::NAME, "billing").await;
+
+    // The broker cannot be reached until `up` is set
+    let up = Arc::new(AtomicBool::new(false));
+    let reachable = up.clone();
+    let mut connect = memory(&broker);
+    let connect = move || match reachable.load(Ordering::SeqCst) {
+        true => connect(),
+        false => std::future::ready(Err(TransportError::Closed)),
+    };
+
+    let orders =
+        EventBridge::new("orders", JsonCodec).with_reconnect_delay(Duration::from_millis(5));
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<OrderPlacedEvent>(orders.forward::<OrderPlacedEvent>());
+    let orders_bus = EventBus::new(registry);
+    tokio::spawn(orders.clone().run(orders_bus.clone(), connect));
+
+    orders_bus
+        .dispatch(OrderPlacedEvent { order_id: 3 })
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(orders.unacknowledged(), 1);
+
+    up.store(true, Ordering::SeqCst);
+    assert_eq!(next(&mut placed_orders).await.order_id, 3);
+    while orders.unacknowledged() > 0 {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_received_events_are_not_forwarded_back() {
+    let broker = EventBroker::new();
+    let mut buses = Vec::new();
+    let mut receivers = Vec::new();
+    for name in ["orders", "billing"] {
+        // Both processes publish and receive the same event type
+        let bridge = EventBridge::new(name, JsonCodec).receive::<OrderPlacedEvent>();
+        let (placed, placed_orders) = recorder::<OrderPlacedEvent>();
+        let mut registry = EventHandlerRegistry::new();
+        registry.register::<OrderPlacedEvent>(bridge.forward::<OrderPlacedEvent>());
+        registry.register::<OrderPlacedEvent>(placed);
+        let bus = EventBus::new(registry);
+        tokio::spawn(bridge.run(bus.clone(), memory(&broker)));
+        wait_for_receiver(&broker, OrderPlacedEvent::NAME, name).await;
+        buses.push(bus);
+        receivers.push(placed_orders);
+    }
+
+    buses[0]
+        .dispatch(OrderPlacedEvent { order_id: 5 })
+        .await
+        .unwrap();
+    assert_eq!(next(&mut receivers[0]).await.order_id, 5);
+    assert_eq!(next(&mut receivers[1]).await.order_id, 5);
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(receivers[0].try_recv().is_err());
+    assert!(receivers[1].try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_kept_events_are_bounded_until_the_receiver_is_forgotten() {
+    let broker = EventBroker::new().with_max_unacknowledged(1);
+
+    let orders = EventBridge::new("orders", JsonCodec);
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<OrderPlacedEvent>(orders.forward::<OrderPlacedEvent>());
+    let orders_bus = EventBus::new(registry);
+    tokio::spawn(orders.clone().run(orders_bus.clone(), memory(&broker)));
+
+    let billing = EventBridge::new("billing", JsonCodec).receive::<OrderPlacedEvent>();
+    let task =
+        tokio::spawn(billing.run(EventBus::new(EventHandlerRegistry::new()), memory(&broker)));
+    wait_for_receiver(&broker, OrderPlacedEvent::NAME, "billing").await;
+    task.abort();
+    let _ = task.await;
+
+    for order_id in [1, 2] {
+        orders_bus
+            .dispatch(OrderPlacedEvent { order_id })
+            .await
+            .unwrap();
+    }
+    while orders.unacknowledged() > 0 {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    // Only the latest event is kept for the disconnected receiver
+    assert_eq!(broker.unacknowledged("billing"), 1);
+
+    let billing = EventBridge::new("billing", JsonCodec)
+        .receive::<OrderPlacedEvent>()
+        .with_reconnect_delay(Duration::from_millis(10));
+    let (placed, mut placed_orders) = recorder::<OrderPlacedEvent>();
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<OrderPlacedEvent>(placed);
+    tokio::spawn(billing.run(EventBus::new(registry), memory(&broker)));
+    assert_eq!(next(&mut placed_orders).await.order_id, 2);
+
+    // A forgotten bridge is disconnected, and known again once it reconnects
+    assert!(broker.forget("billing"));
+    assert!(!broker.forget("unknown"));
+    assert_eq!(broker.unacknowledged("billing"), 0);
+    wait_for_receiver(&broker, OrderPlacedEvent::NAME, "billing").await;
+    orders_bus
+        .dispatch(OrderPlacedEvent { order_id: 3 })
+        .await
+        .unwrap();
+    assert_eq!(next(&mut placed_orders).await.order_id, 3);
+}
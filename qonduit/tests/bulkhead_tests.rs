@@ -0,0 +1,244 @@
+use qonduit::async_trait;
+use qonduit::bulkhead::{BulkheadConfig, BulkheadStats};
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::error::DispatchError;
+use qonduit::event::{Event, EventBus, EventHandler};
+use qonduit::registry::{CommandHandlerRegistry, EventHandlerRegistry};
+use std::error::Error;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+// Error type for tests
+#[derive(Debug, PartialEq)]
+struct TestError;
+
+// Command whose handler blocks until the test opens the gate
+#[derive(Debug)]
+struct BlockingCommand;
+
+impl Command for BlockingCommand {
+    type Response = ();
+    type Error = TestError;
+}
+
+struct BlockingCommandHandler {
+    gate: Arc<Semaphore>,
+}
+
+#[async_trait]
+impl CommandHandler<BlockingCommand> for BlockingCommandHandler {
+    async fn handle(&self, _command: BlockingCommand) -> Result<(), TestError> {
+        self.gate.acquire().await.unwrap().forget();
+        Ok(())
+    }
+}
+
+fn command_bus(config: BulkheadConfig) -> (CommandBus, Arc<Semaphore>) {
+    let gate = Arc::new(Semaphore::new(0));
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register::<BlockingCommand>(BlockingCommandHandler { gate: gate.clone() });
+    let bus = CommandBus::new(registry).with_bulkhead::<BlockingCommand>(config);
+    (bus, gate)
+}
+
+// Lets spawned dispatches run until they block
+async fn settle() {
+    for _ in 0..10 {
+        tokio::task::yield_now().await;
+    }
+}
+
+#[tokio::test]
+async fn test_bulkhead_rejects_beyond_limit_without_queue() {
+    let (bus, gate) = command_bus(BulkheadConfig::new(2));
+
+    let first = tokio::spawn({
+        let bus = bus.clone();
+        async move { bus.dispatch(BlockingCommand).await }
+    });
+    let second = tokio::spawn({
+        let bus = bus.clone();
+        async move { bus.dispatch(BlockingCommand).await }
+    });
+    settle().await;
+
+    assert_eq!(
+        bus.bulkhead_stats::<BlockingCommand>(),
+        Some(BulkheadStats {
+            in_flight: 2,
+            queued: 0
+        })
+    );
+    assert_eq!(
+        bus.dispatch(BlockingCommand).await,
+        Err(DispatchError::BulkheadFull)
+    );
+
+    gate.add_permits(2);
+    assert_eq!(first.await.unwrap(), Ok(()));
+    assert_eq!(second.await.unwrap(), Ok(()));
+    assert_eq!(
+        bus.bulkhead_stats::<BlockingCommand>(),
+        Some(BulkheadStats {
+            in_flight: 0,
+            queued: 0
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_bulkhead_queues_up_to_bound() {
+    let (bus, gate) = command_bus(BulkheadConfig::new(1).with_max_queued(2));
+
+    let mut dispatches = Vec::new();
+    for _ in 0..3 {
+        let bus = bus.clone();
+        dispatches.push(tokio::spawn(
+            async move { bus.dispatch(BlockingCommand).await },
+        ));
+    }
+    settle().await;
+
+    assert_eq!(
+        bus.bulkhead_stats::<BlockingCommand>(),
+        Some(BulkheadStats {
+            in_flight: 1,
+            queued: 2
+        })
+    );
+    assert_eq!(
+        bus.dispatch(BlockingCommand).await,
+        Err(DispatchError::BulkheadFull)
+    );
+
+    // Queued dispatches run once slots free up
+    gate.add_permits(3);
+    for dispatch in dispatches {
+        assert_eq!(dispatch.await.unwrap(), Ok(()));
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_bulkhead_queue_wait_counts_towards_timeout() {
+    let (bus, gate) = command_bus(BulkheadConfig::new(1).with_max_queued(1));
+    let bus = bus.with_default_timeout(Duration::from_millis(100));
+
+    let running = tokio::spawn({
+        let bus = bus.clone();
+        async move { bus.dispatch(BlockingCommand).await }
+    });
+    settle().await;
+
+    // The queued dispatch gives up and releases its queue slot
+    let result = bus.dispatch(BlockingCommand).await;
+    assert!(result.unwrap_err().is_timeout());
+    assert_eq!(
+        bus.bulkhead_stats::<BlockingCommand>()
+            .map(|stats| stats.queued),
+        Some(0)
+    );
+
+    gate.add_permits(1);
+    drop(running);
+}
+
+// Event whose handler blocks until the test opens the gate
+#[derive(Debug, Clone)]
+struct BlockingEvent;
+
+impl Event for BlockingEvent {}
+
+struct BlockingEventHandler {
+    gate: Arc<Semaphore>,
+}
+
+#[async_trait]
+impl EventHandler<BlockingEvent> for BlockingEventHandler {
+    async fn handle(&self, _event: BlockingEvent) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.gate.acquire().await?.forget();
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_event_bus_bulkhead() {
+    let gate = Arc::new(Semaphore::new(0));
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<BlockingEvent>(BlockingEventHandler { gate: gate.clone() });
+    let bus = EventBus::new(registry).with_bulkhead::<BlockingEvent>(BulkheadConfig::new(1));
+
+    let running = tokio::spawn({
+        let bus = bus.clone();
+        async move { bus.dispatch(BlockingEvent).await }
+    });
+    settle().await;
+
+    let result = bus.dispatch(BlockingEvent).await;
+    assert!(matches!(result, Err(DispatchError::BulkheadFull)));
+
+    gate.add_permits(1);
+    assert!(running.await.unwrap().is_ok());
+}
+
+// Handler counting the events it handles without blocking
+struct CountingEventHandler {
+    handled: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl EventHandler<BlockingEvent> for CountingEventHandler {
+    async fn handle(&self, _event: BlockingEvent) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.handled.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_event_bulkhead_is_per_handler() {
+    let gate = Arc::new(Semaphore::new(0));
+    let handled = Arc::new(AtomicUsize::new(0));
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<BlockingEvent>(CountingEventHandler {
+        handled: handled.clone(),
+    });
+    registry.register::<BlockingEvent>(BlockingEventHandler { gate: gate.clone() });
+    let bus = EventBus::new(registry)
+        .with_bulkhead::<BlockingEvent>(BulkheadConfig::new(1).with_max_queued(1));
+
+    // The blocking handler holds its slot, then queues the second event
+    let mut running = Vec::new();
+    for _ in 0..2 {
+        let bus = bus.clone();
+        running.push(tokio::spawn(
+            async move { bus.dispatch(BlockingEvent).await },
+        ));
+        settle().await;
+    }
+    let stats = bus.handler_bulkhead_stats::<BlockingEvent>();
+    assert!(stats[0].0.ends_with("CountingEventHandler"));
+    assert_eq!(
+        stats.iter().map(|(_, stats)| *stats).collect::<Vec<_>>(),
+        vec![
+            BulkheadStats {
+                in_flight: 0,
+                queued: 0
+            },
+            BulkheadStats {
+                in_flight: 1,
+                queued: 1
+            },
+        ]
+    );
+
+    // The saturated handler rejects the third event, but not its sibling
+    let result = bus.dispatch(BlockingEvent).await;
+    assert!(matches!(result, Err(DispatchError::BulkheadFull)));
+    assert_eq!(handled.load(Ordering::SeqCst), 3);
+
+    gate.add_permits(2);
+    for dispatch in running {
+        assert!(dispatch.await.unwrap().is_ok());
+    }
+}
@@ -0,0 +1,231 @@
+use qonduit::async_trait;
+use qonduit::circuit_breaker::{CircuitBreakerConfig, CircuitState, StateTransition};
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::error::DispatchError;
+use qonduit::message::MessageName;
+use qonduit::query::{Query, QueryBus, QueryHandler};
+use qonduit::registry::{CommandHandlerRegistry, QueryHandlerRegistry};
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering::SeqCst;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Error type for tests
+#[derive(Debug, PartialEq)]
+struct TestError;
+
+// Command that succeeds or fails on demand
+#[derive(Debug)]
+struct ChargeCommand(bool);
+
+impl Command for ChargeCommand {
+    type Response = ();
+    type Error = TestError;
+}
+
+impl MessageName for ChargeCommand {
+    const NAME: &'static str = "payments.charge.v1";
+}
+
+// Handler that counts how many times it was invoked
+struct ChargeCommandHandler {
+    calls: Arc<AtomicU32>,
+}
+
+#[async_trait]
+impl CommandHandler<ChargeCommand> for ChargeCommandHandler {
+    async fn handle(&self, command: ChargeCommand) -> Result<(), TestError> {
+        self.calls.fetch_add(1, SeqCst);
+        if command.0 { Ok(()) } else { Err(TestError) }
+    }
+}
+
+fn config() -> CircuitBreakerConfig {
+    CircuitBreakerConfig::new()
+        .with_failure_rate_threshold(0.5)
+        .with_minimum_calls(4)
+        .with_window(Duration::from_secs(10))
+        .with_open_duration(Duration::from_secs(5))
+}
+
+fn command_bus(config: CircuitBreakerConfig) -> (CommandBus, Arc<AtomicU32>) {
+    let calls = Arc::new(AtomicU32::new(0));
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register_named::<ChargeCommand>(ChargeCommandHandler {
+        calls: calls.clone(),
+    });
+    let bus = CommandBus::new(registry).with_circuit_breaker::<ChargeCommand>(config);
+    (bus, calls)
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_circuit_opens_at_failure_rate_threshold() {
+    let (bus, calls) = command_bus(config());
+
+    // Below the minimum number of calls the circuit stays closed
+    for _ in 0..3 {
+        let _ = bus.dispatch(ChargeCommand(false)).await;
+    }
+    assert_eq!(
+        bus.circuit_state::<ChargeCommand>(),
+        Some(CircuitState::Closed)
+    );
+
+    // The fourth call reaches the minimum with a 75% failure rate
+    let _ = bus.dispatch(ChargeCommand(true)).await;
+    assert_eq!(
+        bus.circuit_state::<ChargeCommand>(),
+        Some(CircuitState::Open)
+    );
+
+    // While open, dispatches fail fast without reaching the handler
+    let result = bus.dispatch(ChargeCommand(true)).await;
+    assert_eq!(result, Err(DispatchError::CircuitOpen));
+    assert_eq!(calls.load(SeqCst), 4);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_circuit_ignores_failures_outside_window() {
+    let (bus, _) = command_bus(config());
+
+    for _ in 0..3 {
+        let _ = bus.dispatch(ChargeCommand(false)).await;
+    }
+
+    // The failures fall out of the rolling window
+    tokio::time::advance(Duration::from_secs(11)).await;
+    for _ in 0..3 {
+        let _ = bus.dispatch(ChargeCommand(true)).await;
+    }
+    let _ = bus.dispatch(ChargeCommand(false)).await;
+
+    assert_eq!(
+        bus.circuit_state::<ChargeCommand>(),
+        Some(CircuitState::Closed)
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_circuit_half_open_trial_success_closes() {
+    let (bus, calls) = command_bus(config());
+    for _ in 0..4 {
+        let _ = bus.dispatch(ChargeCommand(false)).await;
+    }
+    assert_eq!(
+        bus.circuit_state::<ChargeCommand>(),
+        Some(CircuitState::Open)
+    );
+
+    tokio::time::advance(Duration::from_secs(5)).await;
+    assert_eq!(
+        bus.circuit_state::<ChargeCommand>(),
+        Some(CircuitState::HalfOpen)
+    );
+
+    let result = bus.dispatch(ChargeCommand(true)).await;
+    assert_eq!(result, Ok(()));
+    assert_eq!(
+        bus.circuit_state::<ChargeCommand>(),
+        Some(CircuitState::Closed)
+    );
+    assert_eq!(calls.load(SeqCst), 5);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_circuit_half_open_trial_failure_reopens() {
+    let (bus, _) = command_bus(config());
+    for _ in 0..4 {
+        let _ = bus.dispatch(ChargeCommand(false)).await;
+    }
+
+    tokio::time::advance(Duration::from_secs(5)).await;
+    let result = bus.dispatch(ChargeCommand(false)).await;
+    assert_eq!(result, Err(DispatchError::Handler(TestError)));
+    assert_eq!(
+        bus.circuit_state::<ChargeCommand>(),
+        Some(CircuitState::Open)
+    );
+
+    // The open duration starts over
+    tokio::time::advance(Duration::from_secs(4)).await;
+    let result = bus.dispatch(ChargeCommand(true)).await;
+    assert_eq!(result, Err(DispatchError::CircuitOpen));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_circuit_state_transitions_are_observable() {
+    let transitions = Arc::new(Mutex::new(Vec::<StateTransition>::new()));
+    let recorded = transitions.clone();
+    let (bus, _) = command_bus(
+        config()
+            .on_state_change(move |transition| recorded.lock().unwrap().push(transition.clone())),
+    );
+
+    for _ in 0..4 {
+        let _ = bus.dispatch(ChargeCommand(false)).await;
+    }
+    tokio::time::advance(Duration::from_secs(5)).await;
+    let _ = bus.dispatch(ChargeCommand(true)).await;
+
+    let states: Vec<_> = transitions
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|transition| (transition.from, transition.to))
+        .collect();
+    assert_eq!(
+        states,
+        vec![
+            (CircuitState::Closed, CircuitState::Open),
+            (CircuitState::Open, CircuitState::HalfOpen),
+            (CircuitState::HalfOpen, CircuitState::Closed),
+        ]
+    );
+    assert_eq!(
+        transitions.lock().unwrap()[0].message_type,
+        "payments.charge.v1"
+    );
+}
+
+// Query that never completes in time
+#[derive(Debug)]
+struct SlowQuery;
+
+impl Query for SlowQuery {
+    type Response = ();
+    type Error = TestError;
+}
+
+struct SlowQueryHandler;
+
+#[async_trait]
+impl QueryHandler<SlowQuery> for SlowQueryHandler {
+    async fn handle(&self, _query: SlowQuery) -> Result<(), TestError> {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        Ok(())
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_query_bus_timeouts_count_as_failures() {
+    let mut registry = QueryHandlerRegistry::new();
+    registry.register::<SlowQuery>(SlowQueryHandler);
+    let bus = QueryBus::new(registry)
+        .with_default_timeout(Duration::from_millis(100))
+        .with_circuit_breaker::<SlowQuery>(config().with_minimum_calls(2));
+
+    assert!(bus.dispatch(SlowQuery).await.unwrap_err().is_timeout());
+    assert!(bus.dispatch(SlowQuery).await.unwrap_err().is_timeout());
+
+    assert_eq!(bus.circuit_state::<SlowQuery>(), Some(CircuitState::Open));
+    assert_eq!(
+        bus.dispatch(SlowQuery).await,
+        Err(DispatchError::CircuitOpen)
+    );
+}
+
+#[tokio::test]
+async fn test_circuit_state_without_breaker() {
+    let bus = QueryBus::new(QueryHandlerRegistry::new());
+    assert_eq!(bus.circuit_state::<SlowQuery>(), None);
+}
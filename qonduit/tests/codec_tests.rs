@@ -0,0 +1,105 @@
+#![cfg(feature = "serde")]
+
+use qonduit::authorization::{Forbidden, Principal};
+use qonduit::codec::{Codec, CodecError, JsonCodec, assert_round_trip, round_trip};
+use qonduit::command::Command;
+use qonduit::error::DispatchError;
+use qonduit::validation::{Violation, Violations};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum PlaceOrderError {
+    OutOfStock { sku: String },
+    PaymentDeclined,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct PlaceOrderCommand {
+    sku: String,
+    quantity: u32,
+    notes: Option<String>,
+}
+
+impl Command for PlaceOrderCommand {
+    type Response = u64;
+    type Error = PlaceOrderError;
+}
+
+// Checks that a command, its response and every kind of dispatch error survive `codec`
+fn assert_codec(codec: &impl Codec) {
+    assert_round_trip(
+        codec,
+        &PlaceOrderCommand {
+            sku: "kb-01".to_string(),
+            quantity: 2,
+            notes: None,
+        },
+    );
+    assert_round_trip(codec, &42u64);
+
+    let outcomes: Vec<Result<u64, DispatchError<PlaceOrderError>>> = vec![
+        Ok(7),
+        Err(DispatchError::Handler(PlaceOrderError::OutOfStock {
+            sku: "kb-01".to_string(),
+        })),
+        Err(DispatchError::Handler(PlaceOrderError::PaymentDeclined)),
+        Err(DispatchError::Timeout(Duration::from_millis(1500))),
+        Err(DispatchError::CircuitOpen),
+        Err(DispatchError::BulkheadFull),
+        Err(DispatchError::RateLimited {
+            retry_after: Duration::from_secs(3),
+        }),
+        Err(DispatchError::Invalid(Violations::from(vec![
+            Violation::new("quantity", "not_positive", "must be positive"),
+        ]))),
+        Err(DispatchError::Forbidden(Forbidden::Denied)),
+        Err(DispatchError::Transport("connection reset".to_string())),
+    ];
+    for outcome in &outcomes {
+        assert_round_trip(codec, outcome);
+    }
+
+    assert_round_trip(
+        codec,
+        &Principal::new("u1")
+            .with_role("admin")
+            .with_claim("tenant", "acme"),
+    );
+}
+
+#[test]
+fn test_json_codec() {
+    assert_codec(&JsonCodec);
+    assert_eq!(JsonCodec.content_type(), "application/json");
+    assert_eq!(
+        JsonCodec.encode(&PlaceOrderError::PaymentDeclined).unwrap(),
+        br#""PaymentDeclined""#
+    );
+}
+
+#[test]
+fn test_decode_error() {
+    let bytes = JsonCodec.encode("not a command").unwrap();
+    let error = JsonCodec.decode::<PlaceOrderCommand>(&bytes).unwrap_err();
+    assert!(matches!(error, CodecError::Decode(_)));
+    assert!(round_trip(&JsonCodec, &f64::NAN).is_err());
+}
+
+#[cfg(feature = "msgpack")]
+#[test]
+fn test_message_pack_codec() {
+    assert_codec(&qonduit::codec::MessagePackCodec);
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn test_bincode_codec() {
+    assert_codec(&qonduit::codec::BincodeCodec);
+}
+
+#[cfg(feature = "cbor")]
+#[test]
+fn test_cbor_codec() {
+    assert_codec(&qonduit::codec::CborCodec);
+}
@@ -0,0 +1,473 @@
+#![cfg(feature = "dead-letter")]
+
+use qonduit::async_trait;
+use qonduit::authorization::{Principal, authenticated, current_principal};
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::dead_letter::{
+    DeadLetter, DeadLetterQueue, DeadLetterStore, FileDeadLetterStore, InMemoryDeadLetterStore,
+    MessageKind,
+};
+use qonduit::error::DispatchError;
+use qonduit::event::{Event, EventBus, EventHandler};
+use qonduit::message::MessageName;
+use qonduit::registry::{CommandHandlerRegistry, EventHandlerRegistry};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct OrderPaidEvent {
+    order_id: u64,
+}
+
+impl Event for OrderPaidEvent {}
+
+impl MessageName for OrderPaidEvent {
+    const NAME: &'static str = "orders.paid.v1";
+}
+
+// Fails while the warehouse is down, and counts the attempts
+#[derive(Clone, Default)]
+struct Warehouse {
+    down: Arc<AtomicBool>,
+    attempts: Arc<AtomicUsize>,
+}
+
+struct ShipOrderEventHandler(Warehouse);
+
+#[async_trait]
+impl EventHandler<OrderPaidEvent> for ShipOrderEventHandler {
+    async fn handle(&self, _event: OrderPaidEvent) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.0.attempts.fetch_add(1, Ordering::SeqCst);
+        if self.0.down.load(Ordering::SeqCst) {
+            return Err("the warehouse is unreachable".into());
+        }
+        Ok(())
+    }
+}
+
+// Counts the events handled
+struct EmailReceiptEventHandler(Arc<AtomicUsize>);
+
+#[async_trait]
+impl EventHandler<OrderPaidEvent> for EmailReceiptEventHandler {
+    async fn handle(&self, _event: OrderPaidEvent) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RefundOrderCommand {
+    order_id: u64,
+}
+
+impl Command for RefundOrderCommand {
+    type Response = ();
+    type Error = String;
+}
+
+impl MessageName for RefundOrderCommand {
+    const NAME: &'static str = "orders.refund.v1";
+}
+
+struct RefundOrderCommandHandler(Warehouse);
+
+#[async_trait]
+impl CommandHandler<RefundOrderCommand> for RefundOrderCommandHandler {
+    async fn handle(&self, _command: RefundOrderCommand) -> Result<(), String> {
+        self.0.attempts.fetch_add(1, Ordering::SeqCst);
+        if self.0.down.load(Ordering::SeqCst) {
+            return Err("the payment provider is unreachable".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn event_bus(queue: &DeadLetterQueue, warehouse: &Warehouse) -> (EventBus, Arc<AtomicUsize>) {
+    let emailed = Arc::new(AtomicUsize::new(0));
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<OrderPaidEvent>(ShipOrderEventHandler(warehouse.clone()));
+    registry.register::<OrderPaidEvent>(EmailReceiptEventHandler(emailed.clone()));
+    let event_bus = EventBus::new(registry)
+        .with_dead_letter_queue(queue.clone())
+        .with_dead_letters::<OrderPaidEvent>(3);
+    (event_bus, emailed)
+}
+
+fn warehouse_down() -> Warehouse {
+    let warehouse = Warehouse::default();
+    warehouse.down.store(true, Ordering::SeqCst);
+    warehouse
+}
+
+#[tokio::test]
+async fn test_failed_events_are_dead_lettered_after_retries() {
+    let queue = DeadLetterQueue::new(InMemoryDeadLetterStore::new());
+    let warehouse = warehouse_down();
+    let (event_bus, emailed) = event_bus(&queue, &warehouse);
+
+    // The dispatch succeeds, and the other handlers still run
+    event_bus
+        .dispatch(OrderPaidEvent { order_id: 42 })
+        .await
+        .unwrap();
+    assert_eq!(warehouse.attempts.load(Ordering::SeqCst), 3);
+    assert_eq!(emailed.load(Ordering::SeqCst), 1);
+
+    let letters = queue.list().await.unwrap();
+    assert_eq!(letters.len(), 1);
+    let letter = &letters[0];
+    assert_eq!(letter.kind, MessageKind::Event);
+    assert_eq!(letter.name, "orders.paid.v1");
+    assert_eq!(letter.payload, json!({ "order_id": 42 }));
+    assert!(letter.handler.ends_with("ShipOrderEventHandler"));
+    assert_eq!(letter.handler_index, 0);
+    assert_eq!(letter.error, "the warehouse is unreachable");
+    assert_eq!(letter.attempts, 3);
+    assert_eq!(queue.get(&letter.id).await.unwrap().as_ref(), Some(letter));
+}
+
+#[tokio::test]
+async fn test_replay_to_the_failed_handler_only() {
+    let queue = DeadLetterQueue::new(InMemoryDeadLetterStore::new());
+    let warehouse = warehouse_down();
+    let (event_bus, emailed) = event_bus(&queue, &warehouse);
+    event_bus
+        .dispatch(OrderPaidEvent { order_id: 42 })
+        .await
+        .unwrap();
+    let id = queue.list().await.unwrap()[0].id.clone();
+
+    // A replay during the outage keeps the dead letter
+    let error = event_bus.replay_dead_letter(&id).await.unwrap_err();
+    assert_eq!(error.to_string(), "the warehouse is unreachable");
+    assert_eq!(queue.get(&id).await.unwrap().unwrap().attempts, 4);
+
+    warehouse.down.store(false, Ordering::SeqCst);
+    assert!(event_bus.replay_dead_letter(&id).await.unwrap());
+    assert_eq!(warehouse.attempts.load(Ordering::SeqCst), 5);
+    assert_eq!(emailed.load(Ordering::SeqCst), 1);
+    assert!(queue.list().await.unwrap().is_empty());
+    assert!(!event_bus.replay_dead_letter(&id).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_replay_to_the_failed_handler_among_handlers_of_the_same_type() {
+    let queue = DeadLetterQueue::new(InMemoryDeadLetterStore::new());
+    let (up, down) = (Warehouse::default(), warehouse_down());
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<OrderPaidEvent>(ShipOrderEventHandler(up.clone()));
+    registry.register::<OrderPaidEvent>(ShipOrderEventHandler(down.clone()));
+    let event_bus = EventBus::new(registry)
+        .with_dead_letter_queue(queue.clone())
+        .with_dead_letters::<OrderPaidEvent>(1);
+    event_bus
+        .dispatch(OrderPaidEvent { order_id: 42 })
+        .await
+        .unwrap();
+    let letter = queue.list().await.unwrap().remove(0);
+    assert_eq!(letter.handler_index, 1);
+
+    // The handler is found by its position, not by its type name
+    down.down.store(false, Ordering::SeqCst);
+    assert!(event_bus.replay_dead_letter(&letter.id).await.unwrap());
+    assert_eq!(up.attempts.load(Ordering::SeqCst), 1);
+    assert_eq!(down.attempts.load(Ordering::SeqCst), 2);
+}
+
+// Fails to save the dead letters, as if its storage was unavailable
+struct UnavailableDeadLetterStore;
+
+#[async_trait]
+impl DeadLetterStore for UnavailableDeadLetterStore {
+    async fn save(&self, _: &DeadLetter) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Err("unavailable".into())
+    }
+
+    async fn get(&self, _: &str) -> Result<Option<DeadLetter>, Box<dyn Error + Send + Sync>> {
+        Ok(None)
+    }
+
+    async fn remove(&self, _: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        Ok(false)
+    }
+
+    async fn load(&self) -> Result<Vec<DeadLetter>, Box<dyn Error + Send + Sync>> {
+        Ok(Vec::new())
+    }
+}
+
+#[tokio::test]
+async fn test_events_whose_dead_letter_cannot_be_saved_fail_the_dispatch() {
+    let queue = DeadLetterQueue::new(UnavailableDeadLetterStore);
+    let warehouse = warehouse_down();
+    let (event_bus, emailed) = event_bus(&queue, &warehouse);
+
+    let result = event_bus.dispatch(OrderPaidEvent { order_id: 42 }).await;
+    let Err(DispatchError::Handler(error)) = result else {
+        panic!("the dispatch should fail");
+    };
+    assert_eq!(error.to_string(), "the warehouse is unreachable");
+    assert_eq!(emailed.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn test_event_types_without_dead_letters_fail_the_dispatch() {
+    let queue = DeadLetterQueue::new(InMemoryDeadLetterStore::new());
+    let warehouse = warehouse_down();
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<OrderPaidEvent>(ShipOrderEventHandler(warehouse.clone()));
+    let event_bus = EventBus::new(registry).with_dead_letter_queue(queue.clone());
+
+    let result = event_bus.dispatch(OrderPaidEvent { order_id: 42 }).await;
+    assert!(matches!(result, Err(DispatchError::Handler(_))));
+    assert_eq!(warehouse.attempts.load(Ordering::SeqCst), 1);
+    assert!(queue.list().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_failed_commands_are_dead_lettered_and_replayed() {
+    let queue = DeadLetterQueue::new(InMemoryDeadLetterStore::new());
+    let provider = warehouse_down();
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register::<RefundOrderCommand>(RefundOrderCommandHandler(provider.clone()));
+    let command_bus = CommandBus::new(registry)
+        .with_dead_letter_queue(queue.clone())
+        .with_dead_letters::<RefundOrderCommand>();
+
+    // The error is still returned to the caller
+    let result = command_bus
+        .dispatch(RefundOrderCommand { order_id: 7 })
+        .await;
+    assert!(matches!(result, Err(DispatchError::Handler(_))));
+    let letter = queue.list().await.unwrap().remove(0);
+    assert_eq!(letter.kind, MessageKind::Command);
+    assert_eq!(letter.name, "orders.refund.v1");
+    assert_eq!(letter.payload, json!({ "order_id": 7 }));
+    assert!(letter.handler.ends_with("RefundOrderCommandHandler"));
+    assert_eq!(letter.error, "\"the payment provider is unreachable\"");
+    assert_eq!(letter.handler_index, 0);
+    assert_eq!(letter.attempts, 1);
+
+    // A replay failing again updates the dead letter instead of recording another
+    assert!(command_bus.replay_dead_letter(&letter.id).await.is_err());
+    let letters = queue.list().await.unwrap();
+    assert_eq!((letters.len(), letters[0].attempts), (1, 2));
+
+    provider.down.store(false, Ordering::SeqCst);
+    assert!(command_bus.replay_dead_letter(&letter.id).await.unwrap());
+    assert_eq!(provider.attempts.load(Ordering::SeqCst), 3);
+    assert!(queue.list().await.unwrap().is_empty());
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ReleaseStockCommand {
+    order_id: u64,
+}
+
+impl Command for ReleaseStockCommand {
+    type Response = ();
+    type Error = String;
+}
+
+impl MessageName for ReleaseStockCommand {
+    const NAME: &'static str = "stock.release.v1";
+}
+
+// The stock service stays down for the whole test
+struct ReleaseStockCommandHandler;
+
+#[async_trait]
+impl CommandHandler<ReleaseStockCommand> for ReleaseStockCommandHandler {
+    async fn handle(&self, _command: ReleaseStockCommand) -> Result<(), String> {
+        Err("the stock service is unreachable".to_string())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CancelOrderCommand {
+    order_id: u64,
+}
+
+impl Command for CancelOrderCommand {
+    type Response = ();
+    type Error = String;
+}
+
+impl MessageName for CancelOrderCommand {
+    const NAME: &'static str = "orders.cancel.v1";
+}
+
+// Releases the stock of the order once the payment provider is reachable
+struct CancelOrderCommandHandler {
+    provider: Warehouse,
+    command_bus: Arc<OnceLock<CommandBus>>,
+}
+
+#[async_trait]
+impl CommandHandler<CancelOrderCommand> for CancelOrderCommandHandler {
+    async fn handle(&self, command: CancelOrderCommand) -> Result<(), String> {
+        if self.provider.down.load(Ordering::SeqCst) {
+            return Err("the payment provider is unreachable".to_string());
+        }
+        let release = ReleaseStockCommand {
+            order_id: command.order_id,
+        };
+        let _ = self.command_bus.get().unwrap().dispatch(release).await;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_failures_while_replaying_are_dead_lettered() {
+    let queue = DeadLetterQueue::new(InMemoryDeadLetterStore::new());
+    let provider = warehouse_down();
+    let command_bus = Arc::new(OnceLock::new());
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register::<CancelOrderCommand>(CancelOrderCommandHandler {
+        provider: provider.clone(),
+        command_bus: command_bus.clone(),
+    });
+    registry.register::<ReleaseStockCommand>(ReleaseStockCommandHandler);
+    let bus = command_bus.get_or_init(|| {
+        CommandBus::new(registry)
+            .with_dead_letter_queue(queue.clone())
+            .with_dead_letters::<CancelOrderCommand>()
+            .with_dead_letters::<ReleaseStockCommand>()
+    });
+
+    let _ = bus.dispatch(CancelOrderCommand { order_id: 7 }).await;
+    let cancelled = queue.list().await.unwrap().remove(0);
+    provider.down.store(false, Ordering::SeqCst);
+    assert!(bus.replay_dead_letter(&cancelled.id).await.unwrap());
+
+    // The command dispatched by the replayed one failed, and was recorded
+    let letters = queue.list().await.unwrap();
+    assert_eq!(letters.len(), 1);
+    assert_eq!(letters[0].name, "stock.release.v1");
+    assert_eq!(letters[0].payload, json!({ "order_id": 7 }));
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CloseAccountCommand;
+
+impl Command for CloseAccountCommand {
+    type Response = ();
+    type Error = String;
+}
+
+impl MessageName for CloseAccountCommand {
+    const NAME: &'static str = "accounts.close.v1";
+}
+
+// Records on whose behalf the accounts were closed
+struct CloseAccountCommandHandler {
+    provider: Warehouse,
+    closed_by: Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl CommandHandler<CloseAccountCommand> for CloseAccountCommandHandler {
+    async fn handle(&self, _command: CloseAccountCommand) -> Result<(), String> {
+        if self.provider.down.load(Ordering::SeqCst) {
+            return Err("the payment provider is unreachable".to_string());
+        }
+        let principal = current_principal().unwrap();
+        let id = principal.id().unwrap().to_string();
+        self.closed_by.lock().unwrap().push(id);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_replay_on_behalf_of_the_original_principal() {
+    let queue = DeadLetterQueue::new(InMemoryDeadLetterStore::new());
+    let provider = warehouse_down();
+    let closed_by = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register::<CloseAccountCommand>(CloseAccountCommandHandler {
+        provider: provider.clone(),
+        closed_by: closed_by.clone(),
+    });
+    let command_bus = CommandBus::new(registry)
+        .with_policy::<CloseAccountCommand>(authenticated())
+        .with_dead_letter_queue(queue.clone())
+        .with_dead_letters::<CloseAccountCommand>();
+
+    let alice = Principal::new("alice");
+    let _ = command_bus
+        .dispatch_as(alice.clone(), CloseAccountCommand)
+        .await;
+    let letter = queue.list().await.unwrap().remove(0);
+    assert_eq!(letter.principal, Some(alice));
+
+    // The operator replaying the command is not authenticated, but the command passes the policy
+    provider.down.store(false, Ordering::SeqCst);
+    assert!(command_bus.replay_dead_letter(&letter.id).await.unwrap());
+    assert_eq!(*closed_by.lock().unwrap(), vec!["alice".to_string()]);
+}
+
+#[tokio::test]
+async fn test_file_dead_letters_survive_restarts() {
+    let directory =
+        std::env::temp_dir().join(format!("qonduit-dead-letters-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    let warehouse = warehouse_down();
+    {
+        let queue = DeadLetterQueue::new(FileDeadLetterStore::open(&directory).unwrap());
+        let (event_bus, _) = event_bus(&queue, &warehouse);
+        for order_id in [1, 2] {
+            event_bus
+                .dispatch(OrderPaidEvent { order_id })
+                .await
+                .unwrap();
+        }
+    }
+
+    // The dead letters recorded before the restart are listed, discarded and replayed
+    let queue = DeadLetterQueue::new(FileDeadLetterStore::open(&directory).unwrap());
+    let (event_bus, _) = event_bus(&queue, &warehouse);
+    let letters = queue.list().await.unwrap();
+    assert_eq!(letters[0].payload, json!({ "order_id": 1 }));
+    assert_eq!(letters[1].payload, json!({ "order_id": 2 }));
+    assert!(queue.discard(&letters[0].id).await.unwrap());
+    assert!(!queue.discard(&letters[0].id).await.unwrap());
+    warehouse.down.store(false, Ordering::SeqCst);
+    assert!(event_bus.replay_dead_letter(&letters[1].id).await.unwrap());
+    assert!(queue.list().await.unwrap().is_empty());
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_sqlite_dead_letters() {
+    use qonduit::dead_letter::SqliteDeadLetterStore;
+
+    let directory = std::env::temp_dir().join(format!(
+        "qonduit-dead-letters-sqlite-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("dead-letters.db");
+    let warehouse = warehouse_down();
+    let queue = DeadLetterQueue::new(SqliteDeadLetterStore::open(&path).unwrap());
+    let (event_bus, _) = event_bus(&queue, &warehouse);
+    event_bus
+        .dispatch(OrderPaidEvent { order_id: 42 })
+        .await
+        .unwrap();
+
+    // Another connection reads the same dead letters
+    let reopened = DeadLetterQueue::new(SqliteDeadLetterStore::open(&path).unwrap());
+    let letters = reopened.list().await.unwrap();
+    assert_eq!(letters, queue.list().await.unwrap());
+    assert_eq!(letters[0].kind, MessageKind::Event);
+    assert_eq!(letters[0].payload, json!({ "order_id": 42 }));
+    assert!(reopened.discard(&letters[0].id).await.unwrap());
+    assert!(queue.list().await.unwrap().is_empty());
+    std::fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,179 @@
+#![cfg(feature = "axum")]
+
+use axum::Router;
+use axum::body::{Body, BodyDataStream};
+use axum::http::{Request, StatusCode};
+use futures_util::StreamExt;
+use qonduit::authorization::Principal;
+use qonduit::event::{Event, EventBus};
+use qonduit::event_stream::EventStream;
+use qonduit::message::MessageName;
+use qonduit::registry::EventHandlerRegistry;
+use serde::Serialize;
+use tower::ServiceExt;
+
+#[derive(Clone, Debug, Serialize)]
+struct OrderPlacedEvent {
+    order_id: u64,
+    customer_id: String,
+}
+
+impl Event for OrderPlacedEvent {}
+
+impl MessageName for OrderPlacedEvent {
+    const NAME: &'static str = "orders.placed.v1";
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct OrderShippedEvent {
+    order_id: u64,
+}
+
+impl Event for OrderShippedEvent {}
+
+impl MessageName for OrderShippedEvent {
+    const NAME: &'static str = "orders.shipped.v1";
+}
+
+fn placed(order_id: u64, customer_id: &str) -> OrderPlacedEvent {
+    OrderPlacedEvent {
+        order_id,
+        customer_id: customer_id.to_string(),
+    }
+}
+
+fn event_stream(bus: &EventBus) -> EventStream {
+    EventStream::new(bus.clone())
+        .event::<OrderPlacedEvent>()
+        .event::<OrderShippedEvent>()
+}
+
+// Opens a connection and returns its body once the stream is subscribed
+async fn connect(router: Router, request: Request<Body>) -> BodyDataStream {
+    let response = router.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.into_body().into_data_stream()
+}
+
+fn get(uri: &str) -> Request<Body> {
+    Request::get(uri).body(Body::empty()).unwrap()
+}
+
+async fn next_message(body: &mut BodyDataStream) -> Option<String> {
+    let chunk = body.next().await?.unwrap();
+    Some(String::from_utf8(chunk.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn test_streams_events_by_name() {
+    let bus = EventBus::new(EventHandlerRegistry::new());
+    let router = event_stream(&bus).into_router();
+
+    let mut all = connect(router.clone(), get("/events")).await;
+    let mut shipped = connect(router, get("/events?events=orders.shipped.v1")).await;
+    assert_eq!(bus.subscriber_count::<OrderShippedEvent>(), 2);
+    assert_eq!(bus.subscriber_count::<OrderPlacedEvent>(), 1);
+
+    bus.dispatch(placed(7, "c1")).await.unwrap();
+    bus.dispatch(OrderShippedEvent { order_id: 7 })
+        .await
+        .unwrap();
+
+    assert_eq!(
+        next_message(&mut all).await.unwrap(),
+        "event: orders.placed.v1\ndata: {\"customer_id\":\"c1\",\"order_id\":7}\n\n"
+    );
+    assert_eq!(
+        next_message(&mut all).await.unwrap(),
+        "event: orders.shipped.v1\ndata: {\"order_id\":7}\n\n"
+    );
+    assert_eq!(
+        next_message(&mut shipped).await.unwrap(),
+        "event: orders.shipped.v1\ndata: {\"order_id\":7}\n\n"
+    );
+
+    // Closing the connection drops its subscriptions
+    drop(all);
+    assert_eq!(bus.subscriber_count::<OrderPlacedEvent>(), 0);
+}
+
+#[tokio::test]
+async fn test_unknown_event_name_is_rejected() {
+    let bus = EventBus::new(EventHandlerRegistry::new());
+    let response = event_stream(&bus)
+        .into_router()
+        .oneshot(get("/events?events=orders.placed.v1,orders.lost.v1"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(
+        bytes,
+        r#"{"error":"unknown_event","name":"orders.lost.v1"}"#.as_bytes()
+    );
+    assert_eq!(bus.subscriber_count::<OrderPlacedEvent>(), 0);
+}
+
+#[tokio::test]
+async fn test_filter_by_principal() {
+    let bus = EventBus::new(EventHandlerRegistry::new());
+    let router = event_stream(&bus)
+        .with_filter(|principal, _name, data| {
+            principal
+                .and_then(|principal| principal.id())
+                .is_some_and(|id| data["customer_id"] == id)
+        })
+        .into_router();
+
+    let mut request = get("/events?events=orders.placed.v1");
+    request.extensions_mut().insert(Principal::new("c2"));
+    let mut body = connect(router, request).await;
+
+    bus.dispatch(placed(1, "c1")).await.unwrap();
+    bus.dispatch(placed(2, "c2")).await.unwrap();
+
+    let message = next_message(&mut body).await.unwrap();
+    assert!(message.contains(r#""order_id":2"#), "{message}");
+}
+
+#[tokio::test]
+async fn test_lagging_client() {
+    let bus = EventBus::new(EventHandlerRegistry::new()).with_subscriber_capacity(1);
+    let mut resuming = connect(event_stream(&bus).into_router(), get("/events")).await;
+    let mut disconnected = connect(
+        event_stream(&bus).with_disconnect_on_lag().into_router(),
+        get("/events"),
+    )
+    .await;
+
+    bus.dispatch(OrderShippedEvent { order_id: 1 })
+        .await
+        .unwrap();
+    bus.dispatch(OrderShippedEvent { order_id: 2 })
+        .await
+        .unwrap();
+
+    let lagged = "event: lagged\ndata: {\"skipped\":1}\n\n";
+    assert_eq!(next_message(&mut resuming).await.unwrap(), lagged);
+    assert_eq!(
+        next_message(&mut resuming).await.unwrap(),
+        "event: orders.shipped.v1\ndata: {\"order_id\":2}\n\n"
+    );
+    assert_eq!(next_message(&mut disconnected).await.unwrap(), lagged);
+    assert_eq!(next_message(&mut disconnected).await, None);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_keep_alive_on_idle_connections() {
+    let bus = EventBus::new(EventHandlerRegistry::new());
+    let router = event_stream(&bus)
+        .with_keep_alive(std::time::Duration::from_secs(15))
+        .into_router();
+    let mut body = connect(router, get("/events")).await;
+
+    // The paused clock advances to the keep-alive once the connection is idle
+    assert_eq!(next_message(&mut body).await.unwrap(), ":\n\n");
+}
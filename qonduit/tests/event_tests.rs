@@ -89,7 +89,7 @@ async fn test_event_registry_single_registration() {
     assert!(!handler.is_empty());
 
     // The handler should work correctly
-    let result = handler.get(0).unwrap().handle(Event1()).await;
+    let result = handler.first().unwrap().handle(Event1()).await;
     assert!(result.is_ok());
 }
 
@@ -107,12 +107,12 @@ async fn test_event_registry_multiple_registrations() {
 
     // Test Event1 handler
     let handlers1 = registry.get_handlers::<Event1>();
-    let result1 = handlers1.get(0).unwrap().handle(Event1()).await;
+    let result1 = handlers1.first().unwrap().handle(Event1()).await;
     assert!(result1.is_ok());
 
     // Test Event2 handler
     let handlers2 = registry.get_handlers::<Event2>();
-    let result2 = handlers2.get(0).unwrap().handle(Event2()).await;
+    let result2 = handlers2.first().unwrap().handle(Event2()).await;
     assert!(result2.is_ok());
 }
 
@@ -0,0 +1,245 @@
+#![cfg(feature = "axum")]
+
+use axum::Router;
+use axum::body::Body;
+use axum::http::{Request, StatusCode, header};
+use qonduit::async_trait;
+use qonduit::authorization::{Principal, has_role};
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::gateway::{Gateway, HttpStatus};
+use qonduit::message::MessageName;
+use qonduit::query::{Query, QueryBus, QueryHandler};
+use qonduit::rate_limit::RateLimitConfig;
+use qonduit::registry::{CommandHandlerRegistry, QueryHandlerRegistry};
+use qonduit::validation::{Validate, Violation, Violations};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::time::Duration;
+use tower::ServiceExt;
+
+#[derive(Debug, Serialize)]
+enum OrderError {
+    OutOfStock { sku: String },
+}
+
+impl HttpStatus for OrderError {
+    fn status(&self) -> StatusCode {
+        StatusCode::CONFLICT
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaceOrderCommand {
+    sku: String,
+    quantity: u32,
+}
+
+impl Command for PlaceOrderCommand {
+    type Response = u64;
+    type Error = OrderError;
+}
+
+impl MessageName for PlaceOrderCommand {
+    const NAME: &'static str = "orders.place.v1";
+}
+
+impl Validate for PlaceOrderCommand {
+    fn validate(&self) -> Result<(), Violations> {
+        match self.quantity {
+            0 => Err(Violations::from(vec![Violation::new(
+                "quantity",
+                "not_positive",
+                "must be positive",
+            )])),
+            _ => Ok(()),
+        }
+    }
+}
+
+struct PlaceOrderCommandHandler;
+
+#[async_trait]
+impl CommandHandler<PlaceOrderCommand> for PlaceOrderCommandHandler {
+    async fn handle(&self, command: PlaceOrderCommand) -> Result<u64, OrderError> {
+        match command.sku.as_str() {
+            "sold-out" => Err(OrderError::OutOfStock { sku: command.sku }),
+            _ => Ok(42),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FindOrderQuery {
+    order_id: u64,
+}
+
+impl Query for FindOrderQuery {
+    type Response = Value;
+    type Error = OrderError;
+}
+
+impl MessageName for FindOrderQuery {
+    const NAME: &'static str = "orders.find.v1";
+}
+
+struct FindOrderQueryHandler;
+
+#[async_trait]
+impl QueryHandler<FindOrderQuery> for FindOrderQueryHandler {
+    async fn handle(&self, query: FindOrderQuery) -> Result<Value, OrderError> {
+        Ok(json!({ "order_id": query.order_id, "status": "placed" }))
+    }
+}
+
+fn gateway(command_bus: impl FnOnce(CommandBus) -> CommandBus) -> Router {
+    let mut commands = CommandHandlerRegistry::new();
+    commands.register_named::<PlaceOrderCommand>(PlaceOrderCommandHandler);
+    let mut queries = QueryHandlerRegistry::new();
+    queries.register_named::<FindOrderQuery>(FindOrderQueryHandler);
+
+    let command_bus = command_bus(CommandBus::new(commands).with_validation::<PlaceOrderCommand>());
+    Gateway::new(command_bus, QueryBus::new(queries))
+        .command::<PlaceOrderCommand>()
+        .query::<FindOrderQuery>()
+        .into_router()
+}
+
+fn post(uri: &str, body: Value) -> Request<Body> {
+    Request::post(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+// Sends `request` and returns the status and JSON body of the response
+async fn send(router: &Router, request: Request<Body>) -> (StatusCode, Value) {
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+    )
+}
+
+#[tokio::test]
+async fn test_command_route() {
+    let router = gateway(|bus| bus);
+
+    let (status, body) = send(
+        &router,
+        post(
+            "/commands/orders.place.v1",
+            json!({ "sku": "kb-01", "quantity": 1 }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!(42));
+
+    let (status, body) = send(
+        &router,
+        post(
+            "/commands/orders.place.v1",
+            json!({ "sku": "sold-out", "quantity": 1 }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(
+        body,
+        json!({ "error": "handler_error", "details": { "OutOfStock": { "sku": "sold-out" } } })
+    );
+}
+
+#[tokio::test]
+async fn test_query_routes() {
+    let router = gateway(|bus| bus);
+    let expected = json!({ "order_id": 7, "status": "placed" });
+
+    let request = Request::get("/queries/orders.find.v1?order_id=7")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(
+        send(&router, request).await,
+        (StatusCode::OK, expected.clone())
+    );
+
+    let request = post("/queries/orders.find.v1", json!({ "order_id": 7 }));
+    assert_eq!(send(&router, request).await, (StatusCode::OK, expected));
+}
+
+#[tokio::test]
+async fn test_rejected_requests() {
+    let router = gateway(|bus| bus);
+
+    let (status, body) = send(
+        &router,
+        post("/commands/orders.place.v1", json!({ "sku": "kb-01" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "invalid_payload");
+
+    let (status, body) = send(
+        &router,
+        post(
+            "/commands/orders.place.v1",
+            json!({ "sku": "kb-01", "quantity": 0 }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(body["error"], "invalid");
+    assert_eq!(body["violations"][0]["code"], "not_positive");
+
+    let (status, body) = send(&router, post("/commands/orders.cancel.v1", json!({}))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        body,
+        json!({ "error": "unknown_message", "name": "orders.cancel.v1" })
+    );
+}
+
+#[test]
+#[should_panic(expected = "No handler registered for query: \"orders.find.v1\"")]
+fn test_routes_require_a_handler() {
+    let command_bus = CommandBus::new(CommandHandlerRegistry::new());
+    let query_bus = QueryBus::new(QueryHandlerRegistry::new());
+    let _ = Gateway::new(command_bus, query_bus).query::<FindOrderQuery>();
+}
+
+#[tokio::test]
+async fn test_principal_from_extensions() {
+    let router = gateway(|bus| bus.with_policy::<PlaceOrderCommand>(has_role("customer")));
+    let payload = json!({ "sku": "kb-01", "quantity": 1 });
+
+    let (status, body) = send(&router, post("/commands/orders.place.v1", payload.clone())).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"], "forbidden");
+
+    let mut request = post("/commands/orders.place.v1", payload);
+    request
+        .extensions_mut()
+        .insert(Principal::new("u1").with_role("customer"));
+    assert_eq!(send(&router, request).await, (StatusCode::OK, json!(42)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_rate_limited_sets_retry_after() {
+    let router = gateway(|bus| {
+        bus.with_rate_limit::<PlaceOrderCommand>(RateLimitConfig::new(1, Duration::from_secs(10)))
+    });
+    let payload = json!({ "sku": "kb-01", "quantity": 1 });
+
+    send(&router, post("/commands/orders.place.v1", payload.clone())).await;
+    let response = router
+        .clone()
+        .oneshot(post("/commands/orders.place.v1", payload))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.headers()[header::RETRY_AFTER], "10");
+}
@@ -0,0 +1,299 @@
+#![cfg(feature = "async-graphql")]
+
+use async_graphql::{Context, Object, Request, Schema, SimpleObject, Subscription, value};
+use futures_util::{Stream, StreamExt};
+use qonduit::async_trait;
+use qonduit::authorization::{Principal, has_role};
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::event::{Event, EventBus};
+use qonduit::graphql::{DynamicSchema, dispatch_command, dispatch_query, subscribe};
+use qonduit::query::{Query, QueryBus, QueryHandler};
+use qonduit::registry::{CommandHandlerRegistry, EventHandlerRegistry, QueryHandlerRegistry};
+use qonduit::validation::{Validate, Violation, Violations};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result as FormatterResult};
+
+#[derive(Debug)]
+struct OutOfStock;
+
+impl Display for OutOfStock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatterResult {
+        write!(f, "out of stock")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaceOrderCommand {
+    sku: String,
+    quantity: u32,
+}
+
+impl Command for PlaceOrderCommand {
+    type Response = u64;
+    type Error = OutOfStock;
+}
+
+impl Validate for PlaceOrderCommand {
+    fn validate(&self) -> Result<(), Violations> {
+        match self.quantity {
+            0 => Err(Violations::from(vec![Violation::new(
+                "quantity",
+                "not_positive",
+                "must be positive",
+            )])),
+            _ => Ok(()),
+        }
+    }
+}
+
+struct PlaceOrderCommandHandler;
+
+#[async_trait]
+impl CommandHandler<PlaceOrderCommand> for PlaceOrderCommandHandler {
+    async fn handle(&self, command: PlaceOrderCommand) -> Result<u64, OutOfStock> {
+        match command.sku.as_str() {
+            "sold-out" => Err(OutOfStock),
+            _ => Ok(42),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct FindOrderQuery {
+    order_id: u64,
+}
+
+impl Query for FindOrderQuery {
+    type Response = Option<Order>;
+    type Error = OutOfStock;
+}
+
+#[derive(Clone, Debug, Serialize, SimpleObject)]
+struct Order {
+    order_id: u64,
+    status: String,
+}
+
+struct FindOrderQueryHandler;
+
+#[async_trait]
+impl QueryHandler<FindOrderQuery> for FindOrderQueryHandler {
+    async fn handle(&self, query: FindOrderQuery) -> Result<Option<Order>, OutOfStock> {
+        Ok(Some(Order {
+            order_id: query.order_id,
+            status: "placed".to_string(),
+        }))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, SimpleObject)]
+struct OrderShippedEvent {
+    order_id: u64,
+}
+
+impl Event for OrderShippedEvent {}
+
+struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn order(
+        &self,
+        ctx: &Context<'_>,
+        order_id: u64,
+    ) -> async_graphql::Result<Option<Order>> {
+        dispatch_query(ctx, FindOrderQuery { order_id }).await
+    }
+}
+
+struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    async fn place_order(
+        &self,
+        ctx: &Context<'_>,
+        sku: String,
+        quantity: u32,
+    ) -> async_graphql::Result<u64> {
+        dispatch_command(ctx, PlaceOrderCommand { sku, quantity }).await
+    }
+}
+
+struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    async fn order_shipped(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<impl Stream<Item = async_graphql::Result<OrderShippedEvent>>> {
+        subscribe::<OrderShippedEvent>(ctx)
+    }
+}
+
+type OrderSchema = Schema<QueryRoot, MutationRoot, SubscriptionRoot>;
+
+fn schema(command_bus: CommandBus, event_bus: &EventBus) -> OrderSchema {
+    let mut queries = QueryHandlerRegistry::new();
+    queries.register::<FindOrderQuery>(FindOrderQueryHandler);
+
+    Schema::build(QueryRoot, MutationRoot, SubscriptionRoot)
+        .data(command_bus.with_validation::<PlaceOrderCommand>())
+        .data(QueryBus::new(queries))
+        .data(event_bus.clone())
+        .finish()
+}
+
+fn command_bus() -> CommandBus {
+    let mut commands = CommandHandlerRegistry::new();
+    commands.register::<PlaceOrderCommand>(PlaceOrderCommandHandler);
+    CommandBus::new(commands)
+}
+
+#[tokio::test]
+async fn test_query_and_mutation() {
+    let schema = schema(command_bus(), &EventBus::new(EventHandlerRegistry::new()));
+
+    let response = schema
+        .execute("{ order(orderId: 7) { orderId status } }")
+        .await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data,
+        value!({ "order": { "orderId": 7, "status": "placed" } })
+    );
+
+    let response = schema
+        .execute(r#"mutation { placeOrder(sku: "kb-01", quantity: 1) }"#)
+        .await;
+    assert_eq!(response.data, value!({ "placeOrder": 42 }));
+}
+
+#[tokio::test]
+async fn test_dispatch_errors_are_mapped() {
+    let schema = schema(command_bus(), &EventBus::new(EventHandlerRegistry::new()));
+
+    let response = schema
+        .execute(r#"mutation { placeOrder(sku: "sold-out", quantity: 1) }"#)
+        .await;
+    let error = &response.errors[0];
+    assert_eq!(error.message, "out of stock");
+    assert_eq!(
+        error.extensions.as_ref().unwrap().get("code"),
+        Some(&value!("handler_error"))
+    );
+
+    let response = schema
+        .execute(r#"mutation { placeOrder(sku: "kb-01", quantity: 0) }"#)
+        .await;
+    let extensions = response.errors[0].extensions.as_ref().unwrap();
+    assert_eq!(extensions.get("code"), Some(&value!("invalid")));
+    assert_eq!(
+        extensions.get("violations"),
+        Some(
+            &value!([{ "field": "quantity", "code": "not_positive", "message": "must be positive" }])
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_principal_from_request_data() {
+    let schema = schema(
+        command_bus().with_policy::<PlaceOrderCommand>(has_role("customer")),
+        &EventBus::new(EventHandlerRegistry::new()),
+    );
+    let mutation = r#"mutation { placeOrder(sku: "kb-01", quantity: 1) }"#;
+
+    let response = schema.execute(mutation).await;
+    let extensions = response.errors[0].extensions.as_ref().unwrap();
+    assert_eq!(extensions.get("code"), Some(&value!("forbidden")));
+
+    let request = Request::new(mutation).data(Principal::new("u1").with_role("customer"));
+    let response = schema.execute(request).await;
+    assert_eq!(response.data, value!({ "placeOrder": 42 }));
+}
+
+#[tokio::test]
+async fn test_subscription() {
+    let event_bus = EventBus::new(EventHandlerRegistry::new());
+    let schema = schema(command_bus(), &event_bus);
+
+    let mut stream = schema.execute_stream("subscription { orderShipped { orderId } }");
+    // The subscription starts when the stream is first polled
+    let next = tokio::spawn(async move { stream.next().await.unwrap() });
+    while event_bus.subscriber_count::<OrderShippedEvent>() == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    event_bus
+        .dispatch(OrderShippedEvent { order_id: 7 })
+        .await
+        .unwrap();
+    let response = next.await.unwrap();
+    assert_eq!(response.data, value!({ "orderShipped": { "orderId": 7 } }));
+}
+
+fn dynamic_schema(event_bus: &EventBus) -> async_graphql::dynamic::Schema {
+    let mut queries = QueryHandlerRegistry::new();
+    queries.register::<FindOrderQuery>(FindOrderQueryHandler);
+
+    DynamicSchema::new()
+        .query::<FindOrderQuery>("order")
+        .command::<PlaceOrderCommand>("placeOrder")
+        .event::<OrderShippedEvent>("orderShipped")
+        .into_builder()
+        .data(command_bus().with_validation::<PlaceOrderCommand>())
+        .data(QueryBus::new(queries))
+        .data(event_bus.clone())
+        .finish()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_dynamic_schema_query_and_mutation() {
+    let schema = dynamic_schema(&EventBus::new(EventHandlerRegistry::new()));
+
+    let response = schema.execute("{ order(input: { order_id: 3 }) }").await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data,
+        value!({ "order": { "order_id": 3, "status": "placed" } })
+    );
+
+    let response = schema
+        .execute(r#"mutation { placeOrder(input: { sku: "kb-1", quantity: 2 }) }"#)
+        .await;
+    assert_eq!(response.data, value!({ "placeOrder": 42 }));
+
+    let response = schema
+        .execute(r#"mutation { placeOrder(input: { sku: "kb-1", quantity: 0 }) }"#)
+        .await;
+    assert_eq!(response.errors.len(), 1);
+    assert_eq!(
+        response.errors[0].extensions.as_ref().unwrap().get("code"),
+        Some(&value!("invalid"))
+    );
+
+    let response = schema.execute("mutation { placeOrder }").await;
+    assert_eq!(response.errors.len(), 1);
+}
+
+#[tokio::test]
+async fn test_dynamic_schema_subscription() {
+    let event_bus = EventBus::new(EventHandlerRegistry::new());
+    let schema = dynamic_schema(&event_bus);
+
+    let mut stream = schema.execute_stream("subscription { orderShipped }");
+    let next = tokio::spawn(async move { stream.next().await.unwrap() });
+    while event_bus.subscriber_count::<OrderShippedEvent>() == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    event_bus
+        .dispatch(OrderShippedEvent { order_id: 9 })
+        .await
+        .unwrap();
+    let response = next.await.unwrap();
+    assert_eq!(response.data, value!({ "orderShipped": { "order_id": 9 } }));
+}
@@ -0,0 +1,233 @@
+#![cfg(feature = "tonic")]
+
+use qonduit::async_trait;
+use qonduit::authorization::{Forbidden, Principal, has_role};
+use qonduit::codec::{Codec, JsonCodec};
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::error::DispatchError;
+use qonduit::grpc::{Envelope, GrpcCode, GrpcCommandBus, GrpcGateway, GrpcQueryBus, Reply};
+use qonduit::message::MessageName;
+use qonduit::query::{Query, QueryBus, QueryHandler};
+use qonduit::rate_limit::RateLimitConfig;
+use qonduit::registry::{CommandHandlerRegistry, QueryHandlerRegistry};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tonic::Code;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::{Channel, Server};
+use tonic_prost::ProstCodec;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum OrderError {
+    OutOfStock { sku: String },
+}
+
+impl GrpcCode for OrderError {
+    fn code(&self) -> Code {
+        Code::FailedPrecondition
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PlaceOrderCommand {
+    sku: String,
+}
+
+impl Command for PlaceOrderCommand {
+    type Response = u64;
+    type Error = OrderError;
+}
+
+impl MessageName for PlaceOrderCommand {
+    const NAME: &'static str = "orders.place.v1";
+}
+
+struct PlaceOrderCommandHandler;
+
+#[async_trait]
+impl CommandHandler<PlaceOrderCommand> for PlaceOrderCommandHandler {
+    async fn handle(&self, command: PlaceOrderCommand) -> Result<u64, OrderError> {
+        match command.sku.as_str() {
+            "sold-out" => Err(OrderError::OutOfStock { sku: command.sku }),
+            _ => Ok(42),
+        }
+    }
+}
+
+// Command the gateway does not expose
+#[derive(Debug, Serialize, Deserialize)]
+struct CancelOrderCommand;
+
+impl Command for CancelOrderCommand {
+    type Response = ();
+    type Error = OrderError;
+}
+
+impl MessageName for CancelOrderCommand {
+    const NAME: &'static str = "orders.cancel.v1";
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CountOrdersQuery {
+    sku: String,
+}
+
+impl Query for CountOrdersQuery {
+    type Response = Vec<u64>;
+    type Error = OrderError;
+}
+
+impl MessageName for CountOrdersQuery {
+    const NAME: &'static str = "orders.count.v1";
+}
+
+struct CountOrdersQueryHandler;
+
+#[async_trait]
+impl QueryHandler<CountOrdersQuery> for CountOrdersQueryHandler {
+    async fn handle(&self, query: CountOrdersQuery) -> Result<Vec<u64>, OrderError> {
+        Ok(vec![query.sku.len() as u64])
+    }
+}
+
+// Serves a gateway on a loopback port, authenticating callers with a `role` metadata entry,
+// and returns a channel connected to it
+async fn serve(command_bus: CommandBus) -> Channel {
+    let mut queries = QueryHandlerRegistry::new();
+    queries.register_named::<CountOrdersQuery>(CountOrdersQueryHandler);
+    let gateway = GrpcGateway::new(command_bus, QueryBus::new(queries), JsonCodec)
+        .command::<PlaceOrderCommand>()
+        .query::<CountOrdersQuery>();
+    let service = InterceptedService::new(gateway, |mut request: tonic::Request<()>| {
+        if let Some(role) = request.metadata().get("role") {
+            let principal = Principal::new("u1").with_role(role.to_str().unwrap());
+            request.extensions_mut().insert(principal);
+        }
+        Ok(request)
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address: SocketAddr = listener.local_addr().unwrap();
+    tokio::spawn(
+        Server::builder()
+            .add_service(service)
+            .serve_with_incoming(TcpIncoming::from(listener)),
+    );
+    Channel::from_shared(format!("http://{address}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap()
+}
+
+fn command_bus() -> CommandBus {
+    let mut commands = CommandHandlerRegistry::new();
+    commands.register_named::<PlaceOrderCommand>(PlaceOrderCommandHandler);
+    CommandBus::new(commands)
+}
+
+fn place_order(sku: &str) -> PlaceOrderCommand {
+    PlaceOrderCommand {
+        sku: sku.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_dispatch_over_loopback() {
+    let channel = serve(command_bus()).await;
+    let commands = GrpcCommandBus::new(channel.clone(), JsonCodec);
+    let queries = GrpcQueryBus::new(channel, JsonCodec);
+
+    assert_eq!(commands.dispatch(place_order("kb-01")).await, Ok(42));
+    assert_eq!(
+        commands.dispatch(place_order("sold-out")).await,
+        Err(DispatchError::Handler(OrderError::OutOfStock {
+            sku: "sold-out".to_string()
+        }))
+    );
+    assert_eq!(
+        queries
+            .dispatch(CountOrdersQuery {
+                sku: "kb-01".to_string()
+            })
+            .await,
+        Ok(vec![5])
+    );
+}
+
+#[tokio::test]
+async fn test_bus_failures_are_decoded() {
+    let channel = serve(
+        command_bus()
+            .with_rate_limit::<PlaceOrderCommand>(RateLimitConfig::new(1, Duration::from_secs(60))),
+    )
+    .await;
+    let commands = GrpcCommandBus::new(channel, JsonCodec);
+
+    assert_eq!(commands.dispatch(place_order("kb-01")).await, Ok(42));
+    let error = commands.dispatch(place_order("kb-01")).await.unwrap_err();
+    assert!(
+        matches!(error, DispatchError::RateLimited { .. }),
+        "{error:?}"
+    );
+    assert_eq!(error.code(), Code::ResourceExhausted);
+}
+
+#[tokio::test]
+async fn test_principal_from_interceptor() {
+    let channel = serve(command_bus().with_policy::<PlaceOrderCommand>(has_role("customer"))).await;
+    assert_eq!(
+        GrpcCommandBus::new(channel.clone(), JsonCodec)
+            .dispatch(place_order("kb-01"))
+            .await,
+        Err(DispatchError::Forbidden(Forbidden::Denied))
+    );
+
+    let mut client = tonic::client::Grpc::new(channel);
+    client.ready().await.unwrap();
+    let envelope = Envelope {
+        name: PlaceOrderCommand::NAME.to_string(),
+        payload: JsonCodec.encode(&place_order("kb-01")).unwrap(),
+    };
+    let mut request = tonic::Request::new(envelope);
+    request
+        .metadata_mut()
+        .insert("role", "customer".parse().unwrap());
+    let reply = client
+        .unary(
+            request,
+            "/qonduit.v1.Gateway/Command".parse().unwrap(),
+            ProstCodec::<Envelope, Reply>::default(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(reply.into_inner().payload, b"42");
+}
+
+#[tokio::test]
+async fn test_transport_errors() {
+    let channel = serve(command_bus()).await;
+    let commands = GrpcCommandBus::new(channel, JsonCodec);
+
+    let error = commands.dispatch(CancelOrderCommand).await.unwrap_err();
+    let DispatchError::Transport(reason) = error else {
+        panic!("unexpected error {error:?}");
+    };
+    assert!(reason.contains("orders.cancel.v1"), "{reason}");
+
+    // Nothing listens on the port of a dropped listener
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    drop(listener);
+    let channel = Channel::from_shared(format!("http://{address}"))
+        .unwrap()
+        .connect_lazy();
+    let error = GrpcCommandBus::new(channel, JsonCodec)
+        .dispatch(place_order("kb-01"))
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), "transport");
+}
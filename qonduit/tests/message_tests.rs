@@ -0,0 +1,142 @@
+use qonduit::async_trait;
+use qonduit::command::{Command, CommandHandler};
+use qonduit::event::{Event, EventHandler};
+use qonduit::message::MessageName;
+use qonduit::registry::{CommandHandlerRegistry, EventHandlerRegistry};
+use std::error::Error;
+
+// Command with a stable name
+#[derive(Debug)]
+struct PlaceOrderCommand;
+
+impl Command for PlaceOrderCommand {
+    type Response = ();
+    type Error = ();
+}
+
+impl MessageName for PlaceOrderCommand {
+    const NAME: &'static str = "orders.place.v2";
+}
+
+// Command reusing the name of another one by mistake
+#[derive(Debug)]
+struct SubmitOrderCommand;
+
+impl Command for SubmitOrderCommand {
+    type Response = ();
+    type Error = ();
+}
+
+impl MessageName for SubmitOrderCommand {
+    const NAME: &'static str = "orders.place.v2";
+}
+
+// Command without a stable name
+#[derive(Debug)]
+struct CancelOrderCommand;
+
+impl Command for CancelOrderCommand {
+    type Response = ();
+    type Error = ();
+}
+
+struct OrderCommandHandler;
+
+#[async_trait]
+impl CommandHandler<PlaceOrderCommand> for OrderCommandHandler {
+    async fn handle(&self, _command: PlaceOrderCommand) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CommandHandler<SubmitOrderCommand> for OrderCommandHandler {
+    async fn handle(&self, _command: SubmitOrderCommand) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CommandHandler<CancelOrderCommand> for OrderCommandHandler {
+    async fn handle(&self, _command: CancelOrderCommand) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_message_name_falls_back_to_type_name() {
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register_named::<PlaceOrderCommand>(OrderCommandHandler);
+    registry.register::<CancelOrderCommand>(OrderCommandHandler);
+
+    assert_eq!(
+        registry.message_name::<PlaceOrderCommand>(),
+        "orders.place.v2"
+    );
+    assert_eq!(
+        registry.message_name::<CancelOrderCommand>(),
+        std::any::type_name::<CancelOrderCommand>()
+    );
+    assert_eq!(
+        registry.message_names().collect::<Vec<_>>(),
+        ["orders.place.v2"]
+    );
+}
+
+#[test]
+#[should_panic(
+    expected = "Message name \"orders.place.v2\" of message_tests::SubmitOrderCommand \
+                           is already used by message_tests::PlaceOrderCommand"
+)]
+fn test_duplicate_name_panics() {
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register_named::<PlaceOrderCommand>(OrderCommandHandler);
+    registry.register_named::<SubmitOrderCommand>(OrderCommandHandler);
+}
+
+// Event with a stable name, handled twice
+#[derive(Clone, Debug)]
+struct OrderPlacedEvent;
+
+impl Event for OrderPlacedEvent {}
+
+impl MessageName for OrderPlacedEvent {
+    const NAME: &'static str = "orders.placed.v1";
+}
+
+struct OrderPlacedEventHandler;
+
+#[async_trait]
+impl EventHandler<OrderPlacedEvent> for OrderPlacedEventHandler {
+    async fn handle(&self, _event: OrderPlacedEvent) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_event_name_is_shared_by_its_handlers() {
+    let mut registry = EventHandlerRegistry::new();
+    registry.register_named::<OrderPlacedEvent>(OrderPlacedEventHandler);
+    registry.register_named::<OrderPlacedEvent>(OrderPlacedEventHandler);
+
+    assert_eq!(registry.get_handlers::<OrderPlacedEvent>().len(), 2);
+    assert_eq!(
+        registry.message_name::<OrderPlacedEvent>(),
+        "orders.placed.v1"
+    );
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn test_derive_message_name() {
+    #[derive(Debug, MessageName)]
+    #[message(name = "orders.ship")]
+    struct ShipOrderCommand;
+
+    #[derive(Debug, MessageName)]
+    #[message(name = "orders.ship", version = 3)]
+    struct ShipOrderCommandV3<T>(T);
+
+    assert_eq!(ShipOrderCommand::NAME, "orders.ship");
+    assert_eq!(<ShipOrderCommandV3<u8>>::NAME, "orders.ship.v3");
+}
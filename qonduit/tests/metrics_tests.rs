@@ -0,0 +1,248 @@
+#![cfg(feature = "metrics")]
+
+use metrics::{SharedString, Unit};
+use metrics_util::CompositeKey;
+use metrics_util::debugging::{DebugValue, DebuggingRecorder, Snapshotter};
+use qonduit::async_trait;
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::event::{Event, EventBus};
+use qonduit::message::MessageName;
+use qonduit::metrics::{DISPATCHED, DURATION, FAILED, IN_FLIGHT, MISSING_HANDLER, SUCCEEDED};
+use qonduit::query::{Query, QueryBus, QueryHandler};
+use qonduit::registry::{CommandHandlerRegistry, EventHandlerRegistry, QueryHandlerRegistry};
+use std::time::Duration;
+
+type Metric = (CompositeKey, Option<Unit>, Option<SharedString>, DebugValue);
+
+// Takes a snapshot of the metrics recorded since the previous snapshot
+fn snapshot(snapshotter: &Snapshotter) -> Vec<Metric> {
+    snapshotter.snapshot().into_vec()
+}
+
+// Returns the value of the metric `name` whose labels include `labels`
+fn value<'a>(metrics: &'a [Metric], name: &str, labels: &[(&str, &str)]) -> Option<&'a DebugValue> {
+    metrics
+        .iter()
+        .find(|(key, _, _, _)| {
+            key.key().name() == name
+                && labels.iter().all(|(label, value)| {
+                    key.key()
+                        .labels()
+                        .any(|l| l.key() == *label && l.value() == *value)
+                })
+        })
+        .map(|(_, _, _, value)| value)
+}
+
+// Error type for tests
+#[derive(Debug, PartialEq)]
+struct TestError;
+
+// Command failing when its quantity is zero
+#[derive(Debug)]
+struct ReserveStockCommand {
+    quantity: u32,
+}
+
+impl Command for ReserveStockCommand {
+    type Response = u32;
+    type Error = TestError;
+}
+
+struct ReserveStockCommandHandler;
+
+#[async_trait]
+impl CommandHandler<ReserveStockCommand> for ReserveStockCommandHandler {
+    async fn handle(&self, command: ReserveStockCommand) -> Result<u32, TestError> {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        match command.quantity {
+            0 => Err(TestError),
+            quantity => Ok(quantity),
+        }
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_command_metrics() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    let _guard = metrics::set_default_local_recorder(&recorder);
+
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register::<ReserveStockCommand>(ReserveStockCommandHandler);
+    let bus = CommandBus::new(registry).with_timeout::<ReserveStockCommand>(Duration::from_secs(1));
+
+    bus.dispatch(ReserveStockCommand { quantity: 2 })
+        .await
+        .unwrap();
+    bus.dispatch(ReserveStockCommand { quantity: 0 })
+        .await
+        .unwrap_err();
+
+    let labels = [
+        ("bus", "command"),
+        ("message_type", std::any::type_name::<ReserveStockCommand>()),
+    ];
+    let metrics = snapshot(&snapshotter);
+    assert_eq!(
+        value(&metrics, DISPATCHED, &labels),
+        Some(&DebugValue::Counter(2))
+    );
+    assert_eq!(
+        value(&metrics, SUCCEEDED, &labels),
+        Some(&DebugValue::Counter(1))
+    );
+    assert_eq!(
+        value(
+            &metrics,
+            FAILED,
+            &[labels[0], labels[1], ("reason", "handler_error")]
+        ),
+        Some(&DebugValue::Counter(1))
+    );
+    let Some(DebugValue::Histogram(durations)) = value(&metrics, DURATION, &labels) else {
+        panic!("Expected a duration histogram");
+    };
+    assert_eq!(
+        durations.iter().map(|d| d.into_inner()).collect::<Vec<_>>(),
+        vec![0.25, 0.25]
+    );
+    assert_eq!(
+        value(&metrics, IN_FLIGHT, &labels),
+        Some(&DebugValue::Gauge(0.0.into()))
+    );
+}
+
+// Query that never completes on its own
+#[derive(Debug)]
+struct ExportCatalogQuery;
+
+impl Query for ExportCatalogQuery {
+    type Response = ();
+    type Error = TestError;
+}
+
+struct ExportCatalogQueryHandler;
+
+#[async_trait]
+impl QueryHandler<ExportCatalogQuery> for ExportCatalogQueryHandler {
+    async fn handle(&self, _query: ExportCatalogQuery) -> Result<(), TestError> {
+        std::future::pending().await
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_in_flight_gauge_and_failure_reason() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    let _guard = metrics::set_default_local_recorder(&recorder);
+
+    let mut registry = QueryHandlerRegistry::new();
+    registry.register::<ExportCatalogQuery>(ExportCatalogQueryHandler);
+    let bus = QueryBus::new(registry).with_timeout::<ExportCatalogQuery>(Duration::from_secs(5));
+
+    let labels = [
+        ("bus", "query"),
+        ("message_type", std::any::type_name::<ExportCatalogQuery>()),
+    ];
+    let dispatch = bus.dispatch(ExportCatalogQuery);
+    tokio::pin!(dispatch);
+    tokio::select! {
+        _ = &mut dispatch => panic!("Expected the query to still be running"),
+        _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+    }
+    let metrics = snapshot(&snapshotter);
+    assert_eq!(
+        value(&metrics, IN_FLIGHT, &labels),
+        Some(&DebugValue::Gauge(1.0.into()))
+    );
+    assert_eq!(value(&metrics, FAILED, &labels), None);
+
+    assert!(dispatch.await.unwrap_err().is_timeout());
+    let metrics = snapshot(&snapshotter);
+    assert_eq!(
+        value(
+            &metrics,
+            FAILED,
+            &[labels[0], labels[1], ("reason", "timeout")]
+        ),
+        Some(&DebugValue::Counter(1))
+    );
+}
+
+// Event without any handler
+#[derive(Clone, Debug)]
+struct CatalogExportedEvent;
+
+impl Event for CatalogExportedEvent {}
+
+#[tokio::test]
+async fn test_missing_handler() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    let _guard = metrics::set_default_local_recorder(&recorder);
+
+    let bus = EventBus::new(EventHandlerRegistry::new());
+    bus.dispatch(CatalogExportedEvent).await.unwrap();
+
+    let labels = [
+        ("bus", "event"),
+        (
+            "message_type",
+            std::any::type_name::<CatalogExportedEvent>(),
+        ),
+    ];
+    let metrics = snapshot(&snapshotter);
+    assert_eq!(
+        value(&metrics, MISSING_HANDLER, &labels),
+        Some(&DebugValue::Counter(1))
+    );
+    assert_eq!(
+        value(&metrics, SUCCEEDED, &labels),
+        Some(&DebugValue::Counter(1))
+    );
+}
+
+// Query with a stable name
+#[derive(Debug)]
+struct CountProductsQuery;
+
+impl Query for CountProductsQuery {
+    type Response = usize;
+    type Error = TestError;
+}
+
+impl MessageName for CountProductsQuery {
+    const NAME: &'static str = "catalog.count_products.v1";
+}
+
+struct CountProductsQueryHandler;
+
+#[async_trait]
+impl QueryHandler<CountProductsQuery> for CountProductsQueryHandler {
+    async fn handle(&self, _query: CountProductsQuery) -> Result<usize, TestError> {
+        Ok(3)
+    }
+}
+
+#[tokio::test]
+async fn test_message_name_label() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    let _guard = metrics::set_default_local_recorder(&recorder);
+
+    let mut registry = QueryHandlerRegistry::new();
+    registry.register_named::<CountProductsQuery>(CountProductsQueryHandler);
+    let bus = QueryBus::new(registry);
+    bus.dispatch(CountProductsQuery).await.unwrap();
+
+    let labels = [
+        ("bus", "query"),
+        ("message_type", "catalog.count_products.v1"),
+    ];
+    let metrics = snapshot(&snapshotter);
+    assert_eq!(
+        value(&metrics, SUCCEEDED, &labels),
+        Some(&DebugValue::Counter(1))
+    );
+}
@@ -0,0 +1,169 @@
+#![cfg(feature = "serde")]
+
+use qonduit::async_trait;
+use qonduit::authorization::{Principal, has_role};
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::error::DispatchError;
+use qonduit::message::MessageName;
+use qonduit::named::NamedDispatchError;
+use qonduit::query::{Query, QueryBus, QueryHandler};
+use qonduit::registry::{CommandHandlerRegistry, QueryHandlerRegistry};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+// Error type serialized back to the caller
+#[derive(Debug, PartialEq, Serialize)]
+enum OrderError {
+    OutOfStock { sku: String },
+}
+
+#[derive(Debug, Deserialize)]
+struct PlaceOrderCommand {
+    sku: String,
+    quantity: u32,
+}
+
+impl Command for PlaceOrderCommand {
+    type Response = OrderPlaced;
+    type Error = OrderError;
+}
+
+impl MessageName for PlaceOrderCommand {
+    const NAME: &'static str = "orders.place";
+}
+
+#[derive(Debug, Serialize)]
+struct OrderPlaced {
+    order_id: u64,
+    quantity: u32,
+}
+
+struct PlaceOrderCommandHandler;
+
+#[async_trait]
+impl CommandHandler<PlaceOrderCommand> for PlaceOrderCommandHandler {
+    async fn handle(&self, command: PlaceOrderCommand) -> Result<OrderPlaced, OrderError> {
+        match command.sku.as_str() {
+            "sold-out" => Err(OrderError::OutOfStock { sku: command.sku }),
+            _ => Ok(OrderPlaced {
+                order_id: 7,
+                quantity: command.quantity,
+            }),
+        }
+    }
+}
+
+fn command_bus() -> CommandBus {
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register_json::<PlaceOrderCommand>(PlaceOrderCommandHandler);
+    CommandBus::new(registry)
+}
+
+#[tokio::test]
+async fn test_dispatch_json_command() {
+    let bus = command_bus();
+
+    let response = bus
+        .dispatch_json("orders.place", json!({ "sku": "kb-01", "quantity": 2 }))
+        .await
+        .unwrap();
+    assert_eq!(response, json!({ "order_id": 7, "quantity": 2 }));
+
+    let error = bus
+        .dispatch_json("orders.place", json!({ "sku": "sold-out", "quantity": 1 }))
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), "handler_error");
+    assert_eq!(
+        error.into_dispatch_error(),
+        Some(DispatchError::Handler(
+            json!({ "OutOfStock": { "sku": "sold-out" } })
+        ))
+    );
+
+    // Typed dispatches keep working for named commands
+    let placed = bus
+        .dispatch(PlaceOrderCommand {
+            sku: "kb-01".to_string(),
+            quantity: 3,
+        })
+        .await
+        .unwrap();
+    assert_eq!(placed.quantity, 3);
+}
+
+#[tokio::test]
+async fn test_unknown_name_and_invalid_payload() {
+    let bus = command_bus();
+
+    let error = bus
+        .dispatch_json("orders.cancel", json!({}))
+        .await
+        .unwrap_err();
+    assert!(matches!(&error, NamedDispatchError::UnknownName(name) if name == "orders.cancel"));
+
+    let error = bus
+        .dispatch_json("orders.place", json!({ "sku": "kb-01" }))
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), "invalid_payload");
+}
+
+#[tokio::test]
+async fn test_dispatch_json_as_applies_policies() {
+    let bus = command_bus().with_policy::<PlaceOrderCommand>(has_role("customer"));
+    let payload = json!({ "sku": "kb-01", "quantity": 1 });
+
+    let error = bus
+        .dispatch_json("orders.place", payload.clone())
+        .await
+        .unwrap_err();
+    assert_eq!(error.kind(), "forbidden");
+
+    let customer = Principal::new("u1").with_role("customer");
+    assert!(
+        bus.dispatch_json_as(customer, "orders.place", payload)
+            .await
+            .is_ok()
+    );
+}
+
+#[derive(Debug, Deserialize)]
+struct CountOrdersQuery {
+    sku: String,
+}
+
+impl Query for CountOrdersQuery {
+    type Response = usize;
+    type Error = ();
+}
+
+impl MessageName for CountOrdersQuery {
+    const NAME: &'static str = "orders.count";
+}
+
+struct CountOrdersQueryHandler;
+
+#[async_trait]
+impl QueryHandler<CountOrdersQuery> for CountOrdersQueryHandler {
+    async fn handle(&self, query: CountOrdersQuery) -> Result<usize, ()> {
+        Ok(query.sku.len())
+    }
+}
+
+#[tokio::test]
+async fn test_dispatch_json_query() {
+    let mut registry = QueryHandlerRegistry::new();
+    registry.register_json::<CountOrdersQuery>(CountOrdersQueryHandler);
+    assert_eq!(
+        registry.message_names().collect::<Vec<_>>(),
+        ["orders.count"]
+    );
+    let bus = QueryBus::new(registry);
+
+    let response = bus
+        .dispatch_json("orders.count", json!({ "sku": "kb-01" }))
+        .await
+        .unwrap();
+    assert_eq!(response, json!(5));
+}
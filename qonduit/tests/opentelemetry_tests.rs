@@ -0,0 +1,160 @@
+#![cfg(feature = "opentelemetry")]
+
+use opentelemetry::trace::{SpanContext, SpanId, Status, TraceContextExt, TraceId};
+use opentelemetry::{Context, KeyValue, global};
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
+use qonduit::async_trait;
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::event::{Event, EventBus, EventHandler};
+use qonduit::registry::{CommandHandlerRegistry, EventHandlerRegistry};
+use std::error::Error;
+use std::sync::{Mutex, OnceLock};
+
+// Installs a global tracer provider exporting to memory, shared by all tests
+fn exporter() -> &'static InMemorySpanExporter {
+    static EXPORTER: OnceLock<InMemorySpanExporter> = OnceLock::new();
+    EXPORTER.get_or_init(|| {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        global::set_tracer_provider(provider);
+        exporter
+    })
+}
+
+// Returns the finished spans of the trace `trace_id`, as tests run concurrently
+fn trace(trace_id: TraceId) -> Vec<SpanData> {
+    exporter()
+        .get_finished_spans()
+        .unwrap()
+        .into_iter()
+        .filter(|span| span.span_context.trace_id() == trace_id)
+        .collect()
+}
+
+fn attribute(span: &SpanData, key: &str) -> Option<String> {
+    span.attributes
+        .iter()
+        .find(|attribute| attribute.key.as_str() == key)
+        .map(|attribute| attribute.value.to_string())
+}
+
+// Error type for tests
+#[derive(Debug, PartialEq)]
+struct TestError;
+
+// Event published by the command handler below
+#[derive(Clone, Debug)]
+struct InvoiceIssuedEvent;
+
+impl Event for InvoiceIssuedEvent {}
+
+// Records the span context visible to the handler
+struct InvoiceIssuedEventHandler {
+    seen: &'static Mutex<Option<SpanContext>>,
+}
+
+#[async_trait]
+impl EventHandler<InvoiceIssuedEvent> for InvoiceIssuedEventHandler {
+    async fn handle(&self, _event: InvoiceIssuedEvent) -> Result<(), Box<dyn Error + Send + Sync>> {
+        *self.seen.lock().unwrap() = Some(Context::current().span().span_context().clone());
+        Ok(())
+    }
+}
+
+// Command whose handler publishes an event in the background
+#[derive(Debug)]
+struct IssueInvoiceCommand;
+
+impl Command for IssueInvoiceCommand {
+    type Response = SpanContext;
+    type Error = TestError;
+}
+
+struct IssueInvoiceCommandHandler {
+    event_bus: EventBus,
+}
+
+#[async_trait]
+impl CommandHandler<IssueInvoiceCommand> for IssueInvoiceCommandHandler {
+    async fn handle(&self, _command: IssueInvoiceCommand) -> Result<SpanContext, TestError> {
+        self.event_bus
+            .spawn_dispatch(InvoiceIssuedEvent)
+            .await
+            .unwrap()
+            .map_err(|_| TestError)?;
+        Ok(Context::current().span().span_context().clone())
+    }
+}
+
+fn command_bus(seen: &'static Mutex<Option<SpanContext>>) -> CommandBus {
+    let mut event_registry = EventHandlerRegistry::new();
+    event_registry.register::<InvoiceIssuedEvent>(InvoiceIssuedEventHandler { seen });
+    let event_bus = EventBus::new(event_registry);
+
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register::<IssueInvoiceCommand>(IssueInvoiceCommandHandler { event_bus });
+    CommandBus::new(registry)
+}
+
+#[tokio::test]
+async fn test_command_span_is_current_in_handler() {
+    exporter();
+    static SEEN: Mutex<Option<SpanContext>> = Mutex::new(None);
+    let bus = command_bus(&SEEN);
+
+    let context = bus.dispatch(IssueInvoiceCommand).await.unwrap();
+
+    let spans = trace(context.trace_id());
+    let command = spans
+        .iter()
+        .find(|span| span.span_context == context)
+        .expect("Expected the command span to be current in the handler");
+    assert!(command.name.starts_with("command "));
+    assert_eq!(command.parent_span_id, SpanId::INVALID);
+    assert_eq!(command.status, Status::Ok);
+    assert_eq!(
+        attribute(command, "qonduit.message_type").as_deref(),
+        Some(std::any::type_name::<IssueInvoiceCommand>())
+    );
+    assert_eq!(
+        attribute(command, "qonduit.handler_type").as_deref(),
+        Some(std::any::type_name::<IssueInvoiceCommandHandler>())
+    );
+    assert!(
+        command
+            .attributes
+            .contains(&KeyValue::new("qonduit.outcome", "ok"))
+    );
+}
+
+#[tokio::test]
+async fn test_background_event_stays_in_command_trace() {
+    exporter();
+    static SEEN: Mutex<Option<SpanContext>> = Mutex::new(None);
+    let bus = command_bus(&SEEN);
+
+    let command_context = bus.dispatch(IssueInvoiceCommand).await.unwrap();
+    let command_spans = trace(command_context.trace_id());
+
+    // The event dispatch span is a child of the command span
+    let event = command_spans
+        .iter()
+        .find(|span| span.name.starts_with("event "))
+        .expect("Expected an event dispatch span in the command trace");
+    assert_eq!(event.parent_span_id, command_context.span_id());
+
+    // The event handler span is a child of the event span, linked to the command span
+    let handler = command_spans
+        .iter()
+        .find(|span| span.name.starts_with("event_handler "))
+        .expect("Expected an event handler span in the command trace");
+    assert_eq!(handler.parent_span_id, event.span_context.span_id());
+    assert_eq!(handler.links.len(), 1);
+    assert_eq!(handler.links[0].span_context, command_context);
+
+    // The handler ran with its own span as the current context
+    let seen = SEEN.lock().unwrap().clone().unwrap();
+    assert_eq!(seen, handler.span_context);
+}
@@ -0,0 +1,362 @@
+#![cfg(feature = "queue")]
+
+use qonduit::async_trait;
+use qonduit::authorization::{Principal, current_principal};
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::message::MessageName;
+use qonduit::queue::{
+    CommandQueue, FileJobStore, InMemoryJobStore, JobFailure, JobStatus, WorkerPool,
+};
+use qonduit::registry::CommandHandlerRegistry;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SendInvoiceCommand {
+    invoice_id: u64,
+}
+
+impl Command for SendInvoiceCommand {
+    type Response = String;
+    type Error = String;
+}
+
+impl MessageName for SendInvoiceCommand {
+    const NAME: &'static str = "invoices.send.v1";
+}
+
+// Counts the attempts, and hangs on the attempts listed in `hanging` as if the worker had crashed
+struct SendInvoiceCommandHandler {
+    attempts: Arc<AtomicUsize>,
+    hanging: Vec<usize>,
+}
+
+#[async_trait]
+impl CommandHandler<SendInvoiceCommand> for SendInvoiceCommandHandler {
+    async fn handle(&self, command: SendInvoiceCommand) -> Result<String, String> {
+        let attempt = self.attempts.fetch_add(1, Ordering::SeqCst) + 1;
+        if self.hanging.contains(&attempt) {
+            std::future::pending::<()>().await;
+        }
+        match command.invoice_id {
+            0 => Err("unknown invoice".to_string()),
+            id => Ok(format!("invoice-{id}")),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WhoAmICommand;
+
+impl Command for WhoAmICommand {
+    type Response = Option<String>;
+    type Error = String;
+}
+
+impl MessageName for WhoAmICommand {
+    const NAME: &'static str = "whoami.v1";
+}
+
+struct WhoAmICommandHandler;
+
+#[async_trait]
+impl CommandHandler<WhoAmICommand> for WhoAmICommandHandler {
+    async fn handle(&self, _: WhoAmICommand) -> Result<Option<String>, String> {
+        Ok(current_principal().and_then(|principal| principal.id().map(str::to_string)))
+    }
+}
+
+fn command_bus(queue: &CommandQueue, hanging: Vec<usize>) -> (CommandBus, Arc<AtomicUsize>) {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register_json::<SendInvoiceCommand>(SendInvoiceCommandHandler {
+        attempts: attempts.clone(),
+        hanging,
+    });
+    registry.register_json::<WhoAmICommand>(WhoAmICommandHandler);
+    (
+        CommandBus::new(registry).with_queue(queue.clone()),
+        attempts,
+    )
+}
+
+fn pool(command_bus: &CommandBus, queue: &CommandQueue) -> WorkerPool {
+    WorkerPool::new(command_bus.clone(), queue.clone())
+        .with_workers(2)
+        .with_visibility_timeout(Duration::from_millis(100))
+        .with_poll_interval(Duration::from_millis(5))
+}
+
+async fn completed(queue: &CommandQueue, id: &str) -> JobStatus {
+    loop {
+        match queue.status(id).await.unwrap() {
+            Some(status) if status.is_complete() => return status,
+            _ => tokio::time::sleep(Duration::from_millis(5)).await,
+        }
+    }
+}
+
+fn directory(name: &str) -> std::path::PathBuf {
+    let directory =
+        std::env::temp_dir().join(format!("qonduit-queue-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    directory
+}
+
+#[tokio::test]
+async fn test_enqueued_commands_are_executed_by_the_workers() {
+    let queue = CommandQueue::new(InMemoryJobStore::new());
+    let (command_bus, _) = command_bus(&queue, Vec::new());
+
+    let sent = command_bus
+        .enqueue(SendInvoiceCommand { invoice_id: 42 })
+        .await
+        .unwrap();
+    let unknown = command_bus
+        .enqueue(SendInvoiceCommand { invoice_id: 0 })
+        .await
+        .unwrap();
+    assert_eq!(queue.status(&sent).await.unwrap(), Some(JobStatus::Queued));
+    assert!(sent < unknown);
+
+    tokio::spawn(pool(&command_bus, &queue).run());
+    assert_eq!(
+        completed(&queue, &sent).await,
+        JobStatus::Succeeded(json!("invoice-42"))
+    );
+    assert_eq!(
+        completed(&queue, &unknown).await,
+        JobStatus::Failed(JobFailure {
+            kind: "handler_error".to_string(),
+            message: "\"unknown invoice\"".to_string(),
+            error: Some(json!("unknown invoice")),
+        })
+    );
+
+    let job = queue.job(&sent).await.unwrap().unwrap();
+    assert_eq!(job.name, "invoices.send.v1");
+    assert_eq!(job.payload, json!({ "invoice_id": 42 }));
+    assert_eq!(job.attempts, 1);
+    queue.remove(&sent).await.unwrap();
+    assert_eq!(queue.status(&sent).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_unacknowledged_jobs_are_claimed_again() {
+    let queue = CommandQueue::new(InMemoryJobStore::new());
+    // The first attempt never completes, as if its process had crashed
+    let (command_bus, attempts) = command_bus(&queue, vec![1]);
+    let workers = tokio::spawn(pool(&command_bus, &queue).run());
+
+    let id = command_bus
+        .enqueue(SendInvoiceCommand { invoice_id: 7 })
+        .await
+        .unwrap();
+    while attempts.load(Ordering::SeqCst) == 0 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert_eq!(queue.status(&id).await.unwrap(), Some(JobStatus::Running));
+    workers.abort();
+
+    // Another pool claims the job once its visibility timeout expires
+    tokio::spawn(pool(&command_bus, &queue).run());
+    assert_eq!(
+        completed(&queue, &id).await,
+        JobStatus::Succeeded(json!("invoice-7"))
+    );
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    assert_eq!(queue.job(&id).await.unwrap().unwrap().attempts, 2);
+}
+
+#[tokio::test]
+async fn test_jobs_fail_after_max_attempts() {
+    let queue = CommandQueue::new(InMemoryJobStore::new());
+    let (command_bus, attempts) = command_bus(&queue, vec![1, 2]);
+    tokio::spawn(
+        pool(&command_bus, &queue)
+            .with_workers(3)
+            .with_max_attempts(2)
+            .run(),
+    );
+
+    let id = command_bus
+        .enqueue(SendInvoiceCommand { invoice_id: 7 })
+        .await
+        .unwrap();
+    let JobStatus::Failed(failure) = completed(&queue, &id).await else {
+        panic!("the job should fail");
+    };
+    assert_eq!(failure.kind, "attempts_exhausted");
+    assert_eq!(
+        failure.message,
+        "the job was claimed 3 times without completing"
+    );
+    assert_eq!(failure.error, None);
+    // The third claim is not dispatched
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    assert_eq!(queue.job(&id).await.unwrap().unwrap().attempts, 3);
+}
+
+#[tokio::test]
+async fn test_transient_failures_are_retried() {
+    let queue = CommandQueue::new(InMemoryJobStore::new());
+    // The first attempt times out, the second one succeeds
+    let (command_bus, attempts) = command_bus(&queue, vec![1]);
+    let command_bus = command_bus.with_timeout::<SendInvoiceCommand>(Duration::from_millis(20));
+    tokio::spawn(
+        pool(&command_bus, &queue)
+            .with_visibility_timeout(Duration::from_secs(60))
+            .with_retry_backoff(Duration::from_millis(10), Duration::from_millis(10))
+            .run(),
+    );
+
+    let id = command_bus
+        .enqueue(SendInvoiceCommand { invoice_id: 5 })
+        .await
+        .unwrap();
+    // The job is retried after its backoff, not after its visibility timeout
+    assert_eq!(
+        completed(&queue, &id).await,
+        JobStatus::Succeeded(json!("invoice-5"))
+    );
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    assert_eq!(queue.job(&id).await.unwrap().unwrap().attempts, 2);
+}
+
+#[tokio::test]
+async fn test_transient_failures_fail_the_last_attempt() {
+    let queue = CommandQueue::new(InMemoryJobStore::new());
+    let (command_bus, attempts) = command_bus(&queue, vec![1, 2]);
+    let command_bus = command_bus.with_timeout::<SendInvoiceCommand>(Duration::from_millis(20));
+    tokio::spawn(
+        pool(&command_bus, &queue)
+            .with_max_attempts(2)
+            .with_retry_backoff(Duration::from_millis(10), Duration::from_millis(10))
+            .run(),
+    );
+
+    let id = command_bus
+        .enqueue(SendInvoiceCommand { invoice_id: 5 })
+        .await
+        .unwrap();
+    let JobStatus::Failed(failure) = completed(&queue, &id).await else {
+        panic!("the job should fail");
+    };
+    assert_eq!(failure.kind, "timeout");
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_jobs_are_dispatched_on_behalf_of_their_principal() {
+    let queue = CommandQueue::new(InMemoryJobStore::new());
+    let (command_bus, _) = command_bus(&queue, Vec::new());
+    tokio::spawn(pool(&command_bus, &queue).run());
+
+    let id = command_bus
+        .enqueue_as(Principal::new("alice"), WhoAmICommand)
+        .await
+        .unwrap();
+    assert_eq!(
+        completed(&queue, &id).await,
+        JobStatus::Succeeded(json!("alice"))
+    );
+    let job = queue.job(&id).await.unwrap().unwrap();
+    assert_eq!(job.principal, Some(Principal::new("alice")));
+
+    let id = command_bus.enqueue(WhoAmICommand).await.unwrap();
+    assert_eq!(
+        completed(&queue, &id).await,
+        JobStatus::Succeeded(json!(null))
+    );
+}
+
+#[tokio::test]
+async fn test_file_queue_survives_restarts() {
+    let directory = directory("file");
+    let id = {
+        let queue = CommandQueue::new(FileJobStore::open(&directory).unwrap());
+        let (command_bus, _) = command_bus(&queue, Vec::new());
+        command_bus
+            .enqueue(SendInvoiceCommand { invoice_id: 3 })
+            .await
+            .unwrap()
+    };
+
+    // The jobs enqueued before the restart are executed
+    let queue = CommandQueue::new(FileJobStore::open(&directory).unwrap());
+    let (command_bus, _) = command_bus(&queue, Vec::new());
+    assert_eq!(queue.status(&id).await.unwrap(), Some(JobStatus::Queued));
+    tokio::spawn(pool(&command_bus, &queue).run());
+    assert_eq!(
+        completed(&queue, &id).await,
+        JobStatus::Succeeded(json!("invoice-3"))
+    );
+    // Completed jobs are moved out of the jobs read by the claims
+    assert!(!directory.join(format!("{id}.json")).exists());
+    assert!(
+        directory
+            .join("completed")
+            .join(format!("{id}.json"))
+            .exists()
+    );
+    queue.remove(&id).await.unwrap();
+    assert_eq!(queue.status(&id).await.unwrap(), None);
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[tokio::test]
+async fn test_file_queue_rejects_ids_outside_of_its_directory() {
+    let directory = directory("ids");
+    let queue = CommandQueue::new(FileJobStore::open(&directory).unwrap());
+    for id in ["../jobs", "a/b", "..", ""] {
+        assert!(queue.status(id).await.is_err(), "{id:?}");
+        assert!(queue.remove(id).await.is_err(), "{id:?}");
+    }
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[cfg(feature = "sqlite")]
+#[tokio::test]
+async fn test_sqlite_queue_shared_by_several_pools() {
+    use qonduit::queue::SqliteJobStore;
+
+    let directory = directory("sqlite");
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("jobs.db");
+    let producer = CommandQueue::new(SqliteJobStore::open(&path).unwrap());
+    let (producer_bus, _) = command_bus(&producer, Vec::new());
+    let mut ids = Vec::new();
+    for invoice_id in 1..=20 {
+        ids.push(
+            producer_bus
+                .enqueue(SendInvoiceCommand { invoice_id })
+                .await
+                .unwrap(),
+        );
+    }
+
+    // Each pool has its own connection, as if it ran in another process
+    let mut handled = Vec::new();
+    for _ in 0..2 {
+        let queue = CommandQueue::new(SqliteJobStore::open(&path).unwrap());
+        let (command_bus, attempts) = command_bus(&queue, Vec::new());
+        tokio::spawn(pool(&command_bus, &queue).run());
+        handled.push(attempts);
+    }
+    for (invoice_id, id) in (1..=20).zip(&ids) {
+        assert_eq!(
+            completed(&producer, id).await,
+            JobStatus::Succeeded(json!(format!("invoice-{invoice_id}")))
+        );
+    }
+    // Every job was executed exactly once
+    let total: usize = handled
+        .iter()
+        .map(|attempts| attempts.load(Ordering::SeqCst))
+        .sum();
+    assert_eq!(total, 20);
+    std::fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,134 @@
+use qonduit::async_trait;
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::error::DispatchError;
+use qonduit::rate_limit::RateLimitConfig;
+use qonduit::registry::CommandHandlerRegistry;
+use std::time::Duration;
+
+// Error type for tests
+#[derive(Debug, PartialEq)]
+struct TestError;
+
+// Command issued on behalf of a tenant
+#[derive(Debug)]
+struct PlaceOrderCommand {
+    tenant_id: u64,
+}
+
+impl Command for PlaceOrderCommand {
+    type Response = u64;
+    type Error = TestError;
+}
+
+struct PlaceOrderCommandHandler;
+
+#[async_trait]
+impl CommandHandler<PlaceOrderCommand> for PlaceOrderCommandHandler {
+    async fn handle(&self, command: PlaceOrderCommand) -> Result<u64, TestError> {
+        Ok(command.tenant_id)
+    }
+}
+
+fn registry() -> CommandHandlerRegistry {
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register::<PlaceOrderCommand>(PlaceOrderCommandHandler);
+    registry
+}
+
+fn order(tenant_id: u64) -> PlaceOrderCommand {
+    PlaceOrderCommand { tenant_id }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_rate_limit_per_type() {
+    let bus = CommandBus::new(registry())
+        .with_rate_limit::<PlaceOrderCommand>(RateLimitConfig::new(2, Duration::from_secs(1)));
+
+    assert_eq!(bus.dispatch(order(1)).await, Ok(1));
+    assert_eq!(bus.dispatch(order(2)).await, Ok(2));
+
+    // The bucket is empty; a token refills every 500ms
+    assert_eq!(
+        bus.dispatch(order(3)).await,
+        Err(DispatchError::RateLimited {
+            retry_after: Duration::from_millis(500)
+        })
+    );
+
+    tokio::time::advance(Duration::from_millis(200)).await;
+    assert_eq!(
+        bus.dispatch(order(3)).await,
+        Err(DispatchError::RateLimited {
+            retry_after: Duration::from_millis(300)
+        })
+    );
+
+    tokio::time::advance(Duration::from_millis(300)).await;
+    assert_eq!(bus.dispatch(order(3)).await, Ok(3));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_rate_limit_burst() {
+    let bus = CommandBus::new(registry()).with_rate_limit::<PlaceOrderCommand>(
+        RateLimitConfig::new(1, Duration::from_secs(1)).with_burst(3),
+    );
+
+    // A long idle period only refills up to the burst size
+    tokio::time::advance(Duration::from_secs(60)).await;
+    for tenant_id in 0..3 {
+        assert_eq!(bus.dispatch(order(tenant_id)).await, Ok(tenant_id));
+    }
+    assert!(matches!(
+        bus.dispatch(order(3)).await,
+        Err(DispatchError::RateLimited { .. })
+    ));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_rate_limit_by_key() {
+    let bus = CommandBus::new(registry()).with_rate_limit_by_key(
+        RateLimitConfig::new(1, Duration::from_secs(10)),
+        |command: &PlaceOrderCommand| command.tenant_id,
+    );
+
+    assert_eq!(bus.dispatch(order(1)).await, Ok(1));
+    assert_eq!(
+        bus.dispatch(order(1)).await,
+        Err(DispatchError::RateLimited {
+            retry_after: Duration::from_secs(10)
+        })
+    );
+
+    // Other tenants have their own budget
+    assert_eq!(bus.dispatch(order(2)).await, Ok(2));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_rate_limit_by_key_drops_least_recent_keys() {
+    let bus = CommandBus::new(registry()).with_rate_limit_by_key(
+        RateLimitConfig::new(1, Duration::from_secs(10)).with_max_keys(2),
+        |command: &PlaceOrderCommand| command.tenant_id,
+    );
+
+    assert_eq!(bus.dispatch(order(1)).await, Ok(1));
+    assert_eq!(bus.dispatch(order(2)).await, Ok(2));
+    assert!(bus.dispatch(order(1)).await.is_err());
+
+    // Tenant 2 is the least recently seen, so its bucket makes room for tenant 3
+    assert_eq!(bus.dispatch(order(3)).await, Ok(3));
+    assert_eq!(bus.dispatch(order(2)).await, Ok(2));
+    assert!(bus.dispatch(order(3)).await.is_err());
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_rate_limit_shared_between_clones() {
+    let bus = CommandBus::new(registry())
+        .with_rate_limit::<PlaceOrderCommand>(RateLimitConfig::new(1, Duration::from_secs(1)));
+    let clone = bus.clone();
+
+    assert_eq!(bus.dispatch(order(1)).await, Ok(1));
+    assert!(matches!(
+        clone.dispatch(order(1)).await,
+        Err(DispatchError::RateLimited { .. })
+    ));
+}
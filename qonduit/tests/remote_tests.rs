@@ -0,0 +1,368 @@
+#![cfg(feature = "remote")]
+
+use qonduit::async_trait;
+use qonduit::authorization::{Principal, current_principal};
+use qonduit::codec::{Codec, JsonCodec};
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::error::DispatchError;
+use qonduit::message::MessageName;
+use qonduit::query::{Query, QueryBus, QueryHandler};
+use qonduit::registry::{CommandHandlerRegistry, QueryHandlerRegistry};
+use qonduit::remote::{
+    Frame, FrameKind, MemoryTransport, RemoteCommandBus, RemoteQueryBus, RemoteServer,
+    TcpTransport, Transport,
+};
+use qonduit::timeout;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum OrderError {
+    OutOfStock { sku: String },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PlaceOrderCommand {
+    sku: String,
+}
+
+impl Command for PlaceOrderCommand {
+    type Response = String;
+    type Error = OrderError;
+}
+
+impl MessageName for PlaceOrderCommand {
+    const NAME: &'static str = "orders.place.v1";
+}
+
+struct PlaceOrderCommandHandler;
+
+#[async_trait]
+impl CommandHandler<PlaceOrderCommand> for PlaceOrderCommandHandler {
+    async fn handle(&self, command: PlaceOrderCommand) -> Result<String, OrderError> {
+        match command.sku.as_str() {
+            "sold-out" => Err(OrderError::OutOfStock { sku: command.sku }),
+            "slow" => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                Ok(command.sku)
+            }
+            _ => Ok(format!("order-{}", command.sku)),
+        }
+    }
+}
+
+// Command the server does not serve
+#[derive(Debug, Serialize, Deserialize)]
+struct CancelOrderCommand;
+
+impl Command for CancelOrderCommand {
+    type Response = ();
+    type Error = OrderError;
+}
+
+impl MessageName for CancelOrderCommand {
+    const NAME: &'static str = "orders.cancel.v1";
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CountOrdersQuery {
+    sku: String,
+}
+
+impl Query for CountOrdersQuery {
+    type Response = usize;
+    type Error = OrderError;
+}
+
+impl MessageName for CountOrdersQuery {
+    const NAME: &'static str = "orders.count.v1";
+}
+
+struct CountOrdersQueryHandler;
+
+#[async_trait]
+impl QueryHandler<CountOrdersQuery> for CountOrdersQueryHandler {
+    async fn handle(&self, query: CountOrdersQuery) -> Result<usize, OrderError> {
+        Ok(query.sku.len())
+    }
+}
+
+// Returns the principal and remaining deadline of its dispatch, after a delay
+#[derive(Debug, Serialize, Deserialize)]
+struct WhoAmIQuery {
+    delay_ms: u64,
+}
+
+impl Query for WhoAmIQuery {
+    type Response = (Option<String>, Option<u64>);
+    type Error = OrderError;
+}
+
+impl MessageName for WhoAmIQuery {
+    const NAME: &'static str = "whoami.v1";
+}
+
+struct WhoAmIQueryHandler {
+    completed: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl QueryHandler<WhoAmIQuery> for WhoAmIQueryHandler {
+    async fn handle(
+        &self,
+        query: WhoAmIQuery,
+    ) -> Result<(Option<String>, Option<u64>), OrderError> {
+        let remaining = timeout::remaining().map(|remaining| remaining.as_millis() as u64);
+        tokio::time::sleep(Duration::from_millis(query.delay_ms)).await;
+        self.completed.fetch_add(1, Ordering::SeqCst);
+        let principal = current_principal().and_then(|principal| principal.id().map(String::from));
+        Ok((principal, remaining))
+    }
+}
+
+fn whoami_server(completed: &Arc<AtomicUsize>) -> RemoteServer<JsonCodec> {
+    let mut queries = QueryHandlerRegistry::new();
+    queries.register::<WhoAmIQuery>(WhoAmIQueryHandler {
+        completed: completed.clone(),
+    });
+    RemoteServer::new(
+        CommandBus::new(CommandHandlerRegistry::new()),
+        QueryBus::new(queries),
+        JsonCodec,
+    )
+    .query::<WhoAmIQuery>()
+}
+
+fn whoami_frame(correlation_id: u64, delay_ms: u64) -> Frame {
+    Frame {
+        correlation_id,
+        kind: FrameKind::Query,
+        name: WhoAmIQuery::NAME.to_string(),
+        principal: Some(Principal::new("alice").with_role("admin")),
+        deadline: Some(Duration::from_secs(5)),
+        payload: JsonCodec.encode(&WhoAmIQuery { delay_ms }).unwrap(),
+    }
+}
+
+fn server() -> RemoteServer<JsonCodec> {
+    let mut commands = CommandHandlerRegistry::new();
+    commands.register::<PlaceOrderCommand>(PlaceOrderCommandHandler);
+    let mut queries = QueryHandlerRegistry::new();
+    queries.register::<CountOrdersQuery>(CountOrdersQueryHandler);
+    RemoteServer::new(CommandBus::new(commands), QueryBus::new(queries), JsonCodec)
+        .command::<PlaceOrderCommand>()
+        .query::<CountOrdersQuery>()
+}
+
+// Serves a server on one end of an in-memory connection and returns the other end
+fn connect() -> MemoryTransport {
+    let (client, connection) = MemoryTransport::pair();
+    tokio::spawn(async move { server().serve(connection).await });
+    client
+}
+
+fn place_order(sku: &str) -> PlaceOrderCommand {
+    PlaceOrderCommand {
+        sku: sku.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_dispatch_over_memory_transport() {
+    let commands = RemoteCommandBus::new(connect(), JsonCodec);
+    let queries = commands.query_bus();
+
+    assert_eq!(
+        commands.dispatch(place_order("kb-01")).await,
+        Ok("order-kb-01".to_string())
+    );
+    assert_eq!(
+        commands.dispatch(place_order("sold-out")).await,
+        Err(DispatchError::Handler(OrderError::OutOfStock {
+            sku: "sold-out".to_string()
+        }))
+    );
+    assert_eq!(
+        queries
+            .dispatch(CountOrdersQuery {
+                sku: "kb-01".to_string()
+            })
+            .await,
+        Ok(5)
+    );
+
+    let error = commands.dispatch(CancelOrderCommand).await.unwrap_err();
+    let DispatchError::Transport(reason) = error else {
+        panic!("unexpected error {error:?}");
+    };
+    assert!(reason.contains("orders.cancel.v1"), "{reason}");
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_timeout() {
+    let commands =
+        RemoteCommandBus::new(connect(), JsonCodec).with_timeout(Duration::from_millis(50));
+
+    assert_eq!(
+        commands.dispatch(place_order("slow")).await,
+        Err(DispatchError::Timeout(Duration::from_millis(50)))
+    );
+    // The late reply is dropped, and does not disturb the next dispatch
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert_eq!(
+        commands.dispatch(place_order("kb-01")).await,
+        Ok("order-kb-01".to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_closed_connection() {
+    let (client, connection) = MemoryTransport::pair();
+    let commands = RemoteCommandBus::new(client, JsonCodec);
+    drop(connection);
+
+    let error = commands.dispatch(place_order("kb-01")).await.unwrap_err();
+    assert_eq!(error.kind(), "transport");
+}
+
+#[tokio::test]
+async fn test_concurrent_dispatches_over_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(server().serve_tcp(listener));
+
+    let stream = TcpStream::connect(address).await.unwrap();
+    let queries = RemoteQueryBus::new(TcpTransport::from(stream), JsonCodec);
+    let commands = queries.command_bus();
+
+    // Replies are matched to their request whatever the order they arrive in
+    let (slow, fast, count) = tokio::join!(
+        commands.dispatch(place_order("slow")),
+        commands.dispatch(place_order("kb-01")),
+        queries.dispatch(CountOrdersQuery {
+            sku: "mouse".to_string()
+        }),
+    );
+    assert_eq!(slow, Ok("slow".to_string()));
+    assert_eq!(fast, Ok("order-kb-01".to_string()));
+    assert_eq!(count, Ok(5));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_dispatch_over_unix_socket() {
+    use qonduit::remote::UnixTransport;
+    use tokio::net::{UnixListener, UnixStream};
+
+    let path = std::env::temp_dir().join(format!("qonduit-remote-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).unwrap();
+    tokio::spawn(server().serve_unix(listener));
+
+    let stream = UnixStream::connect(&path).await.unwrap();
+    let commands = RemoteCommandBus::new(UnixTransport::from(stream), JsonCodec);
+    assert_eq!(
+        commands.dispatch(place_order("kb-01")).await,
+        Ok("order-kb-01".to_string())
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_frame_round_trip_over_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let client = TcpTransport::from(TcpStream::connect(address).await.unwrap());
+    let server = TcpTransport::from(listener.accept().await.unwrap().0);
+
+    let frame = whoami_frame(3, 0);
+    client.send(frame.clone()).await.unwrap();
+    assert_eq!(server.recv().await.unwrap(), Some(frame));
+
+    let frame = Frame {
+        principal: None,
+        deadline: None,
+        ..whoami_frame(4, 0)
+    };
+    client.send(frame.clone()).await.unwrap();
+    assert_eq!(server.recv().await.unwrap(), Some(frame));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_principal_and_deadline_of_requests() {
+    let completed = Arc::new(AtomicUsize::new(0));
+    for (server, principal) in [
+        (whoami_server(&completed), None),
+        (
+            whoami_server(&completed).with_trusted_principals(),
+            Some("alice".to_string()),
+        ),
+    ] {
+        let (client, connection) = MemoryTransport::pair();
+        tokio::spawn(async move { server.serve(connection).await });
+
+        client.send(whoami_frame(1, 0)).await.unwrap();
+        let reply = client.recv().await.unwrap().unwrap();
+        assert_eq!(reply.kind, FrameKind::Reply);
+        let outcome: (Option<String>, Option<u64>) = JsonCodec.decode(&reply.payload).unwrap();
+        assert_eq!(outcome, (principal, Some(5000)));
+    }
+
+    // The dispatch is cancelled once the deadline sent with it passes
+    let server = whoami_server(&completed);
+    let (client, connection) = MemoryTransport::pair();
+    tokio::spawn(async move { server.serve(connection).await });
+    client.send(whoami_frame(2, 10_000)).await.unwrap();
+    let reply = client.recv().await.unwrap().unwrap();
+    let error: DispatchError<OrderError> = JsonCodec.decode(&reply.payload).unwrap();
+    assert_eq!(error, DispatchError::Timeout(Duration::from_secs(5)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_remote_dispatch_sends_remaining_deadline() {
+    let completed = Arc::new(AtomicUsize::new(0));
+    let (client, connection) = MemoryTransport::pair();
+    let server = whoami_server(&completed).with_trusted_principals();
+    tokio::spawn(async move { server.serve(connection).await });
+
+    let queries = RemoteQueryBus::new(client, JsonCodec).with_timeout(Duration::from_secs(2));
+    assert_eq!(
+        queries.dispatch(WhoAmIQuery { delay_ms: 0 }).await,
+        Ok((None, Some(2000)))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_max_in_flight() {
+    let completed = Arc::new(AtomicUsize::new(0));
+    let server = whoami_server(&completed).with_max_in_flight(1);
+    let (client, connection) = MemoryTransport::pair();
+    tokio::spawn(async move { server.serve(connection).await });
+
+    let started_at = tokio::time::Instant::now();
+    client.send(whoami_frame(1, 1000)).await.unwrap();
+    client.send(whoami_frame(2, 1000)).await.unwrap();
+    client.recv().await.unwrap().unwrap();
+    client.recv().await.unwrap().unwrap();
+    // The second request waits for the first one to complete
+    assert!(started_at.elapsed() >= Duration::from_secs(2));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_dispatches_cancelled_when_connection_closes() {
+    let completed = Arc::new(AtomicUsize::new(0));
+    let server = whoami_server(&completed);
+    let (client, connection) = MemoryTransport::pair();
+    let serve = tokio::spawn(async move { server.serve(connection).await });
+
+    client.send(whoami_frame(1, 1000)).await.unwrap();
+    tokio::task::yield_now().await;
+    drop(client);
+    serve.await.unwrap().unwrap();
+
+    tokio::time::sleep(Duration::from_secs(2)).await;
+    assert_eq!(completed.load(Ordering::SeqCst), 0);
+}
@@ -0,0 +1,231 @@
+#![cfg(feature = "remote")]
+
+use qonduit::async_trait;
+use qonduit::authorization::{Principal, has_role};
+use qonduit::codec::JsonCodec;
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::error::DispatchError;
+use qonduit::message::MessageName;
+use qonduit::query::QueryBus;
+use qonduit::registry::{CommandHandlerRegistry, QueryHandlerRegistry};
+use qonduit::remote::{RemoteCommandBus, RemoteServer, TcpTransport};
+use qonduit::routing::{Route, Router, RoutingTable};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tokio::net::{TcpListener, TcpStream};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PlaceOrderCommand {
+    customer_id: u64,
+}
+
+impl Command for PlaceOrderCommand {
+    type Response = String;
+    type Error = String;
+}
+
+impl MessageName for PlaceOrderCommand {
+    const NAME: &'static str = "orders.place.v1";
+}
+
+// Answers with the name of the node handling the command
+struct PlaceOrderCommandHandler(&'static str);
+
+#[async_trait]
+impl CommandHandler<PlaceOrderCommand> for PlaceOrderCommandHandler {
+    async fn handle(&self, command: PlaceOrderCommand) -> Result<String, String> {
+        Ok(format!("{}:{}", self.0, command.customer_id))
+    }
+}
+
+fn registry(node: &'static str) -> CommandHandlerRegistry {
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register::<PlaceOrderCommand>(PlaceOrderCommandHandler(node));
+    registry
+}
+
+// Starts a node on localhost and returns a bus connected to it
+async fn node(name: &'static str) -> RemoteCommandBus<JsonCodec> {
+    serve(RemoteServer::new(
+        CommandBus::new(registry(name)),
+        QueryBus::new(QueryHandlerRegistry::new()),
+        JsonCodec,
+    ))
+    .await
+}
+
+async fn serve(server: RemoteServer<JsonCodec>) -> RemoteCommandBus<JsonCodec> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(server.command::<PlaceOrderCommand>().serve_tcp(listener));
+
+    let stream = TcpStream::connect(address).await.unwrap();
+    RemoteCommandBus::new(TcpTransport::from(stream), JsonCodec)
+}
+
+fn place_order(customer_id: u64) -> PlaceOrderCommand {
+    PlaceOrderCommand { customer_id }
+}
+
+fn to_node(name: &str) -> RoutingTable {
+    RoutingTable::new().with_route(PlaceOrderCommand::NAME, Route::Node(name.to_string()))
+}
+
+#[tokio::test]
+async fn test_route_to_named_node() {
+    let router = Router::new(to_node("orders")).with_node("orders", node("orders").await);
+
+    // The handler is not registered locally
+    let command_bus = CommandBus::new(CommandHandlerRegistry::new())
+        .with_routing::<PlaceOrderCommand, _>(&router);
+
+    assert_eq!(
+        command_bus.dispatch(place_order(7)).await,
+        Ok("orders:7".to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_reload_routing_table() {
+    let router = Router::new(RoutingTable::new()).with_node("orders", node("orders").await);
+    let command_bus =
+        CommandBus::new(registry("local")).with_routing::<PlaceOrderCommand, _>(&router);
+    assert_eq!(
+        command_bus.dispatch(place_order(1)).await,
+        Ok("local:1".to_string())
+    );
+
+    router.reload(to_node("orders"));
+    assert_eq!(router.table(), to_node("orders"));
+    assert_eq!(
+        command_bus.dispatch(place_order(2)).await,
+        Ok("orders:2".to_string())
+    );
+
+    router.reload(RoutingTable::new());
+    assert_eq!(
+        command_bus.dispatch(place_order(3)).await,
+        Ok("local:3".to_string())
+    );
+}
+
+fn consistent_hash(nodes: &[&str]) -> RoutingTable {
+    let nodes = nodes.iter().map(|node| node.to_string()).collect();
+    RoutingTable::new().with_route(PlaceOrderCommand::NAME, Route::ConsistentHash(nodes))
+}
+
+#[tokio::test]
+async fn test_consistent_hash_by_key() {
+    let router = Router::new(consistent_hash(&["orders-1", "orders-2"]))
+        .with_node("orders-1", node("orders-1").await)
+        .with_node("orders-2", node("orders-2").await)
+        .with_node("orders-3", node("orders-3").await);
+    let command_bus = CommandBus::new(CommandHandlerRegistry::new())
+        .with_routing_by_key(&router, |command: &PlaceOrderCommand| command.customer_id);
+
+    let mut before = Vec::new();
+    for customer_id in 0..20 {
+        let first = command_bus.dispatch(place_order(customer_id)).await;
+        let second = command_bus.dispatch(place_order(customer_id)).await;
+        // The commands of a customer always go to the same node
+        assert_eq!(first, second);
+        before.push(first.unwrap());
+    }
+    let used: HashSet<_> = before.iter().map(|reply| &reply[..8]).collect();
+    assert_eq!(used.len(), 2);
+
+    // Adding a node only moves keys to the new node
+    router.reload(consistent_hash(&["orders-1", "orders-2", "orders-3"]));
+    let mut moved = 0;
+    for (customer_id, before) in (0..20).zip(before) {
+        let after = command_bus
+            .dispatch(place_order(customer_id))
+            .await
+            .unwrap();
+        if after != before {
+            assert_eq!(after, format!("orders-3:{customer_id}"));
+            moved += 1;
+        }
+    }
+    assert!(moved > 0 && moved < 20, "{moved} keys moved");
+}
+
+#[tokio::test]
+async fn test_unknown_node() {
+    let router = Router::<JsonCodec>::new(to_node("reports"));
+    let command_bus =
+        CommandBus::new(registry("local")).with_routing::<PlaceOrderCommand, _>(&router);
+
+    let error = command_bus.dispatch(place_order(1)).await.unwrap_err();
+    let DispatchError::Transport(reason) = error else {
+        panic!("unexpected error {error:?}");
+    };
+    assert!(reason.contains("reports"), "{reason}");
+}
+
+#[tokio::test]
+async fn test_routing_table_from_json() {
+    let table: RoutingTable = serde_json::from_str(
+        r#"{
+            "orders.place.v1": { "consistent_hash": ["orders-1", "orders-2"] },
+            "reports.generate.v1": { "node": "reports" },
+            "orders.cancel.v1": "local"
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        table.route("orders.place.v1"),
+        &Route::ConsistentHash(vec!["orders-1".to_string(), "orders-2".to_string()])
+    );
+    assert_eq!(
+        table.route("reports.generate.v1"),
+        &Route::Node("reports".to_string())
+    );
+    assert_eq!(table.route("orders.cancel.v1"), &Route::Local);
+    assert_eq!(table.route("orders.refund.v1"), &Route::Local);
+}
+
+#[tokio::test]
+async fn test_local_policy_applies_to_routed_commands() {
+    let router = Router::new(to_node("orders")).with_node("orders", node("orders").await);
+    let command_bus = CommandBus::new(CommandHandlerRegistry::new())
+        .with_routing::<PlaceOrderCommand, _>(&router)
+        .with_policy::<PlaceOrderCommand>(has_role("clerk"));
+
+    // The command is denied locally, and never reaches the node
+    let error = command_bus.dispatch(place_order(1)).await.unwrap_err();
+    assert_eq!(error.kind(), "forbidden");
+
+    let clerk = Principal::new("alice").with_role("clerk");
+    assert_eq!(
+        command_bus.dispatch_as(clerk, place_order(2)).await,
+        Ok("orders:2".to_string())
+    );
+}
+
+#[tokio::test]
+async fn test_principal_is_sent_with_routed_commands() {
+    // The node trusts the principal of the sender, and checks it with its own policy
+    let orders = serve(
+        RemoteServer::new(
+            CommandBus::new(registry("orders")).with_policy::<PlaceOrderCommand>(has_role("clerk")),
+            QueryBus::new(QueryHandlerRegistry::new()),
+            JsonCodec,
+        )
+        .with_trusted_principals(),
+    )
+    .await;
+    let router = Router::new(to_node("orders")).with_node("orders", orders);
+    let command_bus = CommandBus::new(CommandHandlerRegistry::new())
+        .with_routing::<PlaceOrderCommand, _>(&router);
+
+    let error = command_bus.dispatch(place_order(1)).await.unwrap_err();
+    assert_eq!(error.kind(), "forbidden");
+
+    let clerk = Principal::new("alice").with_role("clerk");
+    assert_eq!(
+        command_bus.dispatch_as(clerk, place_order(2)).await,
+        Ok("orders:2".to_string())
+    );
+}
@@ -0,0 +1,415 @@
+#![cfg(feature = "schedule")]
+
+use qonduit::async_trait;
+use qonduit::authorization::{Principal, current_principal};
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::message::MessageName;
+use qonduit::registry::CommandHandlerRegistry;
+use qonduit::schedule::{
+    FileScheduleStore, InMemoryScheduleStore, ManualClock, MisfirePolicy, Schedule, ScheduleStore,
+    ScheduledCommand, Scheduler,
+};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{Notify, Semaphore};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SendReminderCommand {
+    customer_id: u64,
+}
+
+impl Command for SendReminderCommand {
+    type Response = ();
+    type Error = String;
+}
+
+impl MessageName for SendReminderCommand {
+    const NAME: &'static str = "reminders.send.v1";
+}
+
+// Records the customers reminded, and fails for the unknown customer 0
+struct SendReminderCommandHandler(Arc<Mutex<Vec<u64>>>);
+
+#[async_trait]
+impl CommandHandler<SendReminderCommand> for SendReminderCommandHandler {
+    async fn handle(&self, command: SendReminderCommand) -> Result<(), String> {
+        if command.customer_id == 0 {
+            return Err("unknown customer".to_string());
+        }
+        self.0.lock().unwrap().push(command.customer_id);
+        Ok(())
+    }
+}
+
+// 2025-10-09 08:53:20 UTC
+const START: u64 = 1_760_000_000;
+
+fn at(seconds: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(seconds)
+}
+
+fn scheduler(clock: &ManualClock) -> (Scheduler, Arc<Mutex<Vec<u64>>>) {
+    let reminded = Arc::new(Mutex::new(Vec::new()));
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register_json::<SendReminderCommand>(SendReminderCommandHandler(reminded.clone()));
+    let scheduler = Scheduler::new(CommandBus::new(registry))
+        .with_clock(clock.clone())
+        .with_misfire_threshold(Duration::from_secs(5));
+    (scheduler, reminded)
+}
+
+fn reminder(customer_id: u64) -> SendReminderCommand {
+    SendReminderCommand { customer_id }
+}
+
+fn minutes(minutes: u64) -> Duration {
+    Duration::from_secs(minutes * 60)
+}
+
+#[tokio::test]
+async fn test_commands_run_at_a_time_or_after_a_delay() {
+    let clock = ManualClock::new(at(START));
+    let (scheduler, reminded) = scheduler(&clock);
+    scheduler
+        .schedule(reminder(1), Schedule::after(Duration::from_secs(10)))
+        .await
+        .unwrap();
+    scheduler
+        .schedule(reminder(2), Schedule::at(at(START + 20)))
+        .await
+        .unwrap();
+
+    assert_eq!(scheduler.tick().await.unwrap(), 0);
+    clock.advance(Duration::from_secs(10));
+    assert_eq!(scheduler.tick().await.unwrap(), 1);
+    assert_eq!(*reminded.lock().unwrap(), vec![1]);
+    clock.advance(Duration::from_secs(10));
+    assert_eq!(scheduler.tick().await.unwrap(), 1);
+    assert_eq!(*reminded.lock().unwrap(), vec![1, 2]);
+
+    // Commands running once are removed after their run
+    assert!(scheduler.scheduled().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_cron_schedule() {
+    let clock = ManualClock::new(at(START));
+    let (scheduler, reminded) = scheduler(&clock);
+    let every_quarter = Schedule::cron("0 */15 * * * *").unwrap();
+    scheduler
+        .schedule(reminder(1), every_quarter)
+        .await
+        .unwrap();
+
+    // The first run is at 09:00
+    let next_run = scheduler.scheduled().await.unwrap()[0].next_run;
+    assert_eq!(next_run, (START + 400) * 1000);
+    clock.advance(Duration::from_secs(399));
+    assert_eq!(scheduler.tick().await.unwrap(), 0);
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(scheduler.tick().await.unwrap(), 1);
+    clock.advance(minutes(15));
+    assert_eq!(scheduler.tick().await.unwrap(), 1);
+    assert_eq!(*reminded.lock().unwrap(), vec![1, 1]);
+
+    let scheduled = scheduler.scheduled().await.unwrap();
+    assert_eq!(scheduled[0].next_run, (START + 400 + 1800) * 1000);
+    assert_eq!(scheduled[0].cron.as_deref(), Some("0 */15 * * * *"));
+    assert!(Schedule::cron("every monday").is_err());
+}
+
+#[tokio::test]
+async fn test_cancel_by_handle() {
+    let clock = ManualClock::new(at(START));
+    let (scheduler, reminded) = scheduler(&clock);
+    let handle = scheduler
+        .schedule(reminder(1), Schedule::cron("0 * * * * *").unwrap())
+        .await
+        .unwrap();
+    let kept = scheduler
+        .schedule(reminder(2), Schedule::after(minutes(1)))
+        .await
+        .unwrap();
+
+    assert!(handle.cancel().await.unwrap());
+    assert!(!handle.cancel().await.unwrap());
+    clock.advance(minutes(1));
+    assert_eq!(scheduler.tick().await.unwrap(), 1);
+    assert_eq!(*reminded.lock().unwrap(), vec![2]);
+
+    // Handles are found again by id
+    assert!(!scheduler.handle(kept.id()).cancel().await.unwrap());
+}
+
+#[tokio::test]
+async fn test_misfire_policies() {
+    let clock = ManualClock::new(at(START));
+    let (scheduler, reminded) = scheduler(&clock);
+    let policies = [
+        MisfirePolicy::Skip,
+        MisfirePolicy::FireOnce,
+        MisfirePolicy::CatchUp,
+    ];
+    for (customer_id, policy) in (1..).zip(policies) {
+        let every_minute = Schedule::cron("0 * * * * *").unwrap().with_misfire(policy);
+        scheduler
+            .schedule(reminder(customer_id), every_minute)
+            .await
+            .unwrap();
+    }
+
+    // The runs from 08:54 to 09:03 are missed, as if the process had been stopped
+    clock.advance(minutes(10));
+    assert_eq!(scheduler.tick().await.unwrap(), 11);
+    let mut reminded = reminded.lock().unwrap().clone();
+    reminded.sort();
+    assert_eq!(reminded, [vec![2], vec![3; 10]].concat());
+
+    // The schedules go on from the next run
+    for scheduled in scheduler.scheduled().await.unwrap() {
+        assert_eq!(scheduled.next_run, (START + 640) * 1000);
+    }
+}
+
+#[tokio::test]
+async fn test_schedules_survive_restarts() {
+    let directory = std::env::temp_dir().join(format!("qonduit-schedules-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    let clock = ManualClock::new(at(START));
+    let id = {
+        let (scheduler, _) = scheduler(&clock);
+        let scheduler = scheduler.with_store(FileScheduleStore::open(&directory).unwrap());
+        let handle = scheduler
+            .schedule(reminder(1), Schedule::after(Duration::from_secs(3)))
+            .await
+            .unwrap();
+        handle.id().to_string()
+    };
+
+    // The process restarts shortly after the run was due
+    clock.advance(Duration::from_secs(4));
+    let (scheduler, reminded) = scheduler(&clock);
+    let scheduler = scheduler.with_store(FileScheduleStore::open(&directory).unwrap());
+    assert_eq!(scheduler.scheduled().await.unwrap()[0].id, id);
+    assert_eq!(scheduler.tick().await.unwrap(), 1);
+    assert_eq!(*reminded.lock().unwrap(), vec![1]);
+    assert!(scheduler.scheduled().await.unwrap().is_empty());
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+// Waits for the test to open the gate, as if the dispatch was slow
+struct SlowReminderCommandHandler {
+    started: Arc<Notify>,
+    gate: Arc<Semaphore>,
+}
+
+#[async_trait]
+impl CommandHandler<SendReminderCommand> for SlowReminderCommandHandler {
+    async fn handle(&self, _command: SendReminderCommand) -> Result<(), String> {
+        self.started.notify_one();
+        self.gate.acquire().await.unwrap().forget();
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_cancel_during_dispatch() {
+    let clock = ManualClock::new(at(START));
+    let started = Arc::new(Notify::new());
+    let gate = Arc::new(Semaphore::new(0));
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register_json::<SendReminderCommand>(SlowReminderCommandHandler {
+        started: started.clone(),
+        gate: gate.clone(),
+    });
+    let scheduler = Scheduler::new(CommandBus::new(registry)).with_clock(clock.clone());
+    let handle = scheduler
+        .schedule(reminder(1), Schedule::cron("0 * * * * *").unwrap())
+        .await
+        .unwrap();
+
+    clock.advance(minutes(1));
+    let tick = tokio::spawn({
+        let scheduler = scheduler.clone();
+        async move { scheduler.tick().await }
+    });
+    started.notified().await;
+    assert!(handle.cancel().await.unwrap());
+
+    // The run completing after the cancellation does not schedule the command again
+    gate.add_permits(1);
+    assert_eq!(tick.await.unwrap().unwrap(), 1);
+    assert!(scheduler.scheduled().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_corrupted_schedules_are_kept_as_failed() {
+    let clock = ManualClock::new(at(START));
+    let store = InMemoryScheduleStore::new();
+    let corrupted = ScheduledCommand {
+        id: "corrupted".to_string(),
+        name: "reminders.send.v1".to_string(),
+        payload: serde_json::json!({ "customer_id": 1 }),
+        cron: Some("every monday".to_string()),
+        misfire: MisfirePolicy::FireOnce,
+        next_run: START * 1000,
+        last_error: None,
+        failed: false,
+        principal: None,
+    };
+    store.save(&corrupted).await.unwrap();
+    let (scheduler, reminded) = scheduler(&clock);
+    let scheduler = scheduler.with_store(store);
+
+    assert_eq!(scheduler.tick().await.unwrap(), 0);
+    let scheduled = scheduler.scheduled().await.unwrap();
+    assert!(scheduled[0].failed);
+    assert!(scheduled[0].last_error.is_some());
+
+    // The failed command is no longer run, until it is cancelled
+    clock.advance(minutes(1));
+    assert_eq!(scheduler.tick().await.unwrap(), 0);
+    assert!(reminded.lock().unwrap().is_empty());
+    assert!(scheduler.handle("corrupted").cancel().await.unwrap());
+}
+
+#[tokio::test]
+async fn test_failed_commands_running_once_are_kept_as_failed() {
+    let clock = ManualClock::new(at(START));
+    let (scheduler, _) = scheduler(&clock);
+    let handle = scheduler
+        .schedule(reminder(0), Schedule::after(Duration::from_secs(10)))
+        .await
+        .unwrap();
+
+    clock.advance(Duration::from_secs(10));
+    assert_eq!(scheduler.tick().await.unwrap(), 1);
+    let scheduled = scheduler.scheduled().await.unwrap();
+    assert!(scheduled[0].failed);
+    assert!(
+        scheduled[0]
+            .last_error
+            .as_deref()
+            .unwrap()
+            .contains("unknown customer")
+    );
+
+    // The failed command is not dispatched again
+    clock.advance(minutes(1));
+    assert_eq!(scheduler.tick().await.unwrap(), 0);
+    assert!(handle.cancel().await.unwrap());
+}
+
+#[tokio::test]
+async fn test_unreadable_schedules_are_kept_as_failed() {
+    let directory = std::env::temp_dir().join(format!(
+        "qonduit-schedules-unreadable-{}",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&directory);
+    let clock = ManualClock::new(at(START));
+    let (scheduler, reminded) = scheduler(&clock);
+    let scheduler = scheduler.with_store(FileScheduleStore::open(&directory).unwrap());
+    std::fs::write(directory.join("broken.json"), b"{ not json").unwrap();
+    scheduler
+        .schedule(reminder(1), Schedule::after(Duration::from_secs(10)))
+        .await
+        .unwrap();
+
+    // The unreadable command does not keep the others from running
+    clock.advance(Duration::from_secs(10));
+    assert_eq!(scheduler.tick().await.unwrap(), 1);
+    assert_eq!(*reminded.lock().unwrap(), vec![1]);
+    let scheduled = scheduler.scheduled().await.unwrap();
+    assert_eq!(scheduled.len(), 1);
+    assert_eq!(scheduled[0].id, "broken");
+    assert!(scheduled[0].failed);
+    assert!(scheduled[0].last_error.is_some());
+
+    // Cancelling the unreadable command removes its file
+    assert!(scheduler.cancel("broken").await.unwrap());
+    assert!(!directory.join("broken.json").exists());
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+// Records the principals on whose behalf the commands are dispatched
+struct PrincipalReminderCommandHandler(Arc<Mutex<Vec<Option<String>>>>);
+
+#[async_trait]
+impl CommandHandler<SendReminderCommand> for PrincipalReminderCommandHandler {
+    async fn handle(&self, _command: SendReminderCommand) -> Result<(), String> {
+        let principal =
+            current_principal().and_then(|principal| principal.id().map(str::to_string));
+        self.0.lock().unwrap().push(principal);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_commands_are_dispatched_on_behalf_of_their_principal() {
+    let clock = ManualClock::new(at(START));
+    let principals = Arc::new(Mutex::new(Vec::new()));
+    let mut registry = CommandHandlerRegistry::new();
+    registry
+        .register_json::<SendReminderCommand>(PrincipalReminderCommandHandler(principals.clone()));
+    let scheduler = Scheduler::new(CommandBus::new(registry)).with_clock(clock.clone());
+    scheduler
+        .schedule_as(
+            Principal::new("alice"),
+            reminder(1),
+            Schedule::after(Duration::from_secs(10)),
+        )
+        .await
+        .unwrap();
+    scheduler
+        .schedule(reminder(2), Schedule::after(Duration::from_secs(10)))
+        .await
+        .unwrap();
+    let scheduled = scheduler.scheduled().await.unwrap();
+    assert_eq!(scheduled[0].principal, Some(Principal::new("alice")));
+
+    clock.advance(Duration::from_secs(10));
+    assert_eq!(scheduler.tick().await.unwrap(), 2);
+    assert_eq!(
+        *principals.lock().unwrap(),
+        vec![Some("alice".to_string()), None]
+    );
+}
+
+// Fails to load the scheduled commands, as if its storage was unavailable
+struct UnavailableScheduleStore;
+
+#[async_trait]
+impl ScheduleStore for UnavailableScheduleStore {
+    async fn save(&self, _: &ScheduledCommand) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Err("unavailable".into())
+    }
+
+    async fn remove(&self, _: &str) -> Result<bool, Box<dyn Error + Send + Sync>> {
+        Err("unavailable".into())
+    }
+
+    async fn load(&self) -> Result<Vec<ScheduledCommand>, Box<dyn Error + Send + Sync>> {
+        Err("unavailable".into())
+    }
+}
+
+#[tokio::test]
+async fn test_tick_errors_are_passed_to_the_callback() {
+    let clock = ManualClock::new(at(START));
+    let (scheduler, _) = scheduler(&clock);
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let scheduler = scheduler
+        .with_store(UnavailableScheduleStore)
+        .with_poll_interval(Duration::from_millis(5))
+        .on_tick_error(move |error| {
+            let _ = sender.send(error.to_string());
+        });
+    let run = tokio::spawn(scheduler.run());
+
+    assert_eq!(receiver.recv().await.unwrap(), "unavailable");
+    assert_eq!(receiver.recv().await.unwrap(), "unavailable");
+    run.abort();
+}
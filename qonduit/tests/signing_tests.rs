@@ -0,0 +1,313 @@
+#![cfg(feature = "signing")]
+
+use qonduit::async_trait;
+use qonduit::bridge::{EventBridge, EventBroker};
+use qonduit::codec::JsonCodec;
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::error::DispatchError;
+use qonduit::event::{Event, EventBus, EventHandler};
+use qonduit::message::MessageName;
+use qonduit::query::QueryBus;
+use qonduit::registry::{CommandHandlerRegistry, EventHandlerRegistry, QueryHandlerRegistry};
+use qonduit::remote::{
+    Frame, FrameKind, MemoryTransport, RemoteCommandBus, RemoteServer, Transport, TransportError,
+};
+use qonduit::signing::{
+    KeyRing, SignedEnvelope, Signer, SigningKey, VerificationError, Verifier, VerifyingKey,
+};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+use tokio::time::timeout;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PlaceOrderCommand {
+    sku: String,
+}
+
+impl Command for PlaceOrderCommand {
+    type Response = String;
+    type Error = String;
+}
+
+impl MessageName for PlaceOrderCommand {
+    const NAME: &'static str = "orders.place.v1";
+}
+
+// Counts the commands reaching the handler
+struct PlaceOrderCommandHandler(Arc<AtomicUsize>);
+
+#[async_trait]
+impl CommandHandler<PlaceOrderCommand> for PlaceOrderCommandHandler {
+    async fn handle(&self, command: PlaceOrderCommand) -> Result<String, String> {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        Ok(format!("order-{}", command.sku))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct OrderPlacedEvent {
+    order_id: u64,
+}
+
+impl Event for OrderPlacedEvent {}
+
+impl MessageName for OrderPlacedEvent {
+    const NAME: &'static str = "orders.placed.v1";
+}
+
+struct RecordingEventHandler(UnboundedSender<OrderPlacedEvent>);
+
+#[async_trait]
+impl EventHandler<OrderPlacedEvent> for RecordingEventHandler {
+    async fn handle(&self, event: OrderPlacedEvent) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.0.send(event)?;
+        Ok(())
+    }
+}
+
+// Serves a server verifying signatures with `verifier`, and returns the other end
+fn connect(verifier: Verifier, handled: Arc<AtomicUsize>) -> MemoryTransport {
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register::<PlaceOrderCommand>(PlaceOrderCommandHandler(handled));
+    let server = RemoteServer::new(
+        CommandBus::new(registry),
+        QueryBus::new(QueryHandlerRegistry::new()),
+        JsonCodec,
+    )
+    .command::<PlaceOrderCommand>()
+    .with_verifier(verifier);
+
+    let (client, connection) = MemoryTransport::pair();
+    tokio::spawn(async move { server.serve(connection).await });
+    client
+}
+
+fn place_order() -> PlaceOrderCommand {
+    PlaceOrderCommand {
+        sku: "kb-01".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_remote_dispatch_with_hmac_signatures() {
+    let key = SigningKey::hmac_sha256("2026-10", "shared secret");
+    let verifier = Verifier::new(KeyRing::new().with_key(key.verifying_key()));
+    let handled = Arc::new(AtomicUsize::new(0));
+    let transport = connect(verifier, handled.clone());
+    let signed = RemoteCommandBus::new(transport, JsonCodec).with_signer(Signer::new(key));
+
+    assert_eq!(
+        signed.dispatch(place_order()).await,
+        Ok("order-kb-01".to_string())
+    );
+
+    // Unsigned commands, and commands signed with an untrusted secret, never reach the handler
+    let verifier = Verifier::new(
+        KeyRing::new().with_key(VerifyingKey::hmac_sha256("2026-10", "shared secret")),
+    );
+    let unsigned = RemoteCommandBus::new(connect(verifier.clone(), handled.clone()), JsonCodec);
+    let error = unsigned.dispatch(place_order()).await.unwrap_err();
+    assert_eq!(error.kind(), "unauthenticated");
+
+    let forged = SigningKey::hmac_sha256("2026-10", "guessed secret");
+    let forger = RemoteCommandBus::new(connect(verifier, handled.clone()), JsonCodec)
+        .with_signer(Signer::new(forged));
+    assert_eq!(
+        forger.dispatch(place_order()).await,
+        Err(DispatchError::Unauthenticated(
+            "invalid signature".to_string()
+        ))
+    );
+    assert_eq!(handled.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_tampered_envelopes_are_rejected() {
+    let key = SigningKey::ed25519("node-a", [7; 32]);
+    let verifier = Verifier::new(KeyRing::new().with_key(key.verifying_key()));
+    let signer = Signer::new(key);
+    let envelope = signer.sign(PlaceOrderCommand::NAME, b"{\"sku\":\"kb-01\"}".to_vec());
+
+    let mut tampered = envelope.clone();
+    tampered.payload = b"{\"sku\":\"kb-99\"}".to_vec();
+    assert_eq!(
+        verifier.verify(&tampered),
+        Err(VerificationError::InvalidSignature)
+    );
+    let mut renamed = envelope.clone();
+    renamed.name = "orders.cancel.v1".to_string();
+    assert_eq!(
+        verifier.verify(&renamed),
+        Err(VerificationError::InvalidSignature)
+    );
+
+    let decoded = SignedEnvelope::from_bytes(&envelope.to_bytes()).unwrap();
+    assert_eq!(decoded, envelope);
+    assert_eq!(verifier.verify(&decoded), Ok(()));
+    assert!(matches!(
+        SignedEnvelope::from_bytes(&envelope.to_bytes()[..20]),
+        Err(VerificationError::Malformed(_))
+    ));
+}
+
+#[test]
+fn test_expired_and_replayed_envelopes_are_rejected() {
+    let key = SigningKey::hmac_sha256("2026-10", "shared secret");
+    let verifier = Verifier::new(KeyRing::new().with_key(key.verifying_key()))
+        .with_max_age(Duration::from_secs(60));
+    let signer = Signer::new(key);
+
+    let envelope = signer.sign(PlaceOrderCommand::NAME, Vec::new());
+    assert_eq!(verifier.verify(&envelope), Ok(()));
+    assert_eq!(verifier.verify(&envelope), Err(VerificationError::Replayed));
+
+    let envelope = signer.sign(PlaceOrderCommand::NAME, Vec::new());
+    let later = SystemTime::now() + Duration::from_secs(61);
+    assert_eq!(
+        verifier.verify_at(&envelope, later),
+        Err(VerificationError::Expired)
+    );
+    let earlier = SystemTime::now() - Duration::from_secs(60);
+    assert_eq!(
+        verifier.verify_at(&envelope, earlier),
+        Err(VerificationError::NotYetValid)
+    );
+}
+
+#[test]
+fn test_key_rotation() {
+    let old = SigningKey::ed25519("2026-09", [1; 32]);
+    let new = SigningKey::ed25519("2026-10", [2; 32]);
+    // Receivers only know the public keys
+    let public_key = old.verifying_key().public_key().unwrap();
+    let key_ring = KeyRing::new().with_key(VerifyingKey::ed25519("2026-09", public_key).unwrap());
+    let verifier = Verifier::new(key_ring.clone());
+    let signer = Signer::new(old);
+
+    let before = signer.sign(PlaceOrderCommand::NAME, Vec::new());
+    key_ring.add(new.verifying_key());
+    signer.rotate(new);
+    assert_eq!(signer.key_id(), "2026-10");
+    let after = signer.sign(PlaceOrderCommand::NAME, Vec::new());
+    assert_eq!(verifier.verify(&after), Ok(()));
+
+    // Once the old key is removed, the messages it signed are rejected
+    key_ring.remove("2026-09");
+    assert_eq!(key_ring.key_ids(), vec!["2026-10".to_string()]);
+    assert_eq!(
+        verifier.verify(&before),
+        Err(VerificationError::UnknownKey("2026-09".to_string()))
+    );
+}
+
+#[tokio::test]
+async fn test_signed_events_over_the_bridge() {
+    let broker = EventBroker::new();
+    let receivers = broker.clone();
+    let connect = move || {
+        let (bridge, connection) = MemoryTransport::pair();
+        let broker = broker.clone();
+        tokio::spawn(async move { broker.serve(connection).await });
+        std::future::ready(Ok::<_, TransportError>(bridge))
+    };
+
+    let key = SigningKey::hmac_sha256("2026-10", "shared secret");
+    let billing = EventBridge::new("billing", JsonCodec)
+        .receive::<OrderPlacedEvent>()
+        .with_verifier(Verifier::new(KeyRing::new().with_key(key.verifying_key())));
+    let (sender, mut placed_orders) = unbounded_channel();
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<OrderPlacedEvent>(RecordingEventHandler(sender));
+    tokio::spawn(billing.run(EventBus::new(registry), connect.clone()));
+    while receivers.receivers(OrderPlacedEvent::NAME).is_empty() {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    let mut buses = Vec::new();
+    for (name, signer) in [("orders", Some(Signer::new(key))), ("intruder", None)] {
+        let mut bridge = EventBridge::new(name, JsonCodec);
+        if let Some(signer) = signer {
+            bridge = bridge.with_signer(signer);
+        }
+        let mut registry = EventHandlerRegistry::new();
+        registry.register::<OrderPlacedEvent>(bridge.forward::<OrderPlacedEvent>());
+        let bus = EventBus::new(registry);
+        tokio::spawn(bridge.clone().run(bus.clone(), connect.clone()));
+        buses.push((bus, bridge));
+    }
+
+    // The events of both processes are delivered, but only the signed one is dispatched
+    let (intruder, intruder_bridge) = &buses[1];
+    intruder
+        .dispatch(OrderPlacedEvent { order_id: 666 })
+        .await
+        .unwrap();
+    while intruder_bridge.unacknowledged() > 0 {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    let (orders, _) = &buses[0];
+    orders
+        .dispatch(OrderPlacedEvent { order_id: 1 })
+        .await
+        .unwrap();
+    let event = timeout(Duration::from_secs(5), placed_orders.recv())
+        .await
+        .expect("no event received");
+    assert_eq!(event, Some(OrderPlacedEvent { order_id: 1 }));
+}
+
+#[tokio::test]
+async fn test_broker_verifies_hello() {
+    let key = SigningKey::hmac_sha256("2026-10", "shared secret");
+    let broker = EventBroker::new()
+        .with_verifier(Verifier::new(KeyRing::new().with_key(key.verifying_key())));
+
+    // An unsigned hello closes the connection
+    let (bridge, connection) = MemoryTransport::pair();
+    let serve = tokio::spawn({
+        let broker = broker.clone();
+        async move { broker.serve(connection).await }
+    });
+    bridge
+        .send(Frame {
+            correlation_id: 0,
+            kind: FrameKind::Hello,
+            name: "billing".to_string(),
+            principal: None,
+            deadline: None,
+            payload: Vec::new(),
+        })
+        .await
+        .unwrap();
+    assert!(matches!(
+        serve.await.unwrap(),
+        Err(TransportError::Unauthenticated(_))
+    ));
+
+    // A bridge signing with a trusted key is accepted
+    let connect = {
+        let broker = broker.clone();
+        move || {
+            let (bridge, connection) = MemoryTransport::pair();
+            let broker = broker.clone();
+            tokio::spawn(async move { broker.serve(connection).await });
+            std::future::ready(Ok::<_, TransportError>(bridge))
+        }
+    };
+    let billing = EventBridge::new("billing", JsonCodec)
+        .receive::<OrderPlacedEvent>()
+        .with_signer(Signer::new(key));
+    tokio::spawn(billing.run(EventBus::new(EventHandlerRegistry::new()), connect));
+    let subscribed = async {
+        while broker.receivers(OrderPlacedEvent::NAME).is_empty() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    };
+    timeout(Duration::from_secs(5), subscribed)
+        .await
+        .expect("bridge not accepted");
+}
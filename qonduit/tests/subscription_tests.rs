@@ -0,0 +1,76 @@
+use qonduit::async_trait;
+use qonduit::event::{Event, EventBus, EventHandler};
+use qonduit::registry::EventHandlerRegistry;
+use qonduit::subscription::SubscriptionError;
+use std::error::Error;
+
+#[derive(Clone, Debug, PartialEq)]
+struct OrderShippedEvent {
+    order_id: u64,
+}
+
+impl Event for OrderShippedEvent {}
+
+struct FailingHandler;
+
+#[async_trait]
+impl EventHandler<OrderShippedEvent> for FailingHandler {
+    async fn handle(&self, _event: OrderShippedEvent) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Err("projection unavailable".into())
+    }
+}
+
+#[tokio::test]
+async fn test_subscribers_receive_dispatched_events() {
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<OrderShippedEvent>(FailingHandler);
+    let bus = EventBus::new(registry);
+
+    bus.dispatch(OrderShippedEvent { order_id: 1 })
+        .await
+        .unwrap_err();
+    let mut first = bus.subscribe::<OrderShippedEvent>();
+    let mut second = bus.clone().subscribe::<OrderShippedEvent>();
+
+    // Subscribers see the event even though its handler fails
+    bus.dispatch(OrderShippedEvent { order_id: 2 })
+        .await
+        .unwrap_err();
+    assert_eq!(first.recv().await, Ok(OrderShippedEvent { order_id: 2 }));
+    assert_eq!(second.recv().await, Ok(OrderShippedEvent { order_id: 2 }));
+}
+
+#[tokio::test]
+async fn test_dropped_subscription_is_removed() {
+    let bus = EventBus::new(EventHandlerRegistry::new());
+    assert_eq!(bus.subscriber_count::<OrderShippedEvent>(), 0);
+
+    let subscription = bus.subscribe::<OrderShippedEvent>();
+    let _other = bus.subscribe::<OrderShippedEvent>();
+    assert_eq!(bus.subscriber_count::<OrderShippedEvent>(), 2);
+
+    drop(subscription);
+    assert_eq!(bus.subscriber_count::<OrderShippedEvent>(), 1);
+}
+
+#[tokio::test]
+async fn test_lagging_subscriber() {
+    let bus = EventBus::new(EventHandlerRegistry::new()).with_subscriber_capacity(2);
+    let mut subscription = bus.subscribe::<OrderShippedEvent>();
+
+    for order_id in 1..=5 {
+        bus.dispatch(OrderShippedEvent { order_id }).await.unwrap();
+    }
+    assert_eq!(subscription.recv().await, Err(SubscriptionError::Lagged(3)));
+    assert_eq!(
+        subscription.recv().await,
+        Ok(OrderShippedEvent { order_id: 4 })
+    );
+    assert_eq!(
+        subscription.recv().await,
+        Ok(OrderShippedEvent { order_id: 5 })
+    );
+
+    drop(bus);
+    assert_eq!(subscription.recv().await, Err(SubscriptionError::Closed));
+}
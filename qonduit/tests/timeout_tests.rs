@@ -0,0 +1,164 @@
+use qonduit::async_trait;
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::error::DispatchError;
+use qonduit::event::{Event, EventBus, EventHandler};
+use qonduit::query::{Query, QueryBus, QueryHandler};
+use qonduit::registry::{CommandHandlerRegistry, EventHandlerRegistry, QueryHandlerRegistry};
+use qonduit::timeout;
+use std::error::Error;
+use std::time::Duration;
+
+// Error type for tests
+#[derive(Debug, PartialEq)]
+struct TestError;
+
+// Query that sleeps for the given number of milliseconds
+#[derive(Debug)]
+struct SleepQuery(u64);
+
+impl Query for SleepQuery {
+    type Response = Option<Duration>;
+    type Error = TestError;
+}
+
+// Handler that sleeps, then reports the budget it had left
+struct SleepQueryHandler;
+
+#[async_trait]
+impl QueryHandler<SleepQuery> for SleepQueryHandler {
+    async fn handle(&self, query: SleepQuery) -> Result<Option<Duration>, TestError> {
+        tokio::time::sleep(Duration::from_millis(query.0)).await;
+        Ok(timeout::remaining())
+    }
+}
+
+fn query_bus() -> QueryBus {
+    let mut registry = QueryHandlerRegistry::new();
+    registry.register::<SleepQuery>(SleepQueryHandler);
+    QueryBus::new(registry)
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_query_bus_without_timeout() {
+    let bus = query_bus();
+
+    let result = bus.dispatch(SleepQuery(60_000)).await;
+
+    assert_eq!(result, Ok(None));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_query_bus_default_timeout() {
+    let bus = query_bus().with_default_timeout(Duration::from_millis(100));
+
+    // Completes within the budget
+    let result = bus.dispatch(SleepQuery(40)).await;
+    assert_eq!(result, Ok(Some(Duration::from_millis(60))));
+
+    // Exceeds the budget
+    let result = bus.dispatch(SleepQuery(150)).await;
+    assert_eq!(
+        result,
+        Err(DispatchError::Timeout(Duration::from_millis(100)))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_query_bus_per_type_timeout_overrides_default() {
+    let bus = query_bus()
+        .with_default_timeout(Duration::from_millis(100))
+        .with_timeout::<SleepQuery>(Duration::from_millis(500));
+
+    let result = bus.dispatch(SleepQuery(150)).await;
+
+    assert_eq!(result, Ok(Some(Duration::from_millis(350))));
+}
+
+// Command that dispatches a nested query through the query bus
+#[derive(Debug)]
+struct NestedCommand(u64);
+
+impl Command for NestedCommand {
+    type Response = Option<Duration>;
+    type Error = DispatchError<TestError>;
+}
+
+struct NestedCommandHandler {
+    query_bus: QueryBus,
+}
+
+#[async_trait]
+impl CommandHandler<NestedCommand> for NestedCommandHandler {
+    async fn handle(
+        &self,
+        command: NestedCommand,
+    ) -> Result<Option<Duration>, DispatchError<TestError>> {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        self.query_bus.dispatch(SleepQuery(command.0)).await
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_nested_dispatch_inherits_deadline() {
+    // The query bus has a larger timeout than the outer command
+    let query_bus = query_bus().with_default_timeout(Duration::from_secs(10));
+
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register::<NestedCommand>(NestedCommandHandler { query_bus });
+    let bus = CommandBus::new(registry).with_timeout::<NestedCommand>(Duration::from_millis(200));
+
+    // The nested query sees what remains of the command's budget
+    let result = bus.dispatch(NestedCommand(30)).await;
+    assert_eq!(result, Ok(Some(Duration::from_millis(120))));
+
+    // The nested query times out with the remaining budget, not its own timeout
+    let result = bus.dispatch(NestedCommand(1_000)).await;
+    assert_eq!(
+        result,
+        Err(DispatchError::Handler(DispatchError::Timeout(
+            Duration::from_millis(150)
+        )))
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_nested_dispatch_inherits_deadline_without_own_timeout() {
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register::<NestedCommand>(NestedCommandHandler {
+        query_bus: query_bus(),
+    });
+    let bus = CommandBus::new(registry).with_default_timeout(Duration::from_millis(200));
+
+    let result = bus.dispatch(NestedCommand(30)).await;
+
+    assert_eq!(result, Ok(Some(Duration::from_millis(120))));
+}
+
+// Event whose handler sleeps for the given number of milliseconds
+#[derive(Debug, Clone)]
+struct SleepEvent(u64);
+
+impl Event for SleepEvent {}
+
+struct SleepEventHandler;
+
+#[async_trait]
+impl EventHandler<SleepEvent> for SleepEventHandler {
+    async fn handle(&self, event: SleepEvent) -> Result<(), Box<dyn Error + Send + Sync>> {
+        tokio::time::sleep(Duration::from_millis(event.0)).await;
+        Ok(())
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_event_bus_timeout_covers_all_handlers() {
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<SleepEvent>(SleepEventHandler);
+    registry.register::<SleepEvent>(SleepEventHandler);
+    let bus = EventBus::new(registry).with_timeout::<SleepEvent>(Duration::from_millis(100));
+
+    // Each handler fits within the budget, both together do not
+    assert!(bus.dispatch(SleepEvent(40)).await.is_ok());
+    let result = bus.dispatch(SleepEvent(60)).await;
+    assert!(result.unwrap_err().is_timeout());
+}
@@ -0,0 +1,277 @@
+#![cfg(feature = "tracing")]
+
+use qonduit::async_trait;
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::event::{Event, EventBus, EventHandler};
+use qonduit::registry::{CommandHandlerRegistry, EventHandlerRegistry};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Subscriber, subscriber};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{Layer, Registry};
+
+// A closed span with its fields and the name of its parent
+#[derive(Clone, Debug, Default)]
+struct CapturedSpan {
+    name: &'static str,
+    parent: Option<&'static str>,
+    fields: HashMap<&'static str, String>,
+}
+
+impl CapturedSpan {
+    fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).map(String::as_str)
+    }
+}
+
+impl Visit for CapturedSpan {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.fields.insert(field.name(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.fields.insert(field.name(), format!("{value:?}"));
+    }
+}
+
+// Layer collecting every span once it is closed
+#[derive(Clone, Default)]
+struct CaptureLayer {
+    closed: Arc<Mutex<Vec<CapturedSpan>>>,
+}
+
+impl CaptureLayer {
+    fn spans(&self, name: &str) -> Vec<CapturedSpan> {
+        let closed = self.closed.lock().unwrap();
+        closed
+            .iter()
+            .filter(|span| span.name == name)
+            .cloned()
+            .collect()
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for CaptureLayer {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let span = ctx.span(id).unwrap();
+        let mut captured = CapturedSpan {
+            name: span.name(),
+            parent: span.parent().map(|parent| parent.name()),
+            ..CapturedSpan::default()
+        };
+        attrs.record(&mut captured);
+        span.extensions_mut().insert(captured);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let span = ctx.span(id).unwrap();
+        let mut extensions = span.extensions_mut();
+        values.record(extensions.get_mut::<CapturedSpan>().unwrap());
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let span = ctx.span(&id).unwrap();
+        let captured = span.extensions_mut().remove::<CapturedSpan>().unwrap();
+        self.closed.lock().unwrap().push(captured);
+    }
+}
+
+fn capture() -> (CaptureLayer, subscriber::DefaultGuard) {
+    let layer = CaptureLayer::default();
+    let guard = subscriber::set_default(Registry::default().with(layer.clone()));
+    (layer, guard)
+}
+
+// Error type for tests
+#[derive(Debug, PartialEq)]
+struct TestError;
+
+// Command carrying a secret
+#[derive(Debug)]
+struct ChangePasswordCommand {
+    password: String,
+}
+
+impl Command for ChangePasswordCommand {
+    type Response = ();
+    type Error = TestError;
+}
+
+// Command failing when asked to
+#[derive(Debug)]
+struct RenameProductCommand {
+    name: String,
+}
+
+impl Command for RenameProductCommand {
+    type Response = ();
+    type Error = TestError;
+}
+
+struct ChangePasswordCommandHandler;
+
+#[async_trait]
+impl CommandHandler<ChangePasswordCommand> for ChangePasswordCommandHandler {
+    async fn handle(&self, command: ChangePasswordCommand) -> Result<(), TestError> {
+        match command.password.is_empty() {
+            true => Err(TestError),
+            false => Ok(()),
+        }
+    }
+}
+
+struct RenameProductCommandHandler;
+
+#[async_trait]
+impl CommandHandler<RenameProductCommand> for RenameProductCommandHandler {
+    async fn handle(&self, command: RenameProductCommand) -> Result<(), TestError> {
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        match command.name.is_empty() {
+            true => Err(TestError),
+            false => Ok(()),
+        }
+    }
+}
+
+fn command_bus() -> CommandBus {
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register::<ChangePasswordCommand>(ChangePasswordCommandHandler);
+    registry.register::<RenameProductCommand>(RenameProductCommandHandler);
+    CommandBus::new(registry)
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_command_dispatch_span() {
+    let (layer, _guard) = capture();
+    let bus = command_bus();
+
+    bus.dispatch(RenameProductCommand {
+        name: "Desk".to_string(),
+    })
+    .await
+    .unwrap();
+    bus.dispatch(RenameProductCommand {
+        name: String::new(),
+    })
+    .await
+    .unwrap_err();
+
+    let spans = layer.spans("qonduit.dispatch");
+    assert_eq!(spans.len(), 2);
+    assert_eq!(spans[0].field("bus"), Some("command"));
+    assert_eq!(
+        spans[0].field("message_type"),
+        Some(std::any::type_name::<RenameProductCommand>())
+    );
+    assert_eq!(
+        spans[0].field("handler_type"),
+        Some(std::any::type_name::<RenameProductCommandHandler>())
+    );
+    assert_eq!(spans[0].field("outcome"), Some("ok"));
+    assert_eq!(spans[0].field("duration_ms"), Some("40.0"));
+    assert_eq!(spans[0].field("message"), None);
+    assert_eq!(spans[1].field("outcome"), Some("handler_error"));
+}
+
+#[tokio::test]
+async fn test_message_recording_with_opt_out() {
+    let (layer, _guard) = capture();
+    let bus = command_bus()
+        .with_message_recording()
+        .without_message_recording::<ChangePasswordCommand>();
+
+    bus.dispatch(RenameProductCommand {
+        name: "Desk".to_string(),
+    })
+    .await
+    .unwrap();
+    bus.dispatch(ChangePasswordCommand {
+        password: "hunter2".to_string(),
+    })
+    .await
+    .unwrap();
+
+    let spans = layer.spans("qonduit.dispatch");
+    assert_eq!(
+        spans[0].field("message"),
+        Some(r#"RenameProductCommand { name: "Desk" }"#)
+    );
+    assert_eq!(spans[1].field("message"), None);
+    assert!(
+        spans[1]
+            .fields
+            .values()
+            .all(|value| !value.contains("hunter2"))
+    );
+}
+
+// Event handled by two handlers
+#[derive(Clone, Debug)]
+struct ProductRenamedEvent;
+
+impl Event for ProductRenamedEvent {}
+
+struct SearchIndexEventHandler;
+
+#[async_trait]
+impl EventHandler<ProductRenamedEvent> for SearchIndexEventHandler {
+    async fn handle(
+        &self,
+        _event: ProductRenamedEvent,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Ok(())
+    }
+}
+
+struct FailingEventHandler;
+
+#[async_trait]
+impl EventHandler<ProductRenamedEvent> for FailingEventHandler {
+    async fn handle(
+        &self,
+        _event: ProductRenamedEvent,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Err("search cluster unavailable".into())
+    }
+}
+
+#[tokio::test]
+async fn test_event_handler_child_spans() {
+    let (layer, _guard) = capture();
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<ProductRenamedEvent>(SearchIndexEventHandler);
+    registry.register::<ProductRenamedEvent>(FailingEventHandler);
+    let bus = EventBus::new(registry);
+
+    bus.dispatch(ProductRenamedEvent).await.unwrap_err();
+
+    let dispatches = layer.spans("qonduit.dispatch");
+    assert_eq!(dispatches.len(), 1);
+    assert_eq!(dispatches[0].field("bus"), Some("event"));
+    assert_eq!(dispatches[0].field("handler_type"), None);
+    assert_eq!(dispatches[0].field("outcome"), Some("handler_error"));
+
+    let handlers = layer.spans("qonduit.event_handler");
+    assert_eq!(handlers.len(), 2);
+    assert!(
+        handlers
+            .iter()
+            .all(|span| span.parent == Some("qonduit.dispatch"))
+    );
+    assert_eq!(
+        handlers[0].field("handler_type"),
+        Some(std::any::type_name::<SearchIndexEventHandler>())
+    );
+    assert_eq!(handlers[0].field("outcome"), Some("ok"));
+    assert_eq!(
+        handlers[1].field("handler_type"),
+        Some(std::any::type_name::<FailingEventHandler>())
+    );
+    assert_eq!(handlers[1].field("outcome"), Some("handler_error"));
+}
@@ -0,0 +1,170 @@
+use qonduit::async_trait;
+use qonduit::command::{Command, CommandBus, CommandHandler};
+use qonduit::error::DispatchError;
+use qonduit::query::{Query, QueryBus, QueryHandler};
+use qonduit::registry::{CommandHandlerRegistry, QueryHandlerRegistry};
+use qonduit::validation::{Validate, Violation, Violations};
+use std::sync::Arc;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering::SeqCst;
+
+// Error type for tests
+#[derive(Debug, PartialEq)]
+struct TestError;
+
+// Command with two validated fields
+#[derive(Debug)]
+struct AddProductCommand {
+    name: String,
+    price: f64,
+}
+
+impl Command for AddProductCommand {
+    type Response = u64;
+    type Error = TestError;
+}
+
+impl Validate for AddProductCommand {
+    fn validate(&self) -> Result<(), Violations> {
+        let mut violations = Violations::new();
+        if self.name.is_empty() {
+            violations.push(Violation::new("name", "required", "must not be empty"));
+        }
+        if self.price <= 0.0 {
+            violations.push(Violation::new(
+                "price",
+                "not_positive",
+                "must be greater than zero",
+            ));
+        }
+        violations.into_result()
+    }
+}
+
+// Handler that counts how many times it was invoked
+struct AddProductCommandHandler {
+    calls: Arc<AtomicU32>,
+}
+
+#[async_trait]
+impl CommandHandler<AddProductCommand> for AddProductCommandHandler {
+    async fn handle(&self, _command: AddProductCommand) -> Result<u64, TestError> {
+        Ok(u64::from(self.calls.fetch_add(1, SeqCst)) + 1)
+    }
+}
+
+fn command_bus() -> (CommandBus, Arc<AtomicU32>) {
+    let calls = Arc::new(AtomicU32::new(0));
+    let mut registry = CommandHandlerRegistry::new();
+    registry.register::<AddProductCommand>(AddProductCommandHandler {
+        calls: calls.clone(),
+    });
+    (CommandBus::new(registry), calls)
+}
+
+#[tokio::test]
+async fn test_command_bus_valid_command_reaches_handler() {
+    let (bus, calls) = command_bus();
+    let bus = bus.with_validation::<AddProductCommand>();
+
+    let command = AddProductCommand {
+        name: "Keyboard".to_string(),
+        price: 59.99,
+    };
+
+    assert_eq!(bus.dispatch(command).await, Ok(1));
+    assert_eq!(calls.load(SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_command_bus_invalid_command_is_rejected() {
+    let (bus, calls) = command_bus();
+    let bus = bus.with_validation::<AddProductCommand>();
+
+    let command = AddProductCommand {
+        name: String::new(),
+        price: 0.0,
+    };
+
+    let Err(DispatchError::Invalid(violations)) = bus.dispatch(command).await else {
+        panic!("Expected a validation error");
+    };
+    assert_eq!(
+        violations,
+        Violations::from(vec![
+            Violation::new("name", "required", "must not be empty"),
+            Violation::new("price", "not_positive", "must be greater than zero"),
+        ])
+    );
+    assert_eq!(violations.for_field("price").count(), 1);
+    assert_eq!(calls.load(SeqCst), 0);
+}
+
+#[tokio::test]
+async fn test_command_bus_without_validation_enabled() {
+    let (bus, calls) = command_bus();
+
+    let command = AddProductCommand {
+        name: String::new(),
+        price: 0.0,
+    };
+
+    // Validation is opt-in per type, so the handler is invoked
+    assert_eq!(bus.dispatch(command).await, Ok(1));
+    assert_eq!(calls.load(SeqCst), 1);
+}
+
+// Query with a bounded page size
+#[derive(Debug)]
+struct ListProductsQuery {
+    page_size: u32,
+}
+
+impl Query for ListProductsQuery {
+    type Response = u32;
+    type Error = TestError;
+}
+
+impl Validate for ListProductsQuery {
+    fn validate(&self) -> Result<(), Violations> {
+        let mut violations = Violations::new();
+        if !(1..=100).contains(&self.page_size) {
+            violations.push(Violation::new(
+                "page_size",
+                "out_of_range",
+                "must be between 1 and 100",
+            ));
+        }
+        violations.into_result()
+    }
+}
+
+struct ListProductsQueryHandler;
+
+#[async_trait]
+impl QueryHandler<ListProductsQuery> for ListProductsQueryHandler {
+    async fn handle(&self, query: ListProductsQuery) -> Result<u32, TestError> {
+        Ok(query.page_size)
+    }
+}
+
+#[tokio::test]
+async fn test_query_bus_validation() {
+    let mut registry = QueryHandlerRegistry::new();
+    registry.register::<ListProductsQuery>(ListProductsQueryHandler);
+    let bus = QueryBus::new(registry).with_validation::<ListProductsQuery>();
+
+    assert_eq!(
+        bus.dispatch(ListProductsQuery { page_size: 20 }).await,
+        Ok(20)
+    );
+
+    let result = bus.dispatch(ListProductsQuery { page_size: 500 }).await;
+    let Err(DispatchError::Invalid(violations)) = result else {
+        panic!("Expected a validation error");
+    };
+    assert_eq!(
+        violations.to_string(),
+        "page_size: must be between 1 and 100"
+    );
+}
@@ -0,0 +1,254 @@
+#![cfg(feature = "webhook")]
+
+use qonduit::event::{Event, EventBus};
+use qonduit::message::MessageName;
+use qonduit::registry::EventHandlerRegistry;
+use qonduit::webhook::{
+    Delivery, DeliveryStats, DeliveryStatus, DeliveryStore, Endpoint, FileDeliveryStore,
+    RetryConfig, Webhooks, verify_signature,
+};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+use tokio::time::timeout;
+
+#[derive(Clone, Debug, Serialize)]
+struct OrderShippedEvent {
+    order_id: u64,
+}
+
+impl Event for OrderShippedEvent {}
+
+impl MessageName for OrderShippedEvent {
+    const NAME: &'static str = "orders.shipped.v1";
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct OrderPlacedEvent {
+    order_id: u64,
+}
+
+impl Event for OrderPlacedEvent {}
+
+impl MessageName for OrderPlacedEvent {
+    const NAME: &'static str = "orders.placed.v1";
+}
+
+#[derive(Debug)]
+struct Request {
+    path: String,
+    headers: HashMap<String, String>,
+    body: String,
+}
+
+// Serves HTTP requests on localhost, answering with `statuses` in turn, then with 200
+async fn endpoint(statuses: &[u16]) -> (String, UnboundedReceiver<Request>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let statuses = Arc::new(Mutex::new(VecDeque::from(statuses.to_vec())));
+    let (sender, receiver) = unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (sender, statuses) = (sender.clone(), statuses.clone());
+            tokio::spawn(async move {
+                let mut stream = BufReader::new(stream);
+                let mut line = String::new();
+                while stream.read_line(&mut line).await.unwrap_or(0) > 0 {
+                    let path = line.split(' ').nth(1).unwrap().to_string();
+                    let mut headers = HashMap::new();
+                    loop {
+                        line.clear();
+                        stream.read_line(&mut line).await.unwrap();
+                        match line.trim_end().split_once(": ") {
+                            Some((name, value)) => {
+                                headers.insert(name.to_lowercase(), value.to_string())
+                            }
+                            None => break,
+                        };
+                    }
+                    let len = headers["content-length"].parse().unwrap();
+                    let mut body = vec![0; len];
+                    stream.read_exact(&mut body).await.unwrap();
+                    let body = String::from_utf8(body).unwrap();
+                    let _ = sender.send(Request {
+                        path,
+                        headers,
+                        body,
+                    });
+
+                    let status = statuses.lock().unwrap().pop_front().unwrap_or(200);
+                    let response = format!("HTTP/1.1 {status} Status\r\ncontent-length: 0\r\n\r\n");
+                    stream
+                        .get_mut()
+                        .write_all(response.as_bytes())
+                        .await
+                        .unwrap();
+                    line.clear();
+                }
+            });
+        }
+    });
+    (url, receiver)
+}
+
+async fn next(requests: &mut UnboundedReceiver<Request>) -> Request {
+    timeout(Duration::from_secs(5), requests.recv())
+        .await
+        .expect("no request received")
+        .unwrap()
+}
+
+async fn wait_for(webhooks: &Webhooks, done: impl Fn(DeliveryStats) -> bool) {
+    while !done(webhooks.stats()) {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+}
+
+fn bus(webhooks: &Webhooks) -> EventBus {
+    let mut registry = EventHandlerRegistry::new();
+    registry.register::<OrderShippedEvent>(webhooks.handler::<OrderShippedEvent>());
+    registry.register::<OrderPlacedEvent>(webhooks.handler::<OrderPlacedEvent>());
+    EventBus::new(registry)
+}
+
+#[tokio::test]
+async fn test_events_are_posted_to_their_endpoints() {
+    let (url, mut requests) = endpoint(&[]).await;
+    let webhooks = Webhooks::new()
+        .with_endpoint::<OrderShippedEvent>(
+            Endpoint::new(format!("{url}/signed")).with_secret("shared secret"),
+        )
+        .with_endpoint::<OrderShippedEvent>(Endpoint::new(format!("{url}/unsigned")));
+    let event_bus = bus(&webhooks);
+
+    // No endpoint is configured for placed orders
+    event_bus
+        .dispatch(OrderPlacedEvent { order_id: 1 })
+        .await
+        .unwrap();
+    event_bus
+        .dispatch(OrderShippedEvent { order_id: 42 })
+        .await
+        .unwrap();
+
+    let mut received = vec![next(&mut requests).await, next(&mut requests).await];
+    received.sort_by(|a, b| a.path.cmp(&b.path));
+    let [signed, unsigned] = <[Request; 2]>::try_from(received).unwrap();
+    assert_eq!(signed.path, "/signed");
+    assert_eq!(signed.body, r#"{"order_id":42}"#);
+    assert_eq!(signed.headers["x-webhook-event"], "orders.shipped.v1");
+    assert_eq!(signed.headers["content-type"], "application/json");
+    assert!(verify_signature(
+        b"shared secret",
+        &signed.headers["x-webhook-timestamp"],
+        &signed.body,
+        &signed.headers["x-webhook-signature"],
+    ));
+    assert!(!verify_signature(
+        b"another secret",
+        &signed.headers["x-webhook-timestamp"],
+        &signed.body,
+        &signed.headers["x-webhook-signature"],
+    ));
+    assert!(!unsigned.headers.contains_key("x-webhook-signature"));
+
+    wait_for(&webhooks, |stats| stats.delivered == 2).await;
+    assert!(webhooks.undelivered().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_failed_attempts_are_retried_with_backoff() {
+    let (url, mut requests) = endpoint(&[500, 503]).await;
+    let webhooks = Webhooks::new()
+        .with_endpoint::<OrderShippedEvent>(Endpoint::new(url))
+        .with_retry(RetryConfig::new(3, Duration::from_millis(10)));
+    bus(&webhooks)
+        .dispatch(OrderShippedEvent { order_id: 7 })
+        .await
+        .unwrap();
+
+    let first = next(&mut requests).await;
+    let pending = webhooks.undelivered().await.unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].status, DeliveryStatus::Pending);
+
+    // Every attempt carries the same delivery id
+    for _ in 0..2 {
+        let retry = next(&mut requests).await;
+        assert_eq!(
+            retry.headers["x-webhook-delivery"],
+            first.headers["x-webhook-delivery"]
+        );
+    }
+    wait_for(&webhooks, |stats| stats.delivered == 1).await;
+    assert_eq!(webhooks.stats().failed_attempts, 2);
+    assert!(webhooks.undelivered().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_failed_deliveries_are_kept_until_retried() {
+    let (url, requests) = endpoint(&[500, 500]).await;
+    let webhooks = Webhooks::new()
+        .with_endpoint::<OrderShippedEvent>(Endpoint::new(url))
+        .with_retry(RetryConfig::new(2, Duration::from_millis(1)));
+    bus(&webhooks)
+        .dispatch(OrderShippedEvent { order_id: 7 })
+        .await
+        .unwrap();
+
+    wait_for(&webhooks, |stats| stats.failed == 1).await;
+    let failed = webhooks.undelivered().await.unwrap();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].status, DeliveryStatus::Failed);
+    assert_eq!(failed[0].attempts, 2);
+    assert!(failed[0].last_error.as_ref().unwrap().contains("500"));
+
+    // Once the endpoint is fixed
+    assert!(webhooks.retry(&failed[0].id).await.unwrap());
+    wait_for(&webhooks, |stats| stats.delivered == 1).await;
+    assert_eq!(requests.len(), 3);
+    assert!(webhooks.undelivered().await.unwrap().is_empty());
+    assert!(!webhooks.retry(&failed[0].id).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_pending_deliveries_are_resumed_after_a_restart() {
+    let directory = std::env::temp_dir().join(format!("qonduit-webhooks-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    let (url, mut requests) = endpoint(&[]).await;
+
+    // A previous process stopped while the delivery was pending
+    let store = FileDeliveryStore::open(&directory).unwrap();
+    store
+        .save(&Delivery {
+            id: "0001760000000000-000000".to_string(),
+            event: OrderShippedEvent::NAME.to_string(),
+            url: url.clone(),
+            body: r#"{"order_id":3}"#.to_string(),
+            attempts: 1,
+            status: DeliveryStatus::Pending,
+            last_error: Some("endpoint answered with status 502".to_string()),
+        })
+        .await
+        .unwrap();
+
+    let webhooks = Webhooks::new()
+        .with_endpoint::<OrderShippedEvent>(Endpoint::new(url))
+        .with_store(FileDeliveryStore::open(&directory).unwrap());
+    assert_eq!(webhooks.resume().await.unwrap(), 1);
+
+    let request = next(&mut requests).await;
+    assert_eq!(request.body, r#"{"order_id":3}"#);
+    assert_eq!(
+        request.headers["x-webhook-delivery"],
+        "0001760000000000-000000"
+    );
+    wait_for(&webhooks, |stats| stats.delivered == 1).await;
+    assert!(store.load().await.unwrap().is_empty());
+    std::fs::remove_dir_all(&directory).unwrap();
+}